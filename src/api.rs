@@ -0,0 +1,336 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::io::read_to_string;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tempfile::TempDir;
+use crate::checker::{Checker, CheckerProtocol};
+use crate::compiler::{Compiler, CompilerError};
+use crate::diff_renderer::{DiffOptions, DiffStyle};
+use crate::encoding::open_text_file;
+use crate::executor::simple::SimpleExecutor;
+use crate::executor::{test_to_temp, ProgramEnvironment, TestExecutor};
+use crate::prepare_input::TestInputSource;
+use crate::interrupt;
+use crate::test_errors::{ExecutionError, ExecutionMetrics, TestError};
+use crate::testing_utils::compare_output;
+use crate::toolchain::{CompileProfile, Toolchain};
+
+/// An error that prevented a solution from being compiled or tested
+#[derive(Debug, Clone)]
+pub struct Error(String);
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The verdict of a single test
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Verdict {
+    Correct,
+    WrongAnswer,
+    TimedOut,
+    MemoryLimitExceeded,
+    OutputLimitExceeded,
+    RuntimeError,
+    /// The output of the program wasn't valid UTF-8
+    InvalidOutput,
+    /// The test has no output file to compare the output with
+    NoOutputFile,
+    /// A domain-specific verdict, like "protocol violation", given by the checker or by the callback of
+    /// [`Program::run_with_callback`], with an optional message
+    Custom {
+        label: String,
+        message: String,
+    },
+}
+
+/// The result of running a solution on a single test
+#[derive(Debug, Clone)]
+pub struct TestResult {
+    pub verdict: Verdict,
+    /// The runtime of the program, if it was measured
+    pub time: Option<Duration>,
+    /// A description of the error, such as the difference between the outputs for wrong answers
+    pub message: Option<String>,
+}
+
+/// Compiles solutions and runs them on tests, comparing their output with the expected output.
+///
+/// ```no_run
+/// use std::path::Path;
+/// use std::time::Duration;
+///
+/// let runner = toster::TestRunner::new().with_time_limit(Duration::from_secs(1));
+/// let program = runner.compile(Path::new("sol.cpp"))?;
+/// let result = program.run(Path::new("in/1.in"), Path::new("out/1.out"))?;
+/// println!("{:?}", result.verdict);
+/// # Ok::<(), toster::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct TestRunner {
+    time_limit: Duration,
+    compile_timeout: Duration,
+    compile_command: Option<String>,
+    output_limit: u64,
+    diff_style: DiffStyle,
+    max_diff_lines: usize,
+    max_line_length: usize,
+    /// The source code or executable of the checker and its protocol
+    checker: Option<(PathBuf, CheckerProtocol)>,
+    checker_timeout: Duration,
+}
+
+impl Default for TestRunner {
+    fn default() -> Self {
+        TestRunner {
+            time_limit: Duration::from_secs(5),
+            compile_timeout: Duration::from_secs(10),
+            compile_command: None,
+            output_limit: 256 << 20,
+            diff_style: DiffStyle::Table,
+            max_diff_lines: 99,
+            max_line_length: 200,
+            checker: None,
+            checker_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl TestRunner {
+    /// Creates a runner with the defaults of the command line interface
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the time after which a test times out, 5 seconds by default
+    #[must_use]
+    pub fn with_time_limit(self, time_limit: Duration) -> Self {
+        TestRunner { time_limit, ..self }
+    }
+
+    /// Sets the time after which compilation times out, 10 seconds by default
+    #[must_use]
+    pub fn with_compile_timeout(self, compile_timeout: Duration) -> Self {
+        TestRunner { compile_timeout, ..self }
+    }
+
+    /// Sets the command used to compile solutions, in which `<IN>` gets replaced with the path to the source code
+    /// and `<OUT>` with the executable output location. By default the first available toolchain is used
+    #[must_use]
+    pub fn with_compile_command(self, compile_command: impl Into<String>) -> Self {
+        TestRunner { compile_command: Some(compile_command.into()), ..self }
+    }
+
+    /// Sets the maximum size of the program output in bytes, 256 MiB by default
+    #[must_use]
+    pub fn with_output_limit(self, output_limit: u64) -> Self {
+        TestRunner { output_limit, ..self }
+    }
+
+    /// Sets how the difference between the outputs is shown in the message of a wrong answer,
+    /// a table by default
+    #[must_use]
+    pub fn with_diff_style(self, diff_style: DiffStyle) -> Self {
+        TestRunner { diff_style, ..self }
+    }
+
+    /// Sets the maximum number of differing lines shown in the message of a wrong answer, 99 by default
+    #[must_use]
+    pub fn with_max_diff_lines(self, max_diff_lines: usize) -> Self {
+        TestRunner { max_diff_lines, ..self }
+    }
+
+    /// Sets the maximum number of characters of a line shown in the table and unified diffs, 200 by default.
+    /// Longer lines are shortened to the part around the first difference
+    #[must_use]
+    pub fn with_max_line_length(self, max_line_length: usize) -> Self {
+        TestRunner { max_line_length, ..self }
+    }
+
+    /// Makes the output checked by a checker instead of being compared with the expected output.
+    /// The checker is compiled together with every solution, unless it's already an executable
+    #[must_use]
+    pub fn with_checker(self, checker_path: impl Into<PathBuf>, protocol: CheckerProtocol) -> Self {
+        TestRunner { checker: Some((checker_path.into(), protocol)), ..self }
+    }
+
+    /// Sets the time after which the checker times out, 30 seconds by default
+    #[must_use]
+    pub fn with_checker_timeout(self, checker_timeout: Duration) -> Self {
+        TestRunner { checker_timeout, ..self }
+    }
+
+    /// Compiles the solution, or checks that it can be run if it's already an executable
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no compiler is available, the compile command is invalid
+    /// or the compilation of the solution or the checker fails
+    pub fn compile(&self, source_path: &Path) -> Result<Program, Error> {
+        let compile_command = match &self.compile_command {
+            Some(compile_command) => compile_command.clone(),
+            None => Toolchain::resolve_compile_command(None, CompileProfile::Release).map_err(Error)?,
+        };
+        let tempdir = tempfile::tempdir().map_err(|error| Error(format!("Failed to create a temporary directory: {error}")))?;
+        let compiler = Compiler {
+            tempdir: &tempdir,
+            compile_timeout: self.compile_timeout,
+            compile_command: &compile_command,
+            cache: None,
+            extra_sources: &[],
+            custom_run_command: false,
+        };
+        let (executable_path, _) = compiler.prepare_executable(source_path, "program").map_err(|error| Error(match error {
+            CompilerError::InvalidExecutable(error) => format!("The provided program can't be executed: {error}"),
+            CompilerError::CompilationError(error) => format!("Compilation failed with the following errors:\n{error}"),
+        }))?;
+        let checker = self.checker.as_ref().map(|(checker_path, protocol)| {
+            let (checker_executable, _) = compiler.prepare_executable(checker_path, "checker").map_err(|error| Error(match error {
+                CompilerError::InvalidExecutable(error) => format!("The provided checker can't be executed: {error}"),
+                CompilerError::CompilationError(error) => format!("Checker compilation failed with the following errors:\n{error}"),
+            }))?;
+            Ok(Checker::new(checker_executable, self.checker_timeout, *protocol))
+        }).transpose()?;
+
+        Ok(Program {
+            executor: SimpleExecutor {
+                timeout: self.time_limit,
+                executable_path,
+                arguments: vec![],
+                output_limit: Some(self.output_limit),
+                kill_grace: Duration::ZERO,
+                sanitizer_report: false,
+                capture_stderr: false,
+                file_io: None,
+                scratch_directory: false,
+                environment: ProgramEnvironment::default(),
+            },
+            checker,
+            diff_options: DiffOptions {
+                style: self.diff_style,
+                max_line_length: self.max_line_length,
+                max_lines: self.max_diff_lines,
+                context_lines: None,
+            },
+            _tempdir: tempdir,
+        })
+    }
+}
+
+/// A compiled solution, which can be run on tests
+pub struct Program {
+    executor: SimpleExecutor,
+    checker: Option<Checker>,
+    diff_options: DiffOptions,
+    /// Contains the executables, which are removed together with it
+    _tempdir: TempDir,
+}
+
+impl Program {
+    /// Runs the program on the input file and checks its output with the checker,
+    /// or compares it with the expected output file if there is no checker
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input file doesn't exist, the program or the checker can't be started,
+    /// the checker fails or a temporary file can't be created
+    pub fn run(&self, input_path: &Path, expected_output_path: &Path) -> Result<TestResult, Error> {
+        if !input_path.is_file() {
+            return Err(Error(format!("The input file {} does not exist", input_path.display())));
+        }
+        let (metrics, result) = match &self.checker {
+            Some(checker) => self.run_with_checker(checker, input_path, expected_output_path),
+            None => self.run_and_compare(input_path, expected_output_path),
+        };
+        Self::test_result(metrics.time, result)
+    }
+
+    /// Runs the program on the input file and passes its output to `check`, which gives the verdict.
+    /// Lets embedders check the output themselves and return their own verdicts, like [`Verdict::Custom`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input file doesn't exist, the program can't be started or a temporary file can't be created
+    pub fn run_with_callback(&self, input_path: &Path, check: impl FnOnce(&str) -> Verdict) -> Result<TestResult, Error> {
+        if !input_path.is_file() {
+            return Err(Error(format!("The input file {} does not exist", input_path.display())));
+        }
+        let (metrics, output) = match open_text_file(input_path) {
+            Ok(input) => test_to_temp(&self.executor, &input),
+            Err(error) => (ExecutionMetrics::NONE, Err(error.into())),
+        };
+        let output = output.and_then(|output| read_to_string(output).map_err(|_| ExecutionError::OutputNotUtf8));
+        match output {
+            Ok(output) => Ok(TestResult { verdict: check(&output), time: metrics.time, message: None }),
+            Err(error) => Self::test_result(metrics.time, Err(TestError::ProgramError { error })),
+        }
+    }
+
+    /// Converts the result of the test to the verdict returned by the library
+    fn test_result(time: Option<Duration>, result: Result<(), TestError>) -> Result<TestResult, Error> {
+        // A temporary file couldn't be created, the test was stopped without a verdict
+        if let Some(error) = interrupt::take_fatal_error() {
+            return Err(Error(error));
+        }
+        let (verdict, message) = match result {
+            Ok(()) => (Verdict::Correct, None),
+            Err(TestError::Incorrect { error }) => (Verdict::WrongAnswer, Some(error)),
+            Err(TestError::NoOutputFile) => (Verdict::NoOutputFile, None),
+            Err(TestError::ProgramError { error }) => match error {
+                ExecutionError::TimedOut => (Verdict::TimedOut, None),
+                ExecutionError::MemoryLimitExceeded => (Verdict::MemoryLimitExceeded, None),
+                ExecutionError::OutputLimitExceeded => (Verdict::OutputLimitExceeded, None),
+                ExecutionError::PipeError | ExecutionError::OutputNotUtf8 => (Verdict::InvalidOutput, None),
+                ExecutionError::SandboxError(error) => return Err(Error(error)),
+                error => (Verdict::RuntimeError, Some(error.to_string())),
+            },
+            Err(TestError::Custom { label, message, .. }) => (Verdict::Custom { label, message }, None),
+            Err(error) => return Err(Error(error.message())),
+        };
+        Ok(TestResult { verdict, time, message })
+    }
+
+    fn run_and_compare(&self, input_path: &Path, expected_output_path: &Path) -> (ExecutionMetrics, Result<(), TestError>) {
+        let (metrics, output) = match open_text_file(input_path) {
+            Ok(input) => test_to_temp(&self.executor, &input),
+            Err(error) => (ExecutionMetrics::NONE, Err(error.into())),
+        };
+        let result = output
+            .map_err(|error| TestError::ProgramError { error })
+            .and_then(|output| compare_output(expected_output_path, output, &self.diff_options, None));
+        (metrics, result)
+    }
+
+    fn run_with_checker(&self, checker: &Checker, input_path: &Path, expected_output_path: &Path) -> (ExecutionMetrics, Result<(), TestError>) {
+        let input_source = TestInputSource::File(input_path.to_path_buf());
+        if checker.protocol != CheckerProtocol::Toster {
+            let (metrics, output) = match input_source.get_file() {
+                Ok(input) => test_to_temp(&self.executor, &input),
+                Err(error) => (ExecutionMetrics::NONE, Err(error.into())),
+            };
+            let result = output
+                .map_err(|error| TestError::ProgramError { error })
+                .and_then(|output| checker.check_files(&input_source, &output, expected_output_path, false).0);
+            return (metrics, result);
+        }
+        // The program output is appended to the input in the checker input file
+        let files = Checker::prepare_checker_input(&input_source)
+            .and_then(|checker_input| Ok((checker_input, input_source.get_file()?)));
+        let (checker_input, input) = match files {
+            Ok(files) => files,
+            Err(error) => return (ExecutionMetrics::NONE, Err(error.into())),
+        };
+        let (metrics, result) = self.executor.test_to_file(&input, &checker_input);
+        let result = result
+            .map_err(|error| TestError::ProgramError { error })
+            .and_then(|()| checker.check(checker_input));
+        (metrics, result)
+    }
+}
@@ -1,15 +1,50 @@
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::slice;
 use std::time::Duration;
-use clap::Parser;
+use clap::{ArgAction, ArgGroup, Parser, Subcommand, ValueEnum};
 use crate::args::ExecuteMode::{Simple};
+use crate::builtin_checker::{parse_builtin_checker, BuiltinChecker};
+use crate::checker::{is_wasm_module, CheckerProtocol};
+use crate::compile_cache::CompileCache;
+use crate::manifest::{CheckerOverride, ExpectedVerdict, LimitOverride, Manifest, ScoredGroup, TestDependency};
+use crate::output_names::OutputNames;
+use crate::history::{parse_history_reference, HistoryReference};
+use crate::perf_profile::{parse_perf_selection, PerfSelection};
+use crate::sinol::SinolPackage;
+use crate::polygon::PolygonPackage;
+use crate::noise_guard::NoiseGuardMode;
+use crate::cpu_affinity::{online_cpus, parse_cpu_list};
+use crate::diff_renderer::{DiffOptions, DiffStyle};
+use crate::executor::container::ContainerRuntime;
+use crate::executor::simple::FileIo;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+use crate::executor::sio2jail::TimeMetric;
+use crate::executor::ProgramEnvironment;
+use crate::toolchain::{CompileProfile, Toolchain};
+use crate::program_stderr::StderrMode;
+use crate::test_archive::TestArchive;
+use crate::test_exclusion::TestExclusion;
+use crate::test_pattern::TestPattern;
+use crate::warnings::{add_warning, WarningKind};
+use crate::output::{OutputFormat, Verbosity};
+use crate::theme::{ColorChoice, ThemeStyle};
+use crate::exit_code::ExitCodePolicy;
+use crate::report::ReportFormat;
+use crate::precompiled_header;
 
 #[allow(clippy::doc_markdown)] // Documentation comments have special meaning when deriving `clap::Parser`
+#[allow(clippy::struct_excessive_bools)] // Every flag is a separate field
 #[derive(Parser, Debug)]
 #[command(
-    name = "Toster", version, about = "A simple-as-toast tester for C++ solutions to competitive programming exercises\nReport issues on the bugtracker at https://github.com/MikolajKolek/toster/issues", long_about = None
+    name = "Toster", version, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true, about = "A simple-as-toast tester for C++ solutions to competitive programming exercises\nReport issues on the bugtracker at https://github.com/MikolajKolek/toster/issues", long_about = None
 )]
+// The user and task names are used by both --publish and --webhook
+#[command(group(ArgGroup::new("results_destination").args(["publish", "webhook"]).multiple(true)))]
 pub struct Args {
-    /// Input directory
+    /// Input directory, or a ZIP archive with the tests. Tests in subdirectories are found too, and their names include the path, like "group1/a", with the output file in the same subdirectory of the output directory. Symlinks to directories are not followed
     #[clap(short, long, value_parser, default_value = "in")]
     pub r#in: PathBuf,
 
@@ -17,67 +52,608 @@ pub struct Args {
     #[clap(long, value_parser, default_value = ".in")]
     pub in_ext: String,
 
-    /// Output directory
+    /// Output directory, or a ZIP archive with the output files. If the input is a ZIP archive, the output files are read from it by default
     #[clap(short, long, value_parser, default_value = "out")]
     pub out: PathBuf,
 
-    /// Output file extension
-    #[clap(long, value_parser, default_value = ".out")]
-    pub out_ext: String,
+    /// Output file extension. Several extensions separated by commas (like ".out,.ans,.a") are tried in order, and the first existing file is used
+    #[clap(long, value_parser, value_delimiter = ',', default_value = ".out")]
+    pub out_ext: Vec<String>,
 
-    /// The input and output directory (sets both -i and -o at once)
+    /// The name of the output file of a test, where "{name}" is replaced with the test name, like "{name}.ans" or "{name}/answer.txt". Can be provided multiple times to try several names in order. Overrides --out-ext
+    #[clap(long, value_parser)]
+    pub out_pattern: Vec<String>,
+
+    /// The input and output directory or ZIP archive (sets both -i and -o at once)
     #[clap(long, value_parser)]
     pub io: Option<PathBuf>,
 
     /// The C++ source code or executable of a checker program that verifies if the tested program's output is correct instead of comparing it with given output files
-    /// The checker must use the following protocol:
+    /// With the default --checker-protocol, the checker must use the following protocol:
     /// - The checker receives the contents of the input file and the output of the tested program on stdin, separated by a single "\n" character
     /// - The checker outputs "C" if the output is correct, or "I <OPTIONAL_DATA>" if the output is incorrect. The optional data can include any information useful for understanding why the output is wrong and will be shown when errors are displayed
+    /// - The checker can also output "V <VERDICT>" to give the test a custom verdict, such as "V protocol violation". The verdict can end with its color in brackets, like "V partial [yellow]", otherwise it's shown in magenta. The following lines can contain an optional message. Tests with custom verdicts are counted separately for each verdict
+    /// - With --feedback-report, the checker receives the path of a file as its only argument, where it can write feedback for the student
+    /// - Checkers compiled to WASI modules (.wasm files) are run in-process with the files protocol, and the test files are available in the checker as /input, /output and /answer
     #[clap(short, long, value_parser, verbatim_doc_comment)]
     pub checker: Option<PathBuf>,
 
-    /// The number of seconds after which a test or generation times out if the program does not return
+    /// The way the checker receives the test data and reports the verdict. Defaults to toster, or testlib for the checkers of Polygon packages
+    #[clap(long, value_enum)]
+    pub checker_protocol: Option<CheckerProtocol>,
+
+    /// A comparator built into toster used instead of comparing the output with the output file line by line, for tasks accepting more than one answer:
+    /// - tokens: compares whitespace-separated tokens, ignoring spaces and line breaks
+    /// - floats or floats:<EPSILON>: compares tokens, accepting numbers with an absolute or relative error up to the epsilon (1e-6 by default)
+    /// - unordered-lines: accepts the lines of the output file in any order
+    /// - yesno: compares YES and NO answers case-insensitively
+    #[clap(long, value_name = "CHECKER", value_parser = parse_builtin_checker, conflicts_with_all = ["checker", "generate"], verbatim_doc_comment)]
+    pub builtin_checker: Option<BuiltinChecker>,
+
+    /// The time after which the checker times out if it does not return, independent of the time limit of the tested program, so slow checkers of big outputs don't cause checker errors. Accepts seconds (30, 2.5s) or milliseconds (2500ms)
+    #[clap(long, value_parser = parse_duration, default_value = "30")]
+    pub checker_timeout: Duration,
+
+    /// Runs the checker in the same sandbox as the tested program (sio2jail, isolate or a container), limited by --checker-timeout and the memory limit of the sandbox, so a malicious or buggy checker can't hang or exhaust the machine. Can only be used with the toster checker protocol
+    #[clap(long, action, conflicts_with = "feedback_report")]
+    pub sandbox_checker: bool,
+
+    /// The time after which a test or generation times out if the program does not return. Accepts seconds (5, 1.5s) or milliseconds (1500ms). Defaults to 5 seconds, or the time limit from config.yml in sinol packages
     #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
-    #[clap(short, long, value_parser, default_value = "5")]
-    pub timeout: u64,
+    #[clap(short, long, visible_alias = "time-limit", value_parser = parse_duration)]
+    pub timeout: Option<Duration>,
 
-    /// The number of seconds after which a test or generation (or checker if you're using the --checker flag) times out if the program does not return. WARNING: if you're using the sio2jail flag, this timeout will still work based on time measured directly by toster, not time measured by sio2jail
+    /// The time after which a test or generation times out if the program does not return. Accepts seconds (5, 1.5s) or milliseconds (1500ms). Defaults to 5 seconds, or the time limit from config.yml in sinol packages. WARNING: if you're using the sio2jail flag, this timeout will still work based on time measured directly by toster, not time measured by sio2jail, unless a different --time-metric is chosen
     #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
-    #[clap(short, long, value_parser, default_value = "5")]
-    pub timeout: u64,
+    #[clap(short, long, visible_alias = "time-limit", value_parser = parse_duration)]
+    pub timeout: Option<Duration>,
+
+    /// Reruns tests whose runtime is within this fraction of the time limit (for example 0.1 for 10%) and uses the median runtime to decide if the test timed out, reducing false timeouts caused by system noise
+    #[clap(long, value_parser)]
+    pub rerun_band: Option<f64>,
+
+    /// Marks tests that passed, but whose runtime exceeded this fraction of the time limit (for example 0.8 for 80%), as close to the time limit
+    #[clap(long, value_parser, conflicts_with = "generate")]
+    pub warn_threshold: Option<f64>,
+
+    /// The maximum number of runs of a test within the --rerun-band, including the first run
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..), default_value = "3")]
+    pub rerun_count: u32,
 
-    /// The number of seconds after which compilation times out if it doesn't finish
-    #[clap(long, value_parser, default_value = "10")]
-    pub compile_timeout: u64,
+    /// Runs every test this many times and shows the minimum, average and maximum runtime of every test. The longest runtime is used to decide if the test timed out
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..), conflicts_with_all = ["generate", "compare"])]
+    pub runs: Option<u32>,
 
-    /// The command used to compile the file. <IN> gets replaced with the path to the source code file, <OUT> is the executable output location.
-    #[clap(long, value_parser, default_value = "g++ -std=c++20 -O3 -static <IN> -o <OUT>")]
-    pub compile_command: String,
+    /// The number of untimed runs of every test before the runs measured with --runs, so the disk cache and CPU frequency scaling don't affect the first measurement
+    #[clap(long, value_parser, default_value = "0", requires = "runs")]
+    pub warmup_runs: u32,
+
+    /// The time after which compilation times out if it doesn't finish. Accepts seconds (10, 2.5s) or milliseconds (2500ms)
+    #[clap(long, value_parser = parse_duration, default_value = "10")]
+    pub compile_timeout: Duration,
+
+    /// The command used to compile the file. <IN> gets replaced with the path to the source code file, <OUT> is the executable output location. Defaults to the command of the --compiler toolchain
+    #[clap(long, value_parser)]
+    pub compile_command: Option<String>,
+
+    /// The command used to run the program, for example "python3 <EXE>" for scripts or "taskset -c 0 <EXE>" for wrappers. <EXE> gets replaced with the path to the executable, or to the provided file if it isn't compiled. Can't be used with a sandbox or --valgrind
+    #[clap(long, value_parser)]
+    pub run_command: Option<String>,
+
+    /// The compiler toolchain used to compile the file. If not provided, the first available toolchain is used (on Linux g++, then clang++; on macOS clang++, then g++; on Windows g++, MinGW, clang++, then MSVC)
+    #[clap(long, value_enum, conflicts_with = "compile_command")]
+    pub compiler: Option<Toolchain>,
+
+    /// The preset of compile flags used with the --compiler toolchain. With the sanitizer profiles, the sanitizer report is shown for tests in which the program crashed (if no sandbox is used)
+    #[clap(long, value_enum, default_value = "release", conflicts_with = "compile_command")]
+    pub profile: CompileProfile,
+
+    /// An additional source file compiled together with the solution, or a directory whose source files are compiled with it and whose headers can be included (can be used multiple times). Used for tasks with a grader, for example: --extra-source grader.cpp --extra-source lib/
+    #[clap(long, value_parser)]
+    pub extra_source: Vec<PathBuf>,
+
+    /// Prints the warnings of the compiler if the solution compiles successfully. Executables compiled with warnings aren't stored in the compile cache, so the warnings are shown every time
+    #[clap(long, action)]
+    pub show_warnings: bool,
+
+    /// Fails compilation of the solution if the compiler gives any warnings, like -Werror
+    #[clap(long, action, conflicts_with = "show_warnings")]
+    pub deny_warnings: bool,
+
+    /// Precompiles bits/stdc++.h with the compile command and keeps it in a persistent cache, which makes compiling solutions that include it several times faster. Only supported with GCC
+    #[clap(long, action)]
+    pub precompiled_header: bool,
+
+    /// Stores compiled executables in a persistent cache and reuses them if the source code and compile command didn't change. Cached executables are signed with a per-user key and executables with an invalid signature are never used
+    #[clap(long, action)]
+    pub compile_cache: bool,
+
+    /// The directory of the compile cache (enables the --compile-cache flag). Can be shared between users, as every user only accepts executables signed with their own key
+    #[clap(long, value_parser)]
+    pub compile_cache_dir: Option<PathBuf>,
+
+    /// The maximum total size of the compile cache in bytes, optionally followed by K, M or G. The least recently used executables are removed when it's exceeded
+    #[clap(long, value_parser = parse_size, default_value = "1G")]
+    pub compile_cache_size: u64,
 
     /// Makes toster use sio2jail for measuring program runtime and memory use more accurately. By default limits memory use to 1 GiB. WARNING: enabling this flag can significantly slow down testing
     #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
     #[clap(short, long, action)]
     pub sio2jail: bool,
 
-    /// Sets a memory limit (in KiB) for the executed program and enables the sio2jail flag. WARNING: enabling this flag can significantly slow down testing
+    /// What the time limit is compared with: "real" for the wall-clock time measured by toster, "cpu" for the CPU time of the program, or "instructions" for the measured time used by oiejq and SIO2, where every 2 billion executed instructions count as one second. The "cpu" and "instructions" metrics are limited by sio2jail and enable the sio2jail flag
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    #[clap(long, value_enum, default_value = "real")]
+    pub time_metric: TimeMetric,
+
+    /// The sandbox used to run the tested program. Isolate (https://github.com/ioi/isolate) has to be installed separately and configured to be usable by the current user. The "perf-counters" sandbox doesn't isolate the program, but measures its CPU time with a perf_event_open counter and limits its address space to the memory limit
     #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    #[clap(long, value_enum, conflicts_with_all = ["sio2jail", "container"])]
+    pub sandbox: Option<Sandbox>,
+
+    /// The sandbox used to run the tested program. Only the "perf-counters" sandbox is available on this platform, which doesn't isolate the program, but measures its CPU time with a perf_event_open counter and limits its address space to the memory limit. It's also enabled by --memory-limit
+    #[cfg(all(target_os = "linux", not(target_arch = "x86_64")))]
+    #[clap(long, value_enum, conflicts_with = "container")]
+    pub sandbox: Option<Sandbox>,
+
+    /// The sandbox used to run the tested program. Only the "restricted-token" sandbox is available on this platform, which runs the program with a restricted token inside a job object limiting its memory, without a window and without access to the desktop and clipboard. It's a best-effort sandbox, the program can still read the user's files and access the network. It's also enabled by --memory-limit
+    #[cfg(windows)]
+    #[clap(long, value_enum, conflicts_with = "container")]
+    pub sandbox: Option<Sandbox>,
+
+    /// The sandbox used to run the tested program. Only the "sandbox-exec" sandbox is available on this platform, which runs the program in a sandbox-exec profile denying it access to the network and writes outside the temporary directory. It's also enabled by --no-network
+    #[cfg(target_os = "macos")]
+    #[clap(long, value_enum, conflicts_with = "container")]
+    pub sandbox: Option<Sandbox>,
+
+    /// Runs the tested program in a new container for every test using the given container runtime, with no network access and limited to one CPU. The measured runtime includes the time needed to start the container
+    #[clap(long, value_enum)]
+    pub container: Option<ContainerRuntime>,
+
+    /// The image used for the containers created with --container. The image has to be able to run the compiled executable
+    #[clap(long, value_parser, default_value = "debian:stable-slim", requires = "container")]
+    pub container_image: String,
+
+    /// Sets a memory limit (in KiB) for the executed program and enables the sio2jail flag (the perf-counters sandbox on ARM Linux and the restricted-token sandbox on Windows, where sio2jail isn't available, and on macOS the peak memory use is checked after the program exits), unless a different sandbox is chosen with --sandbox or --container. WARNING: enabling this flag can significantly slow down testing
     #[clap(short, long, value_parser)]
     pub memory_limit: Option<u64>,
 
+    /// Prevents the tested program from accessing the network. On Linux, the program is run in a new network namespace, which requires unprivileged user namespaces. On macOS, it's run with the sandbox-exec sandbox. Isolate and containers always run the program without network access
+    #[clap(long, action)]
+    pub no_network: bool,
+
+    /// The maximum size of the program's output in bytes, optionally followed by K, M or G. Programs exceeding it are stopped, which protects against filling the disk or memory with runaway output
+    #[clap(long, value_parser = parse_size, default_value = "256M")]
+    pub output_limit: u64,
+
+    /// Delivers the input to the program at this many bytes per second (optionally followed by K, M or G) instead of all at once, simulating a slow interactive judge. Reveals programs that incorrectly assume the whole input is available immediately
+    #[clap(long, value_parser = parse_size)]
+    pub input_rate: Option<u64>,
+
+    /// Makes the program read its input from a file and write its output to a file instead of stdin and stdout, for tasks like "read task.in and write task.out". Every test is run in a separate working directory with the input saved under the first name, and the file with the second name is used as the output. Can't be used with a sandbox or --valgrind
+    #[clap(long, value_name = "IN_NAME:OUT_NAME", value_parser = parse_file_io, conflicts_with = "input_rate")]
+    pub file_io: Option<FileIo>,
+
+    /// Runs every test in its own empty working directory, which is removed after the test, so programs creating files can't interfere with each other when tests are run in parallel. Can't be used with a sandbox or --valgrind
+    #[clap(long, action)]
+    pub scratch_dir: bool,
+
+    /// An environment variable set for the tested program, in the NAME=VALUE format (can be used multiple times)
+    #[clap(long, value_name = "NAME=VALUE", value_parser = parse_env_variable)]
+    pub env: Vec<(String, String)>,
+
+    /// Runs the tested program with only the variables set with --env instead of the environment of toster. Programs run with isolate or in a container always start from an empty environment or the environment of the image
+    #[clap(long, action)]
+    pub clear_env: bool,
+
+    /// A command that receives each test input on stdin and whose output is fed to the tested program instead of the original input (for example to strip a header line used only by the checker). The checker still receives the original input
+    #[clap(long, value_parser)]
+    pub input_transform: Option<String>,
+
+    /// The C++ source code or executable of a validator that checks every test input before the program is run on it, for example a testlib validator. The validator receives the input on stdin and returns a non-zero exit code if it's invalid, optionally describing the problem on stderr. Tests with invalid inputs are reported separately and the program isn't run on them
+    #[clap(long, value_parser)]
+    pub validator: Option<PathBuf>,
+
+    /// The path to a TOML manifest file with per-task configuration. If not provided, toster uses the toster.toml file in the current directory if it exists
+    #[clap(long, value_parser)]
+    pub manifest: Option<PathBuf>,
+
+    /// An additional argument passed to sio2jail before the executed program (can be used multiple times, enables the sio2jail flag). Arguments that toster sets by default, such as --net-namespace, are replaced by the ones provided. For example: --sio2jail-arg=--net-namespace --sio2jail-arg=on
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    #[clap(long, value_parser, allow_hyphen_values = true)]
+    pub sio2jail_arg: Vec<String>,
+
+    /// The way the difference between the expected output and the program's output is shown for wrong answers
+    #[clap(long, visible_alias = "diff", value_enum, default_value = "table")]
+    pub diff_style: DiffStyle,
+
+    /// The maximum number of characters of a line shown in the table and unified diffs. Longer lines are shortened to the part around the first difference, with a note of their full length
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..), default_value = "200")]
+    pub max_line_length: u32,
+
+    /// The maximum number of differing lines shown in the diff of a wrong answer
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..), default_value = "99")]
+    pub diff_max_lines: u32,
+
+    /// The number of unchanged lines shown around the differing lines in the diff of a wrong answer. Defaults to 3 for the unified diff and 0 for the other styles, the JSON diff never contains them
+    #[clap(long, value_name = "LINES", value_parser)]
+    pub diff_context: Option<u32>,
+
+    /// A directory where the output of the program is saved for every test, together with the differences from the output files for wrong answers
+    #[clap(long, value_parser, conflicts_with = "generate")]
+    pub save_outputs: Option<PathBuf>,
+
+    /// Compresses the files saved with --save-outputs with zstd into a single archive with an index, instead of saving them as separate files
+    #[clap(long, action, requires = "save_outputs")]
+    pub compress_saved: bool,
+
+    /// Another solution compared with the main one (can be used multiple times). Every solution is run on all tests, and a table with the verdict and runtime of every solution on every test is shown
+    #[clap(long, value_parser, conflicts_with_all = ["generate", "publish", "save_outputs"])]
+    pub compare: Vec<PathBuf>,
+
+    /// The run the results are compared with: "last" for the previous run, or a run file from the .toster/history directory. New failures, fixed tests and tests that got slower are shown after the results
+    #[clap(long, value_parser = parse_history_reference, conflicts_with_all = ["generate", "compare"])]
+    pub compare_with: Option<HistoryReference>,
+
+    /// The fraction by which the runtime of a test has to grow to be shown as slower by --compare-with (for example 0.2 for 20%)
+    #[clap(long, value_parser, default_value = "0.2", requires = "compare_with")]
+    pub slower_threshold: f64,
+
+    /// Doesn't save the verdicts and runtimes of this run in the .toster/history directory. Every run is saved by default, so later runs can be compared with it using --compare-with
+    #[clap(long, action)]
+    pub no_history: bool,
+
+    /// Profiles tests with perf after testing: "slowest" for the 5 slowest passed tests, "failing" for the first 5 failed tests, or a pattern like "big*" for the matching tests. The "perf stat" counters and the "perf record" data of every test are saved in the perf directory, and can be viewed with "perf report -i". The program is run without a sandbox and can run twice as long as the time limit. Requires perf, which is only available on Linux
+    #[clap(long, value_name = "TESTS", value_parser = parse_perf_selection, conflicts_with = "compare")]
+    pub profile_perf: Option<PerfSelection>,
+
+    /// The directory the data saved by --profile-perf is written to
+    #[clap(long, value_parser, default_value = ".toster/perf", requires = "profile_perf")]
+    pub perf_dir: PathBuf,
+
+    /// A file where the feedback for the student written by the checker is saved. The checker is given the path of a file as its only argument, where it can write feedback for the test (for example hints that don't reveal the answer). The feedback from all tests is collected into a report separate from the test results
+    #[clap(long, value_parser, conflicts_with_all = ["generate", "compare"])]
+    pub feedback_report: Option<PathBuf>,
+
+    /// Prints a report of the results after testing, which can be pasted into a pull request or a chat
+    #[clap(long, value_enum, conflicts_with_all = ["generate", "compare"])]
+    pub report: Option<ReportFormat>,
+
+    /// A file where the report is saved instead of being printed
+    #[clap(long, value_parser, requires = "report")]
+    pub report_file: Option<PathBuf>,
+
+    /// The CPUs the tested programs run on, for example 2,3 or 0-3 (Linux only). One test at a time is run on each CPU. If not provided, the CPUs isolated with the isolcpus or nohz_full kernel parameters are used if there are any
+    #[clap(long, value_parser)]
+    pub cpus: Option<String>,
+
+    /// Pins every testing thread, and the tested program it runs, to its own CPU (Linux only), which makes the measured runtimes more stable. The isolated CPUs are used if there are any, otherwise all online CPUs
+    #[clap(long, action, conflicts_with = "cpus")]
+    pub pin_cpus: bool,
+
+    /// Runs one test at a time, so the tests don't compete for the CPU, memory bandwidth and caches. Testing takes longer, but the measured runtimes are more trustworthy
+    #[clap(long, action, conflicts_with = "jobs")]
+    pub sequential: bool,
+
+    /// Raises the scheduling priority of the tested programs (Linux only, requires root or the CAP_SYS_NICE capability), so other processes interrupt them less often
+    #[clap(long, action, requires = "sequential")]
+    pub high_priority: bool,
+
+    /// Prints a line with the verdict and the runtime of every test as soon as it finishes, so failures are visible before testing ends
+    #[clap(long, action)]
+    pub live: bool,
+
+    /// Opens an interactive browser of the results after testing, with a list of the tests that can be filtered by verdict and the details of the selected test, instead of printing the errors of all tests. Only used if the output is a terminal
+    #[clap(long, action, conflicts_with = "compare")]
+    pub tui: bool,
+
+    /// Only prints the summary of the results. Use the exit code to find out whether all tests passed
+    #[clap(short, long, action, conflicts_with_all = ["verbose", "tui", "live"])]
+    pub quiet: bool,
+
+    /// Shows a desktop notification with the results when testing finishes, so other work can be done during a long run. The terminal bell is rung instead if notifications can't be shown (notify-send is used on Linux and osascript on macOS)
+    #[clap(long, action)]
+    pub notify: bool,
+
+    /// The format of the results
+    #[clap(long, value_enum, default_value = "human", conflicts_with_all = ["verbose", "tui", "live", "quiet", "compare"])]
+    pub format: OutputFormat,
+
+    /// Prints additional details, like the compile command and the temporary directory. Use -vv to also print every command executed while testing
+    #[clap(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// When colors are used in the output. The colors of verdicts can be changed in the theme section of the manifest
+    #[clap(long, value_enum, default_value = "auto")]
+    pub color: ColorChoice,
+
+    /// How the exit code of toster is chosen. With every policy, the exit code is 130 if toster was stopped with Ctrl+C
+    #[clap(long, value_enum, default_value = "detailed")]
+    pub exit_code_policy: ExitCodePolicy,
+
+    /// Skips the tests with the given names, separated by commas, for example "1a,2b". `*` matches any sequence of characters and `?` any single character. Tests depending on a skipped test are skipped too
+    #[clap(long, value_parser, value_delimiter = ',')]
+    pub skip: Vec<String>,
+
+    /// Skips the tests given the tag in the manifest (can be used multiple times or with tags separated by commas), for example known-bad or slow tests. Tests depending on a skipped test are skipped too
+    #[clap(long, value_parser, value_delimiter = ',')]
+    pub skip_tag: Vec<String>,
+
+    /// Stops starting new tests after this many tests failed, so a completely broken solution doesn't have to be run on all tests. The tests that are already running are finished
+    #[clap(long, value_parser)]
+    pub max_failures: Option<usize>,
+
+    /// The time after which no new tests are started, for example when toster runs in a CI job with a hard time limit. The tests that are already running are finished, and the tests that weren't started are reported as skipped. Accepts seconds (600, 600s), milliseconds (1500ms) or minutes (10m)
+    #[clap(long, value_parser = parse_duration, conflicts_with = "compare")]
+    pub total_timeout: Option<Duration>,
+
+    /// How long creating a temporary file waits for other tests to close their files when the limit of open files is reached, before testing is stopped with an error. Accepts seconds (10, 2.5s) or milliseconds (2500ms)
+    #[clap(long, value_parser = parse_duration, default_value = "10")]
+    pub temp_file_timeout: Duration,
+
+    /// Reruns a test up to this many times if the program timed out or crashed, which on a busy machine can be caused by other processes. The result of the last run is used and the outcomes of all runs of rerun tests are printed after the results
+    #[clap(long, value_parser, conflicts_with = "generate")]
+    pub retries: Option<usize>,
+
+    /// Runs every test twice and reports the tests in which the program wrote different outputs, which catches bugs like reading uninitialized memory or iterating over an unordered container that make a solution pass only sometimes. Tests which failed in the first run aren't run again
+    #[clap(long, action)]
+    pub check_determinism: bool,
+
+    /// How long a program that timed out can take to exit after receiving SIGTERM before it's killed, which lets sanitizers and profilers write their reports. Accepts seconds (1, 0.5s) or milliseconds (500ms). By default timed out programs are killed immediately. Not used on Windows
+    #[clap(long, value_parser = parse_duration, default_value = "0")]
+    pub kill_grace: Duration,
+
+    /// Runs the tested program under valgrind's memcheck and reports invalid memory accesses, uses of uninitialized values and other errors it finds as memory errors. Valgrind has to be installed separately
+    #[clap(long, action, conflicts_with_all = ["container", "memory_limit", "no_network"])]
+    pub valgrind: bool,
+
+    /// How many times longer the timeout is for programs run under valgrind, which makes them much slower
+    #[clap(long, value_parser, default_value = "10", requires = "valgrind")]
+    pub valgrind_timeout_factor: f64,
+
+    /// When the end of the tested program's stderr is shown. Only the stderr of programs run without a sandbox is captured
+    #[clap(long, value_enum, default_value = "on-failure")]
+    pub stderr: StderrMode,
+
+    /// The maximum number of tests run in parallel. If not provided, one test is run on every CPU. Running fewer tests at once makes the measured runtimes more stable on shared machines
+    #[clap(short, long, value_parser)]
+    pub jobs: Option<usize>,
+
+    /// What toster does if the system is busy (other processes use the CPU, memory is swapped or other toster instances are running) before testing starts. Checking the system takes 300ms, so it's off by default. Not used with --generate
+    #[clap(long, value_enum, default_value = "off")]
+    pub noise_guard: NoiseGuardMode,
+
+    /// The URL of a results server. If provided, a signed summary of the test results is sent there after testing finishes
+    #[clap(long, value_parser, requires = "token", conflicts_with = "generate")]
+    pub publish: Option<String>,
+
+    /// The token used to authenticate with and sign the results sent to the --publish server
+    #[clap(long, value_parser, requires = "publish")]
+    pub token: Option<String>,
+
+    /// A URL the JSON summary of the test results is sent to in a POST request after testing finishes, for example to forward the results to a chat or a dashboard. Unlike with --publish, the summary isn't signed
+    #[clap(long, value_parser, conflicts_with = "generate")]
+    pub webhook: Option<String>,
+
+    /// The user name sent with the published results. If not provided, the name of the current system user is used
+    #[clap(long, value_parser, requires = "results_destination")]
+    pub user: Option<String>,
+
+    /// The task name sent with the published results. If not provided, the name of the current directory is used
+    #[clap(long, value_parser, requires = "results_destination")]
+    pub task: Option<String>,
+
     /// Makes toster generate output files in the output directory instead of comparing the program's output with the files in the output directory
     #[clap(short, long, action)]
     pub generate: bool,
 
+    /// Prints the tests that were found with their output files and limits, and the commands used to compile and run the program, without compiling or running anything
+    #[clap(long, action)]
+    pub dry_run: bool,
+
     /// The name of the file containing the source code or the executable you want to test
-    #[clap(value_parser)]
-    pub filename: PathBuf,
+    #[clap(value_parser, required_unless_present = "exe")]
+    pub filename: Option<PathBuf>,
+
+    /// An already built executable tested instead of a source file, for example one built by CMake with custom flags. It's never compiled, even if its name looks like a source file
+    #[clap(long, value_parser, conflicts_with = "filename")]
+    pub exe: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[cfg(any(target_os = "linux", windows, target_os = "macos"))]
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Sandbox {
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    Sio2jail,
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    Isolate,
+    #[cfg(target_os = "linux")]
+    PerfCounters,
+    #[cfg(windows)]
+    RestrictedToken,
+    #[cfg(target_os = "macos")]
+    SandboxExec,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Downloads sio2jail, verifies its checksum and installs it in the user's executable directory
+    InstallSio2jail {
+        /// The URL to download sio2jail from
+        #[clap(long, value_parser, requires = "sha256")]
+        url: Option<String>,
+
+        /// The expected SHA-256 checksum of the file at --url
+        #[clap(long, value_parser, requires = "url")]
+        sha256: Option<String>,
+    },
+    /// Checks the environment for problems that would prevent toster from working and prints how to fix them
+    Doctor {
+        /// The compile command to check. <IN> gets replaced with the path to the source code file, <OUT> is the executable output location. Defaults to the command of the --compiler toolchain
+        #[clap(long, value_parser)]
+        compile_command: Option<String>,
+
+        /// The compiler toolchain to check. If not provided, the first available toolchain is checked
+        #[clap(long, value_enum, conflicts_with = "compile_command")]
+        compiler: Option<Toolchain>,
+    },
+    /// Prints statistics of the tests without running a solution: the number of tests in each group (tests "abc1a" and "abc1b" belong to group "abc1"), input sizes, the largest tests and the limits declared in the manifest
+    Stats {
+        /// Input directory
+        #[clap(short, long, value_parser, default_value = "in")]
+        r#in: PathBuf,
+
+        /// Input file extension
+        #[clap(long, value_parser, default_value = ".in")]
+        in_ext: String,
+
+        /// Output directory
+        #[clap(short, long, value_parser, default_value = "out")]
+        out: PathBuf,
+
+        /// Output file extension. Several extensions separated by commas (like ".out,.ans,.a") are tried in order, and the first existing file is used
+        #[clap(long, value_parser, value_delimiter = ',', default_value = ".out")]
+        out_ext: Vec<String>,
+
+        /// The name of the output file of a test, where "{name}" is replaced with the test name, like "{name}.ans" or "{name}/answer.txt". Can be provided multiple times to try several names in order. Overrides --out-ext
+        #[clap(long, value_parser)]
+        out_pattern: Vec<String>,
+
+        /// The input and output directory (sets both -i and -o at once)
+        #[clap(long, value_parser)]
+        io: Option<PathBuf>,
+
+        /// The path to a TOML manifest file. If not provided, toster uses the toster.toml file in the current directory if it exists
+        #[clap(long, value_parser)]
+        manifest: Option<PathBuf>,
+    },
+    /// Tests the solutions of all tasks in the subdirectories of a directory and prints a summary of every task. A subdirectory is a task if it contains a toster.toml manifest or an "in" directory. The solution is set with the "solution" key in the manifest, otherwise the only C++ file in the task directory is used
+    Batch {
+        /// The directory containing the task directories
+        #[clap(value_parser)]
+        directory: PathBuf,
+
+        /// Options used for testing every task, as for testing a single solution. Paths are relative to the task directory
+        #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<OsString>,
+    },
+    /// Removes the executables stored in the compile cache
+    Clean {
+        /// The directory of the compile cache. If not provided, the default directory is used
+        #[clap(long, value_parser)]
+        compile_cache_dir: Option<PathBuf>,
+
+        /// Only removes the least recently used executables until the cache is at most this size in bytes, optionally followed by K, M or G
+        #[clap(long, value_parser = parse_size)]
+        max_size: Option<u64>,
+    },
+    /// Compiles the solution and runs it once, with the input typed in the terminal or read from --input, and prints its output, runtime and memory usage. Useful for quick experiments without creating test files
+    Run {
+        /// The file the input is read from. If not provided, the input is read from the terminal
+        #[clap(short, long, value_parser)]
+        input: Option<PathBuf>,
+
+        /// The time after which compilation times out if it doesn't finish. Accepts seconds (10, 2.5s) or milliseconds (2500ms)
+        #[clap(long, value_parser = parse_duration, default_value = "10")]
+        compile_timeout: Duration,
+
+        /// The command used to compile the file. <IN> gets replaced with the path to the source code file, <OUT> is the executable output location. Defaults to the command of the --compiler toolchain
+        #[clap(long, value_parser)]
+        compile_command: Option<String>,
+
+        /// The compiler toolchain used to compile the file. If not provided, the first available toolchain is used
+        #[clap(long, value_enum, conflicts_with = "compile_command")]
+        compiler: Option<Toolchain>,
+
+        /// The preset of compile flags used with the --compiler toolchain
+        #[clap(long, value_enum, default_value = "release", conflicts_with = "compile_command")]
+        profile: CompileProfile,
+
+        /// An additional source file compiled together with the solution, or a directory whose source files are compiled with it and whose headers can be included (can be used multiple times)
+        #[clap(long, value_parser)]
+        extra_source: Vec<PathBuf>,
+
+        /// The name of the file containing the source code or the executable you want to run
+        #[clap(value_parser)]
+        filename: PathBuf,
+    },
+    /// Compiles the solution without running any tests and saves the executable, printing the compiler's errors and warnings and the compilation time
+    Build {
+        /// The time after which compilation times out if it doesn't finish. Accepts seconds (10, 2.5s) or milliseconds (2500ms)
+        #[clap(long, value_parser = parse_duration, default_value = "10")]
+        compile_timeout: Duration,
+
+        /// The command used to compile the file. <IN> gets replaced with the path to the source code file, <OUT> is the executable output location. Defaults to the command of the --compiler toolchain
+        #[clap(long, value_parser)]
+        compile_command: Option<String>,
+
+        /// The compiler toolchain used to compile the file. If not provided, the first available toolchain is used
+        #[clap(long, value_enum, conflicts_with = "compile_command")]
+        compiler: Option<Toolchain>,
+
+        /// The preset of compile flags used with the --compiler toolchain
+        #[clap(long, value_enum, default_value = "release", conflicts_with = "compile_command")]
+        profile: CompileProfile,
+
+        /// An additional source file compiled together with the solution, or a directory whose source files are compiled with it and whose headers can be included (can be used multiple times)
+        #[clap(long, value_parser)]
+        extra_source: Vec<PathBuf>,
+
+        /// Stores the executable in the persistent compile cache, or takes it from the cache if the source code and compile command didn't change
+        #[clap(long, action)]
+        compile_cache: bool,
+
+        /// The directory of the compile cache (enables the --compile-cache flag)
+        #[clap(long, value_parser)]
+        compile_cache_dir: Option<PathBuf>,
+
+        /// The maximum total size of the compile cache in bytes, optionally followed by K, M or G
+        #[clap(long, value_parser = parse_size, default_value = "1G")]
+        compile_cache_size: u64,
+
+        /// The path the executable is saved to. Defaults to the path of the source file without its extension
+        #[clap(short, long, value_parser)]
+        output: Option<PathBuf>,
+
+        /// The name of the file containing the source code
+        #[clap(value_parser)]
+        filename: PathBuf,
+    },
+    /// Runs a trusted solution on all inputs and writes its outputs to the output directory. Accepts the same options as testing and is equivalent to the --generate flag
+    GenOut {
+        /// The options and the solution to run, as for testing
+        #[clap(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+        args: Vec<OsString>,
+    },
+    /// Runs two versions of a solution (source files or executables) on the same tests and prints the verdict and runtime of both versions on every test, with the speedup of the new version. Accepts the same options as testing
+    Compare {
+        /// The old version of the solution
+        #[clap(value_parser)]
+        old: PathBuf,
+
+        /// The new version of the solution
+        #[clap(value_parser)]
+        new: PathBuf,
+
+        /// Options used for testing both versions, as for testing a single solution
+        #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<OsString>,
+    },
 }
 
 pub(crate) enum InputConfig {
     Directory {
         directory: PathBuf,
         ext: String,
-    }
+    },
+    /// Tests read from a ZIP archive without extracting it
+    Archive {
+        archive: PathBuf,
+        ext: String,
+    },
 }
 
 pub(crate) enum ExecuteMode {
@@ -85,34 +661,246 @@ pub(crate) enum ExecuteMode {
     #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
     Sio2jail {
         memory_limit: u64,
+        extra_args: Vec<String>,
+        time_metric: TimeMetric,
     },
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    Isolate {
+        memory_limit: Option<u64>,
+    },
+    /// Runs the program without isolating it, measuring its CPU time with a performance counter
+    #[cfg(target_os = "linux")]
+    PerfCounters {
+        memory_limit: Option<u64>,
+    },
+    /// Runs the program with a restricted token inside a job object, which limits its memory
+    #[cfg(windows)]
+    RestrictedToken {
+        memory_limit: Option<u64>,
+    },
+    /// Runs the program directly, measuring its peak memory use, optionally in a sandbox-exec profile
+    #[cfg(target_os = "macos")]
+    Rusage {
+        memory_limit: Option<u64>,
+        sandbox_exec: bool,
+    },
+    Container {
+        runtime: ContainerRuntime,
+        image: String,
+        memory_limit: Option<u64>,
+    },
+    /// Runs the program under valgrind's memcheck, without a sandbox
+    Valgrind {
+        /// The factor the timeout is multiplied by
+        timeout_factor: f64,
+    },
+}
+
+impl ExecuteMode {
+    /// Returns true if the program isn't run in a sandbox, so memory limits can't be enforced
+    pub(crate) fn is_unsandboxed(&self) -> bool {
+        matches!(self, Simple | ExecuteMode::Valgrind { .. })
+    }
 }
 
 pub(crate) enum ActionType {
     Generate {
         output_directory: PathBuf,
-        output_ext: String,
+        output_names: OutputNames,
     },
     SimpleCompare {
         output_directory: PathBuf,
-        output_ext: String,
+        output_names: OutputNames,
     },
     Checker {
         path: PathBuf,
+        protocol: CheckerProtocol,
+        /// Used for tests which are compared with the output file instead of being checked
+        output_directory: PathBuf,
+        output_names: OutputNames,
     },
 }
 
+pub(crate) struct MedianRerunConfig {
+    pub(crate) band: f64,
+    pub(crate) max_runs: usize,
+}
+
+pub(crate) struct PublishConfig {
+    pub(crate) url: String,
+    pub(crate) token: String,
+    pub(crate) user: String,
+    pub(crate) task: String,
+}
+
+/// The webhook the unsigned results are sent to.
+/// The user and task names are optional, since the webhook doesn't authenticate anyone
+pub(crate) struct WebhookConfig {
+    pub(crate) url: String,
+    pub(crate) user: Option<String>,
+    pub(crate) task: Option<String>,
+}
+
+#[allow(clippy::struct_excessive_bools)] // Every flag is a separate field
 pub(crate) struct ParsedConfig {
     pub(crate) source_path: PathBuf,
+    /// The source path is an executable built outside of toster, which isn't compiled
+    pub(crate) prebuilt: bool,
     pub(crate) compile_command: String,
+    /// Compiled together with the solution and the compared solutions, but not with checkers
+    pub(crate) extra_sources: Vec<PathBuf>,
+    pub(crate) precompiled_header: bool,
+    pub(crate) show_warnings: bool,
+    pub(crate) deny_warnings: bool,
+    pub(crate) run_command: Option<String>,
+    pub(crate) file_io: Option<FileIo>,
+    pub(crate) scratch_directory: bool,
+    pub(crate) environment: ProgramEnvironment,
+    pub(crate) stderr: StderrMode,
+    /// Makes the executor show the sanitizer report of programs that crashed
+    pub(crate) sanitizer_report: bool,
     pub(crate) compile_timeout: Duration,
+    pub(crate) compile_cache_directory: Option<PathBuf>,
+    pub(crate) compile_cache_size: u64,
     pub(crate) execute_timeout: Duration,
+    pub(crate) checker_timeout: Duration,
+    /// Runs the checkers with the sandbox of the tested program
+    pub(crate) sandbox_checker: bool,
+    pub(crate) output_limit: u64,
+    /// The number of input bytes delivered to the program per second
+    pub(crate) input_rate: Option<u64>,
+    pub(crate) median_rerun: Option<MedianRerunConfig>,
+    /// The fraction of the time limit above which passed tests are marked as close to the time limit
+    pub(crate) warn_threshold: Option<f64>,
+    /// The number of times every test is run
+    pub(crate) runs: Option<usize>,
+    pub(crate) warmup_runs: usize,
     pub(crate) input: InputConfig,
+    /// The ZIP archive the expected outputs are read from instead of the output directory
+    pub(crate) output_archive: Option<PathBuf>,
     pub(crate) execute_mode: ExecuteMode,
     pub(crate) action_type: ActionType,
+    pub(crate) input_transform: Option<String>,
+    pub(crate) validator: Option<PathBuf>,
+    pub(crate) limit_overrides: Vec<LimitOverride>,
+    /// Groups of tests scored together, the score is shown only if there are any
+    pub(crate) groups: Vec<ScoredGroup>,
+    /// The verdicts expected for tests matching a pattern, mismatches are reported after testing
+    pub(crate) expected_verdicts: Vec<ExpectedVerdict>,
+    pub(crate) dependencies: Vec<TestDependency>,
+    /// The tests skipped with --skip and --skip-tag
+    pub(crate) exclusion: TestExclusion,
+    pub(crate) checker_overrides: Vec<CheckerOverride>,
+    pub(crate) diff_options: DiffOptions,
+    pub(crate) builtin_checker: Option<BuiltinChecker>,
+    pub(crate) no_network: bool,
+    pub(crate) noise_guard: NoiseGuardMode,
+    pub(crate) cpus: Option<Vec<usize>>,
+    pub(crate) pin_cpus: bool,
+    pub(crate) jobs: Option<usize>,
+    pub(crate) high_priority: bool,
+    pub(crate) live: bool,
+    pub(crate) tui: bool,
+    pub(crate) verbosity: Verbosity,
+    pub(crate) notify: bool,
+    /// Prints the tests and commands instead of testing
+    pub(crate) dry_run: bool,
+    pub(crate) theme: BTreeMap<String, ThemeStyle>,
+    pub(crate) max_failures: Option<usize>,
+    /// The time after which the remaining tests are skipped
+    pub(crate) total_timeout: Option<Duration>,
+    pub(crate) temp_file_timeout: Duration,
+    pub(crate) retries: Option<usize>,
+    /// Runs every test twice and compares the outputs
+    pub(crate) check_determinism: bool,
+    pub(crate) kill_grace: Duration,
+    pub(crate) save_outputs: Option<PathBuf>,
+    pub(crate) compress_saved: bool,
+    pub(crate) compare: Vec<PathBuf>,
+    pub(crate) compare_with: Option<HistoryReference>,
+    /// The fraction of the previous runtime a test has to be slower by to be shown
+    pub(crate) slower_threshold: f64,
+    /// Saves the results in the run history
+    pub(crate) save_history: bool,
+    /// The tests profiled with perf after testing
+    pub(crate) profile_perf: Option<PerfSelection>,
+    pub(crate) perf_dir: PathBuf,
+    pub(crate) feedback_report: Option<PathBuf>,
+    pub(crate) report: Option<ReportFormat>,
+    pub(crate) report_file: Option<PathBuf>,
+    pub(crate) publish: Option<PublishConfig>,
+    pub(crate) webhook: Option<WebhookConfig>,
+    /// The directory of the detected sinol package
+    pub(crate) sinol_package: Option<PathBuf>,
+    /// The directory of the detected Polygon package
+    pub(crate) polygon_package: Option<PathBuf>,
 }
 
-fn verify_compile_command(command: &str) -> Result<(), String> {
+/// Parses a duration given in seconds (`5`, `1.5s`) or milliseconds (`1500ms`)
+pub(crate) fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, multiplier) = if let Some(number) = value.strip_suffix("ms") {
+        (number, 0.001)
+    } else if let Some(number) = value.strip_suffix('s') {
+        (number, 1.0)
+    } else if let Some(number) = value.strip_suffix('m') {
+        (number, 60.0)
+    } else {
+        (value, 1.0)
+    };
+
+    let number = number.trim().parse::<f64>()
+        .map_err(|_| format!("\"{value}\" is not a valid duration, use a format like 5, 1.5s, 1500ms or 10m"))?;
+    Duration::try_from_secs_f64(number * multiplier)
+        .map_err(|_| format!("\"{value}\" is not a valid duration, it must be a non-negative number"))
+}
+
+/// Parses a variable set for the tested program, like `NAME=VALUE`. The value can be empty
+fn parse_env_variable(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_owned(), value.to_owned())),
+        _ => Err(format!("\"{value}\" is not a valid environment variable, use a format like NAME=VALUE")),
+    }
+}
+
+/// Parses the names of the files the program reads its input from and writes its output to, like `task.in:task.out`
+fn parse_file_io(value: &str) -> Result<FileIo, String> {
+    let Some((input_name, output_name)) = value.split_once(':') else {
+        return Err(format!("\"{value}\" is not a valid pair of file names, use a format like task.in:task.out"));
+    };
+    for name in [input_name, output_name] {
+        if name.is_empty() || name.contains(['/', '\\']) {
+            return Err(format!("\"{name}\" is not a valid file name"));
+        }
+    }
+    if input_name == output_name {
+        return Err("The input and output files must have different names".to_owned());
+    }
+    Ok(FileIo { input_name: input_name.to_owned(), output_name: output_name.to_owned() })
+}
+
+/// Parses a size in bytes, optionally followed by a K, M or G suffix (`1024`, `64K`, `256M`)
+pub(crate) fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (number, multiplier) = match value.char_indices().last() {
+        Some((index, 'K' | 'k')) => (&value[..index], 1 << 10),
+        Some((index, 'M' | 'm')) => (&value[..index], 1 << 20),
+        Some((index, 'G' | 'g')) => (&value[..index], 1 << 30),
+        _ => (value, 1),
+    };
+    number.trim().parse::<u64>().ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .ok_or_else(|| format!("\"{value}\" is not a valid size, use a format like 1024, 64K or 256M"))
+}
+
+pub(crate) fn verify_extra_sources(extra_sources: &[PathBuf]) -> Result<(), String> {
+    match extra_sources.iter().find(|path| !path.exists()) {
+        Some(path) => Err(format!("The extra source {} does not exist", path.display())),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn verify_compile_command(command: &str) -> Result<(), String> {
     let message = format!(
         "The compile command is invalid:\n{}\nRead \"toster -h\" for more info",
         match (command.contains("<IN>"), command.contains("<OUT>")) {
@@ -125,84 +913,536 @@ fn verify_compile_command(command: &str) -> Result<(), String> {
     Err(message)
 }
 
+fn verify_run_command(command: &str, execute_mode: &ExecuteMode) -> Result<(), String> {
+    let run_directly = match execute_mode {
+        Simple => true,
+        #[cfg(target_os = "linux")]
+        ExecuteMode::PerfCounters { .. } => true,
+        #[cfg(windows)]
+        ExecuteMode::RestrictedToken { .. } => true,
+        #[cfg(target_os = "macos")]
+        ExecuteMode::Rusage { .. } => true,
+        _ => false,
+    };
+    if !run_directly {
+        return Err("The --run-command option can't be used with a sandbox or --valgrind".to_owned());
+    }
+    if !command.contains("<EXE>") {
+        return Err("The run command is invalid:\nThe <EXE> argument is missing\nRead \"toster -h\" for more info".to_owned());
+    }
+    Ok(())
+}
+
+fn parse_action_type(
+    generate: bool,
+    checker: Option<(PathBuf, CheckerProtocol)>,
+    output_directory: PathBuf,
+    output_names: OutputNames,
+) -> Result<ActionType, String> {
+    Ok(match (generate, checker) {
+        (true, Some(_)) => {
+            return Err("You can't have the --generate and --checker flags on at the same time".to_owned());
+        }
+        (true, None) => {
+            if output_directory.exists() && !output_directory.is_dir() {
+                return Err("The output path is not a directory".to_owned());
+            }
+            ActionType::Generate {
+                output_directory,
+                output_names,
+            }
+        }
+        (false, None) => {
+            if !output_directory.is_dir() && !TestArchive::is_archive(&output_directory) {
+                return Err("The output directory does not exist".to_owned());
+            }
+            ActionType::SimpleCompare {
+                output_directory,
+                output_names,
+            }
+        }
+        (false, Some((checker_path, protocol))) => {
+            if !checker_path.is_file() {
+                return Err("The provided checker file does not exist".to_owned());
+            }
+            ActionType::Checker {
+                path: checker_path,
+                protocol,
+                output_directory,
+                output_names,
+            }
+        }
+    })
+}
+
+fn parse_container_mode(args: &Args, sio2jail_args: &[String], default_memory_limit: Option<u64>) -> Result<Option<ExecuteMode>, String> {
+    let Some(runtime) = args.container else {
+        return Ok(None);
+    };
+    if !sio2jail_args.is_empty() {
+        return Err("Sio2jail arguments can't be used together with the --container option".to_owned());
+    }
+    Ok(Some(ExecuteMode::Container {
+        runtime,
+        image: args.container_image.clone(),
+        memory_limit: args.memory_limit.or(default_memory_limit),
+    }))
+}
+
+fn parse_valgrind_mode(args: &Args, sio2jail_args: &[String]) -> Result<Option<ExecuteMode>, String> {
+    if !args.valgrind {
+        return Ok(None);
+    }
+    if !sio2jail_args.is_empty() {
+        return Err("Sio2jail arguments can't be used together with the --valgrind flag".to_owned());
+    }
+    if !(args.valgrind_timeout_factor >= 1.0 && args.valgrind_timeout_factor.is_finite()) {
+        return Err("The valgrind timeout factor must be a finite number of at least 1".to_owned());
+    }
+    Ok(Some(ExecuteMode::Valgrind { timeout_factor: args.valgrind_timeout_factor }))
+}
+
+#[cfg(target_os = "linux")]
+fn parse_perf_counters_mode(args: &Args, default_memory_limit: Option<u64>) -> Result<ExecuteMode, String> {
+    if args.no_network {
+        return Err("The --no-network flag can't be used together with the perf-counters sandbox".to_owned());
+    }
+    Ok(ExecuteMode::PerfCounters { memory_limit: args.memory_limit.or(default_memory_limit) })
+}
+
+#[cfg(windows)]
+fn parse_restricted_token_mode(args: &Args, default_memory_limit: Option<u64>) -> Result<ExecuteMode, String> {
+    if args.no_network {
+        return Err("The --no-network flag can't be used together with the restricted-token sandbox".to_owned());
+    }
+    Ok(ExecuteMode::RestrictedToken { memory_limit: args.memory_limit.or(default_memory_limit) })
+}
+
+/// The sandbox-exec profile is used if it's chosen with --sandbox or the network access has to be blocked
+#[cfg(target_os = "macos")]
+fn parse_rusage_mode(args: &Args, default_memory_limit: Option<u64>) -> ExecuteMode {
+    ExecuteMode::Rusage {
+        memory_limit: args.memory_limit.or(default_memory_limit),
+        sandbox_exec: args.sandbox.is_some() || args.no_network,
+    }
+}
+
+/// `default_memory_limit` is used if a sandbox is enabled without setting the memory limit,
+/// but it doesn't enable a sandbox by itself
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn parse_execute_mode(args: &Args, manifest_sio2jail_args: Vec<String>, default_memory_limit: Option<u64>) -> Result<ExecuteMode, String> {
+    let mut extra_args = manifest_sio2jail_args;
+    extra_args.extend(args.sio2jail_arg.iter().cloned());
+    if args.sio2jail && args.container.is_some() {
+        return Err("You can't have the --sio2jail and --container flags on at the same time".to_owned());
+    }
+    let limited_by_sio2jail = args.time_metric != TimeMetric::Real;
+    if limited_by_sio2jail && (args.container.is_some() || args.valgrind || matches!(args.sandbox, Some(Sandbox::Isolate | Sandbox::PerfCounters))) {
+        return Err("The cpu and instructions time metrics can only be used with sio2jail".to_owned());
+    }
+    if let Some(mode) = parse_container_mode(args, &extra_args, default_memory_limit)? {
+        return Ok(mode);
+    }
+    if args.valgrind && (args.sio2jail || args.sandbox.is_some()) {
+        return Err("The --valgrind flag can't be used together with a sandbox".to_owned());
+    }
+    if let Some(mode) = parse_valgrind_mode(args, &extra_args)? {
+        return Ok(mode);
+    }
+    if let Some(Sandbox::Isolate) = args.sandbox {
+        if !extra_args.is_empty() {
+            return Err("Sio2jail arguments can't be used together with the isolate sandbox".to_owned());
+        }
+        return Ok(ExecuteMode::Isolate { memory_limit: args.memory_limit.or(default_memory_limit) });
+    }
+    if let Some(Sandbox::PerfCounters) = args.sandbox {
+        if !extra_args.is_empty() {
+            return Err("Sio2jail arguments can't be used together with the perf-counters sandbox".to_owned());
+        }
+        return parse_perf_counters_mode(args, default_memory_limit);
+    }
+    let memory_limit = match args.memory_limit {
+        Some(memory_limit) => memory_limit,
+        None if args.sio2jail || matches!(args.sandbox, Some(Sandbox::Sio2jail)) || !extra_args.is_empty() || limited_by_sio2jail => {
+            default_memory_limit.unwrap_or(1024 * 1204)
+        }
+        None => return Ok(Simple),
+    };
+    if args.no_network {
+        for option in ["--user-namespace", "--net-namespace"] {
+            if !extra_args.iter().any(|arg| arg == option || arg.starts_with(&format!("{option}="))) {
+                extra_args.extend([option.to_owned(), "on".to_owned()]);
+            }
+        }
+    }
+    Ok(ExecuteMode::Sio2jail { memory_limit, extra_args, time_metric: args.time_metric })
+}
+
+#[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+fn parse_execute_mode(args: &Args, manifest_sio2jail_args: Vec<String>, default_memory_limit: Option<u64>) -> Result<ExecuteMode, String> {
+    if !manifest_sio2jail_args.is_empty() {
+        return Err("Sio2jail arguments were provided in the manifest, but sio2jail is only available on x86-64 Linux".to_owned());
+    }
+    if let Some(mode) = parse_container_mode(args, &[], default_memory_limit)? {
+        return Ok(mode);
+    }
+    #[cfg(any(target_os = "linux", windows, target_os = "macos"))]
+    if args.valgrind && args.sandbox.is_some() {
+        return Err("The --valgrind flag can't be used together with a sandbox".to_owned());
+    }
+    if let Some(mode) = parse_valgrind_mode(args, &[])? {
+        return Ok(mode);
+    }
+    // Sio2jail isn't available on other architectures, so the memory limit is enforced by the perf-counters sandbox
+    #[cfg(target_os = "linux")]
+    if args.sandbox.is_some() || args.memory_limit.is_some() {
+        return parse_perf_counters_mode(args, default_memory_limit);
+    }
+    // On Windows, it's enforced by the job object of the restricted-token sandbox
+    #[cfg(windows)]
+    if args.sandbox.is_some() || args.memory_limit.is_some() {
+        return parse_restricted_token_mode(args, default_memory_limit);
+    }
+    // On macOS, the peak memory use is measured after the program exits, and sandbox-exec blocks the network
+    #[cfg(target_os = "macos")]
+    if args.sandbox.is_some() || args.memory_limit.is_some() || args.no_network {
+        return Ok(parse_rusage_mode(args, default_memory_limit));
+    }
+    if args.memory_limit.is_some() {
+        return Err("The memory limit can only be used together with the --container option on this platform".to_owned());
+    }
+    if args.no_network && !cfg!(target_os = "linux") {
+        return Err("The --no-network flag can only be used together with the --container option on this platform".to_owned());
+    }
+    Ok(Simple)
+}
+
+fn parse_cpus(list: &str) -> Result<Vec<usize>, String> {
+    if !cfg!(target_os = "linux") {
+        return Err("The --cpus option is only available on Linux".to_owned());
+    }
+    let cpus = parse_cpu_list(list)?;
+    if cpus.is_empty() {
+        return Err("The list of CPUs can't be empty".to_owned());
+    }
+    let online_cpus = online_cpus();
+    if let Some(cpu) = cpus.iter().find(|cpu| !online_cpus.contains(cpu)) {
+        return Err(format!("The CPU {cpu} does not exist or is offline"));
+    }
+    Ok(cpus)
+}
+
+/// Returns the per-test limits of a sinol package, without the memory limits if no sandbox is used
+fn package_limit_overrides(package: &SinolPackage, input_directory: &Path, input_ext: &str, execute_mode: &ExecuteMode) -> Vec<LimitOverride> {
+    let mut limit_overrides = package.limit_overrides(input_directory, input_ext);
+    if execute_mode.is_unsandboxed() {
+        if limit_overrides.iter().any(|limit| limit.memory_limit.is_some()) {
+            add_warning(WarningKind::LimitsNotEnforced, "The per-test memory limits of the sinol package are not enforced, because no sandbox is used");
+        }
+        limit_overrides.retain_mut(|limit| {
+            limit.memory_limit = None;
+            limit.time_limit.is_some()
+        });
+    }
+    limit_overrides
+}
+
+/// Returns the user name from --user or the name of the current system user
+fn results_user(args: &Args) -> Option<String> {
+    args.user.clone().or_else(|| env::var("USER").or_else(|_| env::var("USERNAME")).ok())
+}
+
+/// Returns the task name from --task, `default_task` or the name of the current directory
+fn results_task(args: &Args, default_task: Option<&str>) -> Option<String> {
+    args.task.clone().or(default_task.map(str::to_owned)).or_else(|| env::current_dir().ok()
+        .and_then(|directory| directory.file_name().map(|name| name.to_string_lossy().into_owned())))
+}
+
+fn parse_publish_config(args: &mut Args, default_task: Option<&str>) -> Result<Option<PublishConfig>, String> {
+    let Some(url) = args.publish.take() else {
+        return Ok(None);
+    };
+    let user = results_user(args)
+        .ok_or("The current user name couldn't be determined, provide it using --user")?;
+    let task = results_task(args, default_task)
+        .ok_or("The task name couldn't be determined from the current directory, provide it using --task")?;
+
+    Ok(Some(PublishConfig {
+        url,
+        token: args.token.take().expect("The token is required if --publish is used"),
+        user,
+        task,
+    }))
+}
+
+fn parse_webhook_config(args: &mut Args, default_task: Option<&str>) -> Option<WebhookConfig> {
+    Some(WebhookConfig {
+        url: args.webhook.take()?,
+        user: results_user(args),
+        task: results_task(args, default_task),
+    })
+}
+
 impl TryFrom<Args> for ParsedConfig {
     type Error = String;
 
-    fn try_from(args: Args) -> Result<Self, String> {
-        if !args.filename.is_file() {
-            return Err("The provided file does not exist".to_owned());
+    #[allow(clippy::too_many_lines)] // Every argument is validated here
+    fn try_from(mut args: Args) -> Result<Self, String> {
+        let prebuilt = args.exe.is_some();
+        let source_path = args.filename.take().or(args.exe.take()).expect("The filename is required if no subcommand or --exe is used");
+        if !source_path.is_file() {
+            return Err(if prebuilt { "The provided executable does not exist" } else { "The provided file does not exist" }.to_owned());
+        }
+        let manifest = Manifest::load(args.manifest.as_ref())?;
+        let package = SinolPackage::detect(&source_path)?;
+        let polygon = if package.is_none() { PolygonPackage::detect(&source_path)? } else { None };
+        let default_memory_limit = package.as_ref().and_then(|package| package.memory_limit)
+            .or(polygon.as_ref().and_then(|polygon| polygon.memory_limit));
+        let execute_mode = parse_execute_mode(&args, manifest.sio2jail_args, default_memory_limit)?;
+        if let Some(run_command) = &args.run_command {
+            verify_run_command(run_command, &execute_mode)?;
         }
+        if args.file_io.is_some() && !matches!(execute_mode, Simple) {
+            return Err("The --file-io option can't be used with a sandbox or --valgrind".to_owned());
+        }
+        if args.scratch_dir && !matches!(execute_mode, Simple) {
+            return Err("The --scratch-dir flag can't be used with a sandbox or --valgrind".to_owned());
+        }
+        if execute_mode.is_unsandboxed() && default_memory_limit.is_some() {
+            add_warning(WarningKind::LimitsNotEnforced, "The memory limit of the task package is not enforced, because no sandbox is used");
+        }
+        let default_task = package.as_ref().map(|package| package.task_id.as_str())
+            .or(polygon.as_ref().map(|polygon| polygon.short_name.as_str()));
+        let publish = parse_publish_config(&mut args, default_task)?;
+        let webhook = parse_webhook_config(&mut args, default_task);
+
+        // The tests of a Polygon package are only used if no other tests were chosen
+        let polygon_tests = polygon.as_ref().filter(|_| {
+            args.io.is_none() && args.r#in == Path::new("in") && args.out == Path::new("out")
+        });
+        let (in_ext, output_names) = match polygon_tests {
+            Some(polygon) => (polygon.input_ext.clone(), OutputNames::from_extensions(slice::from_ref(&polygon.answer_ext))),
+            None if !args.out_pattern.is_empty() => (args.in_ext, OutputNames::from_patterns(args.out_pattern)?),
+            None => (args.in_ext, OutputNames::from_extensions(&args.out_ext)),
+        };
+        let checker = match (args.checker, polygon_tests) {
+            (Some(checker), _) if is_wasm_module(&checker) => {
+                if args.checker_protocol.is_some_and(|protocol| protocol != CheckerProtocol::Files) {
+                    return Err("WASM checkers can only use the files checker protocol".to_owned());
+                }
+                Some((checker, CheckerProtocol::Files))
+            }
+            (Some(checker), _) => Some((checker, args.checker_protocol.unwrap_or(CheckerProtocol::Toster))),
+            (None, Some(polygon)) if !args.generate && args.builtin_checker.is_none() => polygon.checker.clone()
+                .map(|checker| (checker, args.checker_protocol.unwrap_or(CheckerProtocol::Testlib))),
+            (None, _) => None,
+        };
 
         let (input_directory, output_directory) = if let Some(io) = args.io {
-            if !io.is_dir() {
+            if !io.is_dir() && !TestArchive::is_archive(&io) {
                 return Err("The input/output directory does not exist".to_owned());
             }
             (io.clone(), io)
+        } else if let Some(package) = package.as_ref().filter(|_| args.r#in == Path::new("in") && args.out == Path::new("out")) {
+            (package.directory.join("in"), package.directory.join("out"))
+        } else if let Some(polygon) = polygon_tests {
+            if !polygon.input_directory.is_dir() {
+                return Err(format!(
+                    "The tests of the Polygon package in {} were not found, generate them by running doall.sh in the package directory",
+                    polygon.directory.display(),
+                ));
+            }
+            (polygon.input_directory.clone(), polygon.answer_directory.clone())
         } else {
-            if !args.r#in.is_dir() {
+            if !args.r#in.is_dir() && !TestArchive::is_archive(&args.r#in) {
                 return Err("The input directory does not exist".to_owned());
             }
-            (args.r#in, args.out)
+            // Outputs are read from the same archive as the inputs, unless a different output directory was chosen
+            let output_directory = if TestArchive::is_archive(&args.r#in) && args.out == Path::new("out") && !args.out.is_dir() {
+                args.r#in.clone()
+            } else {
+                args.out
+            };
+            (args.r#in, output_directory)
         };
 
-        verify_compile_command(&args.compile_command)?;
+        let compile_command = match args.compile_command {
+            Some(compile_command) => compile_command,
+            None => Toolchain::resolve_compile_command(args.compiler, args.profile)?,
+        };
+        verify_compile_command(&compile_command)?;
+        verify_extra_sources(&args.extra_source)?;
+        if args.precompiled_header && !precompiled_header::is_supported(&compile_command) {
+            return Err("The --precompiled-header flag is only supported with GCC".to_owned());
+        }
+        if args.pin_cpus && !cfg!(target_os = "linux") {
+            return Err("The --pin-cpus flag is only available on Linux".to_owned());
+        }
+        if args.high_priority && !cfg!(target_os = "linux") {
+            return Err("The --high-priority flag is only available on Linux".to_owned());
+        }
+        if args.max_failures == Some(0) {
+            return Err("The maximum number of failures must be greater than 0".to_owned());
+        }
+        if args.jobs == Some(0) {
+            return Err("The number of jobs must be greater than 0".to_owned());
+        }
+        if args.input_rate == Some(0) {
+            return Err("The input rate must be greater than 0".to_owned());
+        }
+        if args.warn_threshold.is_some_and(|threshold| !(threshold > 0.0 && threshold <= 1.0)) {
+            return Err("The warning threshold must be greater than 0 and at most 1".to_owned());
+        }
+        if args.slower_threshold <= 0.0 {
+            return Err("The slower threshold must be greater than 0".to_owned());
+        }
+        if args.rerun_band.is_some_and(|band| !(band > 0.0 && band < 1.0)) {
+            return Err("The rerun band must be greater than 0 and less than 1".to_owned());
+        }
+
+        // The limits in the manifest take precedence over the limits of the sinol package
+        let mut limit_overrides = manifest.limits;
+        if let Some(package) = &package {
+            limit_overrides.extend(package_limit_overrides(package, &input_directory, &in_ext, &execute_mode));
+        }
 
-        Ok(ParsedConfig {
-            source_path: args.filename,
-            compile_timeout: Duration::from_secs(args.compile_timeout),
-            execute_timeout: Duration::from_secs(args.timeout),
-            compile_command: args.compile_command,
-            input: InputConfig::Directory {
-                directory: input_directory,
-                ext: args.in_ext,
+        let config = ParsedConfig {
+            source_path,
+            prebuilt,
+            compile_timeout: args.compile_timeout,
+            execute_timeout: args.timeout
+                .or(package.as_ref().and_then(|package| package.time_limit))
+                .or(polygon.as_ref().and_then(|polygon| polygon.time_limit))
+                .unwrap_or(Duration::from_secs(5)),
+            checker_timeout: args.checker_timeout,
+            sandbox_checker: args.sandbox_checker,
+            output_limit: args.output_limit,
+            input_rate: args.input_rate,
+            runs: args.runs.map(|runs| runs as usize),
+            warmup_runs: args.warmup_runs as usize,
+            warn_threshold: args.warn_threshold,
+            median_rerun: args.rerun_band.map(|band| MedianRerunConfig {
+                band,
+                max_runs: args.rerun_count as usize,
+            }),
+            compile_command,
+            extra_sources: args.extra_source,
+            precompiled_header: args.precompiled_header,
+            show_warnings: args.show_warnings,
+            deny_warnings: args.deny_warnings,
+            run_command: args.run_command,
+            file_io: args.file_io,
+            scratch_directory: args.scratch_dir,
+            environment: ProgramEnvironment { clear: args.clear_env, variables: args.env },
+            sanitizer_report: args.profile.has_sanitizer(),
+            stderr: args.stderr,
+            compile_cache_size: args.compile_cache_size,
+            compile_cache_directory: match (args.compile_cache_dir, args.compile_cache) {
+                (Some(directory), _) => Some(directory),
+                (None, true) => Some(CompileCache::default_directory().map_err(|error| error.to_string())?),
+                (None, false) => None,
             },
-
-            action_type: match (args.generate, args.checker) {
-                (true, Some(_)) => {
-                    return Err("You can't have the --generate and --checker flags on at the same time".to_owned());
-                }
-                (true, None) => {
-                    if output_directory.exists() && !output_directory.is_dir() {
-                        return Err("The output path is not a directory".to_owned());
-                    }
-                    ActionType::Generate {
-                        output_directory,
-                        output_ext: args.out_ext,
-                    }
+            input_transform: args.input_transform.or(manifest.input_transform),
+            validator: args.validator,
+            input: if TestArchive::is_archive(&input_directory) {
+                InputConfig::Archive {
+                    archive: input_directory,
+                    ext: in_ext,
                 }
-                (false, None) => {
-                    if !output_directory.is_dir() {
-                        return Err("The output directory does not exist".to_owned());
-                    }
-                    ActionType::SimpleCompare {
-                        output_directory,
-                        output_ext: args.out_ext,
-                    }
-                }
-                (false, Some(checker_path)) => {
-                    if !checker_path.is_file() {
-                        return Err("The provided checker file does not exist".to_owned());
-                    }
-                    ActionType::Checker {
-                        path: checker_path,
-                    }
+            } else {
+                InputConfig::Directory {
+                    directory: input_directory,
+                    ext: in_ext,
                 }
             },
+            output_archive: TestArchive::is_archive(&output_directory).then(|| output_directory.clone()),
 
-            execute_mode: {
-                #[cfg(all(target_os = "linux", target_arch = "x86_64"))] {
-                    if let Some(memory_limit) = args.memory_limit {
-                        ExecuteMode::Sio2jail { memory_limit }
-                    } else if args.sio2jail {
-                        ExecuteMode::Sio2jail { memory_limit: 1024 * 1204 }
-                    } else {
-                        Simple
-                    }
-                }
-                #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
-                Simple
+            action_type: parse_action_type(args.generate, checker, output_directory, output_names)?,
+            execute_mode,
+            limit_overrides,
+            dependencies: manifest.dependencies,
+            exclusion: TestExclusion::new(
+                args.skip.iter().cloned().map(TestPattern::new).collect(),
+                &args.skip_tag,
+                manifest.tags,
+            )?,
+            checker_overrides: manifest.checkers,
+            groups: manifest.groups,
+            expected_verdicts: manifest.expected,
+            theme: manifest.theme,
+            max_failures: args.max_failures,
+            total_timeout: args.total_timeout,
+            temp_file_timeout: args.temp_file_timeout,
+            retries: args.retries,
+            check_determinism: args.check_determinism,
+            kill_grace: args.kill_grace,
+            diff_options: DiffOptions {
+                style: args.diff_style,
+                max_line_length: args.max_line_length as usize,
+                max_lines: args.diff_max_lines as usize,
+                context_lines: args.diff_context.map(|lines| lines as usize),
             },
-        })
+            builtin_checker: args.builtin_checker,
+            no_network: args.no_network,
+            noise_guard: args.noise_guard,
+            cpus: args.cpus.as_deref().map(parse_cpus).transpose()?,
+            pin_cpus: args.pin_cpus,
+            jobs: if args.sequential { Some(1) } else { args.jobs },
+            high_priority: args.high_priority,
+            live: args.live,
+            tui: args.tui,
+            // Only the TAP lines are printed while testing
+            verbosity: Verbosity::from_flags(args.quiet || args.format == OutputFormat::Tap, args.verbose),
+            notify: args.notify,
+            dry_run: args.dry_run,
+            save_outputs: args.save_outputs,
+            feedback_report: args.feedback_report,
+            report: args.report,
+            report_file: args.report_file,
+            compress_saved: args.compress_saved,
+            compare: args.compare,
+            compare_with: args.compare_with,
+            slower_threshold: args.slower_threshold,
+            save_history: !args.no_history,
+            profile_perf: args.profile_perf,
+            perf_dir: args.perf_dir,
+            publish,
+            webhook,
+            sinol_package: package.map(|package| package.directory),
+            polygon_package: polygon.map(|polygon| polygon.directory),
+        };
+
+        if config.execute_mode.is_unsandboxed() {
+            if let Some(limit) = config.limit_overrides.iter().find(|limit| limit.memory_limit.is_some()) {
+                return Err(format!(
+                    "The memory limit for tests matching \"{}\" can only be used together with a sandbox",
+                    limit.tests,
+                ));
+            }
+        }
+        if config.sandbox_checker {
+            if config.execute_mode.is_unsandboxed() {
+                return Err("The --sandbox-checker flag can only be used together with a sandbox".to_owned());
+            }
+            if matches!(config.action_type, ActionType::Checker { protocol, .. } if protocol != CheckerProtocol::Toster) {
+                return Err("Only checkers using the toster protocol can be run in the sandbox".to_owned());
+            }
+        }
+        if config.profile_perf.is_some() && config.file_io.is_some() {
+            return Err("The --profile-perf option can't be used with programs using file-based I/O".to_owned());
+        }
+        if config.validator.as_ref().is_some_and(|validator| !validator.is_file()) {
+            return Err("The provided validator file does not exist".to_owned());
+        }
+        for checker_override in &config.checker_overrides {
+            if checker_override.checker.as_ref().is_some_and(|checker| !checker.is_file()) {
+                return Err(format!(
+                    "The checker for tests matching \"{}\" in the manifest does not exist",
+                    checker_override.tests,
+                ));
+            }
+        }
+        Ok(config)
     }
 }
 
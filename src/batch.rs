@@ -0,0 +1,147 @@
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use clap::Parser;
+use colored::Colorize;
+use comfy_table::{Attribute, Cell, Color, Table};
+use comfy_table::ContentArrangement::Dynamic;
+use crate::args::{Args, ParsedConfig};
+use crate::exit_code;
+use crate::formatted_error::FormattedError;
+use crate::generic_utils::strip_ansi_codes;
+use crate::manifest::{Manifest, DEFAULT_MANIFEST_NAME};
+use crate::test_summary::TestSummary;
+use crate::warnings::clear_warnings;
+use crate::interrupt;
+use crate::cli::{set_ctrlc_handler, setup_thread_pool, test_task};
+
+/// The extensions of files recognized as the solution of a task if it's not set in the manifest
+const SOLUTION_EXTENSIONS: [&str; 3] = ["cpp", "cc", "cxx"];
+
+struct Task {
+    name: String,
+    directory: PathBuf,
+    solution: PathBuf,
+}
+
+enum TaskOutcome {
+    Tested { total: usize, correct: usize, time: Duration },
+    Failed(String),
+}
+
+/// Finds the solution of a task, which is set in the manifest or is the only C++ file in the task directory
+fn find_solution(directory: &Path) -> Result<PathBuf, String> {
+    let manifest_path = directory.join(DEFAULT_MANIFEST_NAME);
+    if manifest_path.is_file() {
+        if let Some(solution) = Manifest::load(Some(&manifest_path))?.solution {
+            return Ok(solution);
+        }
+    }
+
+    let mut solutions = read_dir(directory)
+        .map_err(|error| format!("Failed to read the task directory:\n{error}"))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().and_then(OsStr::to_str).is_some_and(|ext| SOLUTION_EXTENSIONS.contains(&ext)));
+    match (solutions.next(), solutions.next()) {
+        (Some(solution), None) => Ok(PathBuf::from(solution.file_name().expect("Files have a name"))),
+        (None, _) => Err("the task directory doesn't contain a C++ file".to_owned()),
+        (Some(_), Some(_)) => Err("the task directory contains multiple C++ files, set the solution in the manifest".to_owned()),
+    }
+}
+
+/// Finds the task directories, which contain a manifest or an input directory
+fn discover_tasks(directory: &Path) -> Result<Vec<Task>, FormattedError> {
+    let directory = directory.canonicalize()
+        .map_err(|error| FormattedError::from_str(&format!("Failed to open the directory {}:\n{error}", directory.display())))?;
+    let mut tasks = vec![];
+    for entry in read_dir(&directory).map_err(|error| FormattedError::from_str(&format!("Failed to read the directory:\n{error}")))? {
+        let path = entry.map_err(|error| FormattedError::from_str(&format!("Failed to read the directory:\n{error}")))?.path();
+        if !path.is_dir() || !(path.join(DEFAULT_MANIFEST_NAME).is_file() || path.join("in").is_dir()) {
+            continue;
+        }
+        let name = path.file_name().expect("Directories have a name").to_string_lossy().into_owned();
+        match find_solution(&path) {
+            Ok(solution) => tasks.push(Task { name, directory: path, solution }),
+            Err(error) => println!("{}", format!("Skipping task {name}: {error}").yellow()),
+        }
+    }
+    tasks.sort_by(|a, b| human_sort::compare(&a.name, &b.name));
+    Ok(tasks)
+}
+
+/// Tests the solution of every task in the subdirectories of `directory`
+/// and prints a table with the results of every task
+pub(crate) fn run_batch(directory: &Path, args: &[OsString]) -> Result<(), FormattedError> {
+    let tasks = discover_tasks(directory)?;
+    if tasks.is_empty() {
+        return Err(FormattedError::from_str("No task directories were found"));
+    }
+    let original_directory = env::current_dir().expect("Failed to read the current directory");
+    let test_summary: Arc<Mutex<Option<TestSummary>>> = Arc::new(Mutex::new(None));
+    set_ctrlc_handler(test_summary.clone());
+
+    let mut thread_pool_configured = false;
+    let mut results = vec![];
+    for task in &tasks {
+        println!("\n{}", format!("Task {}", task.name).bold());
+        // Warnings of a task that failed before its results were printed
+        clear_warnings();
+        env::set_current_dir(&task.directory).expect("Failed to enter the task directory");
+        let args = Args::parse_from(
+            [OsString::from("toster batch")].into_iter()
+                .chain(args.iter().cloned())
+                .chain([task.solution.clone().into_os_string()])
+        );
+        let outcome = ParsedConfig::try_from(args)
+            .map_err(|error| FormattedError::from_str(&error))
+            .and_then(|config| {
+                if !thread_pool_configured {
+                    setup_thread_pool(&config);
+                    thread_pool_configured = true;
+                }
+                test_task(config, &test_summary)
+            });
+        let outcome = match (outcome, test_summary.lock().expect("Failed to lock test summary mutex").take()) {
+            (Err(error), _) => {
+                println!("{error}");
+                exit_code::record_outcome(error.outcome());
+                TaskOutcome::Failed(strip_ansi_codes(&error.to_string()).lines().next().unwrap_or_default().to_owned())
+            }
+            (Ok(()), Some(summary)) => TaskOutcome::Tested {
+                total: summary.total,
+                correct: summary.success,
+                time: summary.start_time.elapsed(),
+            },
+            (Ok(()), None) => TaskOutcome::Failed("the task wasn't tested".to_owned()),
+        };
+        results.push((&task.name, outcome));
+        if interrupt::received_ctrl_c() {
+            break;
+        }
+    }
+    env::set_current_dir(original_directory).expect("Failed to return to the original directory");
+
+    print_batch_summary(&results);
+    Ok(())
+}
+
+fn print_batch_summary(results: &[(&String, TaskOutcome)]) {
+    let mut table = Table::new();
+    table.set_content_arrangement(Dynamic).set_header(
+        ["Task", "Result", "Time"].map(|header| Cell::new(header).add_attribute(Attribute::Bold))
+    );
+    for (name, outcome) in results {
+        let (result, time) = match outcome {
+            TaskOutcome::Tested { total, correct, time } => (
+                Cell::new(format!("{correct}/{total} correct")).fg(if correct == total { Color::Green } else { Color::Red }),
+                Cell::new(format!("{:.2}s", time.as_secs_f64())),
+            ),
+            TaskOutcome::Failed(error) => (Cell::new(error).fg(Color::Red), Cell::new("-")),
+        };
+        table.add_row([Cell::new(name), result, time]);
+    }
+    println!("\n{}", table.to_string().replace('\r', ""));
+}
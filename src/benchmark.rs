@@ -0,0 +1,111 @@
+use std::fs::File;
+use std::io::Seek;
+use std::sync::Mutex;
+use std::time::Duration;
+use comfy_table::{Attribute, Cell, Table};
+use comfy_table::ContentArrangement::Dynamic;
+use crate::executor::{AnyTestExecutor, TestExecutor};
+use crate::temp_files::create_test_temp_file;
+use crate::test_errors::{ExecutionError, ExecutionMetrics};
+
+/// The runtimes and memory usage of a test run multiple times
+struct RunStatistics {
+    min: Duration,
+    average: Duration,
+    max: Duration,
+    max_memory_kibibytes: Option<u64>,
+}
+
+/// Runs every test multiple times and collects the runtime statistics,
+/// since a single measurement is often too noisy to decide if the program fits in the time limit
+pub(crate) struct Benchmark {
+    runs: usize,
+    /// The number of untimed runs before the measured ones, which warm up the disk cache and the CPU
+    warmup_runs: usize,
+    results: Mutex<Vec<(String, RunStatistics)>>,
+}
+
+impl Benchmark {
+    pub(crate) fn new(runs: usize, warmup_runs: usize) -> Self {
+        Benchmark { runs, warmup_runs, results: Mutex::new(vec![]) }
+    }
+
+    fn prepare_rerun(mut input_file: &File, mut scratch_file: &File) {
+        input_file.rewind().expect("Failed to rewind input file");
+        scratch_file.set_len(0).expect("Failed to truncate memfile");
+        scratch_file.rewind().expect("Failed to rewind memfile");
+    }
+
+    /// Runs the program `warmup_runs` times ignoring the results,
+    /// and then `runs` times, writing the output of the first measured run to `output_file`.
+    ///
+    /// The first failed run is returned as the result, otherwise the metrics contain
+    /// the longest runtime and the highest memory usage
+    pub(crate) fn run(
+        &self,
+        executor: &AnyTestExecutor,
+        test_name: &str,
+        input_file: &File,
+        output_file: &File,
+    ) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        // The output of the first measured run is kept, the other runs are only used for measuring
+        let scratch_file = match create_test_temp_file() {
+            Ok(file) => file,
+            Err(error) => return (ExecutionMetrics::NONE, Err(error.into())),
+        };
+        for _ in 0..self.warmup_runs {
+            let _ = executor.test_to_file(input_file, &scratch_file);
+            Self::prepare_rerun(input_file, &scratch_file);
+        }
+
+        let (metrics, result) = executor.test_to_file(input_file, output_file);
+        if result.is_err() {
+            return (metrics, result);
+        }
+
+        let mut all_metrics = vec![metrics];
+        while all_metrics.len() < self.runs {
+            Self::prepare_rerun(input_file, &scratch_file);
+            let (metrics, result) = executor.test_to_file(input_file, &scratch_file);
+            if result.is_err() {
+                return (metrics, result);
+            }
+            all_metrics.push(metrics);
+        }
+
+        let times: Vec<Duration> = all_metrics.iter().filter_map(|metrics| metrics.time).collect();
+        let max_memory_kibibytes = all_metrics.iter().filter_map(|metrics| metrics.memory_kibibytes).max();
+        let (Some(&min), Some(&max)) = (times.iter().min(), times.iter().max()) else {
+            return (ExecutionMetrics { time: None, memory_kibibytes: max_memory_kibibytes }, Ok(()));
+        };
+        let average = times.iter().sum::<Duration>() / u32::try_from(times.len()).expect("The number of runs fits in u32");
+        self.results.lock().expect("Failed to lock the benchmark results")
+            .push((test_name.to_owned(), RunStatistics { min, average, max, max_memory_kibibytes }));
+        (ExecutionMetrics { time: Some(max), memory_kibibytes: max_memory_kibibytes }, Ok(()))
+    }
+
+    /// Prints a table with the runtime statistics of every test in which all runs succeeded
+    pub(crate) fn print(&self) {
+        let mut results = self.results.lock().expect("Failed to lock the benchmark results");
+        if results.is_empty() {
+            return;
+        }
+        results.sort_by(|a, b| human_sort::compare(&a.0, &b.0));
+
+        let mut table = Table::new();
+        table.set_content_arrangement(Dynamic).set_header(
+            ["Test", "Min", "Average", "Max", "Max memory"].map(|header| Cell::new(header).add_attribute(Attribute::Bold))
+        );
+        for (test_name, statistics) in results.iter() {
+            table.add_row([
+                test_name.clone(),
+                format!("{:.3}s", statistics.min.as_secs_f64()),
+                format!("{:.3}s", statistics.average.as_secs_f64()),
+                format!("{:.3}s", statistics.max.as_secs_f64()),
+                statistics.max_memory_kibibytes.map_or_else(|| "-".to_owned(), |memory| format!("{memory}KiB")),
+            ]);
+        }
+        let warmup = if self.warmup_runs > 0 { format!(" after {} warm-up runs", self.warmup_runs) } else { String::new() };
+        println!("Runtimes over {} runs of every test{warmup}:\n{}", self.runs, table.to_string().replace('\r', ""));
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+
+/// A comparator built into toster, used instead of comparing the output line by line
+/// for tasks which accept more than one output
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BuiltinChecker {
+    /// Compares the outputs token by token, ignoring whitespace and line breaks
+    Tokens,
+    /// Compares the outputs token by token, accepting numbers with an absolute or relative error up to the epsilon
+    Floats {
+        epsilon: f64,
+    },
+    /// Accepts the expected lines in any order
+    UnorderedLines,
+    /// Compares YES and NO answers case-insensitively
+    YesNo,
+}
+
+const DEFAULT_EPSILON: f64 = 1e-6;
+
+/// Parses a built-in checker given as `tokens`, `floats`, `floats:<EPSILON>`, `unordered-lines` or `yesno`
+pub(crate) fn parse_builtin_checker(value: &str) -> Result<BuiltinChecker, String> {
+    let (name, parameter) = match value.split_once(':') {
+        Some((name, parameter)) => (name, Some(parameter)),
+        None => (value, None),
+    };
+    let checker = match name {
+        "tokens" => BuiltinChecker::Tokens,
+        "floats" => BuiltinChecker::Floats {
+            epsilon: match parameter {
+                None => DEFAULT_EPSILON,
+                Some(epsilon) => match epsilon.parse::<f64>() {
+                    Ok(epsilon) if epsilon >= 0.0 && epsilon.is_finite() => epsilon,
+                    _ => return Err(format!("\"{epsilon}\" is not a valid epsilon")),
+                },
+            },
+        },
+        "unordered-lines" => BuiltinChecker::UnorderedLines,
+        "yesno" => BuiltinChecker::YesNo,
+        _ => return Err(format!("unknown built-in checker \"{name}\", expected tokens, floats, unordered-lines or yesno")),
+    };
+    if parameter.is_some() && !matches!(checker, BuiltinChecker::Floats { .. }) {
+        return Err(format!("the {name} checker doesn't take a parameter"));
+    }
+    Ok(checker)
+}
+
+impl BuiltinChecker {
+    /// Checks the output, returning the reason it's incorrect
+    pub(crate) fn check(self, expected: &str, actual: &str) -> Result<(), String> {
+        match self {
+            BuiltinChecker::Tokens => compare_tokens(expected, actual, |expected, actual| {
+                (expected != actual).then(|| format!("expected {expected}, got {actual}"))
+            }),
+            BuiltinChecker::Floats { epsilon } => compare_tokens(expected, actual, |expected, actual| {
+                compare_floats(expected, actual, epsilon)
+            }),
+            BuiltinChecker::UnorderedLines => compare_unordered_lines(expected, actual),
+            BuiltinChecker::YesNo => compare_tokens(expected, actual, |expected, actual| {
+                if !actual.eq_ignore_ascii_case("yes") && !actual.eq_ignore_ascii_case("no") {
+                    Some(format!("expected YES or NO, got {actual}"))
+                } else if !expected.eq_ignore_ascii_case(actual) {
+                    Some(format!("expected {}, got {}", expected.to_uppercase(), actual.to_uppercase()))
+                } else {
+                    None
+                }
+            }),
+        }
+    }
+}
+
+/// Compares the whitespace-separated tokens of the outputs,
+/// `compare` returns the description of the difference between two tokens
+fn compare_tokens(expected: &str, actual: &str, compare: impl Fn(&str, &str) -> Option<String>) -> Result<(), String> {
+    let expected_tokens: Vec<&str> = expected.split_whitespace().collect();
+    let actual_tokens: Vec<&str> = actual.split_whitespace().collect();
+    for (index, (expected, actual)) in expected_tokens.iter().zip(&actual_tokens).enumerate() {
+        if let Some(difference) = compare(expected, actual) {
+            return Err(format!("token {}: {difference}", index + 1));
+        }
+    }
+    if expected_tokens.len() != actual_tokens.len() {
+        return Err(format!("expected {} tokens, got {}", expected_tokens.len(), actual_tokens.len()));
+    }
+    Ok(())
+}
+
+/// Compares the tokens as numbers if both of them are numbers, or as strings otherwise
+fn compare_floats(expected: &str, actual: &str, epsilon: f64) -> Option<String> {
+    let (Ok(expected_value), Ok(actual_value)) = (expected.parse::<f64>(), actual.parse::<f64>()) else {
+        return (expected != actual).then(|| format!("expected {expected}, got {actual}"));
+    };
+    // Infinities and NaN are only accepted if they're written exactly like in the output file
+    if !expected_value.is_finite() || !actual_value.is_finite() {
+        return (expected != actual).then(|| format!("expected {expected}, got {actual}"));
+    }
+    let difference = (expected_value - actual_value).abs();
+    (difference > epsilon * expected_value.abs().max(1.0))
+        .then(|| format!("expected {expected}, got {actual} (difference {difference:.2e})"))
+}
+
+/// Counts the occurrences of non-empty lines, ignoring trailing whitespace
+fn count_lines(output: &str) -> BTreeMap<&str, i64> {
+    let mut counts = BTreeMap::new();
+    for line in output.lines().map(str::trim_end).filter(|line| !line.is_empty()) {
+        *counts.entry(line).or_default() += 1;
+    }
+    counts
+}
+
+/// Compares the non-empty lines of the outputs as multisets
+fn compare_unordered_lines(expected: &str, actual: &str) -> Result<(), String> {
+    let mut counts = count_lines(expected);
+    for (line, count) in count_lines(actual) {
+        *counts.entry(line).or_default() -= count;
+    }
+    if let Some((line, _)) = counts.iter().find(|(_, count)| **count > 0) {
+        return Err(format!("the line \"{line}\" is missing"));
+    }
+    if let Some((line, _)) = counts.iter().find(|(_, count)| **count < 0) {
+        return Err(format!("the line \"{line}\" is unexpected"));
+    }
+    Ok(())
+}
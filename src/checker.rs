@@ -1,34 +1,113 @@
+use std::fs;
 use std::fs::File;
 use std::io::{read_to_string, Seek, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::io;
+use std::process::{Command, Stdio};
 use std::time::Duration;
+use std::str::FromStr;
+use colored::Color;
+use colored::Color::Magenta;
+use tempfile::NamedTempFile;
+use clap::ValueEnum;
 use colored::Colorize;
+use wait_timeout::ChildExt;
 use crate::executor::simple::SimpleExecutor;
-use crate::executor::test_to_temp;
-use crate::prepare_input::TestInputSource;
-use crate::temp_files::create_temp_file;
+use crate::formatted_error::FormattedError;
+use crate::executor::{test_to_temp, AnyTestExecutor, ProgramEnvironment};
+use crate::output::debug;
+use crate::program_stderr::read_tail;
+use crate::process_tree::kill_tree;
+use crate::prepare_input::{InputError, TestInputSource};
+use crate::temp_files::{create_test_named_temp_file, create_test_temp_file, make_cloned_stdio};
 use crate::test_errors::TestError;
-use crate::test_errors::ExecutionError::IncorrectCheckerFormat;
-use crate::test_errors::TestError::CheckerError;
+use crate::test_errors::ExecutionError::{IncorrectCheckerFormat, RuntimeError, SandboxError, TimedOut};
+use crate::test_errors::TestError::{CheckerError, NoOutputFile};
+#[cfg(feature = "wasm-checker")]
+use crate::wasm_checker::WasmChecker;
+
+/// How the checker receives the test data and reports the verdict
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CheckerProtocol {
+    /// The checker receives the input and the program output on stdin and writes the verdict to stdout, as described in the help of --checker
+    Toster,
+    /// The checker is run as "checker <INPUT> <OUTPUT> <ANSWER>" with the paths of the input, the program output and the expected output, and writes the verdict to stdout like with the toster protocol. With --feedback-report, the path of the feedback file is the fourth argument
+    Files,
+    /// The protocol of testlib checkers, used in Polygon packages. The checker is run like with the files protocol, returns the verdict as the exit code and writes the message to stderr
+    Testlib,
+}
+
+/// Returns true if the checker is a WebAssembly module, which is run in-process with the files protocol
+pub(crate) fn is_wasm_module(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "wasm")
+}
 
 pub(crate) struct Checker {
     executor: SimpleExecutor,
+    /// Runs the checker in the sandbox of the tested program instead of `executor`
+    sandbox: Option<AnyTestExecutor>,
+    /// Runs the checker in-process instead of `executor`
+    #[cfg(feature = "wasm-checker")]
+    wasm: Option<WasmChecker>,
+    pub(crate) protocol: CheckerProtocol,
 }
 
 impl Checker {
-    pub(crate) fn new(checker_executable: PathBuf, timeout: Duration) -> Self {
+    pub(crate) fn new(checker_executable: PathBuf, timeout: Duration, protocol: CheckerProtocol) -> Self {
         Checker {
             executor: SimpleExecutor {
                 executable_path: checker_executable,
+                arguments: vec![],
                 timeout,
-            }
+                output_limit: None,
+                kill_grace: Duration::ZERO,
+                sanitizer_report: false,
+                capture_stderr: false,
+                file_io: None,
+                scratch_directory: false,
+                environment: ProgramEnvironment::default(),
+            },
+            sandbox: None,
+            #[cfg(feature = "wasm-checker")]
+            wasm: None,
+            protocol,
         }
     }
 
+    /// Loads a checker compiled to a WASI module
+    #[cfg(feature = "wasm-checker")]
+    pub(crate) fn wasm(path: &Path, timeout: Duration) -> Result<Self, FormattedError> {
+        Ok(Checker {
+            wasm: Some(WasmChecker::new(path, timeout)?),
+            ..Checker::new(path.to_path_buf(), timeout, CheckerProtocol::Files)
+        })
+    }
+
+    #[cfg(not(feature = "wasm-checker"))]
+    pub(crate) fn wasm(_path: &Path, _timeout: Duration) -> Result<Self, FormattedError> {
+        Err(FormattedError::from_str("WASM checkers aren't supported, because toster was built without the wasm-checker feature"))
+    }
+
+    /// Makes the checker run with the sandbox executor instead of directly.
+    /// Only checkers using the Toster protocol without feedback can be sandboxed,
+    /// since the other modes pass paths of files outside the sandbox to the checker
+    pub(crate) fn sandboxed(self, sandbox: AnyTestExecutor) -> Self {
+        Checker { sandbox: Some(sandbox), ..self }
+    }
+
+    /// Splits the color of a custom verdict from its label, like "partial [yellow]".
+    /// Verdicts without a valid color in brackets are shown in magenta
+    fn split_verdict_color(label: &str) -> (&str, Color) {
+        label.strip_suffix(']')
+            .and_then(|label| label.rsplit_once('['))
+            .and_then(|(label, color)| Some((label.trim_end(), Color::from_str(color.trim()).ok()?)))
+            .filter(|(label, _)| !label.is_empty())
+            .unwrap_or((label, Magenta))
+    }
+
     fn parse_checker_output(output: &str) -> Result<(), TestError> {
         match output.chars().nth(0) {
-            None => Err(CheckerError { error: IncorrectCheckerFormat("the checker returned an empty file".to_owned()) }),
+            None => Err(CheckerError { error: IncorrectCheckerFormat("the checker returned an empty file".to_owned()), stderr: None }),
             Some('C') => Ok(()),
             Some('I') => {
                 let checker_error = if output.len() > 1 { output.split_at(2).1.to_owned() } else { String::new() };
@@ -37,7 +116,19 @@ impl Checker {
                     error: error_message.to_string(),
                 })
             }
-            Some(_) => Err(CheckerError { error: IncorrectCheckerFormat("the first character of the checker's output wasn't C or I".to_owned()) })
+            Some('V') => {
+                let (label, message) = output[1..].split_once('\n').unwrap_or((&output[1..], ""));
+                if label.trim().is_empty() {
+                    return Err(CheckerError { error: IncorrectCheckerFormat("the checker returned a custom verdict without a label".to_owned()), stderr: None });
+                }
+                let (label, color) = Self::split_verdict_color(label.trim());
+                Err(TestError::Custom {
+                    label: label.to_owned(),
+                    color,
+                    message: message.trim().to_owned(),
+                })
+            }
+            Some(_) => Err(CheckerError { error: IncorrectCheckerFormat("the first character of the checker's output wasn't C, I or V".to_owned()), stderr: None })
         }
     }
 
@@ -46,27 +137,191 @@ impl Checker {
     ///
     /// The program output should be appended to this file before calling `check()` on it,
     /// which can be done by passing the file as stdin to the tested program.
-    pub(crate) fn prepare_checker_input(input_source: &TestInputSource) -> File {
-        let mut input_memfile = create_temp_file().unwrap();
-        io::copy(&mut input_source.get_file(), &mut input_memfile).unwrap();
+    pub(crate) fn prepare_checker_input(input_source: &TestInputSource) -> Result<File, InputError> {
+        let mut input_memfile = create_test_temp_file()?;
+        io::copy(&mut input_source.get_file()?, &mut input_memfile).unwrap();
         input_memfile.write_all("\n".as_bytes()).unwrap();
-        input_memfile
+        Ok(input_memfile)
     }
 
     /// Run checker on input file created using `prepare_checker_input()`.
     /// The program output should be appended to that file.
     /// `check()` will rewind `checker_input` before running checker.
-    pub(crate) fn check(&self, mut checker_input: File) -> Result<(), TestError> {
-        checker_input.rewind().unwrap();
+    pub(crate) fn check(&self, checker_input: File) -> Result<(), TestError> {
+        match &self.sandbox {
+            Some(sandbox) => Self::run_sandboxed(sandbox, checker_input),
+            None => Self::run(&self.executor, checker_input),
+        }
+    }
 
-        let (_, result) = test_to_temp(&self.executor, &checker_input);
-        let output = match result {
-            Ok(output) => output,
-            Err(error) => {
-                return Err(CheckerError { error });
-            }
+    /// Runs the checker like `check()`, passing it the path of a file
+    /// where it can write feedback for the student as its only argument.
+    ///
+    /// Returns the feedback alongside the result if the checker wrote any
+    pub(crate) fn check_with_feedback(&self, checker_input: File) -> (Result<(), TestError>, Option<String>) {
+        let feedback_file = match create_test_named_temp_file() {
+            Ok(feedback_file) => feedback_file,
+            Err(error) => return (Err(error.into()), None),
+        };
+        let executor = SimpleExecutor {
+            executable_path: self.executor.executable_path.clone(),
+            arguments: vec![feedback_file.path().to_string_lossy().into_owned()],
+            timeout: self.executor.timeout,
+            output_limit: None,
+            kill_grace: Duration::ZERO,
+            sanitizer_report: false,
+            capture_stderr: false,
+            file_io: None,
+            scratch_directory: false,
+            environment: ProgramEnvironment::default(),
         };
+        let result = Self::run(&executor, checker_input);
+        let feedback = fs::read_to_string(feedback_file.path()).ok()
+            .filter(|feedback| !feedback.trim().is_empty());
+        (result, feedback)
+    }
+
+    /// Runs the checker in the sandbox, which doesn't give access to the checker's stderr
+    fn run_sandboxed(sandbox: &AnyTestExecutor, mut checker_input: File) -> Result<(), TestError> {
+        checker_input.rewind().unwrap();
+
+        let (_, result) = test_to_temp(sandbox, &checker_input);
+        let output = result.map_err(|error| CheckerError { error, stderr: None })?;
         let output = read_to_string(output).expect("Failed to read checker output");
         Self::parse_checker_output(&output)
     }
+
+    /// Runs the checker, attaching the end of its stderr to wrong answers and checker errors
+    fn run(executor: &SimpleExecutor, mut checker_input: File) -> Result<(), TestError> {
+        checker_input.rewind().unwrap();
+
+        let mut output = create_test_temp_file()?;
+        let stderr = create_test_temp_file()?;
+        let (_, result) = executor.test_with_stderr(&checker_input, &output, &stderr);
+        output.rewind().expect("Failed to rewind memfile");
+        let result = result
+            .map_err(|error| CheckerError { error, stderr: None })
+            .and_then(|()| Self::parse_checker_output(&read_to_string(output).expect("Failed to read checker output")));
+        result.map_err(|error| Self::attach_stderr(error, read_tail(&stderr)))
+    }
+
+    /// Attaches the end of the checker's stderr to wrong answers and checker errors
+    fn attach_stderr(error: TestError, checker_stderr: Option<String>) -> TestError {
+        match (error, checker_stderr) {
+            (CheckerError { error, .. }, Some(stderr)) => CheckerError { error, stderr: Some(stderr) },
+            (TestError::Incorrect { error }, Some(stderr)) => TestError::Incorrect {
+                error: format!("{error}\nChecker stderr:\n{stderr}"),
+            },
+            (error, _) => error,
+        }
+    }
+
+    /// Runs a checker using the files or testlib protocol on the input, the program output and the answer file.
+    /// The input and output are copied to temporary files, since they're passed as paths.
+    ///
+    /// With `with_feedback`, a files protocol checker also receives the path of the feedback file,
+    /// which is returned alongside the result if the checker wrote any
+    pub(crate) fn check_files(
+        &self,
+        input_source: &TestInputSource,
+        output: &File,
+        answer_path: &Path,
+        with_feedback: bool,
+    ) -> (Result<(), TestError>, Option<String>) {
+        if !answer_path.is_file() {
+            return (Err(NoOutputFile), None);
+        }
+        #[cfg(feature = "wasm-checker")]
+        if let Some(wasm) = &self.wasm {
+            let (result, stderr, feedback) = wasm.check(input_source, output, answer_path, with_feedback);
+            let result = result.and_then(|output| Self::parse_checker_output(&output));
+            return (result.map_err(|error| Self::attach_stderr(error, stderr)), feedback);
+        }
+        let files = input_source.get_file().and_then(|input| Ok((
+            input,
+            create_test_temp_file()?,
+            create_test_named_temp_file()?,
+            create_test_named_temp_file()?,
+            with_feedback.then(create_test_named_temp_file).transpose()?,
+        )));
+        let (mut input, checker_stdin, mut input_file, mut output_file, feedback_file) = match files {
+            Ok(files) => files,
+            Err(error) => return (Err(error.into()), None),
+        };
+        let mut output = output;
+        let copied = io::copy(&mut input, &mut input_file)
+            .and_then(|_| output.rewind())
+            .and_then(|()| io::copy(&mut output, &mut output_file));
+        if let Err(error) = copied {
+            let error = SandboxError(format!("Failed to copy the input and output for the checker: {error}"));
+            return (Err(CheckerError { error, stderr: None }), None);
+        }
+
+        if self.protocol == CheckerProtocol::Testlib {
+            return (self.run_testlib(input_file.path(), output_file.path(), answer_path), None);
+        }
+        let executor = SimpleExecutor {
+            executable_path: self.executor.executable_path.clone(),
+            arguments: [input_file.path(), output_file.path(), answer_path].into_iter()
+                .chain(feedback_file.as_ref().map(NamedTempFile::path))
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect(),
+            timeout: self.executor.timeout,
+            output_limit: None,
+            kill_grace: Duration::ZERO,
+            sanitizer_report: false,
+            capture_stderr: false,
+            file_io: None,
+            scratch_directory: false,
+            environment: ProgramEnvironment::default(),
+        };
+        let result = Self::run(&executor, checker_stdin);
+        let feedback = feedback_file
+            .and_then(|feedback_file| fs::read_to_string(feedback_file.path()).ok())
+            .filter(|feedback| !feedback.trim().is_empty());
+        (result, feedback)
+    }
+
+    /// Runs a testlib checker, which returns the verdict as the exit code and writes the message to stderr
+    fn run_testlib(&self, input_path: &Path, output_path: &Path, answer_path: &Path) -> Result<(), TestError> {
+        debug(&format!(
+            "Running: {} {} {} {}",
+            self.executor.executable_path.display(),
+            input_path.display(),
+            output_path.display(),
+            answer_path.display(),
+        ));
+        let mut stderr = create_test_temp_file()?;
+        let mut child = Command::new(&self.executor.executable_path)
+            .arg(input_path)
+            .arg(output_path)
+            .arg(answer_path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(make_cloned_stdio(&stderr)?)
+            .spawn()
+            .map_err(|error| CheckerError { error: SandboxError(format!("Failed to start the checker: {error}")), stderr: None })?;
+        let status = child.wait_timeout(self.executor.timeout)
+            .map_err(|error| CheckerError { error: SandboxError(format!("Failed to wait for the checker: {error}")), stderr: None })?;
+        let Some(status) = status else {
+            kill_tree(&mut child)
+                .map_err(|error| CheckerError { error: SandboxError(format!("Failed to stop the checker: {error}")), stderr: None })?;
+            return Err(CheckerError { error: TimedOut, stderr: None });
+        };
+        stderr.rewind().unwrap();
+        let message = read_to_string(stderr).expect("Failed to read checker output");
+        let message = message.trim();
+
+        let incorrect = |verdict: &str| TestError::Incorrect {
+            error: format!("{verdict}{}{message}", if message.is_empty() { "" } else { ": " }).red().to_string(),
+        };
+        match status.code() {
+            Some(0) => Ok(()),
+            Some(1) => Err(incorrect("Wrong answer")),
+            Some(2) => Err(incorrect("Presentation error")),
+            Some(3) => Err(CheckerError { error: RuntimeError(format!("- the checker failed: {message}")), stderr: None }),
+            Some(exit_code) => Err(CheckerError { error: RuntimeError(format!("- the checker returned an unknown exit code {exit_code}: {message}")), stderr: None }),
+            None => Err(CheckerError { error: RuntimeError(format!("- the checker was terminated with the following error:\n{status}")), stderr: None }),
+        }
+    }
 }
@@ -0,0 +1,1151 @@
+use std::{env, fs, panic};
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::panic::PanicHookInfo;
+use std::io::IsTerminal;
+use std::{io, iter};
+use std::path::{Path, PathBuf};
+use std::process::{exit, ExitCode};
+use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::{Acquire, Release};
+use clap::Parser;
+use colored::Colorize;
+use human_panic::{handle_dump, print_msg};
+use indicatif::{ProgressBar, ProgressState, ProgressStyle};
+use rayon::prelude::*;
+use tempfile::tempdir;
+use crate::args::{Args, Command};
+use crate::{batch, compile_cache, doctor, dry_run, exit_code, interrupt, output, precompiled_header, process_tree, program_stderr, single_run, stats, temp_files, theme, tui};
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+use crate::install_sio2jail;
+use crate::args::{verify_compile_command, verify_extra_sources, ActionType, InputConfig, ParsedConfig};
+use crate::args::ExecuteMode;
+use crate::checker::{is_wasm_module, Checker, CheckerProtocol};
+use crate::exit_code::Outcome;
+use crate::compile_cache::CompileCache;
+use crate::compiler::{Compiler, ExecutableKind, ExecutableOrigin};
+use crate::executor::container::ContainerExecutor;
+use crate::executor::median::MedianRerunExecutor;
+use crate::executor::simple::SimpleExecutor;
+use crate::executor::throttled::ThrottledInputExecutor;
+use crate::executor::valgrind::ValgrindExecutor;
+#[cfg(target_os = "linux")]
+use crate::executor::perf_counter::PerfCounterExecutor;
+#[cfg(windows)]
+use crate::executor::restricted_token::RestrictedTokenExecutor;
+#[cfg(target_os = "macos")]
+use crate::executor::rusage::RusageExecutor;
+use crate::prepare_input::{prepare_file_inputs, Test, GZIP_EXTENSION};
+use crate::executor::{AnyTestExecutor, ProgramEnvironment};
+use crate::test_errors::TestError;
+use crate::test_errors::TestError::{Cancelled, DependencyFailed, Incorrect, NotStarted};
+use crate::test_summary::TestSummary;
+use crate::toolchain::{CompileProfile, Toolchain};
+use crate::test_archive::TestArchive;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+use crate::executor::sio2jail::{Sio2jailExecutor, TimeMetric};
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+use crate::executor::isolate::IsolateExecutor;
+use crate::formatted_error::FormattedError;
+use crate::generic_utils::{halt, strip_ansi_codes};
+use crate::input_transform::InputTransformer;
+use crate::validator::Validator;
+use crate::test_chains::{build_chains, sort_largest_first, TestChain};
+use crate::test_runner::TestRunner;
+use crate::test_pattern::TestPattern;
+use crate::test_exclusion::TestExclusion;
+use crate::publish::publish_results;
+use crate::report::write_report;
+use crate::notification::notify_finished;
+use crate::noise_guard::guard_against_noise;
+use crate::cpu_affinity::{configure_thread_pool, isolated_cpus, online_cpus};
+#[cfg(target_os = "linux")]
+use crate::cpu_affinity::raise_priority;
+use crate::saved_outputs::OutputSaver;
+use crate::manifest::{Manifest, TestDependency};
+use crate::comparison::ComparisonMatrix;
+use crate::encoding::record_transcoding_warning;
+use crate::warnings::{add_warning, clear_warnings, print_warnings, WarningKind};
+use crate::benchmark::Benchmark;
+use crate::retries::Retries;
+use crate::perf_profile::PerfProfiler;
+use crate::program_stderr::StderrMode;
+use crate::feedback::FeedbackReport;
+use crate::output_names::OutputNames;
+use crate::scoring::GroupScoring;
+use crate::verdict_expectations::VerdictExpectations;
+use crate::history::{RunComparison, RunRecord};
+
+fn print_output(stopped_early: bool, list_errors: bool, test_summary: &mut Option<TestSummary>) {
+    let Some(test_summary) = test_summary else {
+        println!("{}", "Toster was stopped before testing could start".red());
+        return;
+    };
+
+    if stopped_early {
+        println!();
+    }
+
+    let additional_info = match (&test_summary.slowest_test, &test_summary.most_memory_used) {
+        (None, None) => String::new(),
+        (Some((duration, slowest_test_name)), None) => format!(
+            " (Slowest test: {} at {:.3}s)",
+            slowest_test_name, duration.as_secs_f32(),
+        ),
+        (None, Some((memory, most_memory_test_name))) => format!(
+            " (Most memory used: {most_memory_test_name} at {memory:.3}KiB)",
+        ),
+        (Some((duration, slowest_test_name)), Some((memory, most_memory_test_name))) => format!(
+            " (Slowest test: {} at {:.3}s, most memory used: {} at {}KiB)",
+            slowest_test_name, duration.as_secs_f32(),
+            most_memory_test_name, memory,
+        ),
+    };
+
+    println!(
+        "{} {} {:.2}s{}\nResults: {}",
+        if test_summary.generate_mode { "Generating" } else { "Testing" },
+        if stopped_early { "stopped after" } else { "finished in" },
+        test_summary.start_time.elapsed().as_secs_f64(),
+        additional_info,
+        test_summary.format_counts(true),
+    );
+    if test_summary.failure_limit_reached() && test_summary.processed < test_summary.total {
+        println!("{}", format!(
+            "Testing was stopped after {} failed tests, {} tests were not run",
+            test_summary.processed - test_summary.success,
+            test_summary.total - test_summary.processed,
+        ).yellow());
+    }
+    if test_summary.not_started > 0 {
+        println!("{}", format!(
+            "The total timeout was exceeded, {} tests were not started",
+            test_summary.not_started,
+        ).yellow());
+    }
+    if output::is_quiet() {
+        return;
+    }
+    if let Some(runtime_statistics) = test_summary.format_runtime_statistics() {
+        println!("Runtimes: {runtime_statistics}");
+    }
+    if let Some(close_to_time_limit) = test_summary.format_close_to_time_limit() {
+        add_warning(WarningKind::CloseToTimeLimit, format!("Close to the time limit: {close_to_time_limit}"));
+    }
+
+    if list_errors && !test_summary.get_errors().is_empty() {
+        println!("Errors were found in the following tests:");
+
+        for (test_name, error, stderr) in test_summary.get_errors_with_stderr() {
+            println!("{}", error.to_string(test_name));
+            if let Some(stderr) = stderr {
+                println!("{}\n{}", "Program stderr:".bold(), stderr.dimmed());
+            }
+        }
+    }
+}
+
+fn setup_panic() {
+    let is_panicking = AtomicBool::new(false);
+    if human_panic::PanicStyle::default() == human_panic::PanicStyle::Human {
+        let meta = human_panic::metadata!();
+
+        panic::set_hook(Box::new(move |info: &PanicHookInfo| {
+            if is_panicking.load(Acquire) {
+                halt();
+            }
+            is_panicking.store(true, Release);
+
+            let file_path = handle_dump(&meta, info);
+            print_msg(file_path, &meta).expect("human-panic: printing error message to console failed");
+            exit(0);
+        }));
+    }
+}
+
+fn print_compiler_warnings(name: &str, warnings: &str) {
+    output::info(&format!("{}\n{}", format!("{name} compilation gave the following warnings:").yellow(), warnings.trim_end()));
+}
+
+/// Prints the warnings of the compiler with --show-warnings, or fails with --deny-warnings
+fn check_compiler_warnings(name: &str, origin: &ExecutableOrigin, config: &ParsedConfig) -> Result<(), FormattedError> {
+    let ExecutableOrigin::Compiled { warnings: Some(warnings), .. } = origin else {
+        return Ok(());
+    };
+    if config.deny_warnings {
+        return Err(FormattedError::preformatted(format!(
+            "{}\n{}",
+            format!("{name} compilation failed, because the compiler gave warnings:").red(),
+            warnings.trim_end(),
+        )).with_outcome(Outcome::CompileError));
+    }
+    if config.show_warnings {
+        print_compiler_warnings(name, warnings);
+    }
+    Ok(())
+}
+
+fn print_executable_origin(name: &str, origin: &ExecutableOrigin) {
+    match origin {
+        ExecutableOrigin::Provided => {}
+        ExecutableOrigin::Compiled { time, .. } => {
+            output::info(&format!("{name} compilation completed in {:.2}", time.as_secs_f32()).green().to_string());
+        }
+        ExecutableOrigin::Cached => {
+            output::info(&format!("{name} loaded from the compile cache").green().to_string());
+        }
+    }
+}
+
+/// Returns `Cancelled` if testing was stopped with Ctrl+C or because of a fatal error
+pub(crate) fn check_ctrlc() -> Result<(), TestError> {
+    if interrupt::testing_stopped() { Err(Cancelled) } else { Ok(()) }
+}
+
+#[allow(clippy::too_many_lines)] // Every execute mode is initialized here
+fn init_runner(
+    executable: PathBuf,
+    config: &ParsedConfig,
+    time_limit: Duration,
+    memory_limit_override: Option<u64>,
+) -> Result<AnyTestExecutor, FormattedError> {
+    let timeout = match &config.median_rerun {
+        Some(median_rerun) => MedianRerunExecutor::extended_timeout(time_limit, median_rerun.band),
+        None => time_limit,
+    };
+    let executor = match &config.execute_mode {
+        #[cfg(target_os = "linux")]
+        ExecuteMode::Simple if config.no_network => {
+            let (program, arguments) = SimpleExecutor::split_run_command(config.run_command.as_deref(), &executable);
+            AnyTestExecutor::Simple(SimpleExecutor {
+                sanitizer_report: config.sanitizer_report,
+                capture_stderr: config.stderr != StderrMode::Hide,
+                file_io: config.file_io.clone(),
+                scratch_directory: config.scratch_directory,
+                environment: config.environment.clone(),
+                ..SimpleExecutor::without_network(timeout, &program, arguments, Some(config.output_limit), config.kill_grace)?
+            })
+        }
+        ExecuteMode::Simple => {
+            let (executable_path, arguments) = SimpleExecutor::split_run_command(config.run_command.as_deref(), &executable);
+            AnyTestExecutor::Simple(SimpleExecutor {
+                executable_path,
+                arguments,
+                timeout,
+                output_limit: Some(config.output_limit),
+                kill_grace: config.kill_grace,
+                sanitizer_report: config.sanitizer_report,
+                capture_stderr: config.stderr != StderrMode::Hide,
+                file_io: config.file_io.clone(),
+                scratch_directory: config.scratch_directory,
+                environment: config.environment.clone(),
+            })
+        }
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        ExecuteMode::Sio2jail { memory_limit, extra_args, time_metric } => AnyTestExecutor::Sio2Jail(Sio2jailExecutor::init_and_test(
+            timeout,
+            *time_metric,
+            executable,
+            memory_limit_override.unwrap_or(*memory_limit),
+            extra_args.clone(),
+            config.environment.clone(),
+        )?),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        ExecuteMode::Isolate { memory_limit } => AnyTestExecutor::Isolate(IsolateExecutor::init_and_test(
+            timeout,
+            executable,
+            memory_limit_override.or(*memory_limit),
+            config.environment.clone(),
+        )?),
+        #[cfg(target_os = "linux")]
+        ExecuteMode::PerfCounters { memory_limit } => {
+            let (executable_path, arguments) = SimpleExecutor::split_run_command(config.run_command.as_deref(), &executable);
+            AnyTestExecutor::PerfCounter(PerfCounterExecutor::init_and_test(
+                timeout,
+                executable_path,
+                arguments,
+                memory_limit_override.or(*memory_limit),
+                config.environment.clone(),
+            )?)
+        }
+        #[cfg(windows)]
+        ExecuteMode::RestrictedToken { memory_limit } => {
+            let (executable_path, arguments) = SimpleExecutor::split_run_command(config.run_command.as_deref(), &executable);
+            AnyTestExecutor::RestrictedToken(RestrictedTokenExecutor::init_and_test(
+                timeout,
+                executable_path,
+                arguments,
+                memory_limit_override.or(*memory_limit),
+                config.environment.clone(),
+            )?)
+        }
+        #[cfg(target_os = "macos")]
+        ExecuteMode::Rusage { memory_limit, sandbox_exec } => {
+            let (executable_path, arguments) = SimpleExecutor::split_run_command(config.run_command.as_deref(), &executable);
+            AnyTestExecutor::Rusage(RusageExecutor::init_and_test(
+                timeout,
+                executable_path,
+                arguments,
+                memory_limit_override.or(*memory_limit),
+                *sandbox_exec,
+                config.environment.clone(),
+            )?)
+        }
+        ExecuteMode::Container { runtime, image, memory_limit } => AnyTestExecutor::Container(ContainerExecutor::init_and_test(
+            timeout,
+            executable,
+            *runtime,
+            image.clone(),
+            memory_limit_override.or(*memory_limit),
+            config.environment.clone(),
+        )?),
+        ExecuteMode::Valgrind { timeout_factor } => AnyTestExecutor::Valgrind(ValgrindExecutor::init_and_test(
+            timeout.mul_f64(*timeout_factor),
+            executable,
+            config.kill_grace,
+            config.environment.clone(),
+        )?),
+    };
+
+    let executor = match config.input_rate {
+        Some(bytes_per_second) => AnyTestExecutor::ThrottledInput(ThrottledInputExecutor {
+            inner: Box::new(executor),
+            bytes_per_second,
+        }),
+        None => executor,
+    };
+    Ok(match &config.median_rerun {
+        Some(median_rerun) => AnyTestExecutor::MedianRerun(MedianRerunExecutor {
+            inner: Box::new(executor),
+            time_limit,
+            band: median_rerun.band,
+            max_runs: median_rerun.max_runs,
+        }),
+        None => executor,
+    })
+}
+
+/// Creates the executor running a checker in the sandbox of the tested program,
+/// with the checker timeout instead of the time limit
+fn init_checker_sandbox(executable: PathBuf, config: &ParsedConfig) -> Result<AnyTestExecutor, FormattedError> {
+    Ok(match &config.execute_mode {
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        ExecuteMode::Sio2jail { memory_limit, extra_args, .. } => AnyTestExecutor::Sio2Jail(Sio2jailExecutor::init_and_test(
+            config.checker_timeout,
+            TimeMetric::Real,
+            executable,
+            *memory_limit,
+            extra_args.clone(),
+            ProgramEnvironment::default(),
+        )?),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        ExecuteMode::Isolate { memory_limit } => AnyTestExecutor::Isolate(IsolateExecutor::init_and_test(
+            config.checker_timeout,
+            executable,
+            *memory_limit,
+            ProgramEnvironment::default(),
+        )?),
+        #[cfg(target_os = "linux")]
+        ExecuteMode::PerfCounters { memory_limit } => AnyTestExecutor::PerfCounter(PerfCounterExecutor::init_and_test(
+            config.checker_timeout,
+            executable,
+            vec![],
+            *memory_limit,
+            ProgramEnvironment::default(),
+        )?),
+        #[cfg(windows)]
+        ExecuteMode::RestrictedToken { memory_limit } => AnyTestExecutor::RestrictedToken(RestrictedTokenExecutor::init_and_test(
+            config.checker_timeout,
+            executable,
+            vec![],
+            *memory_limit,
+            ProgramEnvironment::default(),
+        )?),
+        #[cfg(target_os = "macos")]
+        ExecuteMode::Rusage { memory_limit, sandbox_exec } => AnyTestExecutor::Rusage(RusageExecutor::init_and_test(
+            config.checker_timeout,
+            executable,
+            vec![],
+            *memory_limit,
+            *sandbox_exec,
+            ProgramEnvironment::default(),
+        )?),
+        ExecuteMode::Container { runtime, image, memory_limit } => AnyTestExecutor::Container(ContainerExecutor::init_and_test(
+            config.checker_timeout,
+            executable,
+            *runtime,
+            image.clone(),
+            *memory_limit,
+            ProgramEnvironment::default(),
+        )?),
+        ExecuteMode::Simple | ExecuteMode::Valgrind { .. } => unreachable!("The checker can only be sandboxed together with a sandbox"),
+    })
+}
+
+/// Creates a checker, running it in the sandbox if it's enabled
+fn init_checker(executable: PathBuf, config: &ParsedConfig, protocol: CheckerProtocol) -> Result<Checker, FormattedError> {
+    let checker = Checker::new(executable.clone(), config.checker_timeout, protocol);
+    if !config.sandbox_checker {
+        return Ok(checker);
+    }
+    Ok(checker.sandboxed(init_checker_sandbox(executable, config)?))
+}
+
+/// Returns the compile command, using the precompiled bits/stdc++.h if it's enabled.
+/// If the header can't be precompiled, the programs are compiled without it
+fn prepare_compile_command(config: &ParsedConfig) -> String {
+    if !config.precompiled_header {
+        return config.compile_command.clone();
+    }
+    match precompiled_header::with_precompiled_header(&config.compile_command, config.compile_timeout) {
+        Ok((compile_command, build_time)) => {
+            if let Some(build_time) = build_time {
+                output::info(&format!("Precompiled header built in {:.2}", build_time.as_secs_f32()).green().to_string());
+            }
+            compile_command
+        }
+        Err(error) => {
+            add_warning(WarningKind::PrecompiledHeader, format!("Failed to precompile bits/stdc++.h, the programs were compiled without it:\n{error}"));
+            config.compile_command.clone()
+        }
+    }
+}
+
+/// Compiles the checker set with the --checker option
+fn compile_checker(compiler: &Compiler, config: &ParsedConfig) -> Result<Option<Checker>, FormattedError> {
+    let ActionType::Checker { path, protocol, .. } = &config.action_type else {
+        return Ok(None);
+    };
+    if is_wasm_module(path) {
+        return Ok(Some(Checker::wasm(path, config.checker_timeout)?));
+    }
+    // The extra sources and the run command belong to the solution
+    let compiler = Compiler { extra_sources: &[], custom_run_command: false, ..*compiler };
+    let (executable, origin) = compiler
+        .prepare_executable(path, "checker")
+        .map_err(|error| error.to_formatted(ExecutableKind::Checker))?;
+    print_executable_origin("Checker", &origin);
+    Ok(Some(init_checker(executable, config, *protocol)?))
+}
+
+/// Compiles the validator set with the --validator option
+fn compile_validator(compiler: &Compiler, config: &ParsedConfig) -> Result<Option<Validator>, FormattedError> {
+    let Some(path) = &config.validator else {
+        return Ok(None);
+    };
+    let compiler = Compiler { extra_sources: &[], custom_run_command: false, ..*compiler };
+    let (executable, origin) = compiler
+        .prepare_executable(path, "validator")
+        .map_err(|error| error.to_formatted(ExecutableKind::Validator))?;
+    print_executable_origin("Validator", &origin);
+    Ok(Some(Validator::new(executable, config.execute_timeout)))
+}
+
+/// Compiles the checkers set for groups of tests in the manifest
+fn compile_checker_overrides(compiler: &Compiler, config: &ParsedConfig) -> Result<Vec<(TestPattern, Option<Checker>)>, FormattedError> {
+    if config.generate_mode() {
+        return Ok(vec![]);
+    }
+    let compiler = Compiler { extra_sources: &[], custom_run_command: false, ..*compiler };
+    config.checker_overrides.iter()
+        .enumerate()
+        .map(|(index, checker_override)| {
+            let checker = checker_override.checker.as_ref().map(|path| -> Result<_, FormattedError> {
+                if is_wasm_module(path) {
+                    return Checker::wasm(path, config.checker_timeout);
+                }
+                let (executable, origin) = compiler
+                    .prepare_executable(path, &format!("checker-{index}"))
+                    .map_err(|error| error.to_formatted(ExecutableKind::Checker))?;
+                print_executable_origin(&format!("Checker for tests matching \"{}\"", checker_override.tests), &origin);
+                init_checker(executable, config, CheckerProtocol::Toster)
+            }).transpose()?;
+            Ok((checker_override.tests.clone(), checker))
+        })
+        .collect()
+}
+
+/// Configures the testing threads according to the system noise and the CPUs chosen for testing
+pub(crate) fn setup_thread_pool(config: &ParsedConfig) {
+    let noise_limit = if config.generate_mode() { None } else { guard_against_noise(config.noise_guard) };
+    let thread_limit = match (config.jobs, noise_limit) {
+        (Some(jobs), Some(limit)) => Some(jobs.min(limit)),
+        (jobs, limit) => jobs.or(limit),
+    };
+    let cpus = config.cpus.clone().or_else(|| {
+        let isolated = isolated_cpus();
+        (!isolated.is_empty()).then(|| {
+            output::info(&format!("Running tests on the isolated CPUs {}", isolated.iter().map(usize::to_string).collect::<Vec<_>>().join(", ")));
+            isolated
+        })
+    }).or_else(|| config.pin_cpus.then(online_cpus).filter(|cpus| !cpus.is_empty()));
+    #[cfg(target_os = "linux")]
+    if config.high_priority {
+        if let Err(error) = raise_priority() {
+            add_warning(WarningKind::Priority, format!("Failed to raise the priority of the tested programs, they were run with the default priority: {error}"));
+        }
+    }
+    configure_thread_pool(cpus, thread_limit);
+}
+
+/// Creates the executor for the program and the executors with limits overridden in the manifest
+fn init_executors(
+    executable: &Path,
+    config: &ParsedConfig,
+) -> Result<(AnyTestExecutor, Vec<(TestPattern, AnyTestExecutor)>), FormattedError> {
+    let executor = init_runner(executable.to_path_buf(), config, config.execute_timeout, None)?;
+    let limit_overrides = config.limit_overrides.iter()
+        .map(|limit| -> Result<_, FormattedError> {
+            let executor = init_runner(
+                executable.to_path_buf(),
+                config,
+                limit.time_limit.unwrap_or(config.execute_timeout),
+                limit.memory_limit,
+            )?;
+            Ok((limit.tests.clone(), executor))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((executor, limit_overrides))
+}
+
+/// Reads the tests without the skipped ones and groups them into chains, returning the number of tests and the chains
+pub(crate) fn prepare_chains(input: &InputConfig, dependencies: &[TestDependency], exclusion: &TestExclusion) -> Result<(usize, Vec<TestChain>), FormattedError> {
+    let inputs = match input {
+        InputConfig::Directory { directory, ext } => {
+            prepare_file_inputs(directory, ext)?
+        }
+        InputConfig::Archive { archive, ext } => {
+            TestArchive::open(archive).map_err(|error| FormattedError::from_str(&error))?.prepare_inputs(ext)?
+        }
+    };
+    let (tests, dependencies) = exclusion.apply(inputs.iterator.collect(), dependencies);
+    if tests.is_empty() {
+        return Err(FormattedError::from_str("All tests were skipped"));
+    }
+    let test_count = tests.len();
+    let mut chains = build_chains(tests, &dependencies)?;
+    sort_largest_first(&mut chains);
+    Ok((test_count, chains))
+}
+
+/// Runs one of the compared solutions on all tests and records the results in `matrix`
+fn run_solution(
+    input: &InputConfig,
+    dependencies: &[TestDependency],
+    exclusion: &TestExclusion,
+    test_summary: &Arc<Mutex<Option<TestSummary>>>,
+    test_runner: &TestRunner,
+    solution: usize,
+    matrix: &ComparisonMatrix,
+) -> Result<(), FormattedError> {
+    let (test_count, chains) = prepare_chains(input, dependencies, exclusion)?;
+    *test_summary.lock().expect("Failed to lock test summary mutex") = Some(TestSummary::new(false, test_count));
+    let progress_bar = ProgressBar::new(test_count as u64).with_style(progress_bar_style(test_summary.clone()));
+    map_tests(chains, &progress_bar, test_summary, test_runner, None, Some((matrix, solution)), None, None)?;
+    progress_bar.finish_and_clear();
+
+    let test_summary = test_summary.lock().expect("Failed to lock test summary mutex");
+    let test_summary = test_summary.as_ref().unwrap();
+    println!(
+        "{} finished in {:.2}s\nResults: {}",
+        matrix.solution_name(solution).bold(),
+        test_summary.start_time.elapsed().as_secs_f64(),
+        test_summary.format_counts(false),
+    );
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)] // Every consumer of the test results is optional
+fn map_tests(
+    chains: Vec<TestChain>,
+    progress_bar: &ProgressBar,
+    test_summary: &Arc<Mutex<Option<TestSummary>>>,
+    test_runner: &TestRunner,
+    output_saver: Option<&OutputSaver>,
+    matrix: Option<(&ComparisonMatrix, usize)>,
+    scoring: Option<&GroupScoring>,
+    expectations: Option<&VerdictExpectations>,
+) -> Result<(), FormattedError> {
+    output::set_progress_bar(Some(progress_bar.clone()));
+    // The chains are taken from a shared queue in order, so the largest tests are started first
+    chains.into_iter().par_bridge().try_for_each(|chain| {
+        // Test names and the kept program outputs of tests that were already run in this chain
+        let mut outputs: Vec<(String, Option<File>)> = Vec::with_capacity(chain.tests.len());
+        for chained_test in chain.tests {
+            if test_summary.lock().expect("Failed to lock test summary mutex").as_ref().is_some_and(TestSummary::failure_limit_reached) {
+                return None;
+            }
+            let test_name = chained_test.test.test_name.clone();
+            let keep_output = chained_test.has_dependents || output_saver.is_some();
+            // The remaining tests are still taken from the queue, so each of them is reported as skipped
+            let total_timeout_reached = test_summary.lock().expect("Failed to lock test summary mutex").as_ref().is_some_and(TestSummary::total_timeout_reached);
+
+            interrupt::start_test(&test_name);
+            let (result, output) = match chained_test.dependency {
+                _ if total_timeout_reached => (Err(NotStarted), None),
+                None => test_runner.run_test(&chained_test.test, keep_output),
+                Some(dependency) => match &outputs[dependency] {
+                    (_, Some(previous_output)) => {
+                        let previous_output = previous_output.try_clone().expect("Failed to clone previous test output");
+                        test_runner.run_test(&chained_test.with_previous_output(previous_output), keep_output)
+                    }
+                    (dependency, None) => (Err(DependencyFailed { dependency: dependency.clone() }), None),
+                },
+            };
+            interrupt::finish_test(&test_name);
+            if let Some(output_saver) = output_saver {
+                if let Some(output) = &output {
+                    output_saver.save_file(&format!("{test_name}.out"), output);
+                }
+                if let Err(error @ Incorrect { .. }) = &result {
+                    output_saver.save_text(&format!("{test_name}.diff"), &strip_ansi_codes(&error.message()));
+                }
+            }
+            if let Some((matrix, solution)) = matrix {
+                matrix.record(solution, &test_name, &result);
+            }
+            if let (Some(scoring), false) = (scoring, matches!(result, Err(Cancelled))) {
+                scoring.record(&test_name, result.is_ok());
+            }
+            if let (Some(expectations), false) = (expectations, matches!(result, Err(Cancelled))) {
+                expectations.record(&test_name, &result);
+            }
+            outputs.push((test_name.clone(), output));
+            output::print_verdict(&test_name, &result);
+            let stderr = program_stderr::take();
+            if let (StderrMode::Show, Ok(_), Some(stderr)) = (program_stderr::mode(), &result, &stderr) {
+                output::print_line(&format!("{}\n{}", format!("Stderr of test {test_name}:").bold(), stderr.dimmed()));
+            }
+            progress_bar.inc(1);
+
+            let mut test_summary = test_summary.lock().expect("Failed to lock test summary mutex");
+            let test_summary = test_summary.as_mut().unwrap();
+            match result {
+                Ok(metrics) => {
+                    if let (Some(time_limit), Some(time)) = (test_runner.close_to_time_limit(&test_name, &metrics), metrics.time) {
+                        test_summary.add_close_to_time_limit(&test_name, time, time_limit);
+                    }
+                    test_summary.add_success(&metrics, &test_name);
+                }
+                Err(Cancelled) => return None,
+                Err(error) => {
+                    if let Some(stderr) = stderr {
+                        test_summary.add_stderr(&test_name, stderr);
+                    }
+                    test_summary.add_test_error(error, test_name);
+                }
+            }
+        }
+        Some(())
+    });
+    output::set_progress_bar(None);
+    if let Some(output_saver) = output_saver {
+        output_saver.finish();
+    }
+    match interrupt::take_fatal_error() {
+        Some(error) => {
+            progress_bar.finish_and_clear();
+            Err(FormattedError::from_str(&error))
+        }
+        None => Ok(()),
+    }
+}
+
+fn progress_bar_style(test_summary: Arc<Mutex<Option<TestSummary>>>) -> ProgressStyle {
+    ProgressStyle::with_template("[{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} ({eta})\n{counts} {ctrlc}")
+        .expect("Progress bar creation failed")
+        .with_key("eta", |state: &ProgressState, w: &mut dyn FmtWrite| write!(w, "{:.1}s", state.eta().as_secs_f64()).expect("Displaying the progress bar failed"))
+        .progress_chars("#>-")
+        .with_key("counts", move |_state: &ProgressState, w: &mut dyn FmtWrite| {
+            write!(w, "{}", test_summary.lock().expect("Failed to lock test summary mutex").as_ref().unwrap().format_counts(false)).expect("Displaying the progress bar failed");
+        })
+        .with_key("ctrlc", |_state: &ProgressState, w: &mut dyn FmtWrite| {
+            write!(w, "{}", "(Press Ctrl+C to stop testing and print current results)".bright_black()).expect("Displaying the progress bar Ctrl+C message failed");
+        })
+}
+
+/// Runs the command line interface of toster with the arguments of the process
+#[must_use]
+pub fn main() -> ExitCode {
+    setup_panic();
+
+    if let Err(error) = try_main() {
+        output::print_error(&error.to_string());
+        exit_code::record_outcome(error.outcome());
+    }
+    ExitCode::from(exit_code::exit_code())
+}
+
+#[allow(clippy::too_many_lines)] // Every subcommand is run here
+fn run_command(command: Command) -> Result<(), FormattedError> {
+    match command {
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        Command::InstallSio2jail { url, sha256 } => {
+            install_sio2jail::install_sio2jail(
+                url.as_deref().unwrap_or(install_sio2jail::DEFAULT_SIO2JAIL_URL),
+                sha256.as_deref().unwrap_or(install_sio2jail::DEFAULT_SIO2JAIL_SHA256),
+            )
+        }
+        #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+        Command::InstallSio2jail { .. } => {
+            Err(FormattedError::from_str("Sio2jail is only available on x86-64 Linux"))
+        }
+        Command::Doctor { compile_command, compiler } => {
+            let compile_command = match compile_command {
+                Some(compile_command) => compile_command,
+                None => Toolchain::resolve_compile_command(compiler, CompileProfile::Release).map_err(|error| FormattedError::from_str(&error))?,
+            };
+            if doctor::run_doctor(&compile_command) {
+                Ok(())
+            } else {
+                Err(FormattedError::from_str("Some problems were found, toster might not work correctly"))
+            }
+        }
+        Command::Stats { r#in, in_ext, out, out_ext, out_pattern, io, manifest } => {
+            let manifest = Manifest::load(manifest.as_ref()).map_err(|error| FormattedError::from_str(&error))?;
+            let (input_directory, output_directory) = match io {
+                Some(io) => (io.clone(), io),
+                None => (r#in, out),
+            };
+            let output_names = if out_pattern.is_empty() {
+                OutputNames::from_extensions(&out_ext)
+            } else {
+                OutputNames::from_patterns(out_pattern).map_err(|error| FormattedError::from_str(&error))?
+            };
+            stats::print_stats(&input_directory, &in_ext, &output_directory, &output_names, &manifest)
+        }
+        Command::Clean { compile_cache_dir, max_size } => {
+            let directory = match compile_cache_dir {
+                Some(directory) => directory,
+                None => CompileCache::default_directory()?,
+            };
+            if !directory.is_dir() {
+                println!("The compile cache is empty");
+                return Ok(());
+            }
+            let (removed, removed_size) = compile_cache::evict(&directory, max_size.unwrap_or(0))
+                .map_err(|error| FormattedError::from_str(&format!("Failed to clean the compile cache {}:\n{error}", directory.display())))?;
+            println!("Removed {removed} executables ({removed_size} bytes) from the compile cache");
+            Ok(())
+        }
+        Command::Run { input, compile_timeout, compile_command, compiler, profile, extra_source, filename } => {
+            if !filename.is_file() {
+                return Err(FormattedError::from_str("The provided file does not exist"));
+            }
+            let compile_command = match compile_command {
+                Some(compile_command) => compile_command,
+                None => Toolchain::resolve_compile_command(compiler, profile).map_err(|error| FormattedError::from_str(&error))?,
+            };
+            verify_compile_command(&compile_command).map_err(|error| FormattedError::from_str(&error))?;
+            verify_extra_sources(&extra_source).map_err(|error| FormattedError::from_str(&error))?;
+            let tempdir = tempdir().expect("Failed to create temporary directory");
+            let compiler = Compiler {
+                tempdir: &tempdir,
+                compile_timeout,
+                compile_command: &compile_command,
+                cache: None,
+                extra_sources: &extra_source,
+                custom_run_command: false,
+            };
+            let (executable, origin) = compiler
+                .prepare_executable(&filename, "program")
+                .map_err(|error| error.to_formatted(ExecutableKind::Program))?;
+            print_executable_origin("Program", &origin);
+            single_run::run_once(&executable, input.as_deref())
+        }
+        Command::Build { compile_timeout, compile_command, compiler, profile, extra_source, compile_cache, compile_cache_dir, compile_cache_size, output, filename } => {
+            if !filename.is_file() {
+                return Err(FormattedError::from_str("The provided file does not exist"));
+            }
+            if !Compiler::is_source_file(&filename) {
+                return Err(FormattedError::from_str("The provided file is already an executable"));
+            }
+            let compile_command = match compile_command {
+                Some(compile_command) => compile_command,
+                None => Toolchain::resolve_compile_command(compiler, profile).map_err(|error| FormattedError::from_str(&error))?,
+            };
+            verify_compile_command(&compile_command).map_err(|error| FormattedError::from_str(&error))?;
+            verify_extra_sources(&extra_source).map_err(|error| FormattedError::from_str(&error))?;
+            let cache_directory = match (compile_cache_dir, compile_cache) {
+                (Some(directory), _) => Some(directory),
+                (None, true) => Some(CompileCache::default_directory()?),
+                (None, false) => None,
+            };
+            let cache = cache_directory.map(|directory| CompileCache::open(directory, compile_cache_size)).transpose()?;
+            let tempdir = tempdir().expect("Failed to create temporary directory");
+            let compiler = Compiler {
+                tempdir: &tempdir,
+                compile_timeout,
+                compile_command: &compile_command,
+                cache: cache.as_ref(),
+                extra_sources: &extra_source,
+                custom_run_command: false,
+            };
+            let output = output.unwrap_or_else(|| filename.with_extension(env::consts::EXE_EXTENSION));
+            if output == filename {
+                return Err(FormattedError::from_str("The executable can't be saved over the source file, choose a different path with --output"));
+            }
+            build_executable(&compiler, &filename, &output)
+        }
+        Command::Batch { directory, args } => batch::run_batch(&directory, &args),
+        Command::GenOut { args } => {
+            let program_name = OsString::from("toster gen-out");
+            let args = Args::parse_from([program_name, OsString::from("--generate")].into_iter().chain(args));
+            exit_code::set_policy(args.exit_code_policy);
+            if args.command.is_some() {
+                return Err(FormattedError::from_str("gen-out can't be combined with other subcommands"));
+            }
+            run_tests(args)
+        }
+        Command::Compare { old, new, args } => {
+            let program_name = OsString::from("toster compare");
+            let args = Args::parse_from(
+                [program_name, OsString::from("--compare"), new.into_os_string()].into_iter()
+                    .chain(args)
+                    .chain([old.into_os_string()])
+            );
+            exit_code::set_policy(args.exit_code_policy);
+            if args.command.is_some() {
+                return Err(FormattedError::from_str("compare can't be combined with other subcommands"));
+            }
+            run_tests(args)
+        }
+    }
+}
+
+/// Compiles the program and saves the executable, printing the warnings of the compiler
+fn build_executable(compiler: &Compiler, source_path: &Path, output_path: &Path) -> Result<(), FormattedError> {
+    let (executable, origin) = compiler
+        .prepare_executable(source_path, "program")
+        .map_err(|error| error.to_formatted(ExecutableKind::Program))?;
+    if let ExecutableOrigin::Compiled { warnings: Some(warnings), .. } = &origin {
+        print_compiler_warnings("Program", warnings);
+    }
+    print_executable_origin("Program", &origin);
+    fs::copy(&executable, output_path).map_err(|error| FormattedError::from_str(
+        &format!("Failed to save the executable to {}:\n{error}", output_path.display())
+    ))?;
+    println!("The executable was saved to {}", output_path.display());
+    Ok(())
+}
+
+fn try_main() -> Result<(), FormattedError> {
+    let mut args = Args::parse();
+    args.color.apply();
+    exit_code::set_policy(args.exit_code_policy);
+    if let Some(command) = args.command.take() {
+        return run_command(command);
+    }
+    run_tests(args)
+}
+
+fn run_tests(args: Args) -> Result<(), FormattedError> {
+    output::set_format(args.format);
+    let config = ParsedConfig::try_from(args)
+        .map_err(|error| FormattedError::from_str(&error))?;
+    let test_summary: Arc<Mutex<Option<TestSummary>>> = Arc::new(Mutex::new(None));
+    set_ctrlc_handler(test_summary.clone());
+    setup_thread_pool(&config);
+    test_task(config, &test_summary)
+}
+
+/// Makes Ctrl+C stop testing: the running programs are killed and no new tests are started,
+/// so testing finishes early and the results of the tests that already finished are printed.
+///
+/// Pressing Ctrl+C before testing starts or a second time exits immediately
+pub(crate) fn set_ctrlc_handler(test_summary: Arc<Mutex<Option<TestSummary>>>) {
+    ctrlc::set_handler(move || {
+        let pressed_again = interrupt::record_ctrl_c();
+        output::stop_progress_bar();
+        process_tree::kill_running();
+        // The summary is locked while the results are printed, testing has already finished then
+        let testing = test_summary.try_lock().map_or(true, |test_summary| test_summary.is_some());
+        if testing && !pressed_again {
+            return;
+        }
+        if !testing {
+            print_output(true, true, &mut None);
+        }
+        record_transcoding_warning();
+        print_warnings();
+        interrupt::remove_temp_directories();
+        exit(i32::from(exit_code::exit_code()));
+    }).expect("Error setting Ctrl-C handler");
+}
+
+/// Prints the results and statistics after all tests were run or testing was stopped with Ctrl+C
+fn print_results(
+    test_runner: &TestRunner,
+    scoring: Option<&GroupScoring>,
+    expectations: Option<&VerdictExpectations>,
+    comparison: Option<(&RunComparison, &RunRecord)>,
+    list_errors: bool,
+    test_summary: &mut Option<TestSummary>,
+) {
+    print_output(interrupt::received_ctrl_c(), list_errors, test_summary);
+    if let Some(scoring) = scoring {
+        scoring.print();
+    }
+    if let Some(expectations) = expectations {
+        expectations.print();
+    }
+    if let Some((comparison, current_run)) = comparison {
+        comparison.print(current_run);
+    }
+    if let Some(retries) = &test_runner.retries {
+        retries.print();
+    }
+    if let Some(benchmark) = &test_runner.benchmark {
+        benchmark.print();
+    }
+    record_transcoding_warning();
+    print_warnings();
+}
+
+/// The maximum number of output files listed in the warning about unused output files
+const MAX_LISTED_UNUSED_OUTPUTS: usize = 10;
+
+/// Records a warning about output files without a matching input file, which usually means a test is missing.
+/// The outputs of skipped tests aren't reported
+fn warn_about_unused_outputs(test_runner: &TestRunner, chains: &[TestChain], exclusion: &TestExclusion, dependencies: &[TestDependency]) {
+    let (ActionType::SimpleCompare { output_directory, output_names }
+        | ActionType::Checker { output_directory, output_names, .. }) = &test_runner.action_type else {
+        return;
+    };
+    let test_names: HashSet<&str> = chains.iter()
+        .flat_map(|chain| &chain.tests)
+        .map(|chained_test| chained_test.test.test_name.as_str())
+        .collect();
+    let output_file_names: Vec<String> = match &test_runner.output_archive {
+        Some(archive) => archive.file_names().map(str::to_owned).collect(),
+        None => fs::read_dir(output_directory).into_iter().flatten()
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .collect(),
+    };
+    let mut unused: Vec<&str> = output_file_names.iter()
+        .filter_map(|name| output_names.test_name(name.strip_suffix(GZIP_EXTENSION).unwrap_or(name)))
+        .filter(|test_name| !test_names.contains(test_name))
+        .collect();
+    let skipped = exclusion.excluded(unused.iter().copied(), dependencies);
+    unused.retain(|test_name| !skipped.contains(test_name));
+    if unused.is_empty() {
+        return;
+    }
+    unused.sort_by(|a, b| human_sort::compare(a, b));
+    let mut message = format!("{} output files don't have a matching input file: {}", unused.len(), unused[..unused.len().min(MAX_LISTED_UNUSED_OUTPUTS)].join(", "));
+    if unused.len() > MAX_LISTED_UNUSED_OUTPUTS {
+        write!(message, " and {} more", unused.len() - MAX_LISTED_UNUSED_OUTPUTS).expect("Writing to a string failed");
+    }
+    add_warning(WarningKind::UnusedOutputFiles, message);
+}
+
+/// Compiles the program and runs it on all tests of a task
+#[allow(clippy::too_many_lines)] // The steps of testing are easiest to follow in order
+pub(crate) fn test_task(config: ParsedConfig, test_summary: &Arc<Mutex<Option<TestSummary>>>) -> Result<(), FormattedError> {
+    output::set_verbosity(config.verbosity);
+    theme::set_theme(config.theme.clone());
+    let tempdir = tempdir().expect("Failed to create temporary directory");
+    output::verbose(&format!("Temporary directory: {}", tempdir.path().display()));
+    interrupt::register_temp_directory(tempdir.path());
+    if let Some(directory) = &config.sinol_package {
+        output::info(&format!("Using the tests and limits of the sinol package in {}", directory.display()));
+    }
+    if let Some(directory) = &config.polygon_package {
+        output::info(&format!("Using the tests, checker and limits of the Polygon package in {}", directory.display()));
+    }
+    if config.dry_run {
+        return dry_run::print_plan(&config, &tempdir);
+    }
+    let comparison = config.compare_with.as_ref()
+        .map(|reference| RunComparison::load(reference, config.slower_threshold))
+        .transpose()
+        .map_err(|error| FormattedError::from_str(&error))?
+        .flatten();
+
+    if let ActionType::Generate { output_directory, .. } = &config.action_type {
+        if !output_directory.is_dir() {
+            fs::create_dir_all(output_directory).expect("Failed to create output directory");
+        }
+    }
+
+    let compile_cache = config.compile_cache_directory.clone()
+        .map(|directory| CompileCache::open(directory, config.compile_cache_size))
+        .transpose()?;
+    let compile_command = prepare_compile_command(&config);
+    let compiler = Compiler {
+        tempdir: &tempdir,
+        compile_timeout: config.compile_timeout,
+        compile_command: &compile_command,
+        cache: compile_cache.as_ref(),
+        extra_sources: &config.extra_sources,
+        custom_run_command: config.run_command.is_some(),
+    };
+
+    let executable = {
+        let prepared = if config.prebuilt {
+            compiler.prepare_provided_executable(&config.source_path, "program")
+        } else {
+            compiler.prepare_executable(&config.source_path, "program")
+        };
+        let (executable, origin) = prepared.map_err(|error| error.to_formatted(ExecutableKind::Program))?;
+        check_compiler_warnings("Program", &origin, &config)?;
+        print_executable_origin("Program", &origin);
+        executable
+    };
+
+    let perf_profiler = config.profile_perf.as_ref()
+        .map(|_| PerfProfiler::new(&executable, config.run_command.as_deref(), config.perf_dir.clone()))
+        .transpose()?;
+    let checker = compile_checker(&compiler, &config)?;
+    let validator = compile_validator(&compiler, &config)?;
+    let (runner, limit_overrides) = init_executors(&executable, &config)?;
+    let compared = config.compare.iter().enumerate()
+        .map(|(index, path)| {
+            let (executable, origin) = compiler
+                .prepare_executable(path, &format!("solution-{index}"))
+                .map_err(|error| error.to_formatted(ExecutableKind::Program))?;
+            check_compiler_warnings(&format!("Solution {}", path.display()), &origin, &config)?;
+            print_executable_origin(&format!("Solution {}", path.display()), &origin);
+            init_executors(&executable, &config)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let input_transformer = config.input_transform.as_deref()
+        .map(|command| InputTransformer::new(command, config.execute_timeout))
+        .transpose()?;
+    let checker_overrides = compile_checker_overrides(&compiler, &config)?;
+
+    output::set_live_verdicts(config.live);
+    program_stderr::set_mode(config.stderr);
+    temp_files::set_timeout(config.temp_file_timeout);
+    let generate_mode = config.generate_mode();
+    let mut test_runner = TestRunner {
+        action_type: config.action_type,
+        executor: runner,
+        limit_overrides,
+        checker,
+        checker_overrides,
+        input_transformer,
+        validator,
+        diff_options: config.diff_options,
+        builtin_checker: config.builtin_checker,
+        output_limit: config.output_limit,
+        benchmark: config.runs.map(|runs| Benchmark::new(runs, config.warmup_runs)),
+        retries: config.retries.map(Retries::new),
+        check_determinism: config.check_determinism,
+        time_limit: config.execute_timeout,
+        time_limit_overrides: config.limit_overrides.iter()
+            .filter_map(|limit| Some((limit.tests.clone(), limit.time_limit?)))
+            .collect(),
+        warn_threshold: config.warn_threshold,
+        feedback: config.feedback_report.as_ref().map(|_| FeedbackReport::new()),
+        output_archive: config.output_archive.as_deref()
+            .map(TestArchive::open)
+            .transpose()
+            .map_err(|error| FormattedError::from_str(&error))?,
+    };
+    if !compared.is_empty() {
+        let matrix = ComparisonMatrix::new(
+            iter::once(&config.source_path).chain(&config.compare).map(|path| path.display().to_string()).collect()
+        );
+        run_solution(&config.input, &config.dependencies, &config.exclusion, test_summary, &test_runner, 0, &matrix)?;
+        for (index, (executor, limit_overrides)) in compared.into_iter().enumerate() {
+            if interrupt::received_ctrl_c() {
+                break;
+            }
+            test_runner.executor = executor;
+            test_runner.limit_overrides = limit_overrides;
+            run_solution(&config.input, &config.dependencies, &config.exclusion, test_summary, &test_runner, index + 1, &matrix)?;
+        }
+        matrix.print();
+        record_transcoding_warning();
+        print_warnings();
+        clear_warnings();
+        return Ok(());
+    }
+
+    let (test_count, chains) = prepare_chains(&config.input, &config.dependencies, &config.exclusion)?;
+    warn_about_unused_outputs(&test_runner, &chains, &config.exclusion, &config.dependencies);
+    *test_summary.lock().expect("Failed to lock test summary mutex") = Some(
+        TestSummary::new(generate_mode, test_count)
+            .with_max_failures(config.max_failures)
+            .with_total_timeout(config.total_timeout)
+    );
+    let progress_bar = ProgressBar::new(test_count as u64).with_style(progress_bar_style(test_summary.clone()));
+    let output_saver = config.save_outputs.clone()
+        .map(|directory| OutputSaver::new(directory, config.compress_saved))
+        .transpose()?;
+    let scoring = (!generate_mode && !config.groups.is_empty()).then(|| GroupScoring::new(
+        config.groups,
+        chains.iter().flat_map(|chain| &chain.tests).map(|chained_test| chained_test.test.test_name.as_str()),
+    ));
+    let expectations = (!generate_mode && !config.expected_verdicts.is_empty())
+        .then(|| VerdictExpectations::new(config.expected_verdicts));
+    if output::is_tap() {
+        output::print_tap_plan(test_count);
+    }
+    map_tests(chains, &progress_bar, test_summary, &test_runner, output_saver.as_ref(), None, scoring.as_ref(), expectations.as_ref())?;
+
+    let mut test_summary = test_summary.lock().expect("Failed to lock test summary mutex");
+    // The errors of all tests are shown in the browser instead of being printed
+    let browse = config.tui && io::stdout().is_terminal();
+    let current_run = match (generate_mode, test_summary.as_mut()) {
+        (false, Some(test_summary)) => Some(RunRecord::new(&config.source_path, test_summary)),
+        _ => None,
+    };
+    // The results of testing stopped with Ctrl+C are incomplete, so they aren't saved
+    if let (true, false, Some(current_run)) = (config.save_history, interrupt::received_ctrl_c(), &current_run) {
+        current_run.save();
+    }
+    if output::is_tap() {
+        record_transcoding_warning();
+        if let Some(test_summary) = test_summary.as_ref() {
+            output::print_tap_summary(test_summary);
+        }
+    } else {
+        print_results(
+            &test_runner,
+            scoring.as_ref(),
+            expectations.as_ref(),
+            comparison.as_ref().zip(current_run.as_ref()),
+            !browse,
+            &mut test_summary,
+        );
+    }
+    if let (true, Some(test_summary)) = (browse, test_summary.as_mut()) {
+        tui::browse_results(test_summary)
+            .map_err(|error| FormattedError::from_str(&format!("Failed to show the results browser:\n{error}")))?;
+    }
+    // The feedback report and published results would be incomplete if testing was stopped with Ctrl+C
+    let completed = !interrupt::received_ctrl_c();
+    if let (true, Some(profiler), Some(selection), Some(test_summary)) = (completed, &perf_profiler, &config.profile_perf, test_summary.as_mut()) {
+        let (_, chains) = prepare_chains(&config.input, &config.dependencies, &config.exclusion)?;
+        let tests: Vec<&Test> = chains.iter().flat_map(|chain| &chain.tests).map(|chained_test| &chained_test.test).collect();
+        let selected = PerfProfiler::select_tests(selection, test_summary, &tests);
+        profiler.profile(&selected, &tests, test_runner.input_transformer.as_ref(), |test_name| test_runner.time_limit_for(test_name));
+    }
+    if let (true, Some(path), Some(feedback)) = (completed, &config.feedback_report, &test_runner.feedback) {
+        feedback.save(path, &config.source_path.display().to_string())?;
+        output::info(&format!("The feedback report was saved to {}", path.display()));
+    }
+    if let (true, Some(format), Some(test_summary)) = (completed, config.report, test_summary.as_mut()) {
+        write_report(format, config.report_file.as_deref(), &config.source_path.display().to_string(), test_summary)?;
+        if let Some(path) = &config.report_file {
+            output::info(&format!("The report was saved to {}", path.display()));
+        }
+    }
+    if let (true, Some(test_summary)) = (completed, test_summary.as_mut()) {
+        publish_results(config.publish.as_ref(), config.webhook.as_ref(), test_summary);
+    }
+    if let (true, true, Some(test_summary)) = (completed, config.notify, test_summary.as_ref()) {
+        notify_finished(&config.source_path.display().to_string(), test_summary);
+    }
+    clear_warnings();
+    if let Some(test_summary) = test_summary.as_ref() {
+        exit_code::record_outcome(Outcome::from_summary(test_summary, expectations.as_ref()));
+    }
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use comfy_table::{Attribute, Cell, Color, Table};
+use comfy_table::ContentArrangement::Dynamic;
+use crate::test_errors::{ExecutionMetrics, TestError};
+
+/// Speedups closer to 1 than this are shown without a color, since they're within the measurement noise
+const SPEEDUP_NOISE: f64 = 0.05;
+
+enum MatrixCell {
+    Correct(Option<Duration>),
+    Failed(String),
+}
+
+/// The verdicts and runtimes of several solutions on every test
+pub(crate) struct ComparisonMatrix {
+    solution_names: Vec<String>,
+    /// The results of every solution, by test name.
+    /// `None` means the solution wasn't run on the test
+    results: Mutex<HashMap<String, Vec<Option<MatrixCell>>>>,
+}
+
+impl ComparisonMatrix {
+    pub(crate) fn new(solution_names: Vec<String>) -> Self {
+        ComparisonMatrix { solution_names, results: Mutex::new(HashMap::new()) }
+    }
+
+    pub(crate) fn solution_name(&self, solution: usize) -> &str {
+        &self.solution_names[solution]
+    }
+
+    pub(crate) fn record(&self, solution: usize, test_name: &str, result: &Result<ExecutionMetrics, TestError>) {
+        let cell = match result {
+            Ok(metrics) => MatrixCell::Correct(metrics.time),
+            Err(TestError::Cancelled) => return,
+            Err(error) => MatrixCell::Failed(error.verdict().to_owned()),
+        };
+        let mut results = self.results.lock().expect("Failed to lock the comparison matrix");
+        let row = results.entry(test_name.to_owned())
+            .or_insert_with(|| (0..self.solution_names.len()).map(|_| None).collect());
+        row[solution] = Some(cell);
+    }
+
+    /// Returns the runtimes of both solutions, if both passed the test and their runtimes were measured
+    fn both_runtimes(row: &[Option<MatrixCell>]) -> Option<(Duration, Duration)> {
+        match row {
+            [Some(MatrixCell::Correct(Some(old_time))), Some(MatrixCell::Correct(Some(new_time)))] => Some((*old_time, *new_time)),
+            _ => None,
+        }
+    }
+
+    fn speedup_cell(times: Option<(Duration, Duration)>) -> Cell {
+        let Some((old_time, new_time)) = times.filter(|(_, new_time)| !new_time.is_zero()) else {
+            return Cell::new("-");
+        };
+        let speedup = old_time.as_secs_f64() / new_time.as_secs_f64();
+        let cell = Cell::new(format!("{speedup:.2}x"));
+        if speedup > 1.0 + SPEEDUP_NOISE {
+            cell.fg(Color::Green)
+        } else if speedup < 1.0 - SPEEDUP_NOISE {
+            cell.fg(Color::Red)
+        } else {
+            cell
+        }
+    }
+
+    /// Prints a table with a row for every test and a column for every solution.
+    /// When two solutions are compared, the speedup of the second one is shown for every test and in total
+    pub(crate) fn print(&self) {
+        let results = self.results.lock().expect("Failed to lock the comparison matrix");
+        let mut test_names: Vec<&String> = results.keys().collect();
+        test_names.sort_by(|a, b| human_sort::compare(a, b));
+
+        let show_speedup = self.solution_names.len() == 2;
+        let mut table = Table::new();
+        table.set_content_arrangement(Dynamic).set_header(
+            [Cell::new("Test").add_attribute(Attribute::Bold)].into_iter()
+                .chain(self.solution_names.iter().map(|name| Cell::new(name).add_attribute(Attribute::Bold)))
+                .chain(show_speedup.then(|| Cell::new("Speedup").add_attribute(Attribute::Bold)))
+                .collect::<Vec<_>>()
+        );
+        let mut total_times = (Duration::ZERO, Duration::ZERO);
+        for test_name in test_names {
+            let row = results[test_name].iter().map(|cell| match cell {
+                Some(MatrixCell::Correct(Some(time))) => Cell::new(format!("OK {:.3}s", time.as_secs_f64())).fg(Color::Green),
+                Some(MatrixCell::Correct(None)) => Cell::new("OK").fg(Color::Green),
+                Some(MatrixCell::Failed(verdict)) => Cell::new(verdict).fg(Color::Red),
+                None => Cell::new("-"),
+            });
+            let speedup = show_speedup.then(|| Self::both_runtimes(&results[test_name]));
+            if let Some(Some((old_time, new_time))) = speedup {
+                total_times = (total_times.0 + old_time, total_times.1 + new_time);
+            }
+            table.add_row([Cell::new(test_name)].into_iter().chain(row).chain(speedup.map(Self::speedup_cell)).collect::<Vec<_>>());
+        }
+        if show_speedup && !total_times.1.is_zero() {
+            // Only the tests passed by both solutions are included in the total
+            table.add_row([
+                Cell::new("Total").add_attribute(Attribute::Bold),
+                Cell::new(format!("{:.3}s", total_times.0.as_secs_f64())),
+                Cell::new(format!("{:.3}s", total_times.1.as_secs_f64())),
+                Self::speedup_cell(Some(total_times)),
+            ]);
+        }
+        println!("{}", table.to_string().replace('\r', ""));
+    }
+}
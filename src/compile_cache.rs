@@ -0,0 +1,207 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use directories::ProjectDirs;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use crate::formatted_error::FormattedError;
+use crate::generic_utils::to_hex;
+use crate::warnings::{add_warning, WarningKind};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const KEY_LENGTH: usize = 32;
+
+/// A persistent cache of compiled executables.
+///
+/// Every cached executable is stored together with a signature
+/// (HMAC-SHA256 of the executable made with a per-user key kept outside the cache directory).
+/// Executables with a missing or mismatched signature are never used,
+/// so a shared cache directory can't be poisoned with a fake executable.
+pub(crate) struct CompileCache {
+    directory: PathBuf,
+    key: Vec<u8>,
+    /// The maximum total size of the cached executables in bytes.
+    /// The least recently used executables are removed when it's exceeded
+    max_size: u64,
+}
+
+/// A cached executable and its signature
+struct CacheEntry {
+    paths: [PathBuf; 2],
+    size: u64,
+    last_used: SystemTime,
+}
+
+/// Lists the entries in the cache directory, the least recently used first
+fn list_entries(directory: &Path) -> io::Result<Vec<CacheEntry>> {
+    let mut entries = vec![];
+    for file in fs::read_dir(directory)? {
+        let executable_path = file?.path();
+        if executable_path.extension() != Some(OsStr::new("bin")) {
+            continue;
+        }
+        let signature_path = executable_path.with_extension("sig");
+        let metadata = executable_path.metadata()?;
+        entries.push(CacheEntry {
+            size: metadata.len() + signature_path.metadata().map_or(0, |metadata| metadata.len()),
+            last_used: metadata.modified()?,
+            paths: [executable_path, signature_path],
+        });
+    }
+    entries.sort_by_key(|entry| entry.last_used);
+    Ok(entries)
+}
+
+/// Removes the least recently used entries from the cache directory until their total size is at most `max_size`.
+///
+/// Returns the number of removed entries and their total size
+pub(crate) fn evict(directory: &Path, max_size: u64) -> io::Result<(usize, u64)> {
+    let entries = list_entries(directory)?;
+    let mut total_size: u64 = entries.iter().map(|entry| entry.size).sum();
+    let (mut removed, mut removed_size) = (0, 0);
+    for entry in entries {
+        if total_size <= max_size {
+            break;
+        }
+        for path in &entry.paths {
+            match fs::remove_file(path) {
+                Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+                _ => {}
+            }
+        }
+        total_size -= entry.size;
+        removed += 1;
+        removed_size += entry.size;
+    }
+    Ok((removed, removed_size))
+}
+
+fn project_dirs() -> Result<ProjectDirs, FormattedError> {
+    ProjectDirs::from("", "", "toster").ok_or_else(|| FormattedError::from_str(
+        "No valid home directory path could be retrieved from the operating system. The compile cache can't be used"
+    ))
+}
+
+impl CompileCache {
+    /// The default cache directory, used if no directory is provided explicitly
+    pub(crate) fn default_directory() -> Result<PathBuf, FormattedError> {
+        Ok(project_dirs()?.cache_dir().join("compile"))
+    }
+
+    fn load_or_create_key() -> Result<Vec<u8>, FormattedError> {
+        let key_path = project_dirs()?.config_dir().join("cache-key");
+        if let Ok(key) = fs::read(&key_path) {
+            if key.len() == KEY_LENGTH {
+                return Ok(key);
+            }
+        }
+
+        let mut key = vec![0; KEY_LENGTH];
+        getrandom::getrandom(&mut key).expect("Failed to generate the compile cache key");
+        let error_message = |error| FormattedError::from_str(
+            &format!("Failed to save the compile cache key to {}:\n{error}", key_path.display())
+        );
+        fs::create_dir_all(key_path.parent().expect("The key path should have a parent")).map_err(error_message)?;
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(&key_path)
+            .and_then(|mut file| file.write_all(&key))
+            .map_err(error_message)?;
+        Ok(key)
+    }
+
+    pub(crate) fn open(directory: PathBuf, max_size: u64) -> Result<CompileCache, FormattedError> {
+        fs::create_dir_all(&directory).map_err(|error| FormattedError::from_str(
+            &format!("Failed to create the compile cache directory {}:\n{error}", directory.display())
+        ))?;
+        Ok(CompileCache {
+            directory,
+            key: Self::load_or_create_key()?,
+            max_size,
+        })
+    }
+
+    /// The name of the cache entry for the given source code (with the extra sources and headers) compiled with the given command
+    fn entry_name(input_files: &[PathBuf], compile_command: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(compile_command.as_bytes());
+        for input_file in input_files {
+            hasher.update([0]);
+            hasher.update(fs::read(input_file).expect("Failed to read the source file"));
+        }
+        to_hex(&hasher.finalize())
+    }
+
+    fn sign(&self, entry_name: &str, executable: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(entry_name.as_bytes());
+        mac.update(&[0]);
+        mac.update(executable);
+        mac
+    }
+
+    fn entry_paths(&self, entry_name: &str) -> (PathBuf, PathBuf) {
+        (
+            self.directory.join(format!("{entry_name}.bin")),
+            self.directory.join(format!("{entry_name}.sig")),
+        )
+    }
+
+    /// Copies the cached executable to `output_path` if there is one with a valid signature.
+    ///
+    /// Returns whether the executable was found in the cache.
+    pub(crate) fn get(&self, input_files: &[PathBuf], compile_command: &str, output_path: &Path) -> bool {
+        let entry_name = Self::entry_name(input_files, compile_command);
+        let (executable_path, signature_path) = self.entry_paths(&entry_name);
+        let (Ok(executable), Ok(signature)) = (fs::read(&executable_path), fs::read_to_string(&signature_path)) else {
+            return false;
+        };
+
+        let expected_signature = to_hex(&self.sign(&entry_name, &executable).finalize().into_bytes());
+        if signature.trim() != expected_signature {
+            add_warning(WarningKind::CompileCache, format!(
+                "The cached executable {} has an invalid signature and was not used",
+                executable_path.display(),
+            ));
+            return false;
+        }
+
+        // The modification time of the executable marks when it was last used, for evicting old entries
+        let _ = File::options().write(true).open(&executable_path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        fs::write(output_path, executable).expect("Failed to copy the cached executable");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(output_path, fs::Permissions::from_mode(0o755))
+                .expect("Failed to set permissions of the cached executable");
+        }
+        true
+    }
+
+    /// Stores the executable compiled from the given source code in the cache.
+    /// Failing to write to the cache is not considered an error.
+    pub(crate) fn store(&self, input_files: &[PathBuf], compile_command: &str, executable_path: &Path) {
+        let entry_name = Self::entry_name(input_files, compile_command);
+        let (cached_executable_path, signature_path) = self.entry_paths(&entry_name);
+        let executable = fs::read(executable_path).expect("Failed to read the compiled executable");
+        let signature = to_hex(&self.sign(&entry_name, &executable).finalize().into_bytes());
+
+        if fs::write(cached_executable_path, executable).is_err() || fs::write(signature_path, signature).is_err() {
+            add_warning(WarningKind::CompileCache, "Failed to save the executable in the compile cache");
+        }
+        if evict(&self.directory, self.max_size).is_err() {
+            add_warning(WarningKind::CompileCache, "Failed to remove old executables from the compile cache");
+        }
+    }
+}
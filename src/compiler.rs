@@ -8,67 +8,191 @@ use colored::Colorize;
 use is_executable::is_executable;
 use tempfile::TempDir;
 use wait_timeout::ChildExt;
+use crate::compile_cache::CompileCache;
 use crate::compiler::CompilerError::{CompilationError, InvalidExecutable};
+use crate::exit_code::Outcome;
 use crate::formatted_error::FormattedError;
-use crate::temp_files::{create_temp_file, make_cloned_stdio};
+use crate::output;
+use crate::process_tree::kill_tree;
+use crate::temp_files::{clone_stdio, create_temp_file};
 
 pub(crate) enum CompilerError {
     InvalidExecutable(io::Error),
     CompilationError(String),
 }
 
+/// The role of a compiled executable, used in error messages
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ExecutableKind {
+    /// The tested program or a compared solution
+    Program,
+    Checker,
+    Validator,
+}
+
+impl ExecutableKind {
+    fn name(self) -> &'static str {
+        match self {
+            ExecutableKind::Program => "program",
+            ExecutableKind::Checker => "checker",
+            ExecutableKind::Validator => "validator",
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            ExecutableKind::Program => "Program",
+            ExecutableKind::Checker => "Checker",
+            ExecutableKind::Validator => "Validator",
+        }
+    }
+}
+
 impl CompilerError {
-    pub fn to_formatted(&self, is_checker: bool) -> FormattedError {
-        FormattedError::preformatted(match self {
+    pub(crate) fn to_formatted(&self, kind: ExecutableKind) -> FormattedError {
+        let error = FormattedError::preformatted(match self {
             InvalidExecutable(error) => {
                 format!(
                     "{}\n{}",
-                    format!(
-                        "The provided {} can't be executed",
-                        if is_checker { "checker" } else { "program" }
-                    ).red(),
+                    format!("The provided {} can't be executed", kind.name()).red(),
                     error
                 )
             }
             CompilationError(error) => {
                 format!(
                     "{}\n{}",
-                    format!(
-                        "{} compilation failed with the following errors:",
-                        if is_checker { "Checker" } else { "Program" }
-                    ).red(),
+                    format!("{} compilation failed with the following errors:", kind.title()).red(),
                     error
                 )
             }
-        })
+        });
+        match self {
+            CompilationError(_) if kind == ExecutableKind::Program => error.with_outcome(Outcome::CompileError),
+            _ => error,
+        }
     }
 }
 
+/// Describes where the executable returned by `prepare_executable()` came from
+pub(crate) enum ExecutableOrigin {
+    /// The provided file was already an executable
+    Provided,
+    Compiled {
+        time: Duration,
+        /// The warnings written by the compiler, if there were any
+        warnings: Option<String>,
+    },
+    /// The executable was taken from the compile cache
+    Cached,
+}
+
+#[derive(Clone, Copy)]
 pub(crate) struct Compiler<'a> {
     pub(crate) tempdir: &'a TempDir,
     pub(crate) compile_timeout: Duration,
     pub(crate) compile_command: &'a str,
+    pub(crate) cache: Option<&'a CompileCache>,
+    /// Source files compiled together with every compiled file, and directories whose source files
+    /// are compiled and whose headers can be included
+    pub(crate) extra_sources: &'a [PathBuf],
+    /// The programs are run with a custom run command, so files that aren't compiled (like scripts)
+    /// are used as they are instead of being checked to be executables
+    pub(crate) custom_run_command: bool,
 }
 
-impl<'a> Compiler<'a> {
-    fn is_source_file(path: &Path) -> bool {
-        if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
-            return matches!(extension, "cpp" | "cc" | "cxx" | "c");
+/// Returns all files in the directory and its subdirectories, sorted
+fn files_in_directory(directory: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    let mut entries: Vec<PathBuf> = fs::read_dir(directory)
+        .expect("Failed to read the extra source directory")
+        .map(|entry| entry.expect("Failed to read the extra source directory").path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            files.extend(files_in_directory(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+impl Compiler<'_> {
+    fn has_source_extension(path: &Path) -> bool {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| matches!(extension, "cpp" | "cc" | "cxx" | "c"))
+    }
+
+    pub(crate) fn is_source_file(path: &Path) -> bool {
+        if path.extension().is_some() {
+            return Self::has_source_extension(path);
         }
         !is_executable(path)
     }
 
-    fn compile_cpp(&self, source_path: &Path, executable_path: &Path) -> Result<Duration, String> {
-        let cmd = self.compile_command
-            .replace("<IN>", source_path.to_str().expect("The provided filename is invalid"))
-            .replace("<OUT>", executable_path.to_str().expect("The provided filename is invalid"));
+    /// Returns the files that affect the compilation of `source_path`, including the headers in the extra directories
+    fn input_files(&self, source_path: &Path) -> Vec<PathBuf> {
+        let mut files = vec![source_path.to_path_buf()];
+        for extra_source in self.extra_sources {
+            if extra_source.is_dir() {
+                files.extend(files_in_directory(extra_source));
+            } else {
+                files.push(extra_source.clone());
+            }
+        }
+        files
+    }
+
+    /// Returns the arguments that replace <IN> in the compile command: the source files
+    /// and the include flags of the extra directories
+    fn compile_inputs(&self, source_path: &Path) -> Vec<String> {
+        let to_str = |path: &Path| path.to_str().expect("The provided filename is invalid").to_owned();
+        let mut inputs = vec![];
+        let mut includes = vec![];
+        let mut source_files = vec![source_path.to_path_buf()];
+        for extra_source in self.extra_sources {
+            if extra_source.is_dir() {
+                source_files.extend(files_in_directory(extra_source).into_iter().filter(|path| Self::has_source_extension(path)));
+                // GCC, Clang and MSVC all accept -I
+                includes.push(format!("-I{}", to_str(extra_source)));
+            } else {
+                source_files.push(extra_source.clone());
+            }
+        }
+
+        // The solution itself can be in a directory with the grader, and a file can be given more than once
+        let mut compiled = vec![];
+        for path in source_files {
+            let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+            if !compiled.contains(&canonical) {
+                inputs.push(to_str(&path));
+                compiled.push(canonical);
+            }
+        }
+        inputs.extend(includes);
+        inputs
+    }
+
+    /// Returns the compile command with the source files and the executable path filled in
+    pub(crate) fn command_line(&self, source_path: &Path, executable_path: &Path) -> String {
+        self.compile_command
+            .replace("<IN>", &self.compile_inputs(source_path).join(" "))
+            .replace("<OUT>", executable_path.to_str().expect("The provided filename is invalid"))
+    }
+
+    /// Returns the compilation time and the warnings written by the compiler, if it wrote anything
+    fn compile_cpp(&self, source_path: &Path, executable_path: &Path) -> Result<(Duration, Option<String>), String> {
+        let cmd = self.command_line(source_path, executable_path);
+        output::verbose(&format!("Compiling with: {cmd}"));
         let mut split_cmd = cmd.split(' ');
 
-        let mut stderr = create_temp_file().expect("Failed to create memfile");
+        let mut stderr = create_temp_file().map_err(|error| format!("Failed to create a temporary file:\n{error}"))?;
         let time_before_compilation = Instant::now();
         let child = Command::new(split_cmd.next().expect("The compile command is invalid"))
             .args(split_cmd)
-            .stderr(make_cloned_stdio(&stderr))
+            .stderr(clone_stdio(&stderr).map_err(|error| format!("Failed to duplicate a file descriptor:\n{error}"))?)
             .spawn();
 
         let mut child = match child {
@@ -80,16 +204,16 @@ impl<'a> Compiler<'a> {
 
         stderr.rewind().unwrap();
 
-        if let Some(status) = result {
-            if status.code().expect("The compiler returned an invalid status code") != 0 {
-                let compilation_result = read_to_string(stderr).expect("Failed to read compiler output");
-                return Err(compilation_result);
-            }
-        } else {
-            child.kill().unwrap();
+        let Some(status) = result else {
+            kill_tree(&mut child).unwrap();
             return Err("Compilation timed out".to_owned());
+        };
+        let compilation_time = time_before_compilation.elapsed();
+        let compilation_result = read_to_string(stderr).expect("Failed to read compiler output");
+        if status.code().expect("The compiler returned an invalid status code") != 0 {
+            return Err(compilation_result);
         }
-        Ok(time_before_compilation.elapsed())
+        Ok((compilation_time, Some(compilation_result).filter(|warnings| !warnings.trim().is_empty())))
     }
 
     fn try_spawning_executable(executable_path: &PathBuf) -> io::Result<()> {
@@ -100,24 +224,52 @@ impl<'a> Compiler<'a> {
             })
     }
 
+    /// Uses an already built executable without compiling it, for example one built with custom flags
+    pub(crate) fn prepare_provided_executable(
+        &self,
+        executable_path: &Path,
+        name: &str,
+    ) -> Result<(PathBuf, ExecutableOrigin), CompilerError> {
+        debug_assert!(PathBuf::from(name).extension().is_none());
+        if self.custom_run_command {
+            // The program can be run in a different working directory with --file-io
+            let path = std::path::absolute(executable_path).expect("The provided filename is invalid");
+            return Ok((path, ExecutableOrigin::Provided));
+        }
+        let output_path = self.tempdir.path().join(format!("{name}.o"));
+        fs::copy(executable_path, &output_path).expect("The provided filename is invalid");
+        if let Err(error) = Self::try_spawning_executable(&output_path) {
+            return Err(InvalidExecutable(error));
+        }
+        Ok((output_path, ExecutableOrigin::Provided))
+    }
+
     pub(crate) fn prepare_executable(
         &self,
         source_path: &Path,
-        name: &'static str,
-    ) -> Result<(PathBuf, Option<Duration>), CompilerError> {
+        name: &str,
+    ) -> Result<(PathBuf, ExecutableOrigin), CompilerError> {
         debug_assert!(PathBuf::from(name).extension().is_none());
+        if !Self::is_source_file(source_path) {
+            return self.prepare_provided_executable(source_path, name);
+        }
         let output_path = self.tempdir.path().join(format!("{name}.o"));
 
-        if !Self::is_source_file(source_path) {
-            fs::copy(source_path, &output_path).expect("The provided filename is invalid");
-            if let Err(error) = Self::try_spawning_executable(&output_path) {
-                return Err(InvalidExecutable(error));
+        let input_files = self.input_files(source_path);
+        if let Some(cache) = self.cache {
+            if cache.get(&input_files, self.compile_command, &output_path) {
+                return Ok((output_path, ExecutableOrigin::Cached));
             }
-            return Ok((output_path, None));
         }
 
         match self.compile_cpp(source_path, &output_path) {
-            Ok(compilation_time) => Ok((output_path, Some(compilation_time))),
+            Ok((time, warnings)) => {
+                // Executables compiled with warnings aren't cached, so the warnings are shown again the next time
+                if let (Some(cache), None) = (self.cache, &warnings) {
+                    cache.store(&input_files, self.compile_command, &output_path);
+                }
+                Ok((output_path, ExecutableOrigin::Compiled { time, warnings }))
+            }
             Err(error) => Err(CompilationError(error)),
         }
     }
@@ -0,0 +1,84 @@
+use std::fs;
+
+/// Parses a list of CPUs in the format used by the kernel, for example `2,3` or `0-3,8`
+pub(crate) fn parse_cpu_list(list: &str) -> Result<Vec<usize>, String> {
+    let mut cpus = vec![];
+    for part in list.trim().split(',').filter(|part| !part.is_empty()) {
+        let parse = |cpu: &str| cpu.trim().parse::<usize>().map_err(|_| format!("\"{part}\" is not a valid CPU number or range"));
+        match part.split_once('-') {
+            Some((start, end)) => cpus.extend(parse(start)?..=parse(end)?),
+            None => cpus.push(parse(part)?),
+        }
+    }
+    cpus.sort_unstable();
+    cpus.dedup();
+    Ok(cpus)
+}
+
+/// Returns the CPUs isolated from the scheduler with the `isolcpus` or `nohz_full` kernel parameters
+pub(crate) fn isolated_cpus() -> Vec<usize> {
+    let mut cpus: Vec<usize> = ["/sys/devices/system/cpu/isolated", "/sys/devices/system/cpu/nohz_full"].iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .filter_map(|list| parse_cpu_list(&list).ok())
+        .flatten()
+        .collect();
+    cpus.sort_unstable();
+    cpus.dedup();
+    cpus
+}
+
+/// Returns the CPUs which are currently online
+pub(crate) fn online_cpus() -> Vec<usize> {
+    fs::read_to_string("/sys/devices/system/cpu/online").ok()
+        .and_then(|list| parse_cpu_list(&list).ok())
+        .unwrap_or_default()
+}
+
+/// Restricts the calling thread (and the processes it spawns later) to a single CPU
+#[cfg(target_os = "linux")]
+fn pin_current_thread(cpu: usize) -> std::io::Result<()> {
+    // SAFETY: `cpu_set_t` is a plain bitmask, for which all zeroes is a valid (empty) value
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    // SAFETY: `set` is a valid `cpu_set_t` and out of range CPU numbers cause a panic, not a write outside of it
+    unsafe { libc::CPU_SET(cpu, &mut set) };
+    // SAFETY: pid 0 means the calling thread and `set` is a valid `cpu_set_t` of the passed size
+    let result = unsafe { libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), &raw const set) };
+    if result == 0 { Ok(()) } else { Err(std::io::Error::last_os_error()) }
+}
+
+/// How much the niceness of the testing threads is lowered by `raise_priority()`
+#[cfg(target_os = "linux")]
+const RAISED_PRIORITY: libc::c_int = -10;
+
+/// Raises the scheduling priority of the calling thread.
+/// Threads created by it later, like the testing threads, and the processes they spawn inherit the priority
+#[cfg(target_os = "linux")]
+pub(crate) fn raise_priority() -> std::io::Result<()> {
+    // SAFETY: `setpriority` only changes the niceness of the calling thread (0 means the caller)
+    let result = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, RAISED_PRIORITY) };
+    if result == 0 { Ok(()) } else { Err(std::io::Error::last_os_error()) }
+}
+
+/// Configures the thread pool running the tests.
+///
+/// If `cpus` is provided, every testing thread is pinned to one of them,
+/// so the tested programs (which inherit the affinity) only run on these CPUs.
+/// `thread_limit` limits the number of tests run in parallel.
+pub(crate) fn configure_thread_pool(cpus: Option<Vec<usize>>, thread_limit: Option<usize>) {
+    let mut builder = rayon::ThreadPoolBuilder::new();
+    let threads = match (&cpus, thread_limit) {
+        (Some(cpus), Some(limit)) => Some(cpus.len().min(limit)),
+        (Some(cpus), None) => Some(cpus.len()),
+        (None, limit) => limit,
+    };
+    if let Some(threads) = threads {
+        builder = builder.num_threads(threads);
+    }
+    #[cfg(target_os = "linux")]
+    if let Some(cpus) = cpus {
+        builder = builder.start_handler(move |index| {
+            pin_current_thread(cpus[index]).expect("Failed to set the CPU affinity of a testing thread");
+        });
+    }
+    builder.build_global().expect("Failed to configure the thread pool");
+}
@@ -0,0 +1,359 @@
+use std::borrow::Cow;
+use std::cmp::max;
+use clap::ValueEnum;
+use colored::Colorize;
+use comfy_table::{Attribute, Cell, Color, Table};
+use comfy_table::ContentArrangement::Dynamic;
+use serde::Serialize;
+use terminal_size::{Height, Width};
+use crate::testing_utils::pluralize_lines;
+
+/// How the difference between the expected and actual output is shown
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct DiffOptions {
+    pub(crate) style: DiffStyle,
+    /// Longer lines are shortened in the table and unified diffs
+    pub(crate) max_line_length: usize,
+    /// The maximum number of differing lines shown
+    pub(crate) max_lines: usize,
+    /// The number of unchanged lines shown around the differing lines, the default of the style if it's not set
+    pub(crate) context_lines: Option<usize>,
+}
+
+impl DiffOptions {
+    fn context_lines(&self) -> usize {
+        self.context_lines.unwrap_or(match self.style {
+            // The same as `diff -u`
+            DiffStyle::Unified => 3,
+            DiffStyle::Table | DiffStyle::SideBySide | DiffStyle::FirstMismatch | DiffStyle::Json => 0,
+        })
+    }
+
+    /// Renders the difference between the outputs as the error message of a wrong answer
+    pub(crate) fn render(&self, expected: &[&str], actual: &[&str], hint: Option<String>) -> String {
+        let mismatch = Mismatch {
+            expected,
+            actual,
+            hint,
+            max_line_length: self.max_line_length,
+            max_lines: self.max_lines,
+            context_lines: self.context_lines(),
+        };
+        self.style.renderer().render(&mismatch)
+    }
+}
+
+/// The difference between the expected output and the output of the program
+pub(crate) struct Mismatch<'a> {
+    pub(crate) expected: &'a [&'a str],
+    pub(crate) actual: &'a [&'a str],
+    /// A short explanation of the likely cause of the wrong answer
+    pub(crate) hint: Option<String>,
+    /// Longer lines are shortened in the table and unified diffs
+    pub(crate) max_line_length: usize,
+    /// The maximum number of differing lines shown
+    pub(crate) max_lines: usize,
+    /// The number of unchanged lines shown around the differing lines
+    pub(crate) context_lines: usize,
+}
+
+struct DifferingLine<'a> {
+    /// The 1-based line number
+    number: usize,
+    expected: Option<&'a str>,
+    actual: Option<&'a str>,
+}
+
+impl DifferingLine<'_> {
+    fn is_changed(&self) -> bool {
+        self.expected.unwrap_or_default() != self.actual.unwrap_or_default()
+    }
+}
+
+impl<'a> Mismatch<'a> {
+    fn line(&self, number: usize) -> DifferingLine<'a> {
+        DifferingLine {
+            number,
+            expected: self.expected.get(number - 1).copied(),
+            actual: self.actual.get(number - 1).copied(),
+        }
+    }
+
+    /// Returns the lines that differ (limited to `max_lines`) and whether there are more
+    fn differing_lines(&self) -> (Vec<DifferingLine<'a>>, bool) {
+        let mut lines = (1..=max(self.expected.len(), self.actual.len()))
+            .map(|number| self.line(number))
+            .filter(DifferingLine::is_changed);
+        let shown: Vec<DifferingLine> = lines.by_ref().take(self.max_lines).collect();
+        let truncated = lines.next().is_some();
+        (shown, truncated)
+    }
+
+    /// Returns the differing lines together with `context_lines` lines around them, and whether there are more differing lines
+    fn lines_with_context(&self) -> (Vec<DifferingLine<'a>>, bool) {
+        let (differing, truncated) = self.differing_lines();
+        let line_count = max(self.expected.len(), self.actual.len());
+        let mut lines: Vec<DifferingLine> = vec![];
+        for line in differing {
+            let start = line.number.saturating_sub(self.context_lines).max(1);
+            let end = (line.number + self.context_lines).min(line_count);
+            // The context of consecutive differing lines overlaps
+            let start = lines.last().map_or(start, |last| start.max(last.number + 1));
+            lines.extend((start..=end).map(|number| self.line(number)));
+        }
+        (lines, truncated)
+    }
+
+    fn with_hint(&self, diff: &str) -> String {
+        match &self.hint {
+            Some(hint) => format!("{}\n{}", format!("Hint: {hint}").yellow(), diff),
+            None => diff.to_owned(),
+        }
+    }
+}
+
+/// Shortens a line longer than `max_length` characters to the part around its first difference from `other`,
+/// noting the full length of the line, so a single very long line doesn't make the diff unreadable
+fn shorten_line<'a>(line: &'a str, other: &str, max_length: usize) -> Cow<'a, str> {
+    let length = line.chars().count();
+    if length <= max_length {
+        return Cow::Borrowed(line);
+    }
+    let first_difference = line.chars().zip(other.chars()).take_while(|(a, b)| a == b).count();
+    // A part of the line before the difference is kept as context
+    let start = first_difference.saturating_sub(max_length / 4).min(length - max_length);
+    let shown: String = line.chars().skip(start).take(max_length).collect();
+    let prefix = if start > 0 { "…" } else { "" };
+    let suffix = if start + max_length < length { "…" } else { "" };
+    Cow::Owned(format!("{prefix}{shown}{suffix} ({length} characters)"))
+}
+
+fn terminal_width() -> u16 {
+    let (Width(width), Height(_)) = terminal_size::terminal_size().unwrap_or((Width(40), Height(0)));
+    width
+}
+
+pub(crate) trait DiffRenderer: Sync + Send {
+    /// Renders the difference between the outputs as the error message of a wrong answer
+    fn render(&self, mismatch: &Mismatch) -> String;
+}
+
+/// The way the difference between the expected and actual output is shown
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum DiffStyle {
+    /// A table with the differing lines
+    Table,
+    /// A unified diff, like the one produced by `diff -u`
+    Unified,
+    /// The differing lines next to each other without table borders, better suited for narrow terminals
+    SideBySide,
+    /// Only the first differing line and the number of missing or extra lines, for quick iterations
+    FirstMismatch,
+    /// A JSON object, for processing by other tools
+    Json,
+}
+
+impl DiffStyle {
+    pub(crate) fn renderer(self) -> &'static dyn DiffRenderer {
+        match self {
+            DiffStyle::Table => &TableRenderer,
+            DiffStyle::Unified => &UnifiedRenderer,
+            DiffStyle::SideBySide => &SideBySideRenderer,
+            DiffStyle::FirstMismatch => &FirstMismatchRenderer,
+            DiffStyle::Json => &JsonRenderer,
+        }
+    }
+}
+
+struct TableRenderer;
+
+impl DiffRenderer for TableRenderer {
+    fn render(&self, mismatch: &Mismatch) -> String {
+        let mut table = Table::new();
+        table.set_content_arrangement(Dynamic).set_width(terminal_width()).set_header(vec![
+            Cell::new("Line").add_attribute(Attribute::Bold),
+            Cell::new("Output file").add_attribute(Attribute::Bold).fg(Color::Green),
+            Cell::new("Your program's output").add_attribute(Attribute::Bold).fg(Color::Red),
+        ]);
+
+        let (lines, truncated) = mismatch.lines_with_context();
+        for line in lines {
+            let (expected, actual) = (line.expected.unwrap_or_default(), line.actual.unwrap_or_default());
+            let (expected_cell, actual_cell) = (
+                Cell::new(shorten_line(expected, actual, mismatch.max_line_length)),
+                Cell::new(shorten_line(actual, expected, mismatch.max_line_length)),
+            );
+            // Unchanged context lines aren't colored
+            table.add_row(if line.is_changed() {
+                vec![Cell::new(line.number), expected_cell.fg(Color::Green), actual_cell.fg(Color::Red)]
+            } else {
+                vec![Cell::new(line.number), expected_cell, actual_cell]
+            });
+        }
+        if truncated {
+            table.add_row(vec![
+                Cell::new("..."),
+                Cell::new("..."),
+                Cell::new("..."),
+            ]);
+        }
+
+        mismatch.with_hint(&table.to_string().replace('\r', ""))
+    }
+}
+
+struct UnifiedRenderer;
+
+impl DiffRenderer for UnifiedRenderer {
+    fn render(&self, mismatch: &Mismatch) -> String {
+        let mut result = vec![
+            "--- output file".green().to_string(),
+            "+++ your program's output".red().to_string(),
+        ];
+
+        let (lines, truncated) = mismatch.lines_with_context();
+        // Consecutive lines are grouped into a single hunk
+        let mut hunks: Vec<Vec<DifferingLine>> = vec![];
+        for line in lines {
+            match hunks.last_mut() {
+                Some(hunk) if hunk.last().is_some_and(|last| last.number + 1 == line.number) => hunk.push(line),
+                _ => hunks.push(vec![line]),
+            }
+        }
+
+        for hunk in hunks {
+            let start = hunk[0].number;
+            let expected_count = hunk.iter().filter(|line| line.expected.is_some()).count();
+            let actual_count = hunk.iter().filter(|line| line.actual.is_some()).count();
+            result.push(format!("@@ -{start},{expected_count} +{start},{actual_count} @@").cyan().to_string());
+
+            let (mut removed, mut added) = (vec![], vec![]);
+            for line in hunk {
+                let (expected, actual) = (line.expected.unwrap_or_default(), line.actual.unwrap_or_default());
+                if line.is_changed() {
+                    if line.expected.is_some() {
+                        removed.push(format!("-{}", shorten_line(expected, actual, mismatch.max_line_length)).green().to_string());
+                    }
+                    if line.actual.is_some() {
+                        added.push(format!("+{}", shorten_line(actual, expected, mismatch.max_line_length)).red().to_string());
+                    }
+                    continue;
+                }
+                // The removed lines of a change are listed before the added ones
+                result.append(&mut removed);
+                result.append(&mut added);
+                if let Some(text) = line.expected.or(line.actual) {
+                    result.push(format!(" {}", shorten_line(text, "", mismatch.max_line_length)));
+                }
+            }
+            result.append(&mut removed);
+            result.append(&mut added);
+        }
+        if truncated {
+            result.push("...".to_owned());
+        }
+
+        mismatch.with_hint(&result.join("\n"))
+    }
+}
+
+struct SideBySideRenderer;
+
+impl DiffRenderer for SideBySideRenderer {
+    fn render(&self, mismatch: &Mismatch) -> String {
+        let (lines, truncated) = mismatch.lines_with_context();
+        let number_width = lines.last().map_or(4, |line| line.number.to_string().len().max(4));
+        let column_width = (usize::from(terminal_width()).saturating_sub(number_width + 4) / 2).max(10);
+        // Long lines are shortened around their first difference, leaving room for the ellipses and the length note
+        let shorten = |text: &str, other: &str| -> String {
+            let length = text.chars().count();
+            if length <= mismatch.max_line_length.min(column_width) {
+                return text.to_owned();
+            }
+            let note_width = format!("…… ({length} characters)").chars().count();
+            let max_length = mismatch.max_line_length.min(column_width.saturating_sub(note_width).max(1));
+            shorten_line(text, other, max_length).into_owned()
+        };
+
+        let mut result = vec![format!(
+            "{:>number_width$}  {}  {}",
+            "Line".bold(),
+            format!("{:column_width$}", "Output file").green().bold(),
+            "Your program's output".red().bold(),
+        )];
+        result.extend(lines.iter().map(|line| {
+            let (expected, actual) = (line.expected.unwrap_or_default(), line.actual.unwrap_or_default());
+            let (expected, actual) = (format!("{:column_width$}", shorten(expected, actual)), shorten(actual, expected));
+            // Unchanged context lines aren't colored
+            if line.is_changed() {
+                format!("{:>number_width$}  {}  {}", line.number, expected.green(), actual.red())
+            } else {
+                format!("{:>number_width$}  {expected}  {actual}", line.number)
+            }
+        }));
+        if truncated {
+            result.push(format!("{:>number_width$}", "..."));
+        }
+
+        mismatch.with_hint(&result.join("\n"))
+    }
+}
+
+struct FirstMismatchRenderer;
+
+impl DiffRenderer for FirstMismatchRenderer {
+    fn render(&self, mismatch: &Mismatch) -> String {
+        let (lines, _) = mismatch.differing_lines();
+        let Some(line) = lines.first() else {
+            return mismatch.with_hint("");
+        };
+        let describe = |text: Option<&str>, other: Option<&str>| match text {
+            Some(text) => shorten_line(text, other.unwrap_or_default(), mismatch.max_line_length).into_owned(),
+            None => "(no line)".to_owned(),
+        };
+        let mut result = vec![
+            format!("First difference at line {}", line.number).bold().to_string(),
+            format!("Expected: {}", describe(line.expected, line.actual)).green().to_string(),
+            format!("Actual:   {}", describe(line.actual, line.expected)).red().to_string(),
+        ];
+
+        let (expected_count, actual_count) = (mismatch.expected.len(), mismatch.actual.len());
+        if actual_count < expected_count {
+            result.push(format!("{} missing at the end", pluralize_lines(expected_count - actual_count)));
+        } else if actual_count > expected_count {
+            result.push(format!("{} extra at the end", pluralize_lines(actual_count - expected_count)));
+        }
+
+        mismatch.with_hint(&result.join("\n"))
+    }
+}
+
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    line: usize,
+    expected: Option<&'a str>,
+    actual: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct JsonDiff<'a> {
+    hint: Option<&'a str>,
+    differences: Vec<JsonLine<'a>>,
+    truncated: bool,
+}
+
+struct JsonRenderer;
+
+impl DiffRenderer for JsonRenderer {
+    fn render(&self, mismatch: &Mismatch) -> String {
+        let (lines, truncated) = mismatch.differing_lines();
+        let diff = JsonDiff {
+            hint: mismatch.hint.as_deref(),
+            differences: lines.into_iter()
+                .map(|line| JsonLine { line: line.number, expected: line.expected, actual: line.actual })
+                .collect(),
+            truncated,
+        };
+        serde_json::to_string(&diff).expect("Failed to serialize the diff")
+    }
+}
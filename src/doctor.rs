@@ -0,0 +1,170 @@
+use std::fs;
+use std::io::{Read, Seek, Write};
+use std::thread::available_parallelism;
+use std::time::Duration;
+use colored::Colorize;
+use tempfile::tempdir;
+use which::which;
+use crate::compiler::{Compiler, ExecutableKind};
+use crate::temp_files::create_temp_file;
+use crate::toolchain::Toolchain;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+use crate::executor::sio2jail::Sio2jailExecutor;
+
+/// The result of a single diagnostic check
+enum CheckResult {
+    Ok(String),
+    /// A problem that doesn't prevent toster from working in the default configuration
+    Warning { message: String, fix: String },
+    Error { message: String, fix: String },
+}
+
+impl CheckResult {
+    fn print(&self, name: &str) {
+        match self {
+            CheckResult::Ok(message) => {
+                println!("{} {}: {}", "[OK]".green(), name.bold(), message);
+            }
+            CheckResult::Warning { message, fix } => {
+                println!("{} {}: {}", "[WARNING]".yellow(), name.bold(), message.trim_end());
+                println!("    {}", fix.bright_black());
+            }
+            CheckResult::Error { message, fix } => {
+                println!("{} {}: {}", "[ERROR]".red(), name.bold(), message.trim_end());
+                println!("    {}", fix.bright_black());
+            }
+        }
+    }
+
+    fn is_error(&self) -> bool {
+        matches!(self, CheckResult::Error { .. })
+    }
+}
+
+fn check_compiler(compile_command: &str) -> CheckResult {
+    let compiler = compile_command.split(' ').next().unwrap_or_default();
+    let Ok(compiler_path) = which(compiler) else {
+        return CheckResult::Error {
+            message: format!("the compiler \"{compiler}\" could not be found"),
+            fix: "Install the compiler or choose a different one using --compiler or --compile-command".to_owned(),
+        };
+    };
+
+    let Ok(tempdir) = tempdir() else {
+        return CheckResult::Error {
+            message: "a temporary directory for test compilation couldn't be created".to_owned(),
+            fix: "Make sure the system temporary directory is writable".to_owned(),
+        };
+    };
+    let source_path = tempdir.path().join("doctor.cpp");
+    fs::write(&source_path, "int main() { return 0; }\n").expect("Failed to write the test program");
+    let compiler_runner = Compiler {
+        tempdir: &tempdir,
+        compile_timeout: Duration::from_secs(30),
+        compile_command,
+        cache: None,
+        extra_sources: &[],
+        custom_run_command: false,
+    };
+    match compiler_runner.prepare_executable(&source_path, "doctor") {
+        Ok(_) => CheckResult::Ok(format!("{} can compile programs", compiler_path.display())),
+        Err(error) => CheckResult::Error {
+            message: format!("compiling a test program failed:\n{}", error.to_formatted(ExecutableKind::Program)),
+            fix: "Make sure the compile command is correct (for example, -static requires static versions of the standard library)".to_owned(),
+        },
+    }
+}
+
+fn check_toolchains() -> CheckResult {
+    let toolchains = Toolchain::detect();
+    if toolchains.is_empty() {
+        return CheckResult::Error {
+            message: "no C++ compiler could be found".to_owned(),
+            fix: "Install g++ or clang++ and make sure it's on the PATH, or only test executables".to_owned(),
+        };
+    }
+    let names: Vec<_> = toolchains.iter().map(|toolchain| toolchain.compiler_name()).collect();
+    CheckResult::Ok(format!("found {}, {} is used by default", names.join(", "), names[0]))
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn check_sio2jail() -> CheckResult {
+    match Sio2jailExecutor::check_installation() {
+        Ok(path) => CheckResult::Ok(format!("{} works correctly", path.display())),
+        Err(error) => CheckResult::Warning {
+            message: format!("sio2jail can't be used:\n{error}"),
+            fix: "Sio2jail is only required for the --sio2jail and --memory-limit flags. Run \"toster install-sio2jail\" to install it".to_owned(),
+        },
+    }
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn check_perf_event_paranoid() -> CheckResult {
+    match Sio2jailExecutor::read_perf_event_paranoid() {
+        Some(-1) => CheckResult::Ok("kernel.perf_event_paranoid is set to -1".to_owned()),
+        Some(value) => CheckResult::Warning {
+            message: format!("kernel.perf_event_paranoid is set to {value}, sio2jail requires -1"),
+            fix: "Run \"sudo sysctl -w kernel.perf_event_paranoid=-1\" (add it to /etc/sysctl.conf to make it persistent)".to_owned(),
+        },
+        None => CheckResult::Warning {
+            message: "the value of kernel.perf_event_paranoid couldn't be read".to_owned(),
+            fix: "Make sure /proc/sys/kernel/perf_event_paranoid exists and is readable".to_owned(),
+        },
+    }
+}
+
+fn check_cpus() -> CheckResult {
+    match available_parallelism() {
+        Ok(count) if count.get() > 1 => CheckResult::Ok(format!("{count} CPUs available for running tests in parallel")),
+        Ok(_) => CheckResult::Warning {
+            message: "only one CPU is available, tests will be run one at a time".to_owned(),
+            fix: "Allow toster to use more CPUs to speed up testing".to_owned(),
+        },
+        Err(error) => CheckResult::Warning {
+            message: format!("the number of available CPUs couldn't be determined: {error}"),
+            fix: "Tests will still be run, but possibly with a suboptimal number of threads".to_owned(),
+        },
+    }
+}
+
+fn check_temp_files() -> CheckResult {
+    let fix = "Make sure the system temporary directory exists and is writable".to_owned();
+    if let Err(error) = tempdir() {
+        return CheckResult::Error { message: format!("a temporary directory couldn't be created: {error}"), fix };
+    }
+
+    let result = create_temp_file().and_then(|mut file| {
+        file.write_all(b"toster")?;
+        file.rewind()?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        Ok(contents)
+    });
+    match result {
+        Ok(contents) if contents == "toster" => CheckResult::Ok("temporary files can be created and written".to_owned()),
+        Ok(_) => CheckResult::Error { message: "a temporary file returned different contents than written".to_owned(), fix },
+        Err(error) => CheckResult::Error { message: format!("a temporary file couldn't be written: {error}"), fix },
+    }
+}
+
+/// Checks the environment for common problems and prints how to fix them.
+///
+/// Returns whether no errors were found.
+pub(crate) fn run_doctor(compile_command: &str) -> bool {
+    let mut checks = vec![
+        ("Toolchains", check_toolchains()),
+        ("Compiler", check_compiler(compile_command)),
+    ];
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    {
+        checks.push(("Sio2jail", check_sio2jail()));
+        checks.push(("Performance counters", check_perf_event_paranoid()));
+    }
+    checks.push(("CPUs", check_cpus()));
+    checks.push(("Temporary files", check_temp_files()));
+
+    for (name, result) in &checks {
+        result.print(name);
+    }
+    !checks.iter().any(|(_, result)| result.is_error())
+}
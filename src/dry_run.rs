@@ -0,0 +1,175 @@
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+use std::fmt::Write;
+use std::time::Duration;
+use clap::ValueEnum;
+use colored::Colorize;
+use comfy_table::{Attribute, Cell, Color, Table};
+use comfy_table::ContentArrangement::Dynamic;
+use tempfile::TempDir;
+use crate::args::{ActionType, ExecuteMode, ParsedConfig};
+use crate::cli::prepare_chains;
+use crate::compiler::Compiler;
+use crate::executor::simple::SimpleExecutor;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+use crate::executor::sio2jail::TimeMetric;
+use crate::formatted_error::FormattedError;
+use crate::test_archive::TestArchive;
+
+/// Returns the time and memory limit of a test, taking the limits of the manifest and the task package into account
+fn limits_for(config: &ParsedConfig, test_name: &str) -> (Duration, Option<u64>) {
+    let default_memory_limit = match &config.execute_mode {
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        ExecuteMode::Sio2jail { memory_limit, .. } => Some(*memory_limit),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        ExecuteMode::Isolate { memory_limit } => *memory_limit,
+        #[cfg(target_os = "linux")]
+        ExecuteMode::PerfCounters { memory_limit } => *memory_limit,
+        #[cfg(windows)]
+        ExecuteMode::RestrictedToken { memory_limit } => *memory_limit,
+        #[cfg(target_os = "macos")]
+        ExecuteMode::Rusage { memory_limit, .. } => *memory_limit,
+        ExecuteMode::Container { memory_limit, .. } => *memory_limit,
+        ExecuteMode::Simple | ExecuteMode::Valgrind { .. } => None,
+    };
+    match config.limit_overrides.iter().find(|limit| limit.tests.matches(test_name)) {
+        Some(limit) => (limit.time_limit.unwrap_or(config.execute_timeout), limit.memory_limit.or(default_memory_limit)),
+        None => (config.execute_timeout, default_memory_limit),
+    }
+}
+
+fn describe_sandbox(execute_mode: &ExecuteMode) -> String {
+    match execute_mode {
+        ExecuteMode::Simple => "none".to_owned(),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        ExecuteMode::Sio2jail { extra_args, time_metric, .. } => {
+            let mut description = "sio2jail".to_owned();
+            if !extra_args.is_empty() {
+                write!(description, " with arguments {}", extra_args.join(" ")).expect("Writing to a string failed");
+            }
+            if *time_metric != TimeMetric::Real {
+                let time_metric = time_metric.to_possible_value().expect("Every time metric has a name");
+                write!(description, ", with the time limit on the {} metric", time_metric.get_name()).expect("Writing to a string failed");
+            }
+            description
+        }
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        ExecuteMode::Isolate { .. } => "isolate".to_owned(),
+        #[cfg(target_os = "linux")]
+        ExecuteMode::PerfCounters { .. } => "perf-counters, the CPU time is measured without isolating the program".to_owned(),
+        #[cfg(windows)]
+        ExecuteMode::RestrictedToken { .. } => "restricted-token, the program runs with a restricted token inside a job object".to_owned(),
+        #[cfg(target_os = "macos")]
+        ExecuteMode::Rusage { sandbox_exec: true, .. } => "sandbox-exec, the program can't access the network and write outside the temporary directory".to_owned(),
+        #[cfg(target_os = "macos")]
+        ExecuteMode::Rusage { sandbox_exec: false, .. } => "none, the peak memory use is checked after the program exits".to_owned(),
+        ExecuteMode::Container { runtime, image, .. } => format!("{} container with the {image} image", runtime.command_name()),
+        ExecuteMode::Valgrind { timeout_factor } => format!("none, run under valgrind with the time limit multiplied by {timeout_factor}"),
+    }
+}
+
+/// Prints how the program would be compiled and run, and how its outputs would be checked
+fn print_commands(config: &ParsedConfig, tempdir: &TempDir) {
+    let compiler = Compiler {
+        tempdir,
+        compile_timeout: config.compile_timeout,
+        compile_command: &config.compile_command,
+        cache: None,
+        extra_sources: &config.extra_sources,
+        custom_run_command: config.run_command.is_some(),
+    };
+    let executable = if !config.prebuilt && Compiler::is_source_file(&config.source_path) {
+        let executable = tempdir.path().join("program.o");
+        println!("{} {}", "Compile command:".bold(), compiler.command_line(&config.source_path, &executable));
+        executable
+    } else {
+        println!("{} {}", "Compile command:".bold(), "none, the program is an executable".bright_black());
+        config.source_path.clone()
+    };
+    let (program, arguments) = SimpleExecutor::split_run_command(config.run_command.as_deref(), &executable);
+    println!("{} {}", "Run command:".bold(), [program.display().to_string()].into_iter().chain(arguments).collect::<Vec<_>>().join(" "));
+    if let Some(file_io) = &config.file_io {
+        println!("{} {} (input), {} (output)", "Files:".bold(), file_io.input_name, file_io.output_name);
+    }
+    println!("{} {}", "Sandbox:".bold(), describe_sandbox(&config.execute_mode));
+    if let Some(input_transform) = &config.input_transform {
+        println!("{} {input_transform}", "Input transform:".bold());
+    }
+    if let Some(validator) = &config.validator {
+        println!("{} {}", "Validator:".bold(), validator.display());
+    }
+    match &config.action_type {
+        ActionType::Generate { output_directory, .. } => {
+            println!("{} generated into {}", "Outputs:".bold(), output_directory.display());
+        }
+        ActionType::SimpleCompare { .. } => {
+            println!("{} compared with the output files{}", "Outputs:".bold(), match config.builtin_checker {
+                Some(_) => " by the built-in checker",
+                None => "",
+            });
+        }
+        ActionType::Checker { path, protocol, .. } => {
+            let protocol = protocol.to_possible_value().expect("Every checker protocol has a name");
+            println!("{} checked by {} with the {} protocol", "Outputs:".bold(), path.display(), protocol.get_name());
+        }
+    }
+}
+
+/// Prints how the program would be compiled and run, and the tests it would be run on
+/// with their output files and limits, without compiling or running anything
+pub(crate) fn print_plan(config: &ParsedConfig, tempdir: &TempDir) -> Result<(), FormattedError> {
+    print_commands(config, tempdir);
+    let (ActionType::Generate { output_names, .. }
+        | ActionType::SimpleCompare { output_names, .. }
+        | ActionType::Checker { output_names, .. }) = &config.action_type;
+    let (_, chains) = prepare_chains(&config.input, &config.dependencies, &config.exclusion)?;
+    let output_archive = config.output_archive.as_deref()
+        .map(TestArchive::open)
+        .transpose()
+        .map_err(|error| FormattedError::from_str(&error))?;
+    let mut tests: Vec<(&str, Option<&str>)> = chains.iter()
+        .flat_map(|chain| chain.tests.iter().map(|chained_test| (
+            chained_test.test.test_name.as_str(),
+            chained_test.dependency.map(|index| chain.tests[index].test.test_name.as_str()),
+        )))
+        .collect();
+    tests.sort_by(|a, b| human_sort::compare(a.0, b.0));
+
+    let mut table = Table::new();
+    table.set_content_arrangement(Dynamic).set_header(
+        ["Test", "Output file", "Time limit", "Memory limit", "After"].map(|header| Cell::new(header).add_attribute(Attribute::Bold))
+    );
+    let mut missing_outputs = 0;
+    for (test_name, after) in &tests {
+        let output_file = match (&config.action_type, &output_archive) {
+            (ActionType::Generate { output_directory, .. }, _) => {
+                Some(output_directory.join(output_names.generated_file_name(test_name)).display().to_string())
+            }
+            (_, Some(archive)) => output_names.file_names(test_name)
+                .find(|file_name| archive.file_names().any(|name| name == file_name)),
+            (ActionType::SimpleCompare { output_directory, .. } | ActionType::Checker { output_directory, .. }, None) => {
+                Some(output_names.find_file(output_directory, test_name))
+                    .filter(|path| path.is_file())
+                    .map(|path| path.display().to_string())
+            }
+        };
+        if output_file.is_none() {
+            missing_outputs += 1;
+        }
+        let (time_limit, memory_limit) = limits_for(config, test_name);
+        table.add_row([
+            Cell::new(test_name),
+            output_file.map_or_else(|| Cell::new("missing").fg(Color::Yellow), Cell::new),
+            Cell::new(format!("{:.3}s", time_limit.as_secs_f64())),
+            Cell::new(memory_limit.map_or("-".to_owned(), |memory_limit| format!("{memory_limit} KiB"))),
+            Cell::new(after.unwrap_or("-")),
+        ]);
+    }
+    println!("\n{}", table.to_string().replace('\r', ""));
+    print!("{} {} tests found", "Tests:".bold(), tests.len());
+    if missing_outputs > 0 {
+        print!(" {}", format!("({missing_outputs} without an output file)").yellow());
+    }
+    println!();
+    Ok(())
+}
+
@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use encoding_rs::{Encoding, WINDOWS_1250};
+use flate2::read::GzDecoder;
+use crate::temp_files::{create_test_temp_file, TempFileError};
+use crate::warnings::{add_warning, WarningKind};
+
+/// The maximum number of transcoded files listed in the warning
+const MAX_LISTED_FILES: usize = 10;
+
+/// Test files that weren't UTF-8 encoded, with the encoding they were transcoded from
+static TRANSCODED_FILES: Mutex<BTreeMap<PathBuf, &'static str>> = Mutex::new(BTreeMap::new());
+
+/// Detects the encoding of a test file that isn't plain UTF-8.
+///
+/// Files with a byte order mark (UTF-8 or UTF-16) are recognized by it.
+/// Other files that aren't valid UTF-8 are assumed to be encoded in Windows-1250,
+/// which is common in old Polish test packages.
+fn detect_encoding(start: &[u8], is_utf8: bool) -> Option<&'static Encoding> {
+    match Encoding::for_bom(start) {
+        Some((encoding, _)) => Some(encoding),
+        None if is_utf8 => None,
+        None => Some(WINDOWS_1250),
+    }
+}
+
+fn decode(path: &Path, bytes: &[u8], encoding: &'static Encoding) -> String {
+    TRANSCODED_FILES.lock().expect("Failed to lock the transcoded files")
+        .insert(path.to_owned(), encoding.name());
+    // Decoding removes the byte order mark
+    encoding.decode(bytes).0.into_owned()
+}
+
+/// Checks if the file is valid UTF-8 without reading the whole file into memory
+fn is_utf8(file: &mut File) -> bool {
+    let mut buffer = vec![0; 1 << 16];
+    // The number of bytes of an incomplete character at the end of the previous chunk
+    let mut pending = 0;
+    loop {
+        let read = file.read(&mut buffer[pending..]).expect("Failed to read test file");
+        if read == 0 {
+            return pending == 0;
+        }
+        let filled = pending + read;
+        match std::str::from_utf8(&buffer[..filled]) {
+            Ok(_) => pending = 0,
+            Err(error) if error.error_len().is_none() => {
+                buffer.copy_within(error.valid_up_to()..filled, 0);
+                pending = filled - error.valid_up_to();
+            }
+            Err(_) => return false,
+        }
+    }
+}
+
+/// Opens a test file, decompressing gzip-compressed files (like `1.in.gz`) to a rewound temporary file
+fn open_test_file(path: &Path) -> Result<File, TempFileError> {
+    let file = File::open(path).expect("Failed to open test file");
+    if path.extension().is_none_or(|ext| ext != "gz") {
+        return Ok(file);
+    }
+    let mut decompressed = create_test_temp_file()?;
+    io::copy(&mut GzDecoder::new(file), &mut decompressed).expect("Failed to decompress test file");
+    decompressed.rewind().expect("Failed to rewind memfile");
+    Ok(decompressed)
+}
+
+/// Reads a text file, transcoding it to UTF-8 if it's encoded differently
+pub(crate) fn read_text_file(path: &Path) -> Result<String, TempFileError> {
+    let mut bytes = vec![];
+    open_test_file(path)?.read_to_end(&mut bytes).expect("Failed to read test file");
+    Ok(decode_text(path, bytes))
+}
+
+/// Decodes the contents of a text file like `read_text_file()`, `path` is only used in the transcoding warning
+pub(crate) fn decode_text(path: &Path, bytes: Vec<u8>) -> String {
+    match detect_encoding(&bytes, std::str::from_utf8(&bytes).is_ok()) {
+        None => String::from_utf8(bytes).expect("The file was checked to be valid UTF-8"),
+        Some(encoding) => decode(path, &bytes, encoding),
+    }
+}
+
+/// Opens a text file, transcoding it to a rewound temporary UTF-8 file if it's encoded differently
+pub(crate) fn open_text_file(path: &Path) -> Result<File, TempFileError> {
+    transcode_text_file(path, open_test_file(path)?)
+}
+
+/// Transcodes an opened text file like `open_text_file()`, `path` is only used in the transcoding warning
+pub(crate) fn transcode_text_file(path: &Path, mut file: File) -> Result<File, TempFileError> {
+    let mut start = [0; 3];
+    let start_length = file.read(&mut start).expect("Failed to read test file");
+    file.rewind().expect("Failed to rewind test file");
+    let is_utf8 = is_utf8(&mut file);
+    file.rewind().expect("Failed to rewind test file");
+
+    let Some(encoding) = detect_encoding(&start[..start_length], is_utf8) else {
+        return Ok(file);
+    };
+    let mut bytes = vec![];
+    file.read_to_end(&mut bytes).expect("Failed to read test file");
+    let mut transcoded = create_test_temp_file()?;
+    transcoded.write_all(decode(path, &bytes, encoding).as_bytes()).expect("Failed to write memfile");
+    transcoded.rewind().expect("Failed to rewind memfile");
+    Ok(transcoded)
+}
+
+/// Records a warning listing the test files that were transcoded to UTF-8 since the last warning, if there are any
+pub(crate) fn record_transcoding_warning() {
+    let files = std::mem::take(&mut *TRANSCODED_FILES.lock().expect("Failed to lock the transcoded files"));
+    if files.is_empty() {
+        return;
+    }
+    let mut message = format!("{} test files weren't UTF-8 encoded and were converted to UTF-8:", files.len());
+    for (path, encoding) in files.iter().take(MAX_LISTED_FILES) {
+        write!(message, "\n  {} ({encoding})", path.display()).expect("Writing to a string failed");
+    }
+    if files.len() > MAX_LISTED_FILES {
+        write!(message, "\n  and {} more", files.len() - MAX_LISTED_FILES).expect("Writing to a string failed");
+    }
+    add_warning(WarningKind::Transcoded, message);
+}
@@ -0,0 +1,174 @@
+use std::fs::File;
+use std::io::{read_to_string, Seek};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::time::{Duration, Instant};
+use clap::ValueEnum;
+use wait_timeout::ChildExt;
+use which::which;
+use crate::executor::simple::SimpleExecutor;
+use crate::executor::{ProgramEnvironment, TestExecutor};
+use crate::formatted_error::FormattedError;
+use crate::temp_files::{create_test_temp_file, make_cloned_stdio};
+use crate::test_errors::{ExecutionError, ExecutionMetrics};
+use crate::test_errors::ExecutionError::{MemoryLimitExceeded, SandboxError, TimedOut};
+
+/// The container runtime used to run the tested program
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ContainerRuntime {
+    Docker,
+    Podman,
+}
+
+impl ContainerRuntime {
+    pub(crate) fn command_name(self) -> &'static str {
+        match self {
+            ContainerRuntime::Docker => "docker",
+            ContainerRuntime::Podman => "podman",
+        }
+    }
+}
+
+/// The path the executable is mounted at inside the container
+const CONTAINER_EXECUTABLE_PATH: &str = "/toster/program";
+/// The exit code of a process killed with SIGKILL, which is how the out-of-memory killer stops the program
+const SIGKILL_EXIT_CODE: i32 = 128 + 9;
+
+/// Used to give every container started by this toster instance a unique name
+static CONTAINER_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Runs the program in a new container for every test.
+///
+/// The executable is mounted read-only, the input is passed on stdin
+/// and the container has no network access.
+/// The measured runtime includes the time needed to start the container.
+pub(crate) struct ContainerExecutor {
+    timeout: Duration,
+    executable_path: PathBuf,
+    runtime_path: PathBuf,
+    image: String,
+    memory_limit: Option<u64>,
+    /// The container starts from the environment of the image, only the variables are passed to the program
+    environment: ProgramEnvironment,
+}
+
+impl ContainerExecutor {
+    fn kill_container(&self, name: &str) {
+        let _ = Command::new(&self.runtime_path)
+            .args(["kill", name])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+
+    fn run_container(&self, input_file: &File, output_file: &File) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        let name = format!("toster-{}-{}", std::process::id(), CONTAINER_COUNTER.fetch_add(1, Relaxed));
+        let mut stderr = match create_test_temp_file() {
+            Ok(file) => file,
+            Err(error) => return (ExecutionMetrics::NONE, Err(error.into())),
+        };
+        let cloned = make_cloned_stdio(input_file)
+            .and_then(|stdin| Ok((stdin, make_cloned_stdio(output_file)?, make_cloned_stdio(&stderr)?)));
+        let (stdin, stdout, stderr_stdio) = match cloned {
+            Ok(cloned) => cloned,
+            Err(error) => return (ExecutionMetrics::NONE, Err(error.into())),
+        };
+
+        let mut command = Command::new(&self.runtime_path);
+        command
+            .args(["run", "--rm", "--interactive", "--network=none", "--cpus=1"])
+            .arg(format!("--name={name}"))
+            .arg(format!("--volume={}:{CONTAINER_EXECUTABLE_PATH}:ro", self.executable_path.display()))
+            .args(self.environment.assignments().map(|assignment| format!("--env={assignment}")));
+        if let Some(memory_limit) = self.memory_limit {
+            // Disables swap, so the program is stopped as soon as it exceeds the limit
+            command.arg(format!("--memory={memory_limit}k")).arg(format!("--memory-swap={memory_limit}k"));
+        }
+        let start_time = Instant::now();
+        let mut child = command
+            .args([&self.image, CONTAINER_EXECUTABLE_PATH])
+            .stdin(stdin)
+            .stdout(stdout)
+            .stderr(stderr_stdio)
+            .spawn().expect("Failed to spawn the container runtime");
+
+        let status = child.wait_timeout(self.timeout).unwrap();
+        let time = start_time.elapsed();
+        let Some(status) = status else {
+            self.kill_container(&name);
+            child.kill().unwrap();
+            child.wait().unwrap();
+            return (ExecutionMetrics { time: Some(self.timeout), memory_kibibytes: None }, Err(TimedOut));
+        };
+
+        let metrics = ExecutionMetrics { time: Some(time), memory_kibibytes: None };
+        let result = match status.code() {
+            Some(0) => Ok(()),
+            Some(SIGKILL_EXIT_CODE) if self.memory_limit.is_some() => Err(MemoryLimitExceeded),
+            // Exit codes used by the runtime itself if the container couldn't be started
+            Some(code @ 125..=127) => {
+                stderr.rewind().unwrap();
+                Err(SandboxError(format!(
+                    "{} failed to run the container (exit code {code}):\n{}",
+                    self.runtime_path.display(),
+                    read_to_string(stderr).unwrap(),
+                )))
+            }
+            _ => SimpleExecutor::map_status_code(status),
+        };
+        (metrics, result)
+    }
+
+    fn test(&self) -> Result<(), FormattedError> {
+        let output = Command::new(&self.runtime_path)
+            .args(["image", "inspect", &self.image])
+            .stdin(Stdio::null())
+            .output()
+            .expect("Failed to spawn the container runtime");
+        if !output.status.success() {
+            return Err(FormattedError::from_str(&format!(
+                "The container image \"{}\" is not available. Pull it with \"{} pull {}\":\n{}",
+                self.image,
+                self.runtime_path.display(),
+                self.image,
+                String::from_utf8_lossy(&output.stderr),
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn init_and_test(
+        timeout: Duration,
+        executable_path: PathBuf,
+        runtime: ContainerRuntime,
+        image: String,
+        memory_limit: Option<u64>,
+        environment: ProgramEnvironment,
+    ) -> Result<ContainerExecutor, FormattedError> {
+        let Ok(runtime_path) = which(runtime.command_name()) else {
+            return Err(FormattedError::from_str(&format!(
+                "The container runtime \"{}\" could not be found",
+                runtime.command_name(),
+            )));
+        };
+        let executor = ContainerExecutor {
+            timeout,
+            executable_path,
+            runtime_path,
+            image,
+            memory_limit,
+            environment,
+        };
+        executor.test()?;
+        Ok(executor)
+    }
+}
+
+impl TestExecutor for ContainerExecutor {
+    fn test_to_file(&self, input_file: &File, output_file: &File) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        self.run_container(input_file, output_file)
+    }
+}
@@ -0,0 +1,240 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io::{read_to_string, Seek};
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tempfile::NamedTempFile;
+use wait_timeout::ChildExt;
+use which::which;
+use crate::executor::{ProgramEnvironment, TestExecutor};
+use crate::process_tree::{register_running, unregister_running};
+use crate::formatted_error::FormattedError;
+use crate::{interrupt, signals};
+use crate::temp_files::{create_test_temp_file, make_cloned_stdio};
+use crate::test_errors::{ExecutionError, ExecutionMetrics};
+use crate::test_errors::ExecutionError::{MemoryLimitExceeded, RuntimeError, SandboxError, TimedOut};
+
+/// Runs the program inside a sandbox created by the [isolate](https://github.com/ioi/isolate) tool.
+///
+/// Every rayon thread uses its own box, so tests can be run in parallel.
+/// The boxes are reserved with lock files, so toster processes running at the same time use different boxes.
+pub(crate) struct IsolateExecutor {
+    timeout: Duration,
+    executable_path: PathBuf,
+    isolate_path: PathBuf,
+    memory_limit: Option<u64>,
+    /// Isolate always starts from an empty environment, only the variables are passed to the program
+    environment: ProgramEnvironment,
+}
+
+/// The name of the executable inside the box
+const BOX_EXECUTABLE_NAME: &str = "program";
+/// The number of boxes isolate allows by default
+const BOX_COUNT: usize = 1000;
+
+thread_local! {
+    /// The box used by the current thread and its lock file, which keeps it reserved until the thread exits
+    static THREAD_BOX: RefCell<Option<(usize, File)>> = const { RefCell::new(None) };
+}
+
+/// Locks the file reserving the box, returning `None` if another toster process uses it
+fn lock_box(box_id: usize) -> Option<File> {
+    let lock_file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(env::temp_dir().join(format!("toster-isolate-box-{box_id}.lock")))
+        .ok()?;
+    lock_file.try_lock().ok()?;
+    Some(lock_file)
+}
+
+impl IsolateExecutor {
+    /// Returns the box of the current thread, reserving the first free one when the thread runs its first test
+    fn box_id() -> Result<usize, ExecutionError> {
+        THREAD_BOX.with_borrow_mut(|thread_box| {
+            if let Some((box_id, _)) = thread_box {
+                return Ok(*box_id);
+            }
+            let (box_id, lock_file) = (0..BOX_COUNT)
+                .find_map(|box_id| Some((box_id, lock_box(box_id)?)))
+                .ok_or_else(|| SandboxError(format!("All {BOX_COUNT} isolate boxes are used by other toster processes")))?;
+            *thread_box = Some((box_id, lock_file));
+            Ok(box_id)
+        })
+    }
+
+    fn isolate_command(&self, box_id: usize) -> Command {
+        let mut command = Command::new(&self.isolate_path);
+        command.arg(format!("--box-id={box_id}"));
+        command
+    }
+
+    /// Creates a new box, returning the path of the directory the program is run in
+    fn init_box(&self, box_id: usize) -> Result<PathBuf, ExecutionError> {
+        // A box left over from an interrupted run has to be removed first
+        self.cleanup_box(box_id);
+        let output = self.isolate_command(box_id)
+            .arg("--init")
+            .stdin(Stdio::null())
+            .output()
+            .expect("Failed to spawn isolate");
+        if !output.status.success() {
+            return Err(SandboxError(format!(
+                "Isolate failed to initialize box {box_id}:\n{}",
+                String::from_utf8_lossy(&output.stderr),
+            )));
+        }
+        Ok(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()).join("box"))
+    }
+
+    fn cleanup_box(&self, box_id: usize) {
+        let _ = self.isolate_command(box_id)
+            .arg("--cleanup")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+
+    fn run_in_box(&self, box_id: usize, input_file: &File, output_file: &File, executable_path: &Path) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        let box_path = match self.init_box(box_id) {
+            Ok(box_path) => box_path,
+            Err(error) => return (ExecutionMetrics::NONE, Err(error)),
+        };
+        if let Err(error) = fs::copy(executable_path, box_path.join(BOX_EXECUTABLE_NAME)) {
+            return (ExecutionMetrics::NONE, Err(SandboxError(format!("Failed to copy the executable into the box:\n{error}"))));
+        }
+
+        let meta_file = NamedTempFile::new().expect("Failed to create the isolate meta file");
+        let mut stderr = match create_test_temp_file() {
+            Ok(file) => file,
+            Err(error) => return (ExecutionMetrics::NONE, Err(error.into())),
+        };
+        let cloned = make_cloned_stdio(input_file)
+            .and_then(|stdin| Ok((stdin, make_cloned_stdio(output_file)?, make_cloned_stdio(&stderr)?)));
+        let (stdin, stdout, stderr_stdio) = match cloned {
+            Ok(cloned) => cloned,
+            Err(error) => return (ExecutionMetrics::NONE, Err(error.into())),
+        };
+        let timeout_seconds = format!("{:.3}", self.timeout.as_secs_f64());
+        let mut command = self.isolate_command(box_id);
+        command
+            .arg(format!("--meta={}", meta_file.path().display()))
+            .arg(format!("--time={timeout_seconds}"))
+            .arg(format!("--wall-time={timeout_seconds}"))
+            .arg("--silent")
+            .args(self.environment.assignments().map(|assignment| format!("--env={assignment}")));
+        if let Some(memory_limit) = self.memory_limit {
+            command.arg(format!("--mem={memory_limit}"));
+        }
+        let mut child = command
+            .args(["--run", "--", &format!("./{BOX_EXECUTABLE_NAME}")])
+            .stdin(stdin)
+            .stdout(stdout)
+            .stderr(stderr_stdio)
+            .spawn().expect("Failed to spawn isolate");
+        register_running(child.id());
+
+        // Isolate enforces the time limit itself, this only guards against it hanging
+        let status = child.wait_timeout(self.timeout + Duration::from_secs(1)).unwrap();
+        unregister_running(child.id());
+        let Some(status) = status else {
+            child.kill().unwrap();
+            self.cleanup_box(box_id);
+            return (ExecutionMetrics { time: Some(self.timeout), memory_kibibytes: None }, Err(TimedOut));
+        };
+        if status.signal() == Some(2) {
+            interrupt::wait_for_handler();
+        }
+
+        stderr.rewind().unwrap();
+        let stderr = read_to_string(stderr).unwrap();
+        let meta = fs::read_to_string(meta_file.path()).unwrap_or_default();
+        self.cleanup_box(box_id);
+
+        // Isolate exits with 0 or 1 depending on the program's result, other exit codes mean an internal error
+        if !matches!(status.code(), Some(0 | 1)) {
+            return (ExecutionMetrics::NONE, Err(SandboxError(format!("Isolate returned an invalid status code: {status}\n{stderr}"))));
+        }
+        self.parse_meta(&meta, &stderr)
+    }
+
+    /// Maps the contents of the isolate meta file to the result of the test
+    fn parse_meta(&self, meta: &str, stderr: &str) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        let values: HashMap<&str, &str> = meta.lines()
+            .filter_map(|line| line.split_once(':'))
+            .collect();
+        let time = values.get("time")
+            .and_then(|time| time.parse::<f64>().ok())
+            .map(Duration::from_secs_f64);
+        let memory_kibibytes = values.get("max-rss")
+            .and_then(|memory| memory.parse::<u64>().ok());
+        let metrics = ExecutionMetrics { memory_kibibytes, time };
+        let message = values.get("message").copied().unwrap_or_default();
+
+        let memory_limit_exceeded = stderr.contains("std::bad_alloc")
+            || self.memory_limit.is_some_and(|limit| memory_kibibytes.is_some_and(|memory| memory >= limit));
+        let result = match values.get("status").copied() {
+            None => Ok(()),
+            Some("TO") => Err(TimedOut),
+            Some("RE" | "SG") if memory_limit_exceeded => Err(MemoryLimitExceeded),
+            Some("RE" | "SG") => Err(RuntimeError(match values.get("exitsig").and_then(|signal| signal.parse().ok()) {
+                Some(signal) => signals::killed_by(signal),
+                None => format!("- {}", message.to_lowercase()),
+            })),
+            Some("XX") => Err(SandboxError(format!("Isolate encountered an internal error: {message}"))),
+            Some(status) => Err(SandboxError(format!("Isolate returned an invalid status in the meta file: {status}"))),
+        };
+        (metrics, result)
+    }
+
+    fn test(&self) -> Result<(), FormattedError> {
+        let Ok(true_command_location) = which("true") else {
+            return Err(FormattedError::from_str("The executable for the \"true\" command could not be found"));
+        };
+
+        let null_file = File::open("/dev/null").expect("Opening /dev/null should not fail");
+        let (_, result) = match Self::box_id() {
+            Ok(box_id) => self.run_in_box(box_id, &null_file, &null_file, &true_command_location),
+            Err(error) => (ExecutionMetrics::NONE, Err(error)),
+        };
+        result.map_err(|error| FormattedError::from_str(&format!("Isolate can't be used: {error}")))
+    }
+
+    pub(crate) fn init_and_test(
+        timeout: Duration,
+        executable_path: PathBuf,
+        memory_limit: Option<u64>,
+        environment: ProgramEnvironment,
+    ) -> Result<IsolateExecutor, FormattedError> {
+        let Ok(isolate_path) = which("isolate") else {
+            return Err(FormattedError::from_str(
+                "Isolate could not be found. Install it from https://github.com/ioi/isolate and make sure it's in your PATH"
+            ));
+        };
+        let executor = IsolateExecutor {
+            timeout,
+            executable_path,
+            isolate_path,
+            memory_limit,
+            environment,
+        };
+        executor.test()?;
+        Ok(executor)
+    }
+}
+
+impl TestExecutor for IsolateExecutor {
+    fn test_to_file(&self, input_file: &File, output_file: &File) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        match Self::box_id() {
+            Ok(box_id) => self.run_in_box(box_id, input_file, output_file, &self.executable_path),
+            Err(error) => (ExecutionMetrics::NONE, Err(error)),
+        }
+    }
+}
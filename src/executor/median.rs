@@ -0,0 +1,71 @@
+use std::fs::File;
+use std::io::Seek;
+use std::time::Duration;
+use crate::executor::{AnyTestExecutor, TestExecutor};
+use crate::temp_files::create_test_temp_file;
+use crate::test_errors::{ExecutionError, ExecutionMetrics};
+use crate::test_errors::ExecutionError::TimedOut;
+
+/// Reruns tests whose runtime lands close to the time limit
+/// and uses the median runtime to decide if the test timed out,
+/// mimicking judges that rerun borderline submissions.
+///
+/// The inner executor should use a timeout extended by the band,
+/// otherwise runs slightly above the limit can't be measured.
+pub(crate) struct MedianRerunExecutor {
+    pub(crate) inner: Box<AnyTestExecutor>,
+    pub(crate) time_limit: Duration,
+    /// The relative distance from the time limit in which a test is considered borderline
+    pub(crate) band: f64,
+    /// The maximum number of runs of a borderline test, including the first one
+    pub(crate) max_runs: usize,
+}
+
+impl MedianRerunExecutor {
+    /// The timeout the inner executor should use for the given time limit and band
+    pub(crate) fn extended_timeout(time_limit: Duration, band: f64) -> Duration {
+        time_limit.mul_f64(1.0 + band)
+    }
+
+    fn is_borderline(&self, metrics: &ExecutionMetrics, result: &Result<(), ExecutionError>) -> bool {
+        let Some(time) = metrics.time else { return false };
+        result.is_ok() && time >= self.time_limit.mul_f64((1.0 - self.band).max(0.0))
+    }
+
+    fn prepare_rerun(mut input_file: &File, mut scratch_file: &File) {
+        input_file.rewind().expect("Failed to rewind input file");
+        scratch_file.set_len(0).expect("Failed to truncate memfile");
+        scratch_file.rewind().expect("Failed to rewind memfile");
+    }
+}
+
+impl TestExecutor for MedianRerunExecutor {
+    fn test_to_file(&self, input_file: &File, output_file: &File) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        let (metrics, result) = self.inner.test_to_file(input_file, output_file);
+        if !self.is_borderline(&metrics, &result) {
+            return (metrics, result);
+        }
+
+        // The output of the first run is kept, reruns are only used for measuring time
+        let scratch_file = match create_test_temp_file() {
+            Ok(file) => file,
+            Err(error) => return (metrics, Err(error.into())),
+        };
+        let mut times = vec![metrics.time.expect("Borderline runs always have a measured time")];
+        let mut memory_kibibytes = metrics.memory_kibibytes;
+        while times.len() < self.max_runs {
+            Self::prepare_rerun(input_file, &scratch_file);
+            let (metrics, result) = self.inner.test_to_file(input_file, &scratch_file);
+            memory_kibibytes = memory_kibibytes.max(metrics.memory_kibibytes);
+            match result {
+                Ok(()) | Err(TimedOut) => times.push(metrics.time.unwrap_or(self.time_limit)),
+                Err(error) => return (ExecutionMetrics { time: metrics.time, memory_kibibytes }, Err(error)),
+            }
+        }
+
+        times.sort_unstable();
+        let median = times[times.len() / 2];
+        let metrics = ExecutionMetrics { time: Some(median), memory_kibibytes };
+        if median > self.time_limit { (metrics, Err(TimedOut)) } else { (metrics, Ok(())) }
+    }
+}
@@ -1,15 +1,63 @@
 pub(crate) mod simple;
+pub(crate) mod median;
+pub(crate) mod container;
+pub(crate) mod throttled;
+pub(crate) mod valgrind;
 #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
 pub(crate) mod sio2jail;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+pub(crate) mod isolate;
+#[cfg(target_os = "linux")]
+pub(crate) mod perf_counter;
+#[cfg(windows)]
+pub(crate) mod restricted_token;
+#[cfg(target_os = "macos")]
+pub(crate) mod rusage;
 
 use std::fs::File;
-use std::io::{Read, Seek};
+use std::io::Seek;
+use std::process::Command;
+use crate::executor::container::ContainerExecutor;
+use crate::executor::median::MedianRerunExecutor;
 use crate::executor::simple::SimpleExecutor;
+use crate::executor::throttled::ThrottledInputExecutor;
+use crate::executor::valgrind::ValgrindExecutor;
 #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
 use crate::executor::sio2jail::Sio2jailExecutor;
-use crate::temp_files::create_temp_file;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+use crate::executor::isolate::IsolateExecutor;
+#[cfg(target_os = "linux")]
+use crate::executor::perf_counter::PerfCounterExecutor;
+#[cfg(windows)]
+use crate::executor::restricted_token::RestrictedTokenExecutor;
+#[cfg(target_os = "macos")]
+use crate::executor::rusage::RusageExecutor;
+use crate::temp_files::create_test_temp_file;
 use crate::test_errors::{ExecutionError, ExecutionMetrics};
 
+/// The environment variables the tested program is run with
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ProgramEnvironment {
+    /// Starts from an empty environment instead of the environment of toster
+    pub(crate) clear: bool,
+    pub(crate) variables: Vec<(String, String)>,
+}
+
+impl ProgramEnvironment {
+    /// Sets the environment of a program run directly, without a sandbox
+    pub(crate) fn apply(&self, command: &mut Command) {
+        if self.clear {
+            command.env_clear();
+        }
+        command.envs(self.variables.iter().map(|(name, value)| (name, value)));
+    }
+
+    /// Returns the variables in the NAME=VALUE format, as passed to sandboxes
+    pub(crate) fn assignments(&self) -> impl Iterator<Item = String> + '_ {
+        self.variables.iter().map(|(name, value)| format!("{name}={value}"))
+    }
+}
+
 pub(crate) trait TestExecutor: Sync + Send {
     /// Executes the program.
     ///
@@ -25,8 +73,11 @@ pub(crate) trait TestExecutor: Sync + Send {
 ///
 /// Stdin is read from `input_file`, stderr is ignored.
 /// `input_file` might not be read fully. Output file **is** rewound before returning.
-pub(crate) fn test_to_temp(executor: &impl TestExecutor, input_file: &File) -> (ExecutionMetrics, Result<impl Read, ExecutionError>) {
-    let mut stdout_memfile = create_temp_file().expect("Failed to create memfile");
+pub(crate) fn test_to_temp(executor: &impl TestExecutor, input_file: &File) -> (ExecutionMetrics, Result<File, ExecutionError>) {
+    let mut stdout_memfile = match create_test_temp_file() {
+        Ok(file) => file,
+        Err(error) => return (ExecutionMetrics::NONE, Err(error.into())),
+    };
     let (metrics, result) = executor.test_to_file(
         input_file,
         &stdout_memfile,
@@ -39,6 +90,18 @@ pub(crate) enum AnyTestExecutor {
     Simple(SimpleExecutor),
     #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
     Sio2Jail(Sio2jailExecutor),
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    Isolate(IsolateExecutor),
+    #[cfg(target_os = "linux")]
+    PerfCounter(PerfCounterExecutor),
+    #[cfg(windows)]
+    RestrictedToken(RestrictedTokenExecutor),
+    #[cfg(target_os = "macos")]
+    Rusage(RusageExecutor),
+    Container(ContainerExecutor),
+    Valgrind(ValgrindExecutor),
+    MedianRerun(MedianRerunExecutor),
+    ThrottledInput(ThrottledInputExecutor),
 }
 
 impl TestExecutor for AnyTestExecutor {
@@ -47,6 +110,18 @@ impl TestExecutor for AnyTestExecutor {
             AnyTestExecutor::Simple(executor) => executor.test_to_file(input_file, output_file),
             #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
             AnyTestExecutor::Sio2Jail(executor) => executor.test_to_file(input_file, output_file),
+            #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+            AnyTestExecutor::Isolate(executor) => executor.test_to_file(input_file, output_file),
+            #[cfg(target_os = "linux")]
+            AnyTestExecutor::PerfCounter(executor) => executor.test_to_file(input_file, output_file),
+            #[cfg(windows)]
+            AnyTestExecutor::RestrictedToken(executor) => executor.test_to_file(input_file, output_file),
+            #[cfg(target_os = "macos")]
+            AnyTestExecutor::Rusage(executor) => executor.test_to_file(input_file, output_file),
+            AnyTestExecutor::Container(executor) => executor.test_to_file(input_file, output_file),
+            AnyTestExecutor::Valgrind(executor) => executor.test_to_file(input_file, output_file),
+            AnyTestExecutor::MedianRerun(executor) => executor.test_to_file(input_file, output_file),
+            AnyTestExecutor::ThrottledInput(executor) => executor.test_to_file(input_file, output_file),
         }
     }
 }
@@ -0,0 +1,258 @@
+use std::fs::File;
+use std::io;
+use std::io::{read_to_string, Read, Seek};
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::net::UnixDatagram;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use which::which;
+use crate::executor::simple::SimpleExecutor;
+use crate::executor::{ProgramEnvironment, TestExecutor};
+use crate::formatted_error::FormattedError;
+use crate::process_tree::{register_running, unregister_running, wait_with_usage};
+use crate::temp_files::{create_test_temp_file, make_cloned_stdio};
+use crate::test_errors::{ExecutionError, ExecutionMetrics};
+use crate::test_errors::ExecutionError::{MemoryLimitExceeded, SandboxError, TimedOut};
+
+/// The `perf_event_attr` structure of the first version of the `perf_event_open` interface,
+/// which is accepted by every kernel supporting it
+#[repr(C)]
+#[derive(Default)]
+struct PerfEventAttr {
+    kind: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+}
+
+const PERF_TYPE_SOFTWARE: u32 = 1;
+/// Counts the CPU time of the task in nanoseconds
+const PERF_COUNT_SW_TASK_CLOCK: u64 = 1;
+const ATTR_DISABLED: u64 = 1 << 0;
+/// Also counts the threads and processes started by the program
+const ATTR_INHERIT: u64 = 1 << 1;
+/// Excluding the kernel lets the counter be opened by unprivileged users with the default `perf_event_paranoid` setting
+const ATTR_EXCLUDE_KERNEL: u64 = 1 << 5;
+const ATTR_EXCLUDE_HV: u64 = 1 << 6;
+/// Starts counting when the program is executed, so the time spent in toster before that isn't counted
+const ATTR_ENABLE_ON_EXEC: u64 = 1 << 12;
+const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 1 << 3;
+
+/// How many times longer than the time limit toster waits for the program,
+/// so a program that sleeps or waits for input still times out
+const WALL_TIMEOUT_FACTOR: u32 = 2;
+
+/// A control message passing a single file descriptor, laid out like the `CMSG_*` macros expect
+#[repr(C)]
+struct CounterMessage {
+    header: libc::cmsghdr,
+    counter: RawFd,
+}
+
+impl CounterMessage {
+    fn new(counter: RawFd) -> Self {
+        // SAFETY: cmsghdr is a plain C structure, which is valid when zeroed
+        let mut header: libc::cmsghdr = unsafe { mem::zeroed() };
+        header.cmsg_level = libc::SOL_SOCKET;
+        header.cmsg_type = libc::SCM_RIGHTS;
+        header.cmsg_len = (mem::offset_of!(CounterMessage, counter) + mem::size_of::<RawFd>()) as _;
+        CounterMessage { header, counter }
+    }
+
+    /// Returns a message with a one byte payload, since a datagram can't carry only a control message
+    fn message(&mut self, iov: &mut libc::iovec) -> libc::msghdr {
+        // SAFETY: msghdr is a plain C structure, which is valid when zeroed
+        let mut message: libc::msghdr = unsafe { mem::zeroed() };
+        message.msg_iov = iov;
+        message.msg_iovlen = 1;
+        message.msg_control = (&raw mut *self).cast();
+        message.msg_controllen = mem::size_of::<CounterMessage>() as _;
+        message
+    }
+}
+
+/// Opens a counter of the CPU time of the calling process and sends it through the socket.
+///
+/// Called in the forked child before the program is executed, so it only makes system calls
+/// and doesn't allocate memory
+fn open_and_send_counter(socket: RawFd) -> io::Result<()> {
+    let attr = PerfEventAttr {
+        kind: PERF_TYPE_SOFTWARE,
+        size: u32::try_from(mem::size_of::<PerfEventAttr>()).expect("The perf_event_attr size fits in u32"),
+        config: PERF_COUNT_SW_TASK_CLOCK,
+        flags: ATTR_DISABLED | ATTR_INHERIT | ATTR_EXCLUDE_KERNEL | ATTR_EXCLUDE_HV | ATTR_ENABLE_ON_EXEC,
+        ..PerfEventAttr::default()
+    };
+    // SAFETY: the attribute structure outlives the call, the process (0) and CPU (-1) select the calling process on any CPU
+    let counter = unsafe {
+        libc::syscall(libc::SYS_perf_event_open, &raw const attr, 0, -1, -1, PERF_FLAG_FD_CLOEXEC)
+    };
+    let Ok(counter) = RawFd::try_from(counter) else {
+        return Err(io::Error::last_os_error());
+    };
+    if counter < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut control = CounterMessage::new(counter);
+    let mut data = [0u8];
+    let mut iov = libc::iovec { iov_base: data.as_mut_ptr().cast(), iov_len: data.len() };
+    let message = control.message(&mut iov);
+    // SAFETY: the message points to buffers living until the end of the function
+    if unsafe { libc::sendmsg(socket, &raw const message, 0) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Receives the counter sent by `open_and_send_counter`
+fn receive_counter(socket: &UnixDatagram) -> io::Result<File> {
+    let mut control = CounterMessage::new(-1);
+    control.header.cmsg_type = 0;
+    let mut data = [0u8];
+    let mut iov = libc::iovec { iov_base: data.as_mut_ptr().cast(), iov_len: data.len() };
+    let mut message = control.message(&mut iov);
+    // SAFETY: the message points to buffers living until the end of the function
+    if unsafe { libc::recvmsg(socket.as_raw_fd(), &raw mut message, libc::MSG_CMSG_CLOEXEC) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if control.header.cmsg_type != libc::SCM_RIGHTS || control.counter < 0 {
+        return Err(io::Error::other("the counter wasn't received"));
+    }
+    // SAFETY: the received file descriptor isn't owned by anything else
+    Ok(unsafe { File::from_raw_fd(control.counter) })
+}
+
+/// Limits the address space of the calling process, so allocations above the memory limit fail
+fn limit_address_space(memory_limit: u64) -> io::Result<()> {
+    let bytes = memory_limit.saturating_mul(1024);
+    let limit = libc::rlimit { rlim_cur: bytes, rlim_max: bytes };
+    // SAFETY: the limit structure outlives the call
+    if unsafe { libc::setrlimit(libc::RLIMIT_AS, &raw const limit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Runs the program directly, measuring its CPU time with a `perf_event_open` counter
+/// and its peak memory use, and limiting its address space to the memory limit.
+///
+/// Unlike sio2jail, it doesn't isolate the program and works on every architecture,
+/// so it's used on ARM Linux, where sio2jail isn't available
+pub(crate) struct PerfCounterExecutor {
+    timeout: Duration,
+    executable_path: PathBuf,
+    arguments: Vec<String>,
+    memory_limit: Option<u64>,
+    environment: ProgramEnvironment,
+}
+
+impl PerfCounterExecutor {
+    fn run(&self, input_file: &File, output_file: &File, executable_path: &Path, arguments: &[String]) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        // Both sockets are closed in the child when the program is executed
+        let (socket, child_end) = UnixDatagram::pair().expect("Failed to create a socket pair");
+        let child_socket = child_end.as_raw_fd();
+        let memory_limit = self.memory_limit;
+        let mut stderr = match create_test_temp_file() {
+            Ok(file) => file,
+            Err(error) => return (ExecutionMetrics::NONE, Err(error.into())),
+        };
+        let cloned = make_cloned_stdio(input_file)
+            .and_then(|stdin| Ok((stdin, make_cloned_stdio(output_file)?, make_cloned_stdio(&stderr)?)));
+        let (stdin, stdout, stderr_stdio) = match cloned {
+            Ok(cloned) => cloned,
+            Err(error) => return (ExecutionMetrics::NONE, Err(error.into())),
+        };
+        let mut command = Command::new(executable_path);
+        self.environment.apply(&mut command);
+        command
+            .args(arguments)
+            .stdin(stdin)
+            .stdout(stdout)
+            .stderr(stderr_stdio);
+        // SAFETY: the closure only makes system calls, which is safe in the forked child
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(memory_limit) = memory_limit {
+                    limit_address_space(memory_limit)?;
+                }
+                open_and_send_counter(child_socket)
+            });
+        }
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(error) => return (ExecutionMetrics::NONE, Err(SandboxError(format!("Failed to start the program with a CPU time counter: {error}")))),
+        };
+        drop(child_end);
+        register_running(child.id());
+        let counter = receive_counter(&socket);
+        let finished = wait_with_usage(&mut child, self.timeout * WALL_TIMEOUT_FACTOR);
+        unregister_running(child.id());
+
+        let Some((status, usage)) = finished else {
+            return (ExecutionMetrics { time: Some(self.timeout), memory_kibibytes: None }, Err(TimedOut));
+        };
+        // On Linux, the peak memory use is given in KiB
+        let memory_kibibytes = u64::try_from(usage.ru_maxrss).unwrap_or(0);
+        let mut nanoseconds = [0u8; 8];
+        if let Err(error) = counter.and_then(|mut counter| counter.read_exact(&mut nanoseconds)) {
+            return (ExecutionMetrics::NONE, Err(SandboxError(format!("Failed to read the CPU time counter: {error}"))));
+        }
+        let time = Duration::from_nanos(u64::from_ne_bytes(nanoseconds));
+        let metrics = ExecutionMetrics { time: Some(time), memory_kibibytes: Some(memory_kibibytes) };
+        if time > self.timeout {
+            return (metrics, Err(TimedOut));
+        }
+        let result = SimpleExecutor::map_status_code(status);
+        if result.is_err() {
+            // Allocations fail once the address space limit is reached, which usually ends the program with std::bad_alloc
+            stderr.rewind().expect("Failed to rewind memfile");
+            let out_of_memory = read_to_string(stderr).unwrap_or_default().contains("std::bad_alloc")
+                || self.memory_limit.is_some_and(|limit| memory_kibibytes >= limit);
+            if out_of_memory {
+                return (metrics, Err(MemoryLimitExceeded));
+            }
+        }
+        (metrics, result)
+    }
+
+    fn test(&self) -> Result<(), FormattedError> {
+        let Ok(true_command_location) = which("true") else {
+            return Err(FormattedError::from_str("The executable for the \"true\" command could not be found"));
+        };
+        let null_file = File::open("/dev/null").expect("Opening /dev/null should not fail");
+        match self.run(&null_file, &null_file, &true_command_location, &[]) {
+            (_, Err(SandboxError(error))) => Err(FormattedError::from_str(&format!(
+                "{error}\nThe CPU time counter requires the kernel.perf_event_paranoid setting to be at most 2, it can be changed with:\nsudo sysctl -w kernel.perf_event_paranoid=2"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn init_and_test(
+        timeout: Duration,
+        executable_path: PathBuf,
+        arguments: Vec<String>,
+        memory_limit: Option<u64>,
+        environment: ProgramEnvironment,
+    ) -> Result<PerfCounterExecutor, FormattedError> {
+        let executor = PerfCounterExecutor { timeout, executable_path, arguments, memory_limit, environment };
+        executor.test()?;
+        Ok(executor)
+    }
+}
+
+impl TestExecutor for PerfCounterExecutor {
+    fn test_to_file(&self, input_file: &File, output_file: &File) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        self.run(input_file, output_file, &self.executable_path, &self.arguments)
+    }
+}
@@ -0,0 +1,424 @@
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io;
+use std::io::{read_to_string, Seek};
+use std::mem;
+use std::os::windows::ffi::OsStrExt;
+use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle};
+use std::os::windows::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::ptr;
+use std::time::Duration;
+use which::which;
+use windows_sys::Win32::Foundation::{SetHandleInformation, HANDLE, HANDLE_FLAG_INHERIT, WAIT_OBJECT_0};
+use windows_sys::Win32::Security::{
+    CreateRestrictedToken, CreateWellKnownSid, SetTokenInformation, TokenIntegrityLevel, WinLowLabelSid,
+    DISABLE_MAX_PRIVILEGE, LUA_TOKEN, SID_AND_ATTRIBUTES, TOKEN_ADJUST_DEFAULT, TOKEN_ASSIGN_PRIMARY,
+    TOKEN_DUPLICATE, TOKEN_MANDATORY_LABEL, TOKEN_QUERY,
+};
+use windows_sys::Win32::System::JobObjects::{
+    AssignProcessToJobObject, CreateJobObjectW, JobObjectBasicAccountingInformation, JobObjectBasicUIRestrictions,
+    JobObjectExtendedLimitInformation, QueryInformationJobObject, SetInformationJobObject, TerminateJobObject,
+    JOBOBJECTINFOCLASS, JOBOBJECT_BASIC_ACCOUNTING_INFORMATION, JOBOBJECT_BASIC_UI_RESTRICTIONS,
+    JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_DIE_ON_UNHANDLED_EXCEPTION, JOB_OBJECT_LIMIT_JOB_MEMORY,
+    JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE, JOB_OBJECT_UILIMIT_DESKTOP, JOB_OBJECT_UILIMIT_DISPLAYSETTINGS,
+    JOB_OBJECT_UILIMIT_EXITWINDOWS, JOB_OBJECT_UILIMIT_GLOBALATOMS, JOB_OBJECT_UILIMIT_HANDLES,
+    JOB_OBJECT_UILIMIT_READCLIPBOARD, JOB_OBJECT_UILIMIT_SYSTEMPARAMETERS, JOB_OBJECT_UILIMIT_WRITECLIPBOARD,
+};
+use windows_sys::Win32::System::Threading::{
+    CreateProcessAsUserW, DeleteProcThreadAttributeList, GetCurrentProcess, GetExitCodeProcess,
+    InitializeProcThreadAttributeList, OpenProcessToken, ResumeThread, UpdateProcThreadAttribute, WaitForSingleObject,
+    CREATE_NO_WINDOW, CREATE_SUSPENDED, CREATE_UNICODE_ENVIRONMENT, EXTENDED_STARTUPINFO_PRESENT,
+    LPPROC_THREAD_ATTRIBUTE_LIST, PROCESS_INFORMATION, PROC_THREAD_ATTRIBUTE_HANDLE_LIST, STARTF_USESTDHANDLES,
+    STARTUPINFOEXW,
+};
+use crate::executor::simple::SimpleExecutor;
+use crate::executor::{ProgramEnvironment, TestExecutor};
+use crate::formatted_error::FormattedError;
+use crate::process_tree::{register_running, unregister_running};
+use crate::temp_files::create_test_temp_file;
+use crate::test_errors::{ExecutionError, ExecutionMetrics};
+use crate::test_errors::ExecutionError::{MemoryLimitExceeded, SandboxError, TimedOut};
+
+/// How many times longer than the time limit toster waits for the program,
+/// so a program that sleeps or waits for input still times out
+const WALL_TIMEOUT_FACTOR: u32 = 2;
+/// The maximum size of a security identifier
+const MAX_SID_SIZE: usize = 68;
+/// Marks a security identifier as the integrity level of a token
+const SE_GROUP_INTEGRITY: u32 = 0x20;
+/// The exit code of a program which ran out of memory without handling the error
+const STATUS_NO_MEMORY: u32 = 0xC000_0017;
+/// Job object times are measured in units of 100 nanoseconds
+const JOB_TIME_UNIT_NANOSECONDS: u64 = 100;
+
+fn last_error(action: &str) -> String {
+    format!("Failed to {action}: {}", io::Error::last_os_error())
+}
+
+/// Takes ownership of a handle returned by the Windows API, so it's closed when dropped
+fn owned_handle(handle: HANDLE) -> OwnedHandle {
+    // SAFETY: the handle is valid and isn't owned by anything else
+    unsafe { OwnedHandle::from_raw_handle(handle as _) }
+}
+
+fn raw(handle: &impl AsRawHandle) -> HANDLE {
+    handle.as_raw_handle() as HANDLE
+}
+
+/// Creates a restricted version of toster's token, without any privileges and with the low integrity level,
+/// so the program can't write to the user's files or interact with other processes of the user
+fn create_restricted_token() -> Result<OwnedHandle, FormattedError> {
+    let mut token: HANDLE = 0;
+    // SAFETY: the token pointer is valid for the duration of the call
+    let opened = unsafe {
+        OpenProcessToken(GetCurrentProcess(), TOKEN_DUPLICATE | TOKEN_QUERY | TOKEN_ASSIGN_PRIMARY | TOKEN_ADJUST_DEFAULT, &mut token)
+    };
+    if opened == 0 {
+        return Err(FormattedError::from_str(&last_error("open the token of toster")));
+    }
+    let token = owned_handle(token);
+
+    let mut restricted: HANDLE = 0;
+    // SAFETY: no SIDs or privileges are passed, the token pointer is valid for the duration of the call
+    let created = unsafe {
+        CreateRestrictedToken(raw(&token), DISABLE_MAX_PRIVILEGE | LUA_TOKEN, 0, ptr::null(), 0, ptr::null(), 0, ptr::null(), &mut restricted)
+    };
+    if created == 0 {
+        return Err(FormattedError::from_str(&last_error("create a restricted token")));
+    }
+    let restricted = owned_handle(restricted);
+
+    let mut sid = [0u8; MAX_SID_SIZE];
+    let mut sid_size = MAX_SID_SIZE as u32;
+    // SAFETY: the SID buffer has the maximum size of a SID
+    if unsafe { CreateWellKnownSid(WinLowLabelSid, ptr::null_mut(), sid.as_mut_ptr().cast(), &mut sid_size) } == 0 {
+        return Err(FormattedError::from_str(&last_error("create the low integrity level SID")));
+    }
+    let label = TOKEN_MANDATORY_LABEL {
+        Label: SID_AND_ATTRIBUTES { Sid: sid.as_mut_ptr().cast(), Attributes: SE_GROUP_INTEGRITY },
+    };
+    // SAFETY: the label points to the SID, which lives until the end of the function
+    let labeled = unsafe {
+        SetTokenInformation(raw(&restricted), TokenIntegrityLevel, (&raw const label).cast(), mem::size_of::<TOKEN_MANDATORY_LABEL>() as u32 + sid_size)
+    };
+    if labeled == 0 {
+        return Err(FormattedError::from_str(&last_error("lower the integrity level of the restricted token")));
+    }
+    Ok(restricted)
+}
+
+fn set_job_information<T>(job: &OwnedHandle, class: JOBOBJECTINFOCLASS, information: &T) -> Result<(), ExecutionError> {
+    // SAFETY: the information structure matches the class and outlives the call
+    if unsafe { SetInformationJobObject(raw(job), class, (information as *const T).cast(), mem::size_of::<T>() as u32) } == 0 {
+        return Err(SandboxError(last_error("set the limits of the job object")));
+    }
+    Ok(())
+}
+
+/// # Safety
+/// `T` has to be the plain C structure matching the information class, which is valid when zeroed
+unsafe fn query_job_information<T>(job: &OwnedHandle, class: JOBOBJECTINFOCLASS) -> T {
+    let mut information: T = mem::zeroed();
+    // SAFETY: the information structure matches the class and outlives the call
+    unsafe { QueryInformationJobObject(raw(job), class, (&raw mut information).cast(), mem::size_of::<T>() as u32, ptr::null_mut()) };
+    information
+}
+
+/// Creates a job object, which kills all processes of the program when it's closed,
+/// limits their total committed memory and prevents them from using the desktop and clipboard
+fn create_job(memory_limit: Option<u64>) -> Result<OwnedHandle, ExecutionError> {
+    // SAFETY: an anonymous job object with the default security is created
+    let job = unsafe { CreateJobObjectW(ptr::null(), ptr::null()) };
+    if job == 0 {
+        return Err(SandboxError(last_error("create a job object")));
+    }
+    let job = owned_handle(job);
+
+    // SAFETY: the limit structure is a plain C structure, which is valid when zeroed
+    let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe { mem::zeroed() };
+    // Crashed programs exit immediately instead of showing the error reporting dialog
+    limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE | JOB_OBJECT_LIMIT_DIE_ON_UNHANDLED_EXCEPTION;
+    if let Some(memory_limit) = memory_limit {
+        limits.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_JOB_MEMORY;
+        limits.JobMemoryLimit = usize::try_from(memory_limit.saturating_mul(1024)).unwrap_or(usize::MAX);
+    }
+    set_job_information(&job, JobObjectExtendedLimitInformation, &limits)?;
+    let restrictions = JOBOBJECT_BASIC_UI_RESTRICTIONS {
+        UIRestrictionsClass: JOB_OBJECT_UILIMIT_DESKTOP | JOB_OBJECT_UILIMIT_DISPLAYSETTINGS | JOB_OBJECT_UILIMIT_EXITWINDOWS
+            | JOB_OBJECT_UILIMIT_GLOBALATOMS | JOB_OBJECT_UILIMIT_HANDLES | JOB_OBJECT_UILIMIT_READCLIPBOARD
+            | JOB_OBJECT_UILIMIT_SYSTEMPARAMETERS | JOB_OBJECT_UILIMIT_WRITECLIPBOARD,
+    };
+    set_job_information(&job, JobObjectBasicUIRestrictions, &restrictions)?;
+    Ok(job)
+}
+
+/// Appends the argument to the command line, quoted the way `CommandLineToArgvW` splits it
+fn push_argument(command_line: &mut String, argument: &str) {
+    if !command_line.is_empty() {
+        command_line.push(' ');
+    }
+    if !argument.is_empty() && !argument.contains([' ', '\t', '\n', '\u{b}', '"']) {
+        command_line.push_str(argument);
+        return;
+    }
+    command_line.push('"');
+    let mut backslashes = 0;
+    for character in argument.chars() {
+        match character {
+            '\\' => backslashes += 1,
+            // The backslashes before a quote have to be escaped, as well as the quote itself
+            '"' => {
+                command_line.extend(std::iter::repeat_n('\\', backslashes + 1));
+                backslashes = 0;
+            }
+            _ => backslashes = 0,
+        }
+        command_line.push(character);
+    }
+    // The backslashes before the closing quote have to be escaped too
+    command_line.extend(std::iter::repeat_n('\\', backslashes));
+    command_line.push('"');
+}
+
+fn to_wide(string: &OsStr) -> Vec<u16> {
+    string.encode_wide().chain([0]).collect()
+}
+
+/// Returns the environment block of the program, which consists of null-terminated NAME=VALUE strings
+/// and ends with an empty string
+fn environment_block(environment: &ProgramEnvironment) -> Vec<u16> {
+    let mut variables: Vec<(String, String)> = if environment.clear {
+        vec![]
+    } else {
+        std::env::vars_os()
+            .map(|(name, value)| (name.to_string_lossy().into_owned(), value.to_string_lossy().into_owned()))
+            .collect()
+    };
+    for (name, value) in &environment.variables {
+        // Variable names aren't case-sensitive on Windows
+        variables.retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+        variables.push((name.clone(), value.clone()));
+    }
+    let mut block: Vec<u16> = variables.iter()
+        .flat_map(|(name, value)| format!("{name}={value}").encode_utf16().chain([0]).collect::<Vec<_>>())
+        .collect();
+    if block.is_empty() {
+        block.push(0);
+    }
+    block.push(0);
+    block
+}
+
+/// A list of the handles inherited by the program, so it doesn't inherit the handles of programs started in parallel
+struct InheritedHandles {
+    handles: Vec<HANDLE>,
+    /// Pointer-aligned storage of the attribute list
+    buffer: Vec<usize>,
+}
+
+impl InheritedHandles {
+    fn new(handles: Vec<HANDLE>) -> Result<Self, ExecutionError> {
+        let mut size = 0;
+        // SAFETY: called without a list to get the required size, which fails by design
+        unsafe { InitializeProcThreadAttributeList(ptr::null_mut(), 1, 0, &mut size) };
+        let mut list = InheritedHandles { handles, buffer: vec![0; size.div_ceil(mem::size_of::<usize>())] };
+        // SAFETY: the buffer has the size required for a list with one attribute
+        if unsafe { InitializeProcThreadAttributeList(list.list(), 1, 0, &mut size) } == 0 {
+            return Err(SandboxError(last_error("create the list of inherited handles")));
+        }
+        // SAFETY: the handles live as long as the list
+        let updated = unsafe {
+            UpdateProcThreadAttribute(
+                list.list(),
+                0,
+                PROC_THREAD_ATTRIBUTE_HANDLE_LIST as usize,
+                list.handles.as_ptr().cast(),
+                mem::size_of_val(list.handles.as_slice()),
+                ptr::null_mut(),
+                ptr::null(),
+            )
+        };
+        if updated == 0 {
+            return Err(SandboxError(last_error("create the list of inherited handles")));
+        }
+        Ok(list)
+    }
+
+    fn list(&mut self) -> LPPROC_THREAD_ATTRIBUTE_LIST {
+        self.buffer.as_mut_ptr().cast()
+    }
+}
+
+impl Drop for InheritedHandles {
+    fn drop(&mut self) {
+        // SAFETY: the list was initialized in `new`
+        unsafe { DeleteProcThreadAttributeList(self.list()) };
+    }
+}
+
+/// Returns a copy of the file's handle, which can be inherited by the program
+fn inheritable_handle(file: &File) -> Result<File, ExecutionError> {
+    let copy = file.try_clone().map_err(|error| SandboxError(format!("Failed to copy a file handle: {error}")))?;
+    // SAFETY: the handle is owned by the copy
+    if unsafe { SetHandleInformation(raw(&copy), HANDLE_FLAG_INHERIT, HANDLE_FLAG_INHERIT) } == 0 {
+        return Err(SandboxError(last_error("make a file handle inheritable")));
+    }
+    Ok(copy)
+}
+
+/// Runs the program with a restricted token inside a job object, measuring its CPU time and peak memory use
+/// and limiting its committed memory to the memory limit.
+///
+/// This is a best-effort sandbox for Windows, where sio2jail and isolate aren't available:
+/// the program can still read the user's files and access the network
+pub(crate) struct RestrictedTokenExecutor {
+    timeout: Duration,
+    executable_path: PathBuf,
+    arguments: Vec<String>,
+    memory_limit: Option<u64>,
+    environment: ProgramEnvironment,
+    token: OwnedHandle,
+}
+
+impl RestrictedTokenExecutor {
+    /// Starts the suspended program and assigns it to the job, so its subprocesses are in the job too.
+    /// Returns the handle and ID of the process
+    fn spawn(&self, job: &OwnedHandle, input_file: &File, output_file: &File, stderr: &File, executable_path: &Path, arguments: &[String]) -> Result<(OwnedHandle, u32), ExecutionError> {
+        let stdio = [inheritable_handle(input_file)?, inheritable_handle(output_file)?, inheritable_handle(stderr)?];
+        let mut inherited = InheritedHandles::new(stdio.iter().map(raw).collect())?;
+
+        let mut command_line = String::new();
+        push_argument(&mut command_line, &executable_path.to_string_lossy());
+        for argument in arguments {
+            push_argument(&mut command_line, argument);
+        }
+        let mut command_line = to_wide(OsStr::new(&command_line));
+        let environment = environment_block(&self.environment);
+
+        // SAFETY: the startup information is a plain C structure, which is valid when zeroed
+        let mut startup_info: STARTUPINFOEXW = unsafe { mem::zeroed() };
+        startup_info.StartupInfo.cb = mem::size_of::<STARTUPINFOEXW>() as u32;
+        startup_info.StartupInfo.dwFlags = STARTF_USESTDHANDLES;
+        startup_info.StartupInfo.hStdInput = raw(&stdio[0]);
+        startup_info.StartupInfo.hStdOutput = raw(&stdio[1]);
+        startup_info.StartupInfo.hStdError = raw(&stdio[2]);
+        startup_info.lpAttributeList = inherited.list();
+        // SAFETY: the process information is a plain C structure, which is valid when zeroed
+        let mut process_info: PROCESS_INFORMATION = unsafe { mem::zeroed() };
+        // SAFETY: all pointers point to buffers living until the end of the function
+        let created = unsafe {
+            CreateProcessAsUserW(
+                raw(&self.token),
+                ptr::null(),
+                command_line.as_mut_ptr(),
+                ptr::null(),
+                ptr::null(),
+                1,
+                CREATE_SUSPENDED | CREATE_NO_WINDOW | CREATE_UNICODE_ENVIRONMENT | EXTENDED_STARTUPINFO_PRESENT,
+                environment.as_ptr().cast(),
+                ptr::null(),
+                &raw const startup_info.StartupInfo,
+                &mut process_info,
+            )
+        };
+        if created == 0 {
+            return Err(SandboxError(last_error("start the program with a restricted token")));
+        }
+        let process = owned_handle(process_info.hProcess);
+        let thread = owned_handle(process_info.hThread);
+        // SAFETY: both handles are valid
+        if unsafe { AssignProcessToJobObject(raw(job), raw(&process)) } == 0 {
+            let error = SandboxError(last_error("assign the program to a job object"));
+            // SAFETY: the job handle is valid, killing the suspended program
+            unsafe { TerminateJobObject(raw(job), 1) };
+            return Err(error);
+        }
+        // SAFETY: the thread handle is valid
+        unsafe { ResumeThread(raw(&thread)) };
+        Ok((process, process_info.dwProcessId))
+    }
+
+    fn run(&self, input_file: &File, output_file: &File, executable_path: &Path, arguments: &[String]) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        let mut stderr = match create_test_temp_file() {
+            Ok(file) => file,
+            Err(error) => return (ExecutionMetrics::NONE, Err(error.into())),
+        };
+        let job = match create_job(self.memory_limit) {
+            Ok(job) => job,
+            Err(error) => return (ExecutionMetrics::NONE, Err(error)),
+        };
+        let (process, pid) = match self.spawn(&job, input_file, output_file, &stderr, executable_path, arguments) {
+            Ok(spawned) => spawned,
+            Err(error) => return (ExecutionMetrics::NONE, Err(error)),
+        };
+        register_running(pid);
+        let wall_timeout = u32::try_from((self.timeout * WALL_TIMEOUT_FACTOR).as_millis()).unwrap_or(u32::MAX - 1);
+        // SAFETY: the process handle is valid
+        let finished = unsafe { WaitForSingleObject(raw(&process), wall_timeout) } == WAIT_OBJECT_0;
+        unregister_running(pid);
+        if !finished {
+            // SAFETY: the job handle is valid
+            unsafe { TerminateJobObject(raw(&job), 1) };
+            return (ExecutionMetrics { time: Some(self.timeout), memory_kibibytes: None }, Err(TimedOut));
+        }
+
+        // SAFETY: the structures match the information classes
+        let (accounting, limits): (JOBOBJECT_BASIC_ACCOUNTING_INFORMATION, JOBOBJECT_EXTENDED_LIMIT_INFORMATION) = unsafe {
+            (query_job_information(&job, JobObjectBasicAccountingInformation), query_job_information(&job, JobObjectExtendedLimitInformation))
+        };
+        let cpu_time = u64::try_from(accounting.TotalUserTime.saturating_add(accounting.TotalKernelTime)).unwrap_or(0);
+        let time = Duration::from_nanos(cpu_time.saturating_mul(JOB_TIME_UNIT_NANOSECONDS));
+        let memory_kibibytes = limits.PeakJobMemoryUsed as u64 / 1024;
+        let metrics = ExecutionMetrics { time: Some(time), memory_kibibytes: Some(memory_kibibytes) };
+        if time > self.timeout {
+            return (metrics, Err(TimedOut));
+        }
+
+        let mut exit_code = 0;
+        // SAFETY: the process handle is valid
+        unsafe { GetExitCodeProcess(raw(&process), &mut exit_code) };
+        let result = SimpleExecutor::map_status_code(ExitStatus::from_raw(exit_code));
+        if result.is_err() {
+            // Allocations fail once the job memory limit is reached, which usually ends the program with std::bad_alloc
+            stderr.rewind().expect("Failed to rewind memfile");
+            let out_of_memory = exit_code == STATUS_NO_MEMORY
+                || read_to_string(stderr).unwrap_or_default().contains("std::bad_alloc");
+            if self.memory_limit.is_some() && out_of_memory {
+                return (metrics, Err(MemoryLimitExceeded));
+            }
+        }
+        (metrics, result)
+    }
+
+    fn test(&self) -> Result<(), FormattedError> {
+        let Ok(cmd_location) = which("cmd") else {
+            return Err(FormattedError::from_str("The executable for the \"cmd\" command could not be found"));
+        };
+        let null_file = File::options().read(true).write(true).open("NUL").expect("Opening NUL should not fail");
+        match self.run(&null_file, &null_file, &cmd_location, &["/C".to_owned(), "exit".to_owned()]) {
+            (_, Err(SandboxError(error))) => Err(FormattedError::from_str(&error)),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn init_and_test(
+        timeout: Duration,
+        executable_path: PathBuf,
+        arguments: Vec<String>,
+        memory_limit: Option<u64>,
+        environment: ProgramEnvironment,
+    ) -> Result<RestrictedTokenExecutor, FormattedError> {
+        let token = create_restricted_token()?;
+        let executor = RestrictedTokenExecutor { timeout, executable_path, arguments, memory_limit, environment, token };
+        executor.test()?;
+        Ok(executor)
+    }
+}
+
+impl TestExecutor for RestrictedTokenExecutor {
+    fn test_to_file(&self, input_file: &File, output_file: &File) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        self.run(input_file, output_file, &self.executable_path, &self.arguments)
+    }
+}
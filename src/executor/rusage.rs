@@ -0,0 +1,128 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use which::which;
+use crate::executor::simple::SimpleExecutor;
+use crate::executor::{ProgramEnvironment, TestExecutor};
+use crate::formatted_error::FormattedError;
+use crate::process_tree::{register_running, unregister_running, wait_with_usage};
+use crate::temp_files::make_cloned_stdio;
+use crate::test_errors::{ExecutionError, ExecutionMetrics};
+use crate::test_errors::ExecutionError::{MemoryLimitExceeded, SandboxError, TimedOut};
+
+/// The profile denying the program access to the network and writes outside the temporary directory.
+/// The standard streams are opened by toster, so writing to them is still allowed
+const SANDBOX_PROFILE: &str = r#"(version 1)
+(allow default)
+(deny network*)
+(deny file-write*)
+(allow file-write* (subpath (param "TEMP_DIR")) (literal "/dev/null"))"#;
+
+/// Runs the program directly on macOS, measuring its peak memory use with the resource usage returned by `wait4`,
+/// optionally inside a `sandbox-exec` profile denying it access to the network and writes outside the temporary directory.
+///
+/// macOS doesn't enforce address space limits, so the memory limit is only checked after the program exits
+pub(crate) struct RusageExecutor {
+    timeout: Duration,
+    executable_path: PathBuf,
+    arguments: Vec<String>,
+    memory_limit: Option<u64>,
+    /// The path of `sandbox-exec`, if the program is run in a sandbox
+    sandbox_exec_path: Option<PathBuf>,
+    environment: ProgramEnvironment,
+}
+
+impl RusageExecutor {
+    fn command(&self, executable_path: &Path, arguments: &[String]) -> Command {
+        let Some(sandbox_exec_path) = &self.sandbox_exec_path else {
+            let mut command = Command::new(executable_path);
+            command.args(arguments);
+            return command;
+        };
+        // The temporary directory is a symlink on macOS, and the profile only matches resolved paths
+        let temp_dir = std::env::temp_dir();
+        let temp_dir = temp_dir.canonicalize().unwrap_or(temp_dir);
+        let mut command = Command::new(sandbox_exec_path);
+        command
+            .arg("-D").arg(format!("TEMP_DIR={}", temp_dir.display()))
+            .args(["-p", SANDBOX_PROFILE])
+            .arg(executable_path)
+            .args(arguments);
+        command
+    }
+
+    fn run(&self, input_file: &File, output_file: &File, executable_path: &Path, arguments: &[String]) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        let cloned = make_cloned_stdio(input_file).and_then(|stdin| Ok((stdin, make_cloned_stdio(output_file)?)));
+        let (stdin, stdout) = match cloned {
+            Ok(cloned) => cloned,
+            Err(error) => return (ExecutionMetrics::NONE, Err(error.into())),
+        };
+        let mut command = self.command(executable_path, arguments);
+        self.environment.apply(&mut command);
+        command
+            .stdin(stdin)
+            .stdout(stdout)
+            .stderr(Stdio::null());
+        let start_time = Instant::now();
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(error) => return (ExecutionMetrics::NONE, Err(SandboxError(format!("Failed to start the program: {error}")))),
+        };
+        register_running(child.id());
+        let finished = wait_with_usage(&mut child, self.timeout);
+        unregister_running(child.id());
+
+        let Some((status, usage)) = finished else {
+            return (ExecutionMetrics { time: Some(self.timeout), memory_kibibytes: None }, Err(TimedOut));
+        };
+        // On macOS, the peak memory use is given in bytes
+        let memory_kibibytes = u64::try_from(usage.ru_maxrss).unwrap_or(0) / 1024;
+        let metrics = ExecutionMetrics { time: Some(start_time.elapsed()), memory_kibibytes: Some(memory_kibibytes) };
+        if self.memory_limit.is_some_and(|limit| memory_kibibytes > limit) {
+            return (metrics, Err(MemoryLimitExceeded));
+        }
+        (metrics, SimpleExecutor::map_status_code(status))
+    }
+
+    fn test(&self) -> Result<(), FormattedError> {
+        if self.sandbox_exec_path.is_none() {
+            return Ok(());
+        }
+        let Ok(true_command_location) = which("true") else {
+            return Err(FormattedError::from_str("The executable for the \"true\" command could not be found"));
+        };
+        let null_file = File::open("/dev/null").expect("Opening /dev/null should not fail");
+        match self.run(&null_file, &null_file, &true_command_location, &[]) {
+            (_, Ok(())) => Ok(()),
+            (_, Err(error)) => Err(FormattedError::from_str(&format!("Failed to run a program with sandbox-exec: {error}"))),
+        }
+    }
+
+    pub(crate) fn init_and_test(
+        timeout: Duration,
+        executable_path: PathBuf,
+        arguments: Vec<String>,
+        memory_limit: Option<u64>,
+        sandbox_exec: bool,
+        environment: ProgramEnvironment,
+    ) -> Result<RusageExecutor, FormattedError> {
+        let sandbox_exec_path = if sandbox_exec {
+            let Ok(path) = which("sandbox-exec") else {
+                return Err(FormattedError::from_str("The \"sandbox-exec\" command could not be found"));
+            };
+            Some(path)
+        } else {
+            None
+        };
+        let executor = RusageExecutor { timeout, executable_path, arguments, memory_limit, sandbox_exec_path, environment };
+        executor.test()?;
+        Ok(executor)
+    }
+}
+
+impl TestExecutor for RusageExecutor {
+    fn test_to_file(&self, input_file: &File, output_file: &File) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        self.run(input_file, output_file, &self.executable_path, &self.arguments)
+    }
+}
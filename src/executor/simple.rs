@@ -1,25 +1,144 @@
+use std::fs;
 use std::fs::File;
-use std::path::PathBuf;
+use std::io;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, ExitStatus, Stdio};
 use std::time::{Duration, Instant};
 use crate::test_errors::{ExecutionError, ExecutionMetrics};
+use tempfile::TempDir;
 use wait_timeout::ChildExt;
-use crate::executor::TestExecutor;
-use crate::test_errors::ExecutionError::{RuntimeError, TimedOut};
+use crate::executor::{ProgramEnvironment, TestExecutor};
+use crate::test_errors::ExecutionError::{OutputLimitExceeded, RuntimeError, SandboxError, TimedOut};
 
 #[cfg(unix)]
-use crate::generic_utils::halt;
+use crate::{interrupt, signals};
 #[cfg(unix)]
 use std::os::unix::process::ExitStatusExt;
-use crate::temp_files::make_cloned_stdio;
+use crate::temp_files::{create_test_temp_file, make_cloned_stdio};
+use crate::process_tree::{kill_tree, register_running, terminate_tree, unregister_running};
+use crate::{output, program_stderr};
+#[cfg(target_os = "linux")]
+use which::which;
+#[cfg(target_os = "linux")]
+use crate::formatted_error::FormattedError;
 
 pub(crate) struct SimpleExecutor {
     pub(crate) timeout: Duration,
     pub(crate) executable_path: PathBuf,
+    pub(crate) arguments: Vec<String>,
+    /// The maximum number of bytes the program can write to the output file
+    pub(crate) output_limit: Option<u64>,
+    /// How long a timed out program can take to exit after receiving SIGTERM before it's killed
+    pub(crate) kill_grace: Duration,
+    /// Captures the program's stderr and adds the sanitizer report from it to runtime errors
+    pub(crate) sanitizer_report: bool,
+    /// Records the end of the program's stderr, which is shown for failed tests
+    pub(crate) capture_stderr: bool,
+    /// Makes the program read its input from a file and write its output to a file in its working directory
+    pub(crate) file_io: Option<FileIo>,
+    /// Runs the program in a new empty working directory, which is removed after the test.
+    /// Programs using file-based I/O are always run in one
+    pub(crate) scratch_directory: bool,
+    pub(crate) environment: ProgramEnvironment,
 }
 
+/// The names of the files a program using file-based I/O reads its input from and writes its output to
+#[derive(Clone, Debug)]
+pub(crate) struct FileIo {
+    pub(crate) input_name: String,
+    pub(crate) output_name: String,
+}
+
+impl FileIo {
+    /// Saves the input in the working directory under the input name
+    fn stage_input(&self, mut input_file: &File, directory: &TempDir) -> Result<(), ExecutionError> {
+        File::create(directory.path().join(&self.input_name))
+            .and_then(|mut staged| io::copy(&mut input_file, &mut staged))
+            .map_err(|error| SandboxError(format!("Failed to save the input file {}: {error}", self.input_name)))?;
+        Ok(())
+    }
+
+    /// Copies the file produced by the program to the output file
+    fn collect_output(&self, directory: &TempDir, mut output_file: &File) -> Result<(), ExecutionError> {
+        let Ok(mut produced) = File::open(directory.path().join(&self.output_name)) else {
+            return Err(RuntimeError(format!("- the program didn't create the output file {}", self.output_name)));
+        };
+        io::copy(&mut produced, &mut output_file)
+            .map_err(|error| SandboxError(format!("Failed to copy the output file {}: {error}", self.output_name)))?;
+        Ok(())
+    }
+}
+
+/// How often the size of the output is checked while the program is running
+const OUTPUT_CHECK_INTERVAL: Duration = Duration::from_millis(20);
+/// The maximum number of lines of the sanitizer report added to the error
+const MAX_SANITIZER_REPORT_LINES: usize = 30;
+
 impl SimpleExecutor {
-    fn map_status_code(status: &ExitStatus) -> Result<(), ExecutionError> {
+    /// Splits the run command into the executed program and its arguments,
+    /// replacing <EXE> with the path of the executable.
+    /// Without a run command, the executable is run directly
+    pub(crate) fn split_run_command(run_command: Option<&str>, executable_path: &Path) -> (PathBuf, Vec<String>) {
+        let Some(run_command) = run_command else {
+            return (executable_path.to_path_buf(), vec![]);
+        };
+        let executable = executable_path.to_str().expect("The provided filename is invalid");
+        let mut split = run_command.split(' ')
+            .filter(|part| !part.is_empty())
+            .map(|part| part.replace("<EXE>", executable));
+        let program = split.next().expect("The run command is invalid");
+        (PathBuf::from(program), split.collect())
+    }
+
+    /// Creates an executor that runs the program in a new network namespace, so it can't access the network.
+    /// The namespace is created by the `unshare` tool inside a new user namespace, which doesn't require root privileges
+    #[cfg(target_os = "linux")]
+    pub(crate) fn without_network(
+        timeout: Duration,
+        executable_path: &Path,
+        executable_arguments: Vec<String>,
+        output_limit: Option<u64>,
+        kill_grace: Duration,
+    ) -> Result<SimpleExecutor, FormattedError> {
+        const UNSHARE_ARGUMENTS: [&str; 2] = ["--net", "--map-root-user"];
+
+        let Ok(unshare_path) = which("unshare") else {
+            return Err(FormattedError::from_str("The \"unshare\" command required by the --no-network flag could not be found"));
+        };
+        let namespace_works = Command::new(&unshare_path)
+            .args(UNSHARE_ARGUMENTS)
+            .arg("true")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        if !namespace_works {
+            return Err(FormattedError::from_str(
+                "Failed to create a network namespace. The --no-network flag requires unprivileged user namespaces to be enabled"
+            ));
+        }
+
+        let mut arguments: Vec<String> = UNSHARE_ARGUMENTS.map(str::to_owned).to_vec();
+        arguments.push("--".to_owned());
+        arguments.push(executable_path.to_string_lossy().into_owned());
+        arguments.extend(executable_arguments);
+        Ok(SimpleExecutor {
+            timeout,
+            executable_path: unshare_path,
+            arguments,
+            output_limit,
+            kill_grace,
+            sanitizer_report: false,
+            capture_stderr: false,
+            file_io: None,
+            scratch_directory: false,
+            environment: ProgramEnvironment::default(),
+        })
+    }
+
+    pub(crate) fn map_status_code(status: ExitStatus) -> Result<(), ExecutionError> {
         match status.code() {
             Some(0) => Ok(()),
             Some(exit_code) => {
@@ -28,36 +147,155 @@ impl SimpleExecutor {
             None => {
                 #[cfg(unix)]
                 if status.signal().expect("The program returned an invalid status code") == 2 {
-                    halt();
+                    interrupt::wait_for_handler();
                 }
 
-                Err(RuntimeError(format!("- the process was terminated with the following error:\n{status}")))
+                Err(RuntimeError(Self::describe_termination(status)))
             }
         }
     }
 
-    fn wait_for_child(&self, mut child: Child) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+    #[cfg(unix)]
+    fn describe_termination(status: ExitStatus) -> String {
+        signals::killed_by(status.signal().expect("The program returned an invalid status code"))
+    }
+
+    #[cfg(not(unix))]
+    fn describe_termination(status: ExitStatus) -> String {
+        format!("- the process was terminated with the following error:\n{status}")
+    }
+
+    /// Returns the sanitizer report from the program's stderr, starting at the first line written by a sanitizer.
+    /// The lines the program wrote before are skipped
+    fn sanitizer_report(mut stderr: &File) -> Option<String> {
+        let mut contents = vec![];
+        stderr.rewind().ok()?;
+        stderr.read_to_end(&mut contents).ok()?;
+        let contents = String::from_utf8_lossy(&contents);
+        let lines: Vec<&str> = contents.lines()
+            .skip_while(|line| !line.contains("Sanitizer") && !line.contains("runtime error:"))
+            .take(MAX_SANITIZER_REPORT_LINES)
+            .collect();
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+
+    /// Waits for the program to exit, stopping it if it times out or the size returned by `output_size` exceeds the output limit
+    fn wait_for_child(&self, mut child: Child, output_size: impl Fn() -> u64) -> (ExecutionMetrics, Result<(), ExecutionError>) {
         let start_time = Instant::now();
-        let status = child.wait_timeout(self.timeout).unwrap();
+        let Some(output_limit) = self.output_limit else {
+            let status = child.wait_timeout(self.timeout).unwrap();
+            return self.map_wait_result(&mut child, status, start_time);
+        };
 
+        // The output file can already contain some data, only the data written by the program is counted
+        let initial_size = output_size();
+        loop {
+            let remaining = self.timeout.saturating_sub(start_time.elapsed());
+            let status = child.wait_timeout(remaining.min(OUTPUT_CHECK_INTERVAL)).unwrap();
+            if status.is_some() || remaining <= OUTPUT_CHECK_INTERVAL {
+                return self.map_wait_result(&mut child, status, start_time);
+            }
+            if output_size().saturating_sub(initial_size) > output_limit {
+                kill_tree(&mut child).unwrap();
+                child.wait().unwrap();
+                return (ExecutionMetrics { time: Some(start_time.elapsed()), memory_kibibytes: None }, Err(OutputLimitExceeded));
+            }
+        }
+    }
+
+    fn map_wait_result(&self, child: &mut Child, status: Option<ExitStatus>, start_time: Instant) -> (ExecutionMetrics, Result<(), ExecutionError>) {
         if let Some(status) = status { (
             ExecutionMetrics { time: Some(start_time.elapsed()), memory_kibibytes: None },
-            SimpleExecutor::map_status_code(&status)
+            SimpleExecutor::map_status_code(status)
         ) } else {
-            child.kill().unwrap();
+            terminate_tree(child, self.kill_grace).unwrap();
             (ExecutionMetrics { time: Some(self.timeout), memory_kibibytes: None }, Err(TimedOut))
         }
     }
 }
 
+impl SimpleExecutor {
+    /// Executes the program like `test_to_file()`, but writes its stderr to `stderr`
+    /// instead of handling it according to `sanitizer_report` and `capture_stderr`
+    pub(crate) fn test_with_stderr(&self, input_file: &File, output_file: &File, stderr: &File) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        match make_cloned_stdio(stderr) {
+            Ok(stderr) => self.execute(input_file, output_file, stderr),
+            Err(error) => (ExecutionMetrics::NONE, Err(error.into())),
+        }
+    }
+
+    fn execute(&self, input_file: &File, output_file: &File, stderr: Stdio) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        output::debug(&format!("Running: {} {}", self.executable_path.display(), self.arguments.join(" ")));
+        let mut command = Command::new(&self.executable_path);
+        command.args(&self.arguments).stderr(stderr);
+        self.environment.apply(&mut command);
+        let working_directory = match (self.scratch_directory || self.file_io.is_some()).then(tempfile::tempdir).transpose() {
+            Ok(working_directory) => working_directory,
+            Err(error) => return (ExecutionMetrics::NONE, Err(SandboxError(format!("Failed to create a working directory: {error}")))),
+        };
+        if let Some(directory) = &working_directory {
+            command.current_dir(directory.path());
+        }
+        match (&self.file_io, &working_directory) {
+            (Some(file_io), Some(directory)) => {
+                if let Err(error) = file_io.stage_input(input_file, directory) {
+                    return (ExecutionMetrics::NONE, Err(error));
+                }
+                command.stdin(Stdio::null()).stdout(Stdio::null());
+            }
+            _ => {
+                match make_cloned_stdio(input_file).and_then(|stdin| Ok((stdin, make_cloned_stdio(output_file)?))) {
+                    Ok((stdin, stdout)) => command.stdin(stdin).stdout(stdout),
+                    Err(error) => return (ExecutionMetrics::NONE, Err(error.into())),
+                };
+            }
+        }
+        let child = match command.spawn() {
+            Ok(child) => child,
+            Err(error) => return (ExecutionMetrics::NONE, Err(SandboxError(format!("Failed to start the program: {error}")))),
+        };
+        let pid = child.id();
+        register_running(pid);
+
+        // A program using file-based I/O writes to the output file in its working directory, which is copied after it exits
+        let output_path = self.file_io.as_ref().zip(working_directory.as_ref())
+            .map(|(file_io, directory)| directory.path().join(&file_io.output_name));
+        let (metrics, result) = self.wait_for_child(child, || match &output_path {
+            Some(output_path) => fs::metadata(output_path),
+            None => output_file.metadata(),
+        }.map_or(0, |metadata| metadata.len()));
+        unregister_running(pid);
+        let result = match (result, &self.file_io, &working_directory) {
+            (Ok(()), Some(file_io), Some(directory)) => file_io.collect_output(directory, output_file),
+            (result, _, _) => result,
+        };
+        (metrics, result)
+    }
+}
+
 impl TestExecutor for SimpleExecutor {
     fn test_to_file(&self, input_file: &File, output_file: &File) -> (ExecutionMetrics, Result<(), ExecutionError>) {
-        let child = Command::new(&self.executable_path)
-            .stdin(make_cloned_stdio(input_file))
-            .stdout(make_cloned_stdio(output_file))
-            .stderr(Stdio::null())
-            .spawn().expect("Failed to spawn child");
-
-        self.wait_for_child(child)
+        let stderr = match (self.sanitizer_report || self.capture_stderr).then(create_test_temp_file).transpose() {
+            Ok(stderr) => stderr,
+            Err(error) => return (ExecutionMetrics::NONE, Err(error.into())),
+        };
+        let stderr_stdio = match stderr.as_ref().map_or_else(|| Ok(Stdio::null()), make_cloned_stdio) {
+            Ok(stderr_stdio) => stderr_stdio,
+            Err(error) => return (ExecutionMetrics::NONE, Err(error.into())),
+        };
+        let (metrics, result) = self.execute(input_file, output_file, stderr_stdio);
+        let report = match (&result, &stderr) {
+            (Err(RuntimeError(_)), Some(stderr)) if self.sanitizer_report => Self::sanitizer_report(stderr),
+            _ => None,
+        };
+        // The sanitizer report already contains the relevant part of the stderr
+        if self.capture_stderr {
+            program_stderr::record(stderr.as_ref().filter(|_| report.is_none()));
+        }
+        let result = match (result, report) {
+            (Err(RuntimeError(error)), Some(report)) => Err(RuntimeError(format!("{error}\n{report}"))),
+            (result, _) => result,
+        };
+        (metrics, result)
     }
 }
@@ -1,28 +1,76 @@
+use std::fs;
 use std::fs::File;
 use std::io::{read_to_string, Seek};
 use std::os::unix::process::ExitStatusExt;
 use std::path::{Path, PathBuf};
 use std::process::{Command, ExitStatus};
 use std::time::Duration;
+use clap::ValueEnum;
 use colored::Colorize;
 use command_fds::{CommandFdExt, FdMapping};
 use directories::BaseDirs;
 use wait_timeout::ChildExt;
 use which::which;
-use crate::temp_files::{create_temp_file, make_cloned_stdio};
-use crate::executor::TestExecutor;
+use crate::temp_files::{create_test_temp_file, make_cloned_stdio};
+use crate::executor::{ProgramEnvironment, TestExecutor};
 use crate::formatted_error::FormattedError;
-use crate::generic_utils::halt;
+use crate::{interrupt, signals};
+use crate::process_tree::{kill_tree, register_running, unregister_running};
 use crate::test_errors::{ExecutionError, ExecutionMetrics};
-use crate::test_errors::ExecutionError::{MemoryLimitExceeded, RuntimeError, Sio2jailError, TimedOut};
+use crate::test_errors::ExecutionError::{MemoryLimitExceeded, OutputLimitExceeded, RuntimeError, SandboxError, TimedOut};
+
+/// The number of instructions counted as one second of measured time, the same as in oiejq and SIO2
+const INSTRUCTIONS_PER_SECOND: u128 = 2_000_000_000;
+/// How many times longer than the time limit toster waits for a program limited by its CPU time or instruction count,
+/// so a program that sleeps or waits for input still times out
+const WALL_TIMEOUT_FACTOR: u32 = 2;
+
+/// What the time limit of a program run in sio2jail is compared with
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimeMetric {
+    /// The wall-clock time measured by toster
+    Real,
+    /// The user and system CPU time of the program, limited by sio2jail
+    Cpu,
+    /// The number of executed instructions converted to time, limited by sio2jail like in oiejq and SIO2
+    Instructions,
+}
+
+impl TimeMetric {
+    /// Returns the sio2jail arguments limiting the program to `time_limit` measured with this metric
+    fn sio2jail_args(self, time_limit: Duration) -> Vec<String> {
+        match self {
+            TimeMetric::Real => vec![],
+            TimeMetric::Cpu => vec!["--ustimelimit".to_owned(), format!("{}ms", time_limit.as_millis())],
+            TimeMetric::Instructions => vec![
+                "--instruction-count-limit".to_owned(),
+                (time_limit.as_millis() * INSTRUCTIONS_PER_SECOND / 1000).to_string(),
+            ],
+        }
+    }
+}
 
 pub(crate) struct Sio2jailExecutor {
     timeout: Duration,
+    time_metric: TimeMetric,
     executable_path: PathBuf,
     sio2jail_path: PathBuf,
     memory_limit: u64,
+    extra_args: Vec<String>,
+    environment: ProgramEnvironment,
 }
 
+/// Sio2jail options toster uses by default, which can be overridden with the `--sio2jail-arg` option
+const DEFAULT_OPTIONS: [(&str, &str); 7] = [
+    ("--mount-namespace", "off"),
+    ("--pid-namespace", "off"),
+    ("--uts-namespace", "off"),
+    ("--ipc-namespace", "off"),
+    ("--net-namespace", "off"),
+    ("--capability-drop", "off"),
+    ("--user-namespace", "off"),
+];
+
 struct Sio2jailOutput {
     status: ExitStatus,
     stderr: String,
@@ -31,7 +79,8 @@ struct Sio2jailOutput {
 }
 
 impl Sio2jailExecutor {
-    fn get_sio2jail_path() -> Result<PathBuf, FormattedError> {
+    /// The path sio2jail is expected to be installed at
+    pub(crate) fn get_sio2jail_install_path() -> Result<PathBuf, FormattedError> {
         let Some(binding) = BaseDirs::new() else {
             return Err(FormattedError::from_str(
                 "No valid home directory path could be retrieved from the operating system. Sio2jail could not be found"
@@ -42,34 +91,53 @@ impl Sio2jailExecutor {
                 "Couldn't locate the user's executable directory. Sio2jail could not be found"
             ));
         };
+        Ok(executable_dir.join("sio2jail"))
+    }
 
-        let result = executable_dir.join("sio2jail");
+    fn get_sio2jail_path() -> Result<PathBuf, FormattedError> {
+        let result = Self::get_sio2jail_install_path()?;
         if !result.exists() {
             return Err(FormattedError::from_str(
-                &format!("Sio2jail could not be found at {}", result.display())
+                &format!("Sio2jail could not be found at {}. Run \"toster install-sio2jail\" to install it", result.display())
             ));
         }
         Ok(result)
     }
 
     fn run_sio2jail(&self, input_file: &File, output_file: &File, executable_path: &Path) -> Result<Sio2jailOutput, ExecutionError> {
-        let mut sio2jail_output = create_temp_file().unwrap();
-        let mut stderr = create_temp_file().unwrap();
+        let mut sio2jail_output = create_test_temp_file()?;
+        let mut stderr = create_test_temp_file()?;
 
-        let mut child = Command::new(&self.sio2jail_path)
-            .args(["-f", "3", "-o", "oiaug", "--mount-namespace", "off", "--pid-namespace", "off", "--uts-namespace", "off", "--ipc-namespace", "off", "--net-namespace", "off", "--capability-drop", "off", "--user-namespace", "off", "-m", &self.memory_limit.to_string(), "--", executable_path.to_str().unwrap()])
+        let default_options = DEFAULT_OPTIONS.iter()
+            .filter(|(option, _)| !self.extra_args.iter().any(|arg| arg == option || arg.starts_with(&format!("{option}="))))
+            .flat_map(|(option, value)| [*option, *value]);
+        let mut command = Command::new(&self.sio2jail_path);
+        self.environment.apply(&mut command);
+        let mut child = command
+            .args(["-f", "3", "-o", "oiaug"])
+            .args(default_options)
+            .args(self.time_metric.sio2jail_args(self.timeout))
+            .args(&self.extra_args)
+            .args(["-m", &self.memory_limit.to_string(), "--", executable_path.to_str().unwrap()])
             .fd_mappings(vec![FdMapping {
                 parent_fd: sio2jail_output.try_clone().unwrap().into(),
                 child_fd: 3,
             }]).expect("Failed to redirect file descriptor 3")
-            .stdout(make_cloned_stdio(output_file))
-            .stderr(make_cloned_stdio(&stderr))
-            .stdin(make_cloned_stdio(input_file))
+            .stdout(make_cloned_stdio(output_file)?)
+            .stderr(make_cloned_stdio(&stderr)?)
+            .stdin(make_cloned_stdio(input_file)?)
             .spawn().expect("Failed to spawn sio2jail");
+        register_running(child.id());
 
-        let status = child.wait_timeout(self.timeout).unwrap();
+        // Sio2jail stops programs exceeding the CPU time or instruction limit by itself
+        let wall_timeout = match self.time_metric {
+            TimeMetric::Real => self.timeout,
+            TimeMetric::Cpu | TimeMetric::Instructions => self.timeout * WALL_TIMEOUT_FACTOR,
+        };
+        let status = child.wait_timeout(wall_timeout).unwrap();
+        unregister_running(child.id());
         let Some(status) = status else {
-            child.kill().unwrap();
+            kill_tree(&mut child).unwrap();
             return Err(TimedOut);
         };
 
@@ -93,13 +161,7 @@ impl Sio2jailExecutor {
             .run_sio2jail(&null_file, &null_file, &true_command_location)
             .map_err(|error| FormattedError::from_str(&format!("Sio2jail error: {error}")))?;
         if output.stderr == "Exception occurred: System error occured: perf event open failed: Permission denied: error 13: Permission denied\n" {
-            return Err(FormattedError::preformatted(format!(
-                "{}\n{}",
-                "You need to run the following command to use toster with sio2jail.\n\
-                You may also put this option in your /etc/sysctl.conf.\n\
-                This will make the setting persist across reboots.".red(),
-                "sudo sysctl -w kernel.perf_event_paranoid=-1".white()
-            )));
+            return Err(FormattedError::preformatted(Self::perf_event_paranoid_advice()));
         }
         if !output.stderr.is_empty() {
             return Err(FormattedError::from_str(&format!("Sio2jail error: {}", output.stderr)));
@@ -107,10 +169,52 @@ impl Sio2jailExecutor {
         Ok(())
     }
 
-    pub(crate) fn init_and_test(timeout: Duration, executable_path: PathBuf, memory_limit: u64) -> Result<Sio2jailExecutor, FormattedError> {
+    /// Checks if sio2jail is installed and can be run, returning its path
+    pub(crate) fn check_installation() -> Result<PathBuf, FormattedError> {
+        let executor = Sio2jailExecutor {
+            timeout: Duration::from_secs(5),
+            time_metric: TimeMetric::Real,
+            memory_limit: 1024 * 1024,
+            extra_args: vec![],
+            environment: ProgramEnvironment::default(),
+            executable_path: PathBuf::new(),
+            sio2jail_path: Self::get_sio2jail_path()?,
+        };
+        executor.test()?;
+        Ok(executor.sio2jail_path)
+    }
+
+    /// Reads the value of the `kernel.perf_event_paranoid` setting, which has to be -1 for sio2jail to work
+    pub(crate) fn read_perf_event_paranoid() -> Option<i32> {
+        fs::read_to_string("/proc/sys/kernel/perf_event_paranoid").ok()
+            .and_then(|value| value.trim().parse::<i32>().ok())
+    }
+
+    /// Instructions for allowing sio2jail to use performance counters
+    pub(crate) fn perf_event_paranoid_advice() -> String {
+        format!(
+            "{}\n{}",
+            "You need to run the following command to use toster with sio2jail.\n\
+            You may also put this option in your /etc/sysctl.conf.\n\
+            This will make the setting persist across reboots.".red(),
+            "sudo sysctl -w kernel.perf_event_paranoid=-1".white()
+        )
+    }
+
+    pub(crate) fn init_and_test(
+        timeout: Duration,
+        time_metric: TimeMetric,
+        executable_path: PathBuf,
+        memory_limit: u64,
+        extra_args: Vec<String>,
+        environment: ProgramEnvironment,
+    ) -> Result<Sio2jailExecutor, FormattedError> {
         let executor = Sio2jailExecutor {
             timeout,
+            time_metric,
             memory_limit,
+            extra_args,
+            environment,
             executable_path,
             sio2jail_path: Self::get_sio2jail_path()?,
         };
@@ -135,18 +239,20 @@ impl TestExecutor for Sio2jailExecutor {
             return if output.stderr == "terminate called after throwing an instance of 'std::bad_alloc'\n  what():  std::bad_alloc\n" {
                 (ExecutionMetrics { time: None, memory_kibibytes: Some(self.memory_limit) }, Err(MemoryLimitExceeded))
             } else {
-                (ExecutionMetrics::NONE, Err(Sio2jailError(output.stderr)))
+                (ExecutionMetrics::NONE, Err(SandboxError(output.stderr)))
             };
         }
 
         let split: Vec<&str> = output.sio2jail_output.split_whitespace().collect();
         if split.len() < 6 {
-            return (ExecutionMetrics::NONE, Err(Sio2jailError(format!("The sio2jail output is too short: {}", output.sio2jail_output))));
+            return (ExecutionMetrics::NONE, Err(SandboxError(format!("The sio2jail output is too short: {}", output.sio2jail_output))));
         }
         let sio2jail_status = split[0];
         let time = Duration::from_secs_f64(split[2].parse::<f64>().expect("Sio2jail returned an invalid runtime in the output") / 1000.0);
         let memory_kibibytes = split[4].parse::<u64>().expect("Sio2jail returned invalid memory usage in the output");
         let error_message = output.sio2jail_output.lines().nth(1);
+        // Sio2jail ends the message with the number of the signal if the program was stopped by one
+        let signal = error_message.and_then(|message| message.rsplit_once("signal ")?.1.trim().parse().ok());
 
         let metrics = ExecutionMetrics {
             time: Some(time),
@@ -157,24 +263,27 @@ impl TestExecutor for Sio2jailExecutor {
             None => {
                 #[cfg(unix)]
                 if cfg!(unix) && output.status.signal().expect("Sio2jail returned an invalid status code") == 2 {
-                    halt();
+                    interrupt::wait_for_handler();
                 }
 
                 return (metrics, Err(RuntimeError(format!("- the process was terminated with the following error:\n{}", output.status))));
             }
             Some(0) => {}
             Some(exit_code) => {
-                return (metrics, Err(Sio2jailError(format!("Sio2jail returned an invalid status code: {exit_code}"))));
+                return (metrics, Err(SandboxError(format!("Sio2jail returned an invalid status code: {exit_code}"))));
             }
         }
 
         (ExecutionMetrics { time: Some(time), memory_kibibytes: Some(memory_kibibytes) }, match sio2jail_status {
             "OK" => Ok(()),
-            "RE" | "RV" => Err(RuntimeError(error_message.map_or(String::new(), |message| format!("- {message}")))),
+            "RE" | "RV" => Err(RuntimeError(match (signal, error_message) {
+                (Some(signal), _) => signals::killed_by(signal),
+                (None, message) => message.map_or(String::new(), |message| format!("- {message}")),
+            })),
             "TLE" => Err(TimedOut),
             "MLE" => Err(MemoryLimitExceeded),
-            "OLE" => Err(RuntimeError("- output limit exceeded".to_owned())),
-            _ => Err(Sio2jailError(format!("Sio2jail returned an invalid status in the output: {sio2jail_status}")))
+            "OLE" => Err(OutputLimitExceeded),
+            _ => Err(SandboxError(format!("Sio2jail returned an invalid status in the output: {sio2jail_status}")))
         })
     }
 }
@@ -0,0 +1,58 @@
+use std::fs::File;
+use std::io::{PipeWriter, Read, Write};
+use std::thread;
+use std::time::Duration;
+use crate::executor::{AnyTestExecutor, TestExecutor};
+use crate::test_errors::{ExecutionError, ExecutionMetrics};
+
+/// The interval between the chunks of input written to the program
+const CHUNK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Delivers the input to the program through a pipe at a limited rate,
+/// simulating a slow interactive judge.
+///
+/// Reveals programs that incorrectly assume the whole input is available immediately.
+pub(crate) struct ThrottledInputExecutor {
+    pub(crate) inner: Box<AnyTestExecutor>,
+    /// The number of input bytes delivered per second
+    pub(crate) bytes_per_second: u64,
+}
+
+impl ThrottledInputExecutor {
+    fn chunk_size(&self) -> usize {
+        let chunks_per_second = Duration::from_secs(1).as_millis() / CHUNK_INTERVAL.as_millis();
+        usize::try_from(u128::from(self.bytes_per_second) / chunks_per_second).unwrap_or(usize::MAX).max(1)
+    }
+
+    /// Writes the input to the pipe one chunk at a time.
+    /// Stops early if the program closes its input
+    fn feed(&self, mut input_file: &File, mut writer: PipeWriter) {
+        let mut buffer = vec![0; self.chunk_size()];
+        loop {
+            let read = input_file.read(&mut buffer).expect("Failed to read input file");
+            if read == 0 || writer.write_all(&buffer[..read]).is_err() {
+                return;
+            }
+            thread::sleep(CHUNK_INTERVAL);
+        }
+    }
+}
+
+impl TestExecutor for ThrottledInputExecutor {
+    fn test_to_file(&self, input_file: &File, output_file: &File) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        let (reader, writer) = std::io::pipe().expect("Failed to create a pipe");
+        #[cfg(unix)]
+        let reader = File::from(std::os::fd::OwnedFd::from(reader));
+        #[cfg(windows)]
+        let reader = File::from(std::os::windows::io::OwnedHandle::from(reader));
+
+        thread::scope(|scope| {
+            let feeder = scope.spawn(|| self.feed(input_file, writer));
+            let result = self.inner.test_to_file(&reader, output_file);
+            // Closing the last read end of the pipe stops the feeder if the program didn't read the whole input
+            drop(reader);
+            feeder.join().expect("The input feeding thread panicked");
+            result
+        })
+    }
+}
@@ -0,0 +1,99 @@
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{Duration, Instant};
+use wait_timeout::ChildExt;
+use which::which;
+use crate::executor::simple::SimpleExecutor;
+use crate::executor::{ProgramEnvironment, TestExecutor};
+use crate::formatted_error::FormattedError;
+use crate::output;
+use crate::process_tree::{register_running, terminate_tree, unregister_running};
+use crate::temp_files::{create_test_temp_file, make_cloned_stdio};
+use crate::test_errors::{ExecutionError, ExecutionMetrics};
+use crate::test_errors::ExecutionError::{MemoryError, TimedOut};
+
+/// The exit code valgrind returns if it found errors, chosen so that it's unlikely to be returned by the program itself
+const VALGRIND_ERROR_EXIT_CODE: i32 = 97;
+/// The maximum number of lines of the memcheck report included in the error
+const MAX_REPORT_LINES: usize = 20;
+
+/// Runs the program under valgrind's memcheck, which reports invalid memory accesses,
+/// uses of uninitialized values and similar errors that often don't crash the program
+pub(crate) struct ValgrindExecutor {
+    timeout: Duration,
+    executable_path: PathBuf,
+    valgrind_path: PathBuf,
+    kill_grace: Duration,
+    environment: ProgramEnvironment,
+}
+
+impl ValgrindExecutor {
+    pub(crate) fn init_and_test(
+        timeout: Duration,
+        executable_path: PathBuf,
+        kill_grace: Duration,
+        environment: ProgramEnvironment,
+    ) -> Result<ValgrindExecutor, FormattedError> {
+        let Ok(valgrind_path) = which("valgrind") else {
+            return Err(FormattedError::from_str("The \"valgrind\" command required by the --valgrind flag could not be found"));
+        };
+        Ok(ValgrindExecutor { timeout, executable_path, valgrind_path, kill_grace, environment })
+    }
+
+    /// Returns the first lines of the report, without the `==PID==` prefix valgrind adds to every line.
+    /// The lines written to stderr by the program itself are skipped
+    fn report_excerpt(report: &str) -> String {
+        report.lines()
+            .filter_map(|line| line.strip_prefix("==")?.split_once("==").map(|(_, message)| message.trim_end()))
+            .filter(|message| !message.is_empty())
+            .take(MAX_REPORT_LINES)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl TestExecutor for ValgrindExecutor {
+    fn test_to_file(&self, input_file: &File, output_file: &File) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        output::debug(&format!("Running under valgrind: {}", self.executable_path.display()));
+        let mut stderr = match create_test_temp_file() {
+            Ok(file) => file,
+            Err(error) => return (ExecutionMetrics::NONE, Err(error.into())),
+        };
+        let cloned = make_cloned_stdio(input_file)
+            .and_then(|stdin| Ok((stdin, make_cloned_stdio(output_file)?, make_cloned_stdio(&stderr)?)));
+        let (stdin, stdout, stderr_stdio) = match cloned {
+            Ok(cloned) => cloned,
+            Err(error) => return (ExecutionMetrics::NONE, Err(error.into())),
+        };
+        let mut command = Command::new(&self.valgrind_path);
+        self.environment.apply(&mut command);
+        let start_time = Instant::now();
+        let mut child = command
+            .args(["--quiet", &format!("--error-exitcode={VALGRIND_ERROR_EXIT_CODE}"), "--"])
+            .arg(&self.executable_path)
+            .stdin(stdin)
+            .stdout(stdout)
+            .stderr(stderr_stdio)
+            .spawn().expect("Failed to spawn valgrind");
+        register_running(child.id());
+
+        let status = child.wait_timeout(self.timeout).unwrap();
+        unregister_running(child.id());
+        let Some(status) = status else {
+            terminate_tree(&mut child, self.kill_grace).unwrap();
+            return (ExecutionMetrics { time: Some(self.timeout), memory_kibibytes: None }, Err(TimedOut));
+        };
+        let metrics = ExecutionMetrics { time: Some(start_time.elapsed()), memory_kibibytes: None };
+        if status.code() != Some(VALGRIND_ERROR_EXIT_CODE) {
+            return (metrics, SimpleExecutor::map_status_code(status));
+        }
+
+        // The program can write invalid UTF-8 to stderr, which is mixed with the report
+        let mut report = vec![];
+        stderr.rewind().unwrap();
+        stderr.read_to_end(&mut report).unwrap();
+        (metrics, Err(MemoryError(Self::report_excerpt(&String::from_utf8_lossy(&report)))))
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::Mutex;
+use clap::ValueEnum;
+use crate::test_summary::TestSummary;
+use crate::verdict_expectations::VerdictExpectations;
+
+/// How the exit code of toster is chosen
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExitCodePolicy {
+    /// 0 if all tests passed, 1 for wrong answers and other failures, 2 if the only failures are timeouts, 3 for compilation errors and 4 for other errors
+    Detailed,
+    /// 0 if all tests passed, 1 otherwise
+    Simple,
+    /// 0 even if some tests failed, 1 only if testing couldn't be finished
+    Zero,
+}
+
+/// The outcome of running toster, from the least to the most severe
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(crate) enum Outcome {
+    Correct,
+    /// All failed tests timed out
+    TimedOut,
+    /// Wrong answers or other failures of the tested program
+    Failed,
+    /// The tested program couldn't be compiled
+    CompileError,
+    /// Testing couldn't be finished because of an error of toster, the checker or the environment
+    InternalError,
+    /// Toster was stopped with Ctrl+C, so the results are incomplete
+    Interrupted,
+}
+
+impl Outcome {
+    /// Tests which got the verdict expected in the manifest aren't counted as failures,
+    /// but tests which were expected to fail and passed are
+    pub(crate) fn from_summary(test_summary: &TestSummary, expectations: Option<&VerdictExpectations>) -> Outcome {
+        let (expected_failures, mismatches) = expectations.map_or((0, 0), VerdictExpectations::counts);
+        let failed = test_summary.processed - test_summary.success - expected_failures;
+        if mismatches > 0 {
+            Outcome::Failed
+        } else if failed == 0 {
+            Outcome::Correct
+        } else if expected_failures == 0 && failed == test_summary.timed_out {
+            Outcome::TimedOut
+        } else {
+            Outcome::Failed
+        }
+    }
+}
+
+static POLICY: Mutex<ExitCodePolicy> = Mutex::new(ExitCodePolicy::Detailed);
+/// The most severe outcome recorded so far, which decides the exit code when toster finishes
+static OUTCOME: Mutex<Outcome> = Mutex::new(Outcome::Correct);
+
+pub(crate) fn set_policy(policy: ExitCodePolicy) {
+    *POLICY.lock().expect("Failed to lock the exit code policy") = policy;
+}
+
+/// Records the outcome of testing a task. `toster batch` records an outcome for every task,
+/// and the most severe one is used
+pub(crate) fn record_outcome(outcome: Outcome) {
+    let mut recorded = OUTCOME.lock().expect("Failed to lock the outcome");
+    *recorded = (*recorded).max(outcome);
+}
+
+/// Returns the exit code of toster for the outcomes recorded so far
+pub(crate) fn exit_code() -> u8 {
+    let outcome = *OUTCOME.lock().expect("Failed to lock the outcome");
+    match (*POLICY.lock().expect("Failed to lock the exit code policy"), outcome) {
+        // The conventional exit code of a process stopped by SIGINT
+        (_, Outcome::Interrupted) => 130,
+        (_, Outcome::Correct) | (ExitCodePolicy::Zero, Outcome::TimedOut | Outcome::Failed) => 0,
+        (ExitCodePolicy::Detailed, Outcome::Failed)
+        | (ExitCodePolicy::Simple, _)
+        | (ExitCodePolicy::Zero, Outcome::CompileError | Outcome::InternalError) => 1,
+        (ExitCodePolicy::Detailed, Outcome::TimedOut) => 2,
+        (ExitCodePolicy::Detailed, Outcome::CompileError) => 3,
+        (ExitCodePolicy::Detailed, Outcome::InternalError) => 4,
+    }
+}
@@ -0,0 +1,50 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use crate::formatted_error::FormattedError;
+
+/// The feedback a checker wrote for a single test
+struct TestFeedback {
+    test_name: String,
+    verdict: String,
+    feedback: String,
+}
+
+/// Collects the feedback written by the checker for the student,
+/// which is saved as a report separate from the test results
+pub(crate) struct FeedbackReport {
+    entries: Mutex<Vec<TestFeedback>>,
+}
+
+impl FeedbackReport {
+    pub(crate) fn new() -> Self {
+        FeedbackReport { entries: Mutex::new(vec![]) }
+    }
+
+    pub(crate) fn add(&self, test_name: &str, verdict: &str, feedback: String) {
+        self.entries.lock().expect("Failed to lock the feedback report").push(TestFeedback {
+            test_name: test_name.to_owned(),
+            verdict: verdict.to_owned(),
+            feedback,
+        });
+    }
+
+    /// Saves the report with the feedback from every test, sorted by the test name
+    pub(crate) fn save(&self, path: &Path, solution_name: &str) -> Result<(), FormattedError> {
+        let mut entries = self.entries.lock().expect("Failed to lock the feedback report");
+        entries.sort_by(|a, b| human_sort::compare(&a.test_name, &b.test_name));
+
+        let mut report = format!("Feedback for {solution_name}\n");
+        if entries.is_empty() {
+            report.push_str("\nThe checker didn't give any feedback\n");
+        }
+        for entry in entries.iter() {
+            writeln!(report, "\nTest {}: {}\n{}", entry.test_name, entry.verdict, entry.feedback.trim_end())
+                .expect("Writing to a string doesn't fail");
+        }
+        fs::write(path, report).map_err(|error| FormattedError::from_str(
+            &format!("Failed to save the feedback report to {}:\n{error}", path.display())
+        ))
+    }
+}
@@ -1,20 +1,33 @@
 use std::fmt::{Display, Formatter};
 use colored::Colorize;
+use crate::exit_code::Outcome;
 
-pub(crate) struct FormattedError(String);
+pub(crate) struct FormattedError {
+    message: String,
+    /// The outcome used for the exit code if toster stops because of this error
+    outcome: Outcome,
+}
 
 impl FormattedError {
     pub(crate) fn preformatted(formatted_string: String) -> FormattedError {
-        FormattedError(formatted_string)
+        FormattedError { message: formatted_string, outcome: Outcome::InternalError }
     }
 
     pub(crate) fn from_str(string: &str) -> FormattedError {
-        FormattedError(string.red().to_string())
+        FormattedError { message: string.red().to_string(), outcome: Outcome::InternalError }
+    }
+
+    pub(crate) fn with_outcome(self, outcome: Outcome) -> FormattedError {
+        FormattedError { outcome, ..self }
+    }
+
+    pub(crate) fn outcome(&self) -> Outcome {
+        self.outcome
     }
 }
 
 impl Display for FormattedError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.0)
+        f.write_str(&self.message)
     }
 }
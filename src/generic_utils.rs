@@ -1,33 +1,37 @@
+use std::fmt::Write;
 use std::thread;
 use std::time::Duration;
 
-pub(crate) trait OptionExt<T> {
-    fn is_none_or<F: FnOnce(&T) -> bool>(&self, fun: F) -> bool;
+/// Formats bytes as a lowercase hexadecimal string
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut result, byte| {
+        write!(result, "{byte:02x}").expect("Writing to a string should not fail");
+        result
+    })
 }
 
-impl<T> OptionExt<T> for Option<T> {
-    fn is_none_or<F: FnOnce(&T) -> bool>(&self, fun: F) -> bool {
-        match self {
-            None => true,
-            Some(val) => fun(val)
+/// Removes ANSI escape sequences (such as colors) from a string
+pub(crate) fn strip_ansi_codes(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(char) = chars.next() {
+        if char != '\x1b' {
+            result.push(char);
+            continue;
         }
-    }
-}
-
-pub(crate) trait ResultExt<T> {
-    fn is_err_or<F: FnOnce(&T) -> bool>(&self, fun: F) -> bool;
-}
-
-impl<T, E> ResultExt<T> for Result<T, E> {
-    fn is_err_or<F: FnOnce(&T) -> bool>(&self, fun: F) -> bool {
-        match self {
-            Err(_) => true,
-            Ok(val) => fun(val)
+        // Skip the CSI sequence until its final byte
+        if chars.next() == Some('[') {
+            for char in chars.by_ref() {
+                if ('\x40'..='\x7e').contains(&char) {
+                    break;
+                }
+            }
         }
     }
+    result
 }
 
-#[deprecated(note = "This is not ideal, there must be a better way to implement it")]
+// This is not ideal, there must be a better way to implement it
 pub(crate) fn halt() -> ! {
     thread::sleep(Duration::from_secs(u64::MAX));
     unreachable!()
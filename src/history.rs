@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use crate::test_summary::TestSummary;
+use crate::warnings::{add_warning, WarningKind};
+
+/// The directory the records of past runs are saved in, relative to the task directory
+const HISTORY_DIRECTORY: &str = ".toster/history";
+/// The number of saved runs, older runs are removed
+const MAX_SAVED_RUNS: usize = 100;
+/// Tests faster than this in the current run aren't reported as slower, since their runtimes are mostly noise
+const MIN_COMPARED_TIME: Duration = Duration::from_millis(10);
+
+/// The run the results are compared with
+#[derive(Clone, Debug)]
+pub enum HistoryReference {
+    /// The most recent saved run
+    Last,
+    /// A saved run record file
+    Path(PathBuf),
+}
+
+pub(crate) fn parse_history_reference(value: &str) -> Result<HistoryReference, String> {
+    if value == "last" {
+        return Ok(HistoryReference::Last);
+    }
+    let path = PathBuf::from(value);
+    if !path.is_file() {
+        return Err(format!("\"{value}\" is neither \"last\" nor a saved run file"));
+    }
+    Ok(HistoryReference::Path(path))
+}
+
+#[derive(Serialize, Deserialize)]
+struct TestRecord {
+    verdict: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    time_seconds: Option<f64>,
+}
+
+/// The verdicts and runtimes of all tests in a run, saved to compare later runs with
+#[derive(Serialize, Deserialize)]
+pub(crate) struct RunRecord {
+    /// The time testing finished, in seconds since the Unix epoch
+    timestamp: u64,
+    solution: String,
+    tests: BTreeMap<String, TestRecord>,
+}
+
+/// Formats how long ago a run was saved, like "5 minutes ago"
+fn format_age(timestamp: u64) -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(timestamp, |now| now.as_secs());
+    let seconds = now.saturating_sub(timestamp);
+    let (count, unit) = match seconds {
+        0..60 => (seconds, "second"),
+        60..3600 => (seconds / 60, "minute"),
+        3600..86400 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+    format!("{count} {unit}{} ago", if count == 1 { "" } else { "s" })
+}
+
+impl RunRecord {
+    pub(crate) fn new(solution: &Path, test_summary: &mut TestSummary) -> Self {
+        let mut tests: BTreeMap<String, TestRecord> = test_summary.successful_tests().iter()
+            .map(|(test_name, time, _)| (test_name.clone(), TestRecord {
+                verdict: "correct".to_owned(),
+                time_seconds: time.map(|time| time.as_secs_f64()),
+            }))
+            .collect();
+        tests.extend(test_summary.get_errors().iter().map(|(test_name, error)| (test_name.clone(), TestRecord {
+            verdict: error.verdict().to_owned(),
+            time_seconds: None,
+        })));
+        RunRecord {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).expect("The system time is before the Unix epoch").as_secs(),
+            solution: solution.display().to_string(),
+            tests,
+        }
+    }
+
+    /// Returns the saved run files, from the oldest to the newest
+    fn saved_runs() -> Vec<PathBuf> {
+        let mut runs: Vec<PathBuf> = fs::read_dir(HISTORY_DIRECTORY).into_iter().flatten()
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension() == Some(OsStr::new("json")))
+            .collect();
+        runs.sort_by(|a, b| human_sort::compare(&a.to_string_lossy(), &b.to_string_lossy()));
+        runs
+    }
+
+    /// Reads the saved run, or returns `None` if the most recent run was requested, but no runs were saved yet
+    fn load(reference: &HistoryReference) -> Result<Option<RunRecord>, String> {
+        let path = match reference {
+            HistoryReference::Path(path) => path.clone(),
+            HistoryReference::Last => match Self::saved_runs().pop() {
+                Some(path) => path,
+                None => return Ok(None),
+            },
+        };
+        let contents = fs::read_to_string(&path)
+            .map_err(|error| format!("Failed to read the saved run {}:\n{error}", path.display()))?;
+        serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(|error| format!("The saved run {} is invalid:\n{error}", path.display()))
+    }
+
+    /// Saves the run in the history directory and removes the oldest runs
+    pub(crate) fn save(&self) {
+        let result = fs::create_dir_all(HISTORY_DIRECTORY).and_then(|()| {
+            let millis = SystemTime::now().duration_since(UNIX_EPOCH).expect("The system time is before the Unix epoch").as_millis();
+            fs::write(
+                Path::new(HISTORY_DIRECTORY).join(format!("{millis}.json")),
+                serde_json::to_string(self).expect("Failed to serialize the run record"),
+            )
+        });
+        if let Err(error) = result {
+            add_warning(WarningKind::History, format!("Failed to save the results in {HISTORY_DIRECTORY}: {error}"));
+            return;
+        }
+        let runs = Self::saved_runs();
+        for path in &runs[..runs.len().saturating_sub(MAX_SAVED_RUNS)] {
+            // A run that can't be removed is removed the next time
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// The saved run the results are compared with
+pub(crate) struct RunComparison {
+    previous: RunRecord,
+    /// The fraction of the previous runtime a test has to be slower by to be shown
+    slower_threshold: f64,
+}
+
+impl RunComparison {
+    /// Reads the saved run, or returns `None` with a warning if there are no saved runs yet
+    pub(crate) fn load(reference: &HistoryReference, slower_threshold: f64) -> Result<Option<Self>, String> {
+        let Some(previous) = RunRecord::load(reference)? else {
+            add_warning(WarningKind::History, "There are no saved runs to compare the results with");
+            return Ok(None);
+        };
+        Ok(Some(RunComparison { previous, slower_threshold }))
+    }
+
+    /// Prints the tests that failed in the current run but passed in the saved one, the tests that were fixed,
+    /// and the tests whose runtime grew by more than the slower threshold
+    pub(crate) fn print(&self, current: &RunRecord) {
+        let (previous, slower_threshold) = (&self.previous, self.slower_threshold);
+        let mut new_failures = vec![];
+        let mut fixed = vec![];
+        let mut slower = vec![];
+        for (test_name, test) in &current.tests {
+            let Some(previous_test) = previous.tests.get(test_name) else { continue };
+            match (previous_test.verdict.as_str(), test.verdict.as_str()) {
+                ("correct", "correct") => {}
+                ("correct", verdict) => new_failures.push(format!("{test_name} ({verdict})")),
+                (_, "correct") => fixed.push(test_name.as_str()),
+                _ => {}
+            }
+            if let (Some(previous_time), Some(time)) = (previous_test.time_seconds, test.time_seconds) {
+                if time > previous_time * (1.0 + slower_threshold) && time >= MIN_COMPARED_TIME.as_secs_f64() {
+                    slower.push(format!("{test_name} ({previous_time:.3}s -> {time:.3}s, +{:.0}%)", (time / previous_time - 1.0) * 100.0));
+                }
+            }
+        }
+
+        println!("{}", format!("Compared with the run of {} from {}:", previous.solution, format_age(previous.timestamp)).bold());
+        if new_failures.is_empty() && slower.is_empty() {
+            println!("{}", "No new failures or slower tests".green());
+        }
+        if !new_failures.is_empty() {
+            println!("{}", format!("New failures: {}", new_failures.join(", ")).red());
+        }
+        if !fixed.is_empty() {
+            println!("{}", format!("Fixed: {}", fixed.join(", ")).green());
+        }
+        if !slower.is_empty() {
+            println!("{}", format!("Slower by more than {:.0}%: {}", slower_threshold * 100.0, slower.join(", ")).yellow());
+        }
+    }
+}
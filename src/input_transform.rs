@@ -0,0 +1,67 @@
+use std::fs::File;
+use std::io::Seek;
+use std::time::Duration;
+use which::which;
+use crate::executor::simple::SimpleExecutor;
+use crate::executor::{ProgramEnvironment, TestExecutor};
+use crate::formatted_error::FormattedError;
+use crate::prepare_input::TestInputSource;
+use crate::temp_files::create_test_temp_file;
+use crate::test_errors::TestError;
+use crate::test_errors::TestError::InputTransformError;
+
+/// Runs a user-provided command on every test input before it's fed to the tested program,
+/// for example to strip a header line used only by the checker.
+pub(crate) struct InputTransformer {
+    executor: SimpleExecutor,
+}
+
+impl InputTransformer {
+    pub(crate) fn new(command: &str, timeout: Duration) -> Result<Self, FormattedError> {
+        let mut split_command = command.split(' ');
+        let program = split_command.next().expect("The input transformation command is invalid");
+        let Ok(executable_path) = which(program) else {
+            return Err(FormattedError::from_str(
+                &format!("The input transformation program \"{program}\" could not be found")
+            ));
+        };
+
+        Ok(InputTransformer {
+            executor: SimpleExecutor {
+                timeout,
+                executable_path,
+                arguments: split_command.map(str::to_owned).collect(),
+                output_limit: None,
+                kill_grace: Duration::ZERO,
+                sanitizer_report: false,
+                capture_stderr: false,
+                file_io: None,
+                scratch_directory: false,
+                environment: ProgramEnvironment::default(),
+            },
+        })
+    }
+
+    /// Creates a new temporary file containing the transformed input.
+    ///
+    /// The returned file is rewound and can be passed as stdin to the tested program.
+    pub(crate) fn transform(&self, input_source: &TestInputSource) -> Result<File, TestError> {
+        let mut output = create_test_temp_file()?;
+        let (_, result) = self.executor.test_to_file(&input_source.get_file()?, &output);
+        result.map_err(|error| InputTransformError { error })?;
+        output.rewind().expect("Failed to rewind memfile");
+        Ok(output)
+    }
+}
+
+/// Returns the file that should be passed as stdin to the tested program,
+/// applying the input transformation if there is one
+pub(crate) fn get_program_input(
+    transformer: Option<&InputTransformer>,
+    input_source: &TestInputSource,
+) -> Result<File, TestError> {
+    match transformer {
+        Some(transformer) => transformer.transform(input_source),
+        None => Ok(input_source.get_file()?),
+    }
+}
@@ -0,0 +1,53 @@
+use std::fs;
+use std::fs::Permissions;
+use std::io::Read;
+use std::os::unix::fs::PermissionsExt;
+use colored::Colorize;
+use sha2::{Digest, Sha256};
+use crate::executor::sio2jail::Sio2jailExecutor;
+use crate::formatted_error::FormattedError;
+use crate::generic_utils::to_hex;
+
+/// The sio2jail build distributed with toster
+pub(crate) const DEFAULT_SIO2JAIL_URL: &str = "https://github.com/MikolajKolek/toster/raw/main/sio2jail";
+/// The SHA-256 checksum of the file at `DEFAULT_SIO2JAIL_URL`
+pub(crate) const DEFAULT_SIO2JAIL_SHA256: &str = "376db13183ca86666153410e6ce92a57f5ab954f26dc85e7f027bb3fc77d33ed";
+
+fn download(url: &str) -> Result<Vec<u8>, FormattedError> {
+    let response = ureq::get(url).call()
+        .map_err(|error| FormattedError::from_str(&format!("Failed to download sio2jail from {url}:\n{error}")))?;
+    let mut contents = vec![];
+    response.into_reader().read_to_end(&mut contents)
+        .map_err(|error| FormattedError::from_str(&format!("Failed to download sio2jail from {url}:\n{error}")))?;
+    Ok(contents)
+}
+
+/// Downloads sio2jail, verifies its checksum and installs it in the user's executable directory
+pub(crate) fn install_sio2jail(url: &str, sha256: &str) -> Result<(), FormattedError> {
+    let install_path = Sio2jailExecutor::get_sio2jail_install_path()?;
+
+    println!("Downloading sio2jail from {url}");
+    let contents = download(url)?;
+
+    let checksum = to_hex(&Sha256::digest(&contents));
+    if !checksum.eq_ignore_ascii_case(sha256) {
+        return Err(FormattedError::from_str(&format!(
+            "The checksum of the downloaded file doesn't match, sio2jail was not installed\n\
+            Expected: {sha256}\n\
+            Actual:   {checksum}"
+        )));
+    }
+
+    let install_error = |error| FormattedError::from_str(
+        &format!("Failed to install sio2jail at {}:\n{error}", install_path.display())
+    );
+    fs::create_dir_all(install_path.parent().expect("The install path should have a parent")).map_err(install_error)?;
+    fs::write(&install_path, contents).map_err(install_error)?;
+    fs::set_permissions(&install_path, Permissions::from_mode(0o755)).map_err(install_error)?;
+    println!("{}", format!("Sio2jail was installed at {}", install_path.display()).green());
+
+    if Sio2jailExecutor::read_perf_event_paranoid().is_none_or(|value| value > -1) {
+        println!("{}", Sio2jailExecutor::perf_event_paranoid_advice());
+    }
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::{Acquire, Release};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::exit_code;
+use crate::exit_code::Outcome;
+use crate::process_tree;
+
+static RECEIVED_CTRL_C: AtomicBool = AtomicBool::new(false);
+/// The error which stopped testing, like a failure to create a temporary file,
+/// with the names of the tests that were running when it happened
+static FATAL_ERROR: Mutex<Option<String>> = Mutex::new(None);
+/// The names of the tests being run, listed in the message of a fatal error
+static RUNNING_TESTS: Mutex<Vec<String>> = Mutex::new(vec![]);
+/// The temporary directories that are removed if toster exits before they're dropped.
+/// Directories that were already dropped are skipped when removing them
+static TEMP_DIRECTORIES: Mutex<Vec<PathBuf>> = Mutex::new(vec![]);
+
+/// How long an executor waits for the Ctrl+C handler after the program was stopped by SIGINT
+const HANDLER_WAIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub(crate) fn received_ctrl_c() -> bool {
+    RECEIVED_CTRL_C.load(Acquire)
+}
+
+/// Marks that Ctrl+C was pressed, returning `true` if it had already been pressed before.
+/// The incomplete run makes toster exit with a non-zero code
+pub(crate) fn record_ctrl_c() -> bool {
+    exit_code::record_outcome(Outcome::Interrupted);
+    RECEIVED_CTRL_C.swap(true, Release)
+}
+
+/// Checks if testing should stop, because Ctrl+C was pressed or a fatal error happened
+pub(crate) fn testing_stopped() -> bool {
+    received_ctrl_c() || FATAL_ERROR.lock().expect("Failed to lock the fatal error").is_some()
+}
+
+pub(crate) fn start_test(test_name: &str) {
+    RUNNING_TESTS.lock().expect("Failed to lock the running tests").push(test_name.to_owned());
+}
+
+pub(crate) fn finish_test(test_name: &str) {
+    RUNNING_TESTS.lock().expect("Failed to lock the running tests").retain(|running| running != test_name);
+}
+
+/// Stops testing because of an error that would make every other test fail the same way.
+/// Only the first error is kept, the later ones are usually caused by stopping testing
+pub(crate) fn record_fatal_error(error: &str) {
+    let mut fatal_error = FATAL_ERROR.lock().expect("Failed to lock the fatal error");
+    if fatal_error.is_some() {
+        return;
+    }
+    let mut running_tests = RUNNING_TESTS.lock().expect("Failed to lock the running tests").clone();
+    running_tests.sort_by(|a, b| human_sort::compare(a, b));
+    *fatal_error = Some(match running_tests.as_slice() {
+        [] => format!("Testing was stopped:\n{error}"),
+        [test_name] => format!("Testing was stopped while running test {test_name}:\n{error}"),
+        test_names => format!("Testing was stopped while running tests {}:\n{error}", test_names.join(", ")),
+    });
+    drop(fatal_error);
+    process_tree::kill_running();
+}
+
+/// Returns the error which stopped testing, if there was one
+pub(crate) fn take_fatal_error() -> Option<String> {
+    FATAL_ERROR.lock().expect("Failed to lock the fatal error").take()
+}
+
+/// Pressing Ctrl+C sends SIGINT to both toster and the tested program, so the program can exit
+/// before the handler runs. Waiting for the handler lets the test be reported as cancelled instead of crashed
+pub(crate) fn wait_for_handler() {
+    let start_time = Instant::now();
+    while !received_ctrl_c() && start_time.elapsed() < HANDLER_WAIT_TIMEOUT {
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+pub(crate) fn register_temp_directory(path: &Path) {
+    TEMP_DIRECTORIES.lock().expect("Failed to lock the temporary directories").push(path.to_owned());
+}
+
+/// Removes the temporary directories, which aren't dropped when the process exits
+pub(crate) fn remove_temp_directories() {
+    for directory in TEMP_DIRECTORIES.lock().expect("Failed to lock the temporary directories").drain(..) {
+        let _ = fs::remove_dir_all(directory);
+    }
+}
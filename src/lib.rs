@@ -0,0 +1,80 @@
+//! A simple-as-toast tester for C++ solutions to competitive programming exercises.
+//!
+//! Besides the `toster` command line tool, the crate can be used as a library by judge frontends and editor plugins.
+//! [`TestRunner`] compiles a solution and runs it on tests, returning a [`TestResult`] for every test.
+
+#![warn(clippy::pedantic)]
+#![warn(clippy::if_then_some_else_none)]
+#![warn(clippy::infinite_loop)]
+#![warn(clippy::multiple_unsafe_ops_per_block)]
+#![warn(clippy::undocumented_unsafe_blocks)]
+#![warn(clippy::self_named_module_files)]
+#![warn(clippy::str_to_string)]
+
+mod args;
+mod test_errors;
+mod testing_utils;
+mod output_names;
+mod prepare_input;
+mod executor;
+mod generic_utils;
+mod test_summary;
+mod temp_files;
+mod checker;
+mod builtin_checker;
+#[cfg(feature = "wasm-checker")]
+mod wasm_checker;
+mod compiler;
+mod formatted_error;
+mod manifest;
+mod input_transform;
+mod validator;
+mod test_runner;
+mod compile_cache;
+mod test_pattern;
+mod test_chains;
+mod test_exclusion;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+mod install_sio2jail;
+mod doctor;
+mod publish;
+mod noise_guard;
+mod diff_renderer;
+mod cpu_affinity;
+mod saved_outputs;
+mod stats;
+mod dry_run;
+mod history;
+mod perf_profile;
+mod comparison;
+mod encoding;
+mod benchmark;
+mod feedback;
+mod report;
+mod notification;
+mod scoring;
+mod verdict_expectations;
+mod sinol;
+mod polygon;
+mod toolchain;
+mod test_archive;
+mod warnings;
+mod batch;
+mod output;
+mod single_run;
+mod tui;
+mod theme;
+mod exit_code;
+mod retries;
+mod process_tree;
+mod interrupt;
+mod program_stderr;
+mod precompiled_header;
+#[cfg(unix)]
+mod signals;
+pub mod cli;
+mod api;
+
+pub use api::{Error, Program, TestResult, TestRunner, Verdict};
+pub use checker::CheckerProtocol;
+pub use diff_renderer::DiffStyle;
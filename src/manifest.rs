@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::{Deserialize, Deserializer};
+use crate::args::parse_duration;
+use crate::test_pattern::TestPattern;
+use crate::theme::ThemeStyle;
+
+/// The name of the manifest file toster looks for in the current directory
+/// if no manifest path is provided explicitly
+pub(crate) const DEFAULT_MANIFEST_NAME: &str = "toster.toml";
+
+/// Per-task configuration read from a TOML manifest file.
+///
+/// Every option set on the command line takes precedence over the manifest.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct Manifest {
+    /// A command that receives the test input on stdin
+    /// and whose output is fed to the tested program instead
+    pub(crate) input_transform: Option<String>,
+    /// Time and memory limits for tests matching a pattern,
+    /// overriding the limits set on the command line
+    pub(crate) limits: Vec<LimitOverride>,
+    /// Tests whose input is preceded by the output the tested program produced for another test
+    pub(crate) dependencies: Vec<TestDependency>,
+    /// Additional arguments passed to sio2jail, see the --sio2jail-arg option
+    pub(crate) sio2jail_args: Vec<String>,
+    /// Checkers used for tests matching a pattern instead of the one set on the command line
+    pub(crate) checkers: Vec<CheckerOverride>,
+    /// The solution tested in this task by `toster batch`
+    pub(crate) solution: Option<PathBuf>,
+    /// Groups of tests scored together
+    pub(crate) groups: Vec<ScoredGroup>,
+    /// The verdicts tests matching a pattern are expected to get
+    pub(crate) expected: Vec<ExpectedVerdict>,
+    /// The colors and attributes of verdicts, by the verdict name
+    pub(crate) theme: BTreeMap<String, ThemeStyle>,
+    /// Tags given to tests matching a pattern, which can be skipped with --skip-tag
+    pub(crate) tags: Vec<TestTags>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct TestTags {
+    pub(crate) tests: TestPattern,
+    pub(crate) tags: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct ScoredGroup {
+    pub(crate) tests: TestPattern,
+    /// The points for the group, given only if all its tests pass
+    pub(crate) points: u32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct ExpectedVerdict {
+    pub(crate) tests: TestPattern,
+    /// The verdict name shown in the results, or one of the abbreviations AC, WA, TLE, MLE, OLE and RE
+    #[serde(deserialize_with = "deserialize_verdict")]
+    pub(crate) verdict: String,
+}
+
+/// Deserializes a verdict name, replacing the common abbreviations with the names used by toster
+fn deserialize_verdict<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let verdict = String::deserialize(deserializer)?;
+    Ok(match verdict.to_uppercase().as_str() {
+        "AC" | "OK" => "correct",
+        "WA" => "wrong answer",
+        "TLE" => "timed out",
+        "MLE" => "out of memory",
+        "OLE" => "output limit exceeded",
+        "RE" => "runtime error",
+        _ => return Ok(verdict),
+    }.to_owned())
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct CheckerOverride {
+    pub(crate) tests: TestPattern,
+    /// The checker source code or executable. If it's not provided,
+    /// the output is compared with the output file instead
+    pub(crate) checker: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct TestDependency {
+    pub(crate) test: String,
+    /// The test whose program output is prepended to the input of `test`
+    pub(crate) after: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct LimitOverride {
+    pub(crate) tests: TestPattern,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub(crate) time_limit: Option<Duration>,
+    pub(crate) memory_limit: Option<u64>,
+}
+
+/// Deserializes a duration given either as a number of seconds
+/// or as a string accepted by `parse_duration()`
+fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawDuration {
+        Seconds(f64),
+        Text(String),
+    }
+
+    let duration = match RawDuration::deserialize(deserializer)? {
+        RawDuration::Seconds(seconds) => parse_duration(&seconds.to_string()),
+        RawDuration::Text(text) => parse_duration(&text),
+    };
+    duration.map(Some).map_err(serde::de::Error::custom)
+}
+
+impl Manifest {
+    fn read(path: &Path) -> Result<Manifest, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|error| format!("Failed to read the manifest file {}:\n{error}", path.display()))?;
+        toml::from_str(&contents)
+            .map_err(|error| format!("The manifest file {} is invalid:\n{error}", path.display()))
+    }
+
+    /// Reads the manifest from `path` or, if it's not provided,
+    /// from the default manifest file if it exists.
+    pub(crate) fn load(path: Option<&PathBuf>) -> Result<Manifest, String> {
+        if let Some(path) = path {
+            if !path.is_file() {
+                return Err("The provided manifest file does not exist".to_owned());
+            }
+            return Self::read(path);
+        }
+
+        let default_path = Path::new(DEFAULT_MANIFEST_NAME);
+        if default_path.is_file() { Self::read(default_path) } else { Ok(Manifest::default()) }
+    }
+}
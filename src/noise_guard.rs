@@ -0,0 +1,183 @@
+use std::fs;
+use std::num::NonZero;
+use std::thread;
+use std::thread::available_parallelism;
+use std::time::{Duration, Instant};
+use clap::ValueEnum;
+use colored::Colorize;
+use crate::output;
+use crate::warnings::{add_warning, WarningKind};
+
+/// How long the system is observed to measure the CPU use and swapping
+const SAMPLE_DURATION: Duration = Duration::from_millis(300);
+/// The fraction of CPUs that can be busy before the system is considered noisy
+const BUSY_CPU_THRESHOLD: f64 = 0.25;
+/// The time between checks in the wait mode
+const WAIT_INTERVAL: Duration = Duration::from_secs(2);
+/// How long the wait mode waits for the system to become idle before testing starts anyway
+const MAX_WAIT: Duration = Duration::from_mins(5);
+
+/// What toster does if the system is busy before testing starts
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoiseGuardMode {
+    /// Don't check the system
+    Off,
+    /// Print a warning
+    Warn,
+    /// Wait until other processes stop using the CPU and swapping memory, for at most 5 minutes
+    Wait,
+    /// Run fewer tests in parallel, leaving the busy CPUs to other processes
+    Reduce,
+}
+
+/// Activity of other processes that can affect the measured runtimes
+struct SystemNoise {
+    cpu_count: usize,
+    busy_cpus: f64,
+    swapped_pages: u64,
+    other_instances: usize,
+}
+
+/// Reads the total and idle CPU time from /proc/stat
+fn read_cpu_times() -> Option<(u64, u64)> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    let times: Vec<u64> = stat.lines().next()?
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|value| value.parse().ok())
+        .collect();
+    // The idle and iowait columns
+    let idle = times.get(3)? + times.get(4).unwrap_or(&0);
+    Some((times.iter().sum(), idle))
+}
+
+/// Reads the total number of pages swapped in and out from /proc/vmstat
+fn read_swapped_pages() -> Option<u64> {
+    let vmstat = fs::read_to_string("/proc/vmstat").ok()?;
+    Some(vmstat.lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter(|(name, _)| *name == "pswpin" || *name == "pswpout")
+        .filter_map(|(_, value)| value.parse::<u64>().ok())
+        .sum())
+}
+
+/// Counts running toster processes other than this one
+fn count_other_instances() -> usize {
+    let Ok(processes) = fs::read_dir("/proc") else { return 0 };
+    let own_pid = std::process::id().to_string();
+    processes
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_str().is_some_and(|pid| pid != own_pid && pid.chars().all(|char| char.is_ascii_digit())))
+        .filter(|entry| fs::read_to_string(entry.path().join("comm")).is_ok_and(|name| name.trim() == "toster"))
+        .count()
+}
+
+impl SystemNoise {
+    #[allow(clippy::cast_precision_loss)]
+    fn measure() -> SystemNoise {
+        let cpu_count = available_parallelism().map_or(1, NonZero::get);
+        let cpu_times_before = read_cpu_times();
+        let swapped_before = read_swapped_pages();
+        thread::sleep(SAMPLE_DURATION);
+        let cpu_times_after = read_cpu_times();
+        let swapped_after = read_swapped_pages();
+
+        let busy_cpus = match (cpu_times_before, cpu_times_after) {
+            (Some((total_before, idle_before)), Some((total_after, idle_after))) if total_after > total_before => {
+                let total = (total_after - total_before) as f64;
+                let idle = idle_after.saturating_sub(idle_before) as f64;
+                (1.0 - idle / total) * cpu_count as f64
+            }
+            _ => 0.0,
+        };
+        let swapped_pages = match (swapped_before, swapped_after) {
+            (Some(before), Some(after)) => after.saturating_sub(before),
+            _ => 0,
+        };
+
+        SystemNoise {
+            cpu_count,
+            busy_cpus,
+            swapped_pages,
+            other_instances: count_other_instances(),
+        }
+    }
+
+    fn warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        #[allow(clippy::cast_precision_loss)]
+        if self.busy_cpus > self.cpu_count as f64 * BUSY_CPU_THRESHOLD {
+            warnings.push(format!("{:.1} of {} CPUs are used by other processes", self.busy_cpus, self.cpu_count));
+        }
+        if self.swapped_pages > 0 {
+            warnings.push("the system is actively swapping memory".to_owned());
+        }
+        if self.other_instances > 0 {
+            warnings.push(format!("{} other toster instance(s) are running", self.other_instances));
+        }
+        warnings
+    }
+
+    /// Checks if other processes use the CPU or memory is swapped.
+    /// Other toster instances aren't included, because instances waiting for each other would never start testing
+    #[allow(clippy::cast_precision_loss)]
+    fn is_busy(&self) -> bool {
+        self.busy_cpus > self.cpu_count as f64 * BUSY_CPU_THRESHOLD || self.swapped_pages > 0
+    }
+
+    /// The number of CPUs not used by other processes
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn idle_cpus(&self) -> usize {
+        self.cpu_count.saturating_sub(self.busy_cpus.round() as usize).max(1)
+    }
+}
+
+fn print_warnings(warnings: &[String]) {
+    output::info(&format!(
+        "Warning: the system is busy, which can make the measured runtimes less accurate:\n- {}",
+        warnings.join("\n- "),
+    ).yellow().to_string());
+}
+
+/// Checks if other processes can affect the measured runtimes and reacts according to the mode.
+///
+/// Returns the maximum number of tests that should be run in parallel, if it should be limited
+pub(crate) fn guard_against_noise(mode: NoiseGuardMode) -> Option<usize> {
+    if mode == NoiseGuardMode::Off || !cfg!(target_os = "linux") {
+        return None;
+    }
+
+    let mut noise = SystemNoise::measure();
+    let warnings = noise.warnings();
+    if warnings.is_empty() {
+        return None;
+    }
+    print_warnings(&warnings);
+    add_warning(WarningKind::BusySystem, format!("The system was busy when testing started: {}", warnings.join(", ")));
+
+    match mode {
+        NoiseGuardMode::Off | NoiseGuardMode::Warn => None,
+        NoiseGuardMode::Wait => {
+            if noise.is_busy() {
+                output::info("Waiting for the system to become idle (press Ctrl-C to stop)");
+            }
+            let start = Instant::now();
+            while noise.is_busy() {
+                if start.elapsed() >= MAX_WAIT {
+                    let message = format!("The system was still busy after waiting for {} minutes, testing was started anyway", MAX_WAIT.as_secs() / 60);
+                    output::info(&format!("Warning: {message}").yellow().to_string());
+                    add_warning(WarningKind::BusySystem, message);
+                    return None;
+                }
+                thread::sleep(WAIT_INTERVAL);
+                noise = SystemNoise::measure();
+            }
+            None
+        }
+        NoiseGuardMode::Reduce => {
+            let threads = noise.idle_cpus();
+            output::info(&format!("Running up to {threads} test(s) in parallel"));
+            Some(threads)
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use crate::generic_utils::strip_ansi_codes;
+use crate::test_summary::TestSummary;
+
+/// Returns the command showing a desktop notification, or `None` if there is no such command on this system
+#[cfg(target_os = "macos")]
+fn notification_command(title: &str, body: &str) -> Option<Command> {
+    let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut command = Command::new("osascript");
+    command.arg("-e").arg(format!("display notification {} with title {}", quote(body), quote(title)));
+    Some(command)
+}
+
+/// Returns the command showing a desktop notification, or `None` if there is no such command on this system
+#[cfg(all(unix, not(target_os = "macos")))]
+fn notification_command(title: &str, body: &str) -> Option<Command> {
+    let notify_send = which::which("notify-send").ok()?;
+    let mut command = Command::new(notify_send);
+    command.args(["--app-name", "toster", title, body]);
+    Some(command)
+}
+
+/// Returns the command showing a desktop notification, or `None` if there is no such command on this system
+#[cfg(not(unix))]
+fn notification_command(_title: &str, _body: &str) -> Option<Command> {
+    None
+}
+
+/// Lets the user know that testing finished with a desktop notification containing the results,
+/// or with the terminal bell if notifications can't be shown
+pub(crate) fn notify_finished(solution_name: &str, test_summary: &TestSummary) {
+    let title = format!("{} {solution_name} finished", if test_summary.generate_mode { "Generating outputs of" } else { "Testing" });
+    let body = format!(
+        "{} in {:.1}s",
+        strip_ansi_codes(&test_summary.format_counts(true)),
+        test_summary.start_time.elapsed().as_secs_f64(),
+    );
+    let shown = notification_command(&title, &body).is_some_and(|mut command| {
+        command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    });
+    if !shown {
+        print!("\x07");
+        // The bell is only a convenience, so a failed flush is ignored
+        let _ = io::stdout().flush();
+    }
+}
@@ -0,0 +1,233 @@
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize};
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Mutex;
+use clap::ValueEnum;
+use colored::{Color, Colorize};
+use indicatif::{ProgressBar, ProgressDrawTarget};
+use crate::test_errors::{ExecutionMetrics, TestError};
+use crate::test_summary::TestSummary;
+use crate::theme;
+use crate::warnings::warnings;
+
+/// The progress bar shown while tests are running.
+/// The mutex also serializes all lines printed with `print_line()`
+static PROGRESS_BAR: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// Whether a line is printed for every finished test
+static LIVE_VERDICTS: AtomicBool = AtomicBool::new(false);
+
+/// The format of the results printed while testing
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutputFormat {
+    /// The results for people, with a progress bar and the errors of failed tests listed after testing
+    Human,
+    /// The Test Anything Protocol: an "ok" or "not ok" line for every finished test, with the details of failures in YAML blocks
+    Tap,
+}
+
+/// Whether the results are printed in the TAP format
+static TAP_OUTPUT: AtomicBool = AtomicBool::new(false);
+/// The number of the last test printed in the TAP format
+static TAP_TEST_NUMBER: AtomicUsize = AtomicUsize::new(0);
+
+/// How much toster prints, from the least to the most detailed
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(crate) enum Verbosity {
+    /// Only the summary of the results
+    Quiet,
+    Normal,
+    /// Also the commands used for compiling and the temporary paths
+    Verbose,
+    /// Also every command executed while testing
+    Debug,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+impl Verbosity {
+    /// Returns the verbosity chosen with the -q and -v flags, where every -v raises the verbosity by one level
+    pub(crate) fn from_flags(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Debug,
+        }
+    }
+}
+
+pub(crate) fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Relaxed);
+}
+
+fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        2 => Verbosity::Verbose,
+        _ => Verbosity::Debug,
+    }
+}
+
+pub(crate) fn is_quiet() -> bool {
+    verbosity() == Verbosity::Quiet
+}
+
+/// Prints a line which isn't a part of the summary of the results, unless quiet mode is enabled
+pub(crate) fn info(line: &str) {
+    if verbosity() >= Verbosity::Normal {
+        print_line(line);
+    }
+}
+
+/// Prints a line with additional details, like the commands used for compiling, if -v is used
+pub(crate) fn verbose(line: &str) {
+    if verbosity() >= Verbosity::Verbose {
+        print_line(&line.dimmed().to_string());
+    }
+}
+
+/// Prints a line with the details of every test, like the executed commands, if -vv is used
+pub(crate) fn debug(line: &str) {
+    if verbosity() >= Verbosity::Debug {
+        print_line(&line.dimmed().to_string());
+    }
+}
+
+/// Sets the progress bar that lines printed with `print_line()` shouldn't interleave with
+pub(crate) fn set_progress_bar(progress_bar: Option<ProgressBar>) {
+    if let (Some(progress_bar), true) = (&progress_bar, is_quiet()) {
+        progress_bar.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    *PROGRESS_BAR.lock().expect("Failed to lock the progress bar") = progress_bar;
+}
+
+/// Prints a line while tests are running.
+///
+/// The progress bar is hidden while the line is printed and lines printed
+/// from different threads are never mixed with each other
+pub(crate) fn print_line(line: &str) {
+    let progress_bar = PROGRESS_BAR.lock().expect("Failed to lock the progress bar");
+    match progress_bar.as_ref() {
+        Some(progress_bar) => progress_bar.suspend(|| println!("{line}")),
+        None => println!("{line}"),
+    }
+}
+
+pub(crate) fn set_live_verdicts(enabled: bool) {
+    LIVE_VERDICTS.store(enabled, Relaxed);
+}
+
+/// Sets the format of the results. Colors are disabled in the TAP format, since it's read by other programs
+pub(crate) fn set_format(format: OutputFormat) {
+    TAP_OUTPUT.store(format == OutputFormat::Tap, Relaxed);
+    if format == OutputFormat::Tap {
+        colored::control::set_override(false);
+    }
+}
+
+pub(crate) fn is_tap() -> bool {
+    TAP_OUTPUT.load(Relaxed)
+}
+
+/// Prints the TAP version and the plan with the number of tests
+pub(crate) fn print_tap_plan(test_count: usize) {
+    println!("TAP version 13\n1..{test_count}");
+}
+
+/// Appends a line indented as a part of a YAML block in the TAP output
+fn push_yaml_line(block: &mut String, line: &str) {
+    block.push_str("  ");
+    block.push_str(line);
+    block.push('\n');
+}
+
+/// Prints the "ok" or "not ok" line of a finished test in the TAP format,
+/// followed by a YAML block with the verdict and the error message of a failed test
+fn print_tap_verdict(test_name: &str, result: &Result<ExecutionMetrics, TestError>) {
+    // A '#' in the description would start a directive
+    let description = test_name.replace('#', "\\#");
+    let progress_bar = PROGRESS_BAR.lock().expect("Failed to lock the progress bar");
+    // The numbers are taken while the lock is held, so they are printed in order
+    let number = TAP_TEST_NUMBER.fetch_add(1, Relaxed) + 1;
+    let line = match result {
+        Ok(_) => format!("ok {number} - {description}"),
+        Err(TestError::Cancelled) => return,
+        Err(error @ (TestError::DependencyFailed { .. } | TestError::NotStarted)) => {
+            format!("ok {number} - {description} # SKIP {}", error.message())
+        }
+        Err(error) => {
+            let mut line = format!("not ok {number} - {description}\n");
+            push_yaml_line(&mut line, "---");
+            push_yaml_line(&mut line, &format!("verdict: {}", error.verdict()));
+            push_yaml_line(&mut line, "message: |-");
+            for message_line in error.message().lines() {
+                push_yaml_line(&mut line, &format!("  {message_line}"));
+            }
+            push_yaml_line(&mut line, "...");
+            line.pop();
+            line
+        }
+    };
+    match progress_bar.as_ref() {
+        Some(progress_bar) => progress_bar.suspend(|| println!("{line}")),
+        None => println!("{line}"),
+    }
+}
+
+/// Prints the counts of verdicts and the warnings as TAP comments after testing
+pub(crate) fn print_tap_summary(test_summary: &TestSummary) {
+    println!("# Results: {}", test_summary.format_counts(true));
+    if test_summary.failure_limit_reached() && test_summary.processed < test_summary.total {
+        println!("# Testing was stopped after {} failed tests", test_summary.processed - test_summary.success);
+    }
+    for warning in warnings() {
+        println!("# Warning: {}", warning.message.replace('\n', "\n# "));
+    }
+}
+
+/// Prints an error that stopped toster, as a "Bail out!" line in the TAP format
+pub(crate) fn print_error(error: &str) {
+    if !is_tap() {
+        println!("{error}");
+        return;
+    }
+    let mut lines = error.lines();
+    println!("Bail out! {}", lines.next().unwrap_or_default());
+    for line in lines {
+        println!("# {line}");
+    }
+}
+
+/// Prints a compact line with the verdict and the runtime of a finished test, if live verdicts are enabled.
+/// In the TAP format, a line is printed for every test
+pub(crate) fn print_verdict(test_name: &str, result: &Result<ExecutionMetrics, TestError>) {
+    if is_tap() {
+        print_tap_verdict(test_name, result);
+        return;
+    }
+    if !LIVE_VERDICTS.load(Relaxed) {
+        return;
+    }
+    let line = match result {
+        Ok(metrics) => {
+            let time = metrics.time.map_or_else(String::new, |time| format!(" {:.3}s", time.as_secs_f64()));
+            format!("{test_name}: {}{time}", theme::paint("correct", "OK", Some(Color::Green)))
+        }
+        Err(TestError::Cancelled) => return,
+        Err(error @ (TestError::DependencyFailed { .. } | TestError::NotStarted)) => {
+            format!("{test_name}: {}", theme::paint(error.verdict(), error.verdict(), Some(Color::Yellow)))
+        }
+        Err(error) => format!("{test_name}: {}", theme::paint(error.verdict(), error.verdict(), Some(Color::Red))),
+    };
+    print_line(&line);
+}
+
+/// Stops drawing the progress bar, leaving its last state on the screen,
+/// so testing threads that are still running don't draw it over the following output
+pub(crate) fn stop_progress_bar() {
+    if let Some(progress_bar) = PROGRESS_BAR.lock().expect("Failed to lock the progress bar").take() {
+        progress_bar.abandon();
+        progress_bar.set_draw_target(ProgressDrawTarget::hidden());
+    }
+}
@@ -0,0 +1,55 @@
+use std::path::{Path, PathBuf};
+use crate::prepare_input::GZIP_EXTENSION;
+
+/// The placeholder replaced with the test name in output file patterns
+const NAME_PLACEHOLDER: &str = "{name}";
+
+/// The possible names of the output file of a test, given as patterns like `{name}.ans`.
+/// The patterns are tried in order and the first existing file is used,
+/// since task packages name their answer files inconsistently
+#[derive(Clone, Debug)]
+pub(crate) struct OutputNames(Vec<String>);
+
+impl OutputNames {
+    /// Parses patterns containing the `{name}` placeholder exactly once
+    pub(crate) fn from_patterns(patterns: Vec<String>) -> Result<Self, String> {
+        if let Some(pattern) = patterns.iter().find(|pattern| pattern.matches(NAME_PLACEHOLDER).count() != 1) {
+            return Err(format!("The output pattern \"{pattern}\" must contain \"{NAME_PLACEHOLDER}\" exactly once"));
+        }
+        Ok(OutputNames(patterns))
+    }
+
+    pub(crate) fn from_extensions(extensions: &[String]) -> Self {
+        OutputNames(extensions.iter().map(|extension| format!("{NAME_PLACEHOLDER}{extension}")).collect())
+    }
+
+    /// Returns the candidate file names of the output file of a test, in the order they are tried
+    pub(crate) fn file_names<'a>(&'a self, test_name: &'a str) -> impl Iterator<Item = String> + 'a {
+        self.0.iter().map(move |pattern| pattern.replace(NAME_PLACEHOLDER, test_name))
+    }
+
+    /// Returns the name generated outputs are saved with, which uses the first pattern
+    pub(crate) fn generated_file_name(&self, test_name: &str) -> String {
+        self.file_names(test_name).next().expect("At least one output pattern is required")
+    }
+
+    /// Returns the name of the test the output file belongs to, if the file name matches any pattern
+    pub(crate) fn test_name<'a>(&self, file_name: &'a str) -> Option<&'a str> {
+        self.0.iter().find_map(|pattern| {
+            let (prefix, suffix) = pattern.split_once(NAME_PLACEHOLDER)?;
+            file_name.strip_prefix(prefix)?.strip_suffix(suffix).filter(|test_name| !test_name.is_empty())
+        })
+    }
+
+    /// Returns the path of the first existing output file of a test, which can be gzip-compressed (like `1.out.gz`).
+    /// If none of the files exist, the path for the first pattern is returned
+    pub(crate) fn find_file(&self, output_directory: &Path, test_name: &str) -> PathBuf {
+        self.file_names(test_name)
+            .flat_map(|file_name| [
+                output_directory.join(&file_name),
+                output_directory.join(format!("{file_name}{GZIP_EXTENSION}")),
+            ])
+            .find(|path| path.is_file())
+            .unwrap_or_else(|| output_directory.join(self.generated_file_name(test_name)))
+    }
+}
@@ -0,0 +1,161 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use colored::Colorize;
+use wait_timeout::ChildExt;
+use which::which;
+use crate::executor::simple::SimpleExecutor;
+use crate::formatted_error::FormattedError;
+use crate::input_transform::{get_program_input, InputTransformer};
+use crate::prepare_input::Test;
+use crate::process_tree::{register_running, terminate_tree, unregister_running};
+use crate::temp_files::clone_stdio;
+use crate::test_errors::TestError;
+use crate::test_pattern::TestPattern;
+use crate::test_summary::TestSummary;
+
+/// The number of tests profiled when the slowest or failing tests are selected
+const MAX_SELECTED_TESTS: usize = 5;
+/// How many times longer than the time limit a profiled program can run, since perf slows it down
+const PERF_TIMEOUT_FACTOR: u32 = 2;
+/// How long perf can take to save its data after a timed out program is stopped
+const PERF_SAVE_GRACE: Duration = Duration::from_secs(5);
+
+/// The tests profiled with perf after testing
+#[derive(Clone, Debug)]
+pub enum PerfSelection {
+    /// The slowest tests the program passed
+    Slowest,
+    /// The tests the program failed
+    Failing,
+    /// The tests matching the pattern
+    Pattern(TestPattern),
+}
+
+pub(crate) fn parse_perf_selection(value: &str) -> Result<PerfSelection, String> {
+    match value {
+        "" => Err("The selected tests can't be empty".to_owned()),
+        "slowest" => Ok(PerfSelection::Slowest),
+        "failing" => Ok(PerfSelection::Failing),
+        pattern => Ok(PerfSelection::Pattern(TestPattern::new(pattern.to_owned()))),
+    }
+}
+
+/// Reruns the selected tests under `perf stat` and `perf record` and saves the data of every test,
+/// so a slow test can be inspected with `perf report` right after testing
+pub(crate) struct PerfProfiler {
+    perf_path: PathBuf,
+    program: PathBuf,
+    arguments: Vec<String>,
+    directory: PathBuf,
+}
+
+impl PerfProfiler {
+    pub(crate) fn new(executable: &Path, run_command: Option<&str>, directory: PathBuf) -> Result<Self, FormattedError> {
+        let Ok(perf_path) = which("perf") else {
+            return Err(FormattedError::from_str("The \"perf\" command required by the --profile-perf option could not be found"));
+        };
+        let (program, arguments) = SimpleExecutor::split_run_command(run_command, executable);
+        Ok(PerfProfiler { perf_path, program, arguments, directory })
+    }
+
+    /// Returns the names of the selected tests, the slowest tests first if they are selected by runtime
+    pub(crate) fn select_tests(selection: &PerfSelection, test_summary: &mut TestSummary, tests: &[&Test]) -> Vec<String> {
+        match selection {
+            PerfSelection::Slowest => {
+                let mut successful: Vec<(String, Duration)> = test_summary.successful_tests().iter()
+                    .filter_map(|(test_name, time, _)| Some((test_name.clone(), (*time)?)))
+                    .collect();
+                successful.sort_by_key(|(_, time)| Reverse(*time));
+                successful.into_iter().take(MAX_SELECTED_TESTS).map(|(test_name, _)| test_name).collect()
+            }
+            PerfSelection::Failing => test_summary.get_errors().iter()
+                // Skipped tests were never run, so there is nothing to profile
+                .filter(|(_, error)| !matches!(error, TestError::DependencyFailed { .. } | TestError::NotStarted))
+                .take(MAX_SELECTED_TESTS)
+                .map(|(test_name, _)| test_name.clone())
+                .collect(),
+            PerfSelection::Pattern(pattern) => {
+                let mut selected: Vec<String> = tests.iter()
+                    .map(|test| test.test_name.clone())
+                    .filter(|test_name| pattern.matches(test_name))
+                    .collect();
+                selected.sort_by(|a, b| human_sort::compare(a, b));
+                selected
+            }
+        }
+    }
+
+    /// Runs the program on the input of the test under perf with the given arguments.
+    /// Returns an error message if perf couldn't be run
+    fn run_perf(&self, perf_arguments: &[&str], input: &fs::File, timeout: Duration) -> Result<(), String> {
+        let mut child = Command::new(&self.perf_path)
+            .args(perf_arguments)
+            .arg("--")
+            .arg(&self.program)
+            .args(&self.arguments)
+            .stdin(clone_stdio(input).map_err(|error| format!("failed to duplicate a file descriptor: {error}"))?)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|error| format!("failed to start perf: {error}"))?;
+        register_running(child.id());
+        let status = child.wait_timeout(timeout).expect("Failed to wait for perf");
+        unregister_running(child.id());
+        match status {
+            // perf saves the data collected so far when it's stopped with SIGTERM
+            None => terminate_tree(&mut child, PERF_SAVE_GRACE).map_err(|error| format!("failed to stop perf: {error}")),
+            // The exit code of perf is the exit code of the program, so a crashing program is still profiled
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Profiles the test and returns the paths of the saved files
+    fn profile_test(
+        &self,
+        test: &Test,
+        input_transformer: Option<&InputTransformer>,
+        timeout: Duration,
+    ) -> Result<(PathBuf, PathBuf), String> {
+        let stat_path = self.directory.join(format!("{}.stat.txt", test.test_name));
+        let record_path = self.directory.join(format!("{}.perf.data", test.test_name));
+        // Tests in subdirectories of the input directory are saved in the same subdirectories
+        fs::create_dir_all(stat_path.parent().expect("The profile path should have a parent"))
+            .map_err(|error| format!("failed to create the profile directory: {error}"))?;
+        let input_error = |error: TestError| format!("failed to prepare the input: {}", error.message());
+
+        let input = get_program_input(input_transformer, &test.input_source).map_err(input_error)?;
+        self.run_perf(&["stat", "-o", &stat_path.to_string_lossy()], &input, timeout)?;
+        let input = get_program_input(input_transformer, &test.input_source).map_err(input_error)?;
+        self.run_perf(&["record", "-g", "-q", "-o", &record_path.to_string_lossy()], &input, timeout)?;
+        if !record_path.is_file() {
+            return Err("perf didn't save any data, the kernel.perf_event_paranoid setting may not allow profiling".to_owned());
+        }
+        Ok((stat_path, record_path))
+    }
+
+    /// Profiles the selected tests one at a time, so they don't slow each other down, and prints where the data was saved.
+    /// `time_limit` returns the time limit of a test
+    pub(crate) fn profile(
+        &self,
+        selected: &[String],
+        tests: &[&Test],
+        input_transformer: Option<&InputTransformer>,
+        time_limit: impl Fn(&str) -> Duration,
+    ) {
+        if selected.is_empty() {
+            println!("{}", "No tests were selected for profiling with perf".yellow());
+            return;
+        }
+        println!("{}", format!("Profiling tests with perf ({}):", selected.len()).bold());
+        for test_name in selected {
+            let Some(test) = tests.iter().find(|test| &test.test_name == test_name) else { continue };
+            match self.profile_test(test, input_transformer, time_limit(test_name) * PERF_TIMEOUT_FACTOR) {
+                Ok((stat_path, record_path)) => println!("- {test_name}: {}, {}", stat_path.display(), record_path.display()),
+                Err(error) => println!("- {test_name}: {}", error.red()),
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use roxmltree::{Document, Node};
+
+/// The description of Codeforces Polygon packages
+const POLYGON_PROBLEM_NAME: &str = "problem.xml";
+
+/// A Polygon problem package, with the tests and answers in the `tests` directory (like `tests/01` and `tests/01.a`),
+/// the limits in `problem.xml` and a testlib checker
+pub(crate) struct PolygonPackage {
+    pub(crate) directory: PathBuf,
+    /// The short name of the problem, like `a-plus-b`
+    pub(crate) short_name: String,
+    pub(crate) time_limit: Option<Duration>,
+    /// The memory limit in KiB
+    pub(crate) memory_limit: Option<u64>,
+    pub(crate) input_directory: PathBuf,
+    /// The part of the input file names after the test number, usually empty
+    pub(crate) input_ext: String,
+    pub(crate) answer_directory: PathBuf,
+    /// The part of the answer file names after the test number, usually `.a`
+    pub(crate) answer_ext: String,
+    /// The source code of the checker, `None` if the package doesn't contain it
+    pub(crate) checker: Option<PathBuf>,
+}
+
+/// Splits a path pattern like `tests/%02d.a` into the directory
+/// and the part of the file name after the test number
+fn split_path_pattern(pattern: &str) -> Option<(&str, &str)> {
+    let (directory, file_name) = pattern.rsplit_once('/').unwrap_or((".", pattern));
+    let number_width = file_name.strip_prefix('%')?.find('d')?;
+    if !file_name[1..=number_width].chars().all(|char| char.is_ascii_digit()) {
+        return None;
+    }
+    Some((directory, &file_name[number_width + 2..]))
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, tag_name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(tag_name))
+}
+
+fn child_number(node: Node, tag_name: &str) -> Result<Option<u64>, String> {
+    child(node, tag_name)
+        .map(|child| {
+            let text = child.text().unwrap_or("").trim();
+            text.parse::<u64>().map_err(|_| format!("The {tag_name} \"{text}\" in {POLYGON_PROBLEM_NAME} is not a valid number"))
+        })
+        .transpose()
+}
+
+impl PolygonPackage {
+    /// Finds the package in the current directory, or the package containing the solution
+    /// in its `solutions` directory, where Polygon packages keep their solutions
+    pub(crate) fn detect(source_path: &Path) -> Result<Option<PolygonPackage>, String> {
+        let solution_package = source_path.canonicalize().ok()
+            .and_then(|path| path.parent().map(Path::to_path_buf))
+            .filter(|directory| directory.file_name().is_some_and(|name| name == "solutions"))
+            .and_then(|directory| directory.parent().map(Path::to_path_buf));
+        let candidates = [PathBuf::from(".")].into_iter().chain(solution_package);
+        for directory in candidates {
+            if directory.join(POLYGON_PROBLEM_NAME).is_file() {
+                return Self::load(directory).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn load(directory: PathBuf) -> Result<PolygonPackage, String> {
+        let path = directory.join(POLYGON_PROBLEM_NAME);
+        let contents = fs::read_to_string(&path)
+            .map_err(|error| format!("Failed to read the Polygon problem description {}:\n{error}", path.display()))?;
+        let document = Document::parse(&contents)
+            .map_err(|error| format!("The Polygon problem description {} is invalid:\n{error}", path.display()))?;
+        let problem = document.root_element();
+
+        // Packages can have multiple testsets, but only the "tests" testset is used for judging
+        let testset = child(problem, "judging")
+            .and_then(|judging| judging.children().find(|testset| {
+                testset.has_tag_name("testset") && testset.attribute("name") == Some("tests")
+            }))
+            .ok_or_else(|| format!("The Polygon problem description {} doesn't contain the \"tests\" testset", path.display()))?;
+        let pattern = |tag_name: &str| -> Result<(String, String), String> {
+            let pattern = child(testset, tag_name).and_then(|pattern| pattern.text()).unwrap_or("tests/%02d").trim();
+            split_path_pattern(pattern)
+                .map(|(directory, ext)| (directory.to_owned(), ext.to_owned()))
+                .ok_or_else(|| format!("The {tag_name} \"{pattern}\" in {POLYGON_PROBLEM_NAME} is not supported"))
+        };
+        let (input_directory, input_ext) = pattern("input-path-pattern")?;
+        let (answer_directory, answer_ext) = pattern("answer-path-pattern")?;
+
+        let checker = child(problem, "assets")
+            .and_then(|assets| child(assets, "checker"))
+            .and_then(|checker| child(checker, "source"))
+            .and_then(|source| source.attribute("path"))
+            .map(|source| directory.join(source))
+            .filter(|source| source.is_file());
+        let short_name = match problem.attribute("short-name") {
+            Some(short_name) => short_name.to_owned(),
+            None => directory.canonicalize().ok()
+                .and_then(|directory| directory.file_name().map(|name| name.to_string_lossy().into_owned()))
+                .ok_or("The short name of the Polygon problem couldn't be determined")?,
+        };
+
+        Ok(PolygonPackage {
+            time_limit: child_number(testset, "time-limit")?.map(Duration::from_millis),
+            // Polygon memory limits are given in bytes
+            memory_limit: child_number(testset, "memory-limit")?.map(|bytes| bytes / 1024),
+            input_directory: directory.join(input_directory),
+            input_ext,
+            answer_directory: directory.join(answer_directory),
+            answer_ext,
+            checker,
+            short_name,
+            directory,
+        })
+    }
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use std::io::{read_to_string, Seek};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+use directories::ProjectDirs;
+use sha2::{Digest, Sha256};
+use wait_timeout::ChildExt;
+use crate::generic_utils::to_hex;
+use crate::output;
+use crate::process_tree::kill_tree;
+use crate::temp_files::{clone_stdio, create_temp_file};
+
+/// Returns the compiler executable of the compile command
+fn compiler_name(compile_command: &str) -> &str {
+    compile_command.split(' ').next().unwrap_or_default()
+}
+
+/// Returns true if the compile command uses GCC, which looks for a precompiled header
+/// in every include directory before the header itself, so it can be used without changing the source code
+pub(crate) fn is_supported(compile_command: &str) -> bool {
+    let compiler = compiler_name(compile_command);
+    compiler.ends_with("g++") && !compiler.contains("clang")
+}
+
+/// The directory with the precompiled header for the compile command.
+/// The compiler version is part of the name, since GCC ignores headers precompiled by other versions
+fn header_directory(compile_command: &str) -> Result<PathBuf, String> {
+    let version = Command::new(compiler_name(compile_command))
+        .arg("--version")
+        .output()
+        .map_err(|error| format!("Failed to check the compiler version:\n{error}"))?;
+    let mut hasher = Sha256::new();
+    hasher.update(&version.stdout);
+    hasher.update([0]);
+    hasher.update(compile_command.as_bytes());
+
+    let project_dirs = ProjectDirs::from("", "", "toster")
+        .ok_or("No valid home directory path could be retrieved from the operating system")?;
+    Ok(project_dirs.cache_dir().join("pch").join(to_hex(&hasher.finalize())))
+}
+
+fn build(compile_command: &str, directory: &Path, header_path: &Path, compile_timeout: Duration) -> Result<(), String> {
+    let error_message = |error: std::io::Error| format!("Failed to create {}:\n{error}", directory.display());
+    fs::create_dir_all(directory.join("bits")).map_err(error_message)?;
+    // The header is compiled from a file including it, so the path of the original header doesn't have to be known
+    let wrapper_path = directory.join("wrapper.h");
+    fs::write(&wrapper_path, "#include <bits/stdc++.h>\n").map_err(error_message)?;
+    // The header is renamed only after it's fully built, so an interrupted build is never used
+    let partial_path = header_path.with_extension("gch.partial");
+
+    let path_str = |path: &Path| path.to_str().expect("The cache path is invalid").to_owned();
+    let cmd = compile_command
+        .replace("<IN>", &format!("-x c++-header {}", path_str(&wrapper_path)))
+        .replace("<OUT>", &path_str(&partial_path));
+    output::verbose(&format!("Precompiling bits/stdc++.h with: {cmd}"));
+    let mut split_cmd = cmd.split(' ');
+
+    let mut stderr = create_temp_file().map_err(|error| format!("Failed to create a temporary file:\n{error}"))?;
+    let mut child = Command::new(split_cmd.next().expect("The compile command is invalid"))
+        .args(split_cmd)
+        .stderr(clone_stdio(&stderr).map_err(|error| format!("Failed to duplicate a file descriptor:\n{error}"))?)
+        .spawn()
+        .map_err(|error| error.to_string())?;
+    let Some(status) = child.wait_timeout(compile_timeout).unwrap() else {
+        kill_tree(&mut child).unwrap();
+        return Err("Precompiling the header timed out".to_owned());
+    };
+    if !status.success() {
+        stderr.rewind().unwrap();
+        return Err(read_to_string(stderr).expect("Failed to read compiler output"));
+    }
+    fs::rename(&partial_path, header_path).map_err(error_message)
+}
+
+/// Builds the precompiled `bits/stdc++.h` for the compile command, unless it's already cached,
+/// and returns the compile command that uses it, together with the build time if it was built
+pub(crate) fn with_precompiled_header(compile_command: &str, compile_timeout: Duration) -> Result<(String, Option<Duration>), String> {
+    let directory = header_directory(compile_command)?;
+    let header_path = directory.join("bits").join("stdc++.h.gch");
+    let build_time = if header_path.is_file() {
+        None
+    } else {
+        let start_time = Instant::now();
+        build(compile_command, &directory, &header_path, compile_timeout)?;
+        Some(start_time.elapsed())
+    };
+    let include = format!("<IN> -I{}", directory.to_str().expect("The cache path is invalid"));
+    Ok((compile_command.replace("<IN>", &include), build_time))
+}
@@ -1,19 +1,91 @@
-use std::ffi::OsStr;
 use std::fs::{File, read_dir};
+use std::io;
+use std::io::Seek;
 use std::path::{Path, PathBuf};
 use rayon::iter::{IndexedParallelIterator, IntoParallelIterator};
 use rayon::vec::IntoIter;
+use crate::encoding::{open_text_file, transcode_text_file};
 use crate::formatted_error::FormattedError;
-use crate::generic_utils::ResultExt;
+use crate::temp_files::{create_test_temp_file, TempFileError};
+use crate::test_archive::TestArchive;
+use crate::test_errors::{ExecutionError, TestError};
 
 pub(crate) enum TestInputSource {
-    File(PathBuf)
+    File(PathBuf),
+    /// A file in a ZIP archive, decompressed when the test is run
+    ArchiveEntry {
+        archive: PathBuf,
+        entry: String,
+    },
+    /// The output of the tested program for a previous test followed by the contents of the input
+    Chained {
+        previous_output: File,
+        input: Box<TestInputSource>,
+    },
+}
+
+/// The input of a test couldn't be read
+#[derive(Debug)]
+pub(crate) enum InputError {
+    TempFile(TempFileError),
+    /// A file in a test archive couldn't be extracted, for example because the archive is corrupt
+    InvalidArchiveEntry(String),
+}
+
+impl From<TempFileError> for InputError {
+    fn from(error: TempFileError) -> Self {
+        InputError::TempFile(error)
+    }
+}
+
+impl From<InputError> for TestError {
+    fn from(error: InputError) -> Self {
+        match error {
+            InputError::TempFile(error) => error.into(),
+            InputError::InvalidArchiveEntry(message) => TestError::InvalidInput { message },
+        }
+    }
+}
+
+impl From<InputError> for ExecutionError {
+    fn from(error: InputError) -> Self {
+        match error {
+            InputError::TempFile(error) => error.into(),
+            InputError::InvalidArchiveEntry(message) => ExecutionError::SandboxError(message),
+        }
+    }
 }
 
 impl TestInputSource {
-    pub(crate) fn get_file(&self) -> File {
+    /// The size of the input in bytes, used to run the largest tests first.
+    /// Returns 0 if the size isn't known without reading the input, like for files in archives
+    pub(crate) fn size(&self) -> u64 {
         match self {
-            TestInputSource::File(path) => { File::open(path).expect("Failed to open input file") }
+            TestInputSource::File(path) => path.metadata().map_or(0, |metadata| metadata.len()),
+            TestInputSource::ArchiveEntry { .. } => 0,
+            TestInputSource::Chained { previous_output, input } => {
+                previous_output.metadata().map_or(0, |metadata| metadata.len()) + input.size()
+            }
+        }
+    }
+
+    pub(crate) fn get_file(&self) -> Result<File, InputError> {
+        match self {
+            TestInputSource::File(path) => Ok(open_text_file(path)?),
+            TestInputSource::ArchiveEntry { archive, entry } => {
+                let file = TestArchive::extract(archive, entry)?;
+                Ok(transcode_text_file(&TestArchive::display_path(archive, entry), file)?)
+            }
+            TestInputSource::Chained { previous_output, input: source } => {
+                let mut input = create_test_temp_file()?;
+                let mut previous_output = previous_output;
+                previous_output.rewind().expect("Failed to rewind previous test output");
+                io::copy(&mut previous_output, &mut input).expect("Failed to copy previous test output");
+                io::copy(&mut source.get_file()?, &mut input)
+                    .expect("Failed to copy input file");
+                input.rewind().expect("Failed to rewind memfile");
+                Ok(input)
+            }
         }
     }
 }
@@ -24,48 +96,63 @@ pub(crate) struct Test {
 }
 
 pub(crate) struct TestingInputs<T: IndexedParallelIterator<Item=Test>> {
-    pub(crate) test_count: usize,
     pub(crate) iterator: T,
 }
 
+/// The extension of gzip-compressed test files, which are decompressed when they're read
+pub(crate) const GZIP_EXTENSION: &str = ".gz";
+
+/// Returns the name of the test of an input file, or `None` if the file doesn't have the input extension.
+/// Gzip-compressed files (like `1.in.gz`) are input files too.
+/// An empty extension matches files without an extension, like the tests of Polygon packages
+pub(crate) fn input_test_name<'a>(file_name: &'a str, in_ext: &str) -> Option<&'a str> {
+    let file_name = file_name.strip_suffix(GZIP_EXTENSION).unwrap_or(file_name);
+    if in_ext.is_empty() {
+        return Path::new(file_name).extension().is_none().then_some(file_name);
+    }
+    file_name.strip_suffix(in_ext).filter(|test_name| !test_name.is_empty())
+}
+
+/// Adds the tests of the input files in `directory` and its subdirectories to `tests`.
+/// The names of tests in subdirectories contain their path relative to the input directory, like `group1/a`
+fn find_file_inputs(directory: &Path, name_prefix: &str, in_ext: &str, tests: &mut Vec<Test>) -> Result<(), FormattedError> {
+    let entries = read_dir(directory)
+        .map_err(|error| FormattedError::from_str(&format!("Cannot open input directory {}:\n{error}", directory.display())))?;
+    for entry in entries {
+        let entry = entry.map_err(|error| FormattedError::from_str(
+            &format!("Failed to read contents of input directory:\n{error}")
+        ))?;
+        let path = entry.path();
+        // The file type of the entry itself, symlinks aren't followed, so a symlink loop can't make the search endless
+        let is_dir = entry.file_type()
+            .map_err(|error| FormattedError::from_str(&format!("Failed to read the type of {}:\n{error}", path.display())))?
+            .is_dir();
+        let file_name = entry.file_name();
+        // Only the names of directories and input files are needed, so other files with invalid names are ignored
+        if !is_dir && input_test_name(&file_name.to_string_lossy(), in_ext).is_none() {
+            continue;
+        }
+        let file_name = file_name.to_str()
+            .ok_or(FormattedError::from_str(&format!("The name of the input file {} is not valid UTF-8", path.display())))?;
+        if is_dir {
+            find_file_inputs(&path, &format!("{name_prefix}{file_name}/"), in_ext, tests)?;
+        } else if let Some(test_name) = input_test_name(file_name, in_ext) {
+            tests.push(Test {
+                test_name: format!("{name_prefix}{test_name}"),
+                input_source: TestInputSource::File(path),
+            });
+        }
+    }
+    Ok(())
+}
+
 pub(crate) fn prepare_file_inputs(input_dir: &Path, in_ext: &str) -> Result<TestingInputs<IntoIter<Test>>, FormattedError> {
-    let tests = read_dir(input_dir)
-        .map_err(|error| FormattedError::from_str(&format!("Cannot open input directory:\n{error}")))?
-        .map(|input| -> Result<PathBuf, FormattedError> {
-            let input = input
-                .map_err(|error| FormattedError::from_str(
-                    &format!("Failed to read contents of input directory:\n{error}")
-                ))?;
-            Ok(input.path())
-        })
-        // This `filter` and `map` could be replaced by `filter_ok` and `map_ok`
-        // in the `itertools` crate
-        .filter(|path| {
-            path.is_err_or(|path| {
-                path.extension().is_some_and(|ext| {
-                    ".".to_owned() + ext.to_str().unwrap_or("") == in_ext
-                })
-            })
-        })
-        .map(|file_path| {
-            file_path.and_then(|file_path| {
-                let test_name = file_path.file_stem()
-                    .and_then(OsStr::to_str)
-                    .ok_or(FormattedError::from_str(&format!("The input file {} is invalid", file_path.display())))?
-                    .to_owned();
-                Ok(Test {
-                    test_name,
-                    input_source: TestInputSource::File(file_path),
-                })
-            })
-        })
-        .collect::<Result<Vec<Test>, FormattedError>>()?;
+    let mut tests = vec![];
+    find_file_inputs(input_dir, "", in_ext, &mut tests)?;
 
     if tests.is_empty() {
         return Err(FormattedError::from_str("There are no files in the input directory with the provided file extension"));
     }
 
-    let test_count = tests.len();
-
-    Ok(TestingInputs { test_count, iterator: tests.into_par_iter() })
+    Ok(TestingInputs { iterator: tests.into_par_iter() })
 }
@@ -0,0 +1,184 @@
+use std::io;
+use std::process::Child;
+use std::sync::Mutex;
+use std::time::Duration;
+#[cfg(unix)]
+use wait_timeout::ChildExt;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::os::unix::process::ExitStatusExt;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::process::ExitStatus;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::sync::mpsc;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::thread;
+#[cfg(windows)]
+use std::process::{Command, Stdio};
+
+/// The IDs of the tested programs that are currently running, killed when testing is stopped with Ctrl+C
+static RUNNING_PROCESSES: Mutex<Vec<u32>> = Mutex::new(vec![]);
+
+pub(crate) fn register_running(pid: u32) {
+    RUNNING_PROCESSES.lock().expect("Failed to lock the running processes").push(pid);
+}
+
+pub(crate) fn unregister_running(pid: u32) {
+    RUNNING_PROCESSES.lock().expect("Failed to lock the running processes").retain(|process| *process != pid);
+}
+
+/// Kills all running tested programs and the processes they started, so waiting for them doesn't
+/// block stopping toster. Programs that ignore SIGINT would otherwise run until they time out
+#[cfg(unix)]
+pub(crate) fn kill_running() {
+    for process in RUNNING_PROCESSES.lock().expect("Failed to lock the running processes").iter() {
+        for descendant in descendants(*process) {
+            send_signal(descendant, libc::SIGKILL);
+        }
+        send_signal(*process, libc::SIGKILL);
+    }
+}
+
+/// Kills all running tested programs and the processes they started
+#[cfg(windows)]
+pub(crate) fn kill_running() {
+    for process in RUNNING_PROCESSES.lock().expect("Failed to lock the running processes").iter() {
+        let _ = Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &process.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+/// Returns the IDs of the processes whose parent is `pid`
+#[cfg(target_os = "linux")]
+fn child_processes(pid: u32) -> Vec<u32> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return vec![];
+    };
+    entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|process| {
+            // The parent ID is the second field after the command name, which is in parentheses and can contain spaces
+            std::fs::read_to_string(format!("/proc/{process}/stat")).ok()
+                .and_then(|stat| stat.rsplit_once(')')?.1.split_whitespace().nth(1)?.parse::<u32>().ok())
+                == Some(pid)
+        })
+        .collect()
+}
+
+/// Returns the IDs of the processes whose parent is `pid`
+#[cfg(all(unix, not(target_os = "linux")))]
+fn child_processes(pid: u32) -> Vec<u32> {
+    std::process::Command::new("pgrep")
+        .args(["-P", &pid.to_string()])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).lines().filter_map(|line| line.trim().parse().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Returns the IDs of all processes started by `pid`, its children first
+#[cfg(unix)]
+fn descendants(pid: u32) -> Vec<u32> {
+    let mut descendants = vec![];
+    let mut stack = child_processes(pid);
+    while let Some(process) = stack.pop() {
+        stack.extend(child_processes(process));
+        descendants.push(process);
+    }
+    descendants
+}
+
+/// Kills the child and all processes it started, so a program that starts subprocesses
+/// doesn't leave them running after it times out.
+///
+/// The subprocesses are found before the child is killed, since afterwards they no longer have it as their parent
+#[cfg(unix)]
+pub(crate) fn kill_tree(child: &mut Child) -> io::Result<()> {
+    let descendants = descendants(child.id());
+    child.kill()?;
+    for process in descendants {
+        send_signal(process, libc::SIGKILL);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn send_signal(process: u32, signal: libc::c_int) {
+    let Ok(process) = libc::pid_t::try_from(process) else { return };
+    // SAFETY: `kill` only sends a signal, a process which already exited is ignored
+    unsafe { libc::kill(process, signal) };
+}
+
+/// Waits for the child to exit, returning its exit status and resource usage,
+/// or kills it and the processes it started and returns `None` if it doesn't exit before the timeout.
+/// A program which exits while it's being killed is treated as if it exited before the timeout.
+///
+/// The standard library doesn't return the resource usage of the child, so it's waited for with wait4
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub(crate) fn wait_with_usage(child: &mut Child, timeout: Duration) -> Option<(ExitStatus, libc::rusage)> {
+    let pid = libc::pid_t::try_from(child.id()).expect("The process ID is out of range");
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut status = 0;
+        // SAFETY: rusage is a plain C structure, which is valid when zeroed
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        // SAFETY: the pointers are valid for the duration of the call
+        unsafe { libc::wait4(pid, &raw mut status, 0, &raw mut usage) };
+        let _ = sender.send((ExitStatus::from_raw(status), usage));
+    });
+    if let Ok(result) = receiver.recv_timeout(timeout) {
+        return Some(result);
+    }
+    // The kill fails if the program exited right after the timeout and was already reaped,
+    // then it finished in time and its status is returned
+    let killed = kill_tree(child).is_ok();
+    // Waits until the killed program is reaped
+    let result = receiver.recv().ok()?;
+    (!killed).then_some(result)
+}
+
+/// Asks the child and all processes it started to exit with SIGTERM, which lets tools like sanitizers
+/// and profilers write their reports, and kills them if the child is still running after `grace_period`.
+///
+/// With a zero grace period the processes are killed immediately
+#[cfg(unix)]
+pub(crate) fn terminate_tree(child: &mut Child, grace_period: Duration) -> io::Result<()> {
+    if grace_period.is_zero() {
+        return kill_tree(child);
+    }
+    let descendants = descendants(child.id());
+    send_signal(child.id(), libc::SIGTERM);
+    for process in &descendants {
+        send_signal(*process, libc::SIGTERM);
+    }
+    if child.wait_timeout(grace_period)?.is_none() {
+        child.kill()?;
+    }
+    for process in descendants {
+        send_signal(process, libc::SIGKILL);
+    }
+    Ok(())
+}
+
+/// Kills the child and all processes it started, so a program that starts subprocesses
+/// doesn't leave them running after it times out.
+///
+/// `taskkill /T` finds the subprocesses by their parent, falling back to killing only the child if it fails
+#[cfg(windows)]
+pub(crate) fn kill_tree(child: &mut Child) -> io::Result<()> {
+    let killed = Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &child.id().to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if killed { Ok(()) } else { child.kill() }
+}
+
+/// Kills the child and all processes it started.
+/// Windows has no equivalent of SIGTERM, so the grace period isn't used
+#[cfg(windows)]
+pub(crate) fn terminate_tree(child: &mut Child, _grace_period: Duration) -> io::Result<()> {
+    kill_tree(child)
+}
@@ -0,0 +1,63 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering::Relaxed;
+use clap::ValueEnum;
+
+/// When the stderr of the tested program is shown
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StderrMode {
+    /// The stderr of every test is shown, for passed tests as soon as they finish
+    Show,
+    /// The stderr is discarded
+    Hide,
+    /// The stderr is shown together with the errors of failed tests
+    OnFailure,
+}
+
+static MODE: AtomicU8 = AtomicU8::new(StderrMode::OnFailure as u8);
+
+thread_local! {
+    /// The end of the stderr of the last program run by this thread, which is taken after the test finishes.
+    /// Every test is run by a single thread, including its reruns
+    static LAST_STDERR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// The maximum number of lines from the end of the stderr that are kept
+const MAX_STDERR_LINES: usize = 20;
+
+pub(crate) fn set_mode(mode: StderrMode) {
+    MODE.store(mode as u8, Relaxed);
+}
+
+pub(crate) fn mode() -> StderrMode {
+    match MODE.load(Relaxed) {
+        0 => StderrMode::Show,
+        1 => StderrMode::Hide,
+        _ => StderrMode::OnFailure,
+    }
+}
+
+/// Reads the last lines written to `stderr`, or returns `None` if nothing was written
+pub(crate) fn read_tail(mut stderr: &File) -> Option<String> {
+    let mut contents = vec![];
+    stderr.rewind().ok()?;
+    stderr.read_to_end(&mut contents).ok()?;
+    let contents = String::from_utf8_lossy(&contents);
+    let lines: Vec<&str> = contents.lines().collect();
+    let tail = lines[lines.len().saturating_sub(MAX_STDERR_LINES)..].join("\n");
+    (!tail.trim().is_empty()).then_some(tail)
+}
+
+/// Records the last lines written to `stderr`, replacing the stderr of the previous run of this thread.
+/// If `stderr` is `None`, the recorded stderr is cleared
+pub(crate) fn record(stderr: Option<&File>) {
+    let tail = stderr.and_then(read_tail);
+    LAST_STDERR.with(|last| *last.borrow_mut() = tail);
+}
+
+/// Takes the stderr recorded by the last program run by this thread
+pub(crate) fn take() -> Option<String> {
+    LAST_STDERR.with(|last| last.borrow_mut().take())
+}
@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+use colored::Colorize;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use crate::args::{PublishConfig, WebhookConfig};
+use crate::generic_utils::{strip_ansi_codes, to_hex};
+use crate::output;
+use crate::test_summary::TestSummary;
+use crate::warnings::{add_late_warning, warnings, Warning, WarningKind};
+
+#[derive(Serialize)]
+struct PublishedCounts {
+    total: usize,
+    correct: usize,
+    wrong_answer: usize,
+    timed_out: usize,
+    invalid_output: usize,
+    memory_limit_exceeded: usize,
+    output_limit_exceeded: usize,
+    runtime_error: usize,
+    memory_error: usize,
+    no_output_file: usize,
+    nondeterministic: usize,
+    sandbox_error: usize,
+    checker_error: usize,
+    input_transform_error: usize,
+    invalid_input: usize,
+    skipped: usize,
+    /// The number of tests with each custom verdict
+    custom: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize)]
+struct PublishedError {
+    test: String,
+    verdict: String,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct PublishedTest {
+    test: String,
+    value: f64,
+}
+
+/// The summary of a test run sent to the results server or the webhook
+#[derive(Serialize)]
+struct PublishedSummary {
+    toster_version: &'static str,
+    /// Always sent to the results server, but only sent to the webhook if it's known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    task: Option<String>,
+    duration_seconds: f64,
+    counts: PublishedCounts,
+    slowest_test: Option<PublishedTest>,
+    most_memory_used_kibibytes: Option<PublishedTest>,
+    errors: Vec<PublishedError>,
+    warnings: Vec<Warning>,
+}
+
+impl PublishedSummary {
+    fn new(user: Option<&str>, task: Option<&str>, test_summary: &mut TestSummary) -> Self {
+        PublishedSummary {
+            toster_version: env!("CARGO_PKG_VERSION"),
+            user: user.map(str::to_owned),
+            task: task.map(str::to_owned),
+            duration_seconds: test_summary.start_time.elapsed().as_secs_f64(),
+            counts: PublishedCounts {
+                total: test_summary.total,
+                correct: test_summary.success,
+                wrong_answer: test_summary.incorrect,
+                timed_out: test_summary.timed_out,
+                invalid_output: test_summary.invalid_output,
+                memory_limit_exceeded: test_summary.memory_limit_exceeded,
+                output_limit_exceeded: test_summary.output_limit_exceeded,
+                runtime_error: test_summary.runtime_error,
+                memory_error: test_summary.memory_error,
+                no_output_file: test_summary.no_output_file,
+                nondeterministic: test_summary.nondeterministic,
+                sandbox_error: test_summary.sandbox_error,
+                checker_error: test_summary.checker_error,
+                input_transform_error: test_summary.input_transform_error,
+                invalid_input: test_summary.invalid_input,
+                skipped: test_summary.dependency_failed + test_summary.not_started,
+                custom: test_summary.custom.iter().map(|(label, (count, _))| (label.clone(), *count)).collect(),
+            },
+            slowest_test: test_summary.slowest_test.as_ref().map(|(duration, test)| PublishedTest {
+                test: test.clone(),
+                value: duration.as_secs_f64(),
+            }),
+            #[allow(clippy::cast_precision_loss)]
+            most_memory_used_kibibytes: test_summary.most_memory_used.as_ref().map(|(memory, test)| PublishedTest {
+                test: test.clone(),
+                value: *memory as f64,
+            }),
+            errors: test_summary.get_errors().iter()
+                .map(|(test, error)| PublishedError {
+                    test: test.clone(),
+                    verdict: error.verdict().to_owned(),
+                    message: strip_ansi_codes(&error.message()),
+                })
+                .collect(),
+            warnings: warnings(),
+        }
+    }
+}
+
+/// Sends the test results to the results server.
+///
+/// The request is authenticated with the token as a bearer token,
+/// and the body is signed with HMAC-SHA256 keyed with the token,
+/// so the server can verify that the results weren't modified on the way.
+fn send_to_server(config: &PublishConfig, test_summary: &mut TestSummary) -> Result<(), String> {
+    let body = serde_json::to_string(&PublishedSummary::new(Some(&config.user), Some(&config.task), test_summary))
+        .expect("Failed to serialize the test results");
+    let mut mac = Hmac::<Sha256>::new_from_slice(config.token.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body.as_bytes());
+    let signature = to_hex(&mac.finalize().into_bytes());
+
+    ureq::post(&config.url)
+        .set("Content-Type", "application/json")
+        .set("Authorization", &format!("Bearer {}", config.token))
+        .set("X-Toster-Signature", &format!("sha256={signature}"))
+        .send_string(&body)
+        .map_err(|error| format!("Failed to publish the results to {}:\n{error}", config.url))?;
+    Ok(())
+}
+
+/// Sends the test results to the webhook, without authentication
+fn send_to_webhook(config: &WebhookConfig, test_summary: &mut TestSummary) -> Result<(), String> {
+    let body = serde_json::to_string(&PublishedSummary::new(config.user.as_deref(), config.task.as_deref(), test_summary))
+        .expect("Failed to serialize the test results");
+    ureq::post(&config.url)
+        .set("Content-Type", "application/json")
+        .send_string(&body)
+        .map_err(|error| format!("Failed to send the results to the webhook {}:\n{error}", config.url))?;
+    Ok(())
+}
+
+/// Sends the summary of the test results to the results server and the webhook.
+/// They are independent, so a failure of one of them is only reported as a warning
+pub(crate) fn publish_results(publish: Option<&PublishConfig>, webhook: Option<&WebhookConfig>, test_summary: &mut TestSummary) {
+    if let Some(config) = publish {
+        match send_to_server(config, test_summary) {
+            Ok(()) => output::info(&format!("The results were published to {}", config.url).green().to_string()),
+            Err(error) => add_late_warning(WarningKind::Publish, error),
+        }
+    }
+    if let Some(config) = webhook {
+        match send_to_webhook(config, test_summary) {
+            Ok(()) => output::info(&format!("The results were sent to the webhook {}", config.url).green().to_string()),
+            Err(error) => add_late_warning(WarningKind::Publish, error),
+        }
+    }
+}
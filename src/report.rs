@@ -0,0 +1,80 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use clap::ValueEnum;
+use crate::formatted_error::FormattedError;
+use crate::generic_utils::strip_ansi_codes;
+use crate::test_summary::TestSummary;
+
+/// The format of the report of the results printed after testing
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReportFormat {
+    /// A GitHub-flavored Markdown table with the verdict, time and memory of every test, followed by a summary line
+    Markdown,
+}
+
+/// A row of the report, the time and memory are only measured for successful tests
+struct ReportRow {
+    test_name: String,
+    verdict: String,
+    time: Option<Duration>,
+    memory_kibibytes: Option<u64>,
+}
+
+/// Escapes the pipes, which would end a Markdown table cell
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+fn markdown_report(solution_name: &str, test_summary: &mut TestSummary) -> String {
+    let success_verdict = if test_summary.generate_mode { "successful" } else { "correct" };
+    let mut rows: Vec<ReportRow> = test_summary.get_errors().iter()
+        .map(|(test_name, error)| ReportRow {
+            test_name: test_name.clone(),
+            verdict: error.verdict().to_owned(),
+            time: None,
+            memory_kibibytes: None,
+        })
+        .collect();
+    rows.extend(test_summary.successful_tests().iter().map(|(test_name, time, memory_kibibytes)| ReportRow {
+        test_name: test_name.clone(),
+        verdict: success_verdict.to_owned(),
+        time: *time,
+        memory_kibibytes: *memory_kibibytes,
+    }));
+    rows.sort_by(|a, b| human_sort::compare(&a.test_name, &b.test_name));
+
+    let mut report = format!("### Results of `{}`\n\n| Test | Verdict | Time | Memory |\n| --- | --- | ---: | ---: |\n", escape_markdown(solution_name));
+    for row in rows {
+        writeln!(
+            report,
+            "| {} | {} | {} | {} |",
+            escape_markdown(&row.test_name),
+            escape_markdown(&row.verdict),
+            row.time.map_or_else(|| "-".to_owned(), |time| format!("{:.3}s", time.as_secs_f64())),
+            row.memory_kibibytes.map_or_else(|| "-".to_owned(), |memory| format!("{memory} KiB")),
+        ).expect("Writing to a string doesn't fail");
+    }
+    writeln!(
+        report,
+        "\n**Results:** {} in {:.2}s",
+        strip_ansi_codes(&test_summary.format_counts(true)),
+        test_summary.start_time.elapsed().as_secs_f64(),
+    ).expect("Writing to a string doesn't fail");
+    report
+}
+
+/// Prints the report of the results, or saves it to the file if one is given
+pub(crate) fn write_report(format: ReportFormat, path: Option<&Path>, solution_name: &str, test_summary: &mut TestSummary) -> Result<(), FormattedError> {
+    let report = match format {
+        ReportFormat::Markdown => markdown_report(solution_name, test_summary),
+    };
+    let Some(path) = path else {
+        print!("\n{report}");
+        return Ok(());
+    };
+    fs::write(path, report).map_err(|error| FormattedError::from_str(
+        &format!("Failed to save the report to {}:\n{error}", path.display())
+    ))
+}
@@ -0,0 +1,76 @@
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
+use std::sync::Mutex;
+use colored::Colorize;
+use crate::test_errors::{ExecutionError, ExecutionMetrics};
+use crate::test_errors::ExecutionError::{RuntimeError, TimedOut};
+use crate::interrupt;
+
+/// Reruns tests in which the program timed out or crashed,
+/// since on a busy machine these can be caused by other processes instead of the program
+pub(crate) struct Retries {
+    max_retries: usize,
+    /// The outcomes of every attempt of the tests that were rerun
+    retried: Mutex<Vec<(String, Vec<&'static str>)>>,
+}
+
+impl Retries {
+    pub(crate) fn new(max_retries: usize) -> Self {
+        Retries { max_retries, retried: Mutex::new(vec![]) }
+    }
+
+    fn should_retry(result: &Result<(), ExecutionError>) -> bool {
+        // A program killed because testing was stopped with Ctrl+C isn't rerun
+        !interrupt::received_ctrl_c() && matches!(result, Err(TimedOut | RuntimeError(_)))
+    }
+
+    fn describe(result: &Result<(), ExecutionError>) -> &'static str {
+        match result {
+            Ok(()) => "finished",
+            Err(TimedOut) => "timed out",
+            Err(RuntimeError(_)) => "runtime error",
+            Err(_) => "failed",
+        }
+    }
+
+    /// Runs the program with `run`, rerunning it up to `max_retries` times while it times out or crashes.
+    ///
+    /// Before every rerun the input is rewound and the output written by the previous attempt is removed.
+    /// The result of the last attempt is returned
+    pub(crate) fn run(
+        &self,
+        test_name: &str,
+        mut input_file: &File,
+        mut output_file: &File,
+        run: impl Fn() -> (ExecutionMetrics, Result<(), ExecutionError>),
+    ) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        let output_start = output_file.stream_position().expect("Failed to read the output file position");
+        let (mut metrics, mut result) = run();
+        let mut attempts = vec![];
+        while Self::should_retry(&result) && attempts.len() < self.max_retries {
+            attempts.push(Self::describe(&result));
+            input_file.rewind().expect("Failed to rewind input file");
+            output_file.set_len(output_start).expect("Failed to truncate the output file");
+            output_file.seek(SeekFrom::Start(output_start)).expect("Failed to rewind the output file");
+            (metrics, result) = run();
+        }
+        if !attempts.is_empty() {
+            attempts.push(Self::describe(&result));
+            self.retried.lock().expect("Failed to lock the retried tests").push((test_name.to_owned(), attempts));
+        }
+        (metrics, result)
+    }
+
+    /// Prints the outcomes of every attempt of the tests that were rerun
+    pub(crate) fn print(&self) {
+        let mut retried = self.retried.lock().expect("Failed to lock the retried tests");
+        if retried.is_empty() {
+            return;
+        }
+        retried.sort_by(|a, b| human_sort::compare(&a.0, &b.0));
+        println!("{}", format!("Rerun tests ({}):", retried.len()).bold());
+        for (test_name, attempts) in retried.iter() {
+            println!("- {test_name}: {}", attempts.join(", then "));
+        }
+    }
+}
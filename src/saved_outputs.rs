@@ -0,0 +1,110 @@
+use std::fs;
+use std::fs::File;
+use std::io::{Seek, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use serde::Serialize;
+use crate::formatted_error::FormattedError;
+
+/// The zstd compression level used for saved files
+const COMPRESSION_LEVEL: i32 = 3;
+const ARCHIVE_NAME: &str = "saved.zst";
+const INDEX_NAME: &str = "saved-index.json";
+
+/// The location of a single saved file in the compressed archive
+#[derive(Serialize)]
+struct IndexEntry {
+    name: String,
+    /// The offset of the zstd frame in the archive
+    offset: u64,
+    compressed_size: u64,
+    size: u64,
+}
+
+struct Archive {
+    file: File,
+    index: Vec<IndexEntry>,
+}
+
+/// Saves program outputs and diffs of wrong answers to a directory.
+///
+/// If compression is enabled, every saved file is stored as a separate zstd frame
+/// appended to a single archive, and an index with the location of every file is written next to it.
+/// Any single file can be extracted by decompressing its frame, while decompressing
+/// the whole archive with the `zstd` tool returns all saved files concatenated.
+pub(crate) struct OutputSaver {
+    directory: PathBuf,
+    archive: Option<Mutex<Archive>>,
+}
+
+impl OutputSaver {
+    pub(crate) fn new(directory: PathBuf, compress: bool) -> Result<OutputSaver, FormattedError> {
+        let error_message = |error| FormattedError::from_str(
+            &format!("Failed to create the directory for saved outputs {}:\n{error}", directory.display())
+        );
+        fs::create_dir_all(&directory).map_err(error_message)?;
+        let archive = if compress {
+            let file = File::create(directory.join(ARCHIVE_NAME)).map_err(error_message)?;
+            Some(Mutex::new(Archive { file, index: vec![] }))
+        } else { None };
+        Ok(OutputSaver { directory, archive })
+    }
+
+    /// Saves the contents of `file` (from the beginning) under the given name.
+    /// The file is rewound afterwards
+    pub(crate) fn save_file(&self, name: &str, mut file: &File) {
+        file.rewind().expect("Failed to rewind the saved file");
+        match &self.archive {
+            None => {
+                let mut saved = File::create(self.saved_file_path(name)).expect("Failed to create the saved file");
+                std::io::copy(&mut file, &mut saved).expect("Failed to save the file");
+            }
+            Some(archive) => {
+                let size = file.metadata().expect("Failed to read the saved file metadata").len();
+                let compressed = zstd::encode_all(file, COMPRESSION_LEVEL).expect("Failed to compress the saved file");
+                Self::append_to_archive(archive, name, &compressed, size);
+            }
+        }
+        file.rewind().expect("Failed to rewind the saved file");
+    }
+
+    /// Saves `contents` under the given name
+    pub(crate) fn save_text(&self, name: &str, contents: &str) {
+        match &self.archive {
+            None => fs::write(self.saved_file_path(name), contents).expect("Failed to save the file"),
+            Some(archive) => {
+                let compressed = zstd::encode_all(contents.as_bytes(), COMPRESSION_LEVEL).expect("Failed to compress the saved file");
+                Self::append_to_archive(archive, name, &compressed, contents.len() as u64);
+            }
+        }
+    }
+
+    /// Returns the path of a saved file, creating its directory for tests in subdirectories
+    fn saved_file_path(&self, name: &str) -> PathBuf {
+        let path = self.directory.join(name);
+        fs::create_dir_all(path.parent().expect("The saved file should have a parent")).expect("Failed to create the saved file directory");
+        path
+    }
+
+    fn append_to_archive(archive: &Mutex<Archive>, name: &str, compressed: &[u8], size: u64) {
+        let mut archive = archive.lock().expect("Failed to lock the saved outputs archive");
+        let offset = archive.file.stream_position().expect("Failed to read the archive position");
+        archive.file.write_all(compressed).expect("Failed to write to the saved outputs archive");
+        archive.index.push(IndexEntry {
+            name: name.to_owned(),
+            offset,
+            compressed_size: compressed.len() as u64,
+            size,
+        });
+    }
+
+    /// Writes the index of the compressed archive
+    pub(crate) fn finish(&self) {
+        if let Some(archive) = &self.archive {
+            let mut archive = archive.lock().expect("Failed to lock the saved outputs archive");
+            archive.index.sort_by(|a, b| human_sort::compare(&a.name, &b.name));
+            let index = serde_json::to_string_pretty(&archive.index).expect("Failed to serialize the saved outputs index");
+            fs::write(self.directory.join(INDEX_NAME), index).expect("Failed to write the saved outputs index");
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::sync::Mutex;
+use colored::Colorize;
+use comfy_table::{Attribute, Cell, Color, Table};
+use comfy_table::ContentArrangement::Dynamic;
+use crate::manifest::ScoredGroup;
+use crate::warnings::{add_warning, WarningKind};
+
+struct GroupResult {
+    name: String,
+    points: u32,
+    total: usize,
+    passed: usize,
+    /// The number of tests of the group that were run, the group isn't finished if it's lower than `total`
+    finished: usize,
+}
+
+/// Computes the score of the solution from groups of tests,
+/// where a group is worth its points only if all its tests pass, like in OI-style judges
+pub(crate) struct GroupScoring {
+    groups: Vec<ScoredGroup>,
+    results: Mutex<Vec<GroupResult>>,
+}
+
+impl GroupScoring {
+    /// Creates the scoring for the tests with the given names.
+    /// Groups without any tests aren't scored
+    pub(crate) fn new<'a>(groups: Vec<ScoredGroup>, test_names: impl Iterator<Item = &'a str>) -> Self {
+        let mut results: Vec<GroupResult> = groups.iter().map(|group| GroupResult {
+            name: group.tests.to_string(),
+            points: group.points,
+            total: 0,
+            passed: 0,
+            finished: 0,
+        }).collect();
+        for test_name in test_names {
+            if let Some(index) = Self::group_index(&groups, test_name) {
+                results[index].total += 1;
+            }
+        }
+        GroupScoring { groups, results: Mutex::new(results) }
+    }
+
+    /// Tests belong to the first group matching their name
+    fn group_index(groups: &[ScoredGroup], test_name: &str) -> Option<usize> {
+        groups.iter().position(|group| group.tests.matches(test_name))
+    }
+
+    pub(crate) fn record(&self, test_name: &str, passed: bool) {
+        let Some(index) = Self::group_index(&self.groups, test_name) else {
+            return;
+        };
+        let mut results = self.results.lock().expect("Failed to lock the group results");
+        results[index].finished += 1;
+        if passed {
+            results[index].passed += 1;
+        }
+    }
+
+    /// Prints a table with the verdict and points of every group, and the total score
+    pub(crate) fn print(&self) {
+        let results = self.results.lock().expect("Failed to lock the group results");
+        let mut table = Table::new();
+        table.set_content_arrangement(Dynamic).set_header(
+            ["Group", "Passed", "Points"].map(|header| Cell::new(header).add_attribute(Attribute::Bold))
+        );
+        let (mut score, mut max_score) = (0, 0);
+        for result in results.iter().filter(|result| result.total > 0) {
+            let (points, color) = if result.finished < result.total {
+                (0, Color::DarkGrey)
+            } else if result.passed == result.total {
+                (result.points, Color::Green)
+            } else {
+                (0, Color::Red)
+            };
+            score += points;
+            max_score += result.points;
+            table.add_row([
+                Cell::new(&result.name),
+                Cell::new(format!("{}/{}", result.passed, result.total)).fg(color),
+                Cell::new(format!("{points}/{}", result.points)).fg(color),
+            ]);
+        }
+        if max_score == 0 {
+            add_warning(WarningKind::Scoring, "No tests belong to the scored groups");
+            return;
+        }
+        println!("{}", table.to_string().replace('\r', ""));
+        let score_text = format!("Score: {score}/{max_score}").bold();
+        println!("{}", if score == max_score { score_text.green() } else { score_text.red() });
+    }
+}
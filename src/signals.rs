@@ -0,0 +1,31 @@
+/// Returns the name of a signal that stops a process and a hint about what usually causes it in a solution
+fn signal_info(signal: i32) -> Option<(&'static str, &'static str)> {
+    Some(match signal {
+        libc::SIGSEGV => ("SIGSEGV", "segmentation fault, usually an out of bounds array access, a null pointer dereference or a stack overflow"),
+        libc::SIGBUS => ("SIGBUS", "bus error, usually an invalid memory access"),
+        libc::SIGFPE => ("SIGFPE", "division by zero or integer overflow in division"),
+        libc::SIGABRT => ("SIGABRT", "aborted, usually a failed assertion or an uncaught exception"),
+        libc::SIGILL => ("SIGILL", "illegal instruction, usually the result of undefined behavior or a missing return statement"),
+        libc::SIGTRAP => ("SIGTRAP", "trace trap, usually a failed check of a hardened standard library"),
+        libc::SIGKILL => ("SIGKILL", "killed, possibly by the out-of-memory killer"),
+        libc::SIGTERM => ("SIGTERM", "terminated by another process"),
+        libc::SIGPIPE => ("SIGPIPE", "wrote to a closed pipe"),
+        libc::SIGXCPU => ("SIGXCPU", "exceeded the CPU time limit"),
+        libc::SIGXFSZ => ("SIGXFSZ", "exceeded the file size limit"),
+        libc::SIGSYS => ("SIGSYS", "used a forbidden system call"),
+        _ => return None,
+    })
+}
+
+/// Describes a signal that stopped a process, for example "SIGFPE (division by zero or integer overflow in division)"
+pub(crate) fn describe_signal(signal: i32) -> String {
+    match signal_info(signal) {
+        Some((name, hint)) => format!("{name} ({hint})"),
+        None => format!("signal {signal}"),
+    }
+}
+
+/// The runtime error message of a program that was stopped by a signal
+pub(crate) fn killed_by(signal: i32) -> String {
+    format!("- the program was killed by {}", describe_signal(signal))
+}
@@ -0,0 +1,67 @@
+use std::io;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::Instant;
+use colored::Colorize;
+use crate::encoding::open_text_file;
+use crate::temp_files::TempFileError;
+use crate::exit_code::Outcome;
+use crate::formatted_error::FormattedError;
+
+/// Waits for the program to exit, returning its exit status and the peak memory usage in KiB
+#[cfg(target_os = "linux")]
+fn wait_with_memory(child: &mut Child) -> io::Result<(ExitStatus, Option<u64>)> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let pid = libc::pid_t::try_from(child.id()).expect("The process ID is out of range");
+    let mut status = 0;
+    // SAFETY: `rusage` is plain data, for which all zeroes is a valid value
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    // SAFETY: `pid` is a child of this process that hasn't been waited for yet, and both pointers are valid
+    let result = unsafe { libc::wait4(pid, &raw mut status, 0, &raw mut usage) };
+    if result == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((ExitStatus::from_raw(status), u64::try_from(usage.ru_maxrss).ok()))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn wait_with_memory(child: &mut Child) -> io::Result<(ExitStatus, Option<u64>)> {
+    Ok((child.wait()?, None))
+}
+
+/// Runs the executable once, with the input read from `input` or from the terminal if it isn't provided.
+/// The output of the program is printed directly, followed by its runtime and memory usage.
+///
+/// No time limit is applied, since the input might be typed by hand
+pub(crate) fn run_once(executable: &Path, input: Option<&Path>) -> Result<(), FormattedError> {
+    let stdin = match input {
+        Some(input) if !input.is_file() => {
+            return Err(FormattedError::from_str(&format!("The input file {} does not exist", input.display())));
+        }
+        Some(input) => Stdio::from(open_text_file(input).map_err(|TempFileError(error)| FormattedError::from_str(&error))?),
+        None => {
+            println!("{}", "Reading the input from the terminal, finish it with Ctrl+D (Ctrl+Z and Enter on Windows)".dimmed());
+            Stdio::inherit()
+        }
+    };
+
+    let start_time = Instant::now();
+    let mut child = Command::new(executable)
+        .stdin(stdin)
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|error| FormattedError::from_str(&format!("Failed to run the program:\n{error}")))?;
+    let (status, memory_kibibytes) = wait_with_memory(&mut child)
+        .map_err(|error| FormattedError::from_str(&format!("Failed to wait for the program:\n{error}")))?;
+    let time = start_time.elapsed();
+
+    let memory = memory_kibibytes.map_or_else(String::new, |memory| format!(", using {memory}KiB of memory"));
+    println!("{}", format!("Finished in {:.3}s{memory}", time.as_secs_f64()).bold());
+    if status.success() {
+        Ok(())
+    } else {
+        Err(FormattedError::from_str(&format!("The program exited unsuccessfully: {status}")).with_outcome(Outcome::Failed))
+    }
+}
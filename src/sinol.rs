@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::Deserialize;
+use crate::manifest::LimitOverride;
+use crate::prepare_input::input_test_name;
+use crate::test_pattern::TestPattern;
+
+/// The configuration file of sinol packages, the task package format used by SIO2
+const SINOL_CONFIG_NAME: &str = "config.yml";
+
+/// A key of the per-test limits, which is a group number (like `1`) or a test (like `1a`)
+#[derive(Deserialize, PartialEq, Eq, Hash, Debug)]
+#[serde(untagged)]
+enum LimitKey {
+    Number(u64),
+    Text(String),
+}
+
+impl LimitKey {
+    fn to_text(&self) -> String {
+        match self {
+            LimitKey::Number(number) => number.to_string(),
+            LimitKey::Text(text) => text.clone(),
+        }
+    }
+}
+
+/// The parts of `config.yml` used by toster, other keys are ignored
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+struct SinolConfig {
+    sinol_task_id: Option<String>,
+    /// The time limit in milliseconds
+    time_limit: Option<u64>,
+    /// The memory limit in KiB
+    memory_limit: Option<u64>,
+    time_limits: HashMap<LimitKey, u64>,
+    memory_limits: HashMap<LimitKey, u64>,
+}
+
+/// A sinol task package, with the tests in the `in` and `out` directories
+/// and the limits in `config.yml`
+pub(crate) struct SinolPackage {
+    pub(crate) directory: PathBuf,
+    /// The prefix of all test names, like `abc` in `abc1a`
+    pub(crate) task_id: String,
+    pub(crate) time_limit: Option<Duration>,
+    pub(crate) memory_limit: Option<u64>,
+    config: SinolConfig,
+}
+
+impl SinolPackage {
+    /// Finds the package in the current directory, or the package containing the solution
+    /// in its `prog` directory, where sinol packages keep their solutions
+    pub(crate) fn detect(source_path: &Path) -> Result<Option<SinolPackage>, String> {
+        let solution_package = source_path.canonicalize().ok()
+            .and_then(|path| path.parent().map(Path::to_path_buf))
+            .filter(|directory| directory.file_name().is_some_and(|name| name == "prog"))
+            .and_then(|directory| directory.parent().map(Path::to_path_buf));
+        let candidates = [PathBuf::from(".")].into_iter().chain(solution_package);
+        for directory in candidates {
+            if directory.join(SINOL_CONFIG_NAME).is_file() && directory.join("in").is_dir() {
+                return Self::load(directory).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn load(directory: PathBuf) -> Result<SinolPackage, String> {
+        let path = directory.join(SINOL_CONFIG_NAME);
+        let contents = fs::read_to_string(&path)
+            .map_err(|error| format!("Failed to read the sinol package config {}:\n{error}", path.display()))?;
+        let config: SinolConfig = serde_yaml::from_str(&contents)
+            .map_err(|error| format!("The sinol package config {} is invalid:\n{error}", path.display()))?;
+        let task_id = match &config.sinol_task_id {
+            Some(task_id) => task_id.clone(),
+            // Packages without the task ID in the config are named after it
+            None => directory.canonicalize().ok()
+                .and_then(|directory| directory.file_name().map(|name| name.to_string_lossy().into_owned()))
+                .ok_or("The task ID of the sinol package couldn't be determined, set sinol_task_id in config.yml")?,
+        };
+        Ok(SinolPackage {
+            time_limit: config.time_limit.map(Duration::from_millis),
+            memory_limit: config.memory_limit,
+            directory,
+            task_id,
+            config,
+        })
+    }
+
+    /// Returns the limits of tests with per-test or per-group limits in `config.yml`.
+    ///
+    /// Every test gets its own override, since the limits of a group don't apply to tests of other groups
+    /// with the same prefix (group `1` doesn't contain test `10a`). Per-test limits take precedence over per-group limits
+    pub(crate) fn limit_overrides(&self, input_directory: &Path, input_ext: &str) -> Vec<LimitOverride> {
+        if self.config.time_limits.is_empty() && self.config.memory_limits.is_empty() {
+            return vec![];
+        }
+        let test_names = fs::read_dir(input_directory).into_iter().flatten()
+            .filter_map(|entry| input_test_name(entry.ok()?.file_name().to_str()?, input_ext).map(str::to_owned));
+        test_names.filter_map(|test_name| {
+            let test = test_name.strip_prefix(&self.task_id).unwrap_or(&test_name);
+            let group = test.trim_end_matches(|char: char| char.is_ascii_lowercase());
+            let find = |limits: &HashMap<LimitKey, u64>| {
+                let limit = |key: &str| limits.iter().find(|(limit_key, _)| limit_key.to_text() == key).map(|(_, limit)| *limit);
+                limit(test).or_else(|| limit(group))
+            };
+            let (time_limit, memory_limit) = (find(&self.config.time_limits), find(&self.config.memory_limits));
+            (time_limit.is_some() || memory_limit.is_some()).then(|| LimitOverride {
+                tests: TestPattern::new(test_name.clone()),
+                time_limit: time_limit.map(Duration::from_millis),
+                memory_limit,
+            })
+        }).collect()
+    }
+}
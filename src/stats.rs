@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use colored::Colorize;
+use rayon::prelude::*;
+use crate::formatted_error::FormattedError;
+use crate::manifest::Manifest;
+use crate::prepare_input::{prepare_file_inputs, TestInputSource};
+use crate::output_names::OutputNames;
+
+/// The number of the largest tests listed
+const LARGEST_TEST_COUNT: usize = 5;
+
+struct TestStats {
+    name: String,
+    input_size: u64,
+    has_output: bool,
+}
+
+/// Returns the group of a test, which is its name without trailing lowercase letters,
+/// so tests "abc1a" and "abc1b" belong to group "abc1"
+fn test_group(test_name: &str) -> &str {
+    let group = test_name.trim_end_matches(|char: char| char.is_ascii_lowercase());
+    if group.is_empty() { test_name } else { group }
+}
+
+#[allow(clippy::cast_precision_loss)] // The size is only shown rounded
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Prints statistics of the tests in the input and output directories and the limits declared in the manifest
+pub(crate) fn print_stats(
+    input_directory: &Path,
+    in_ext: &str,
+    output_directory: &Path,
+    output_names: &OutputNames,
+    manifest: &Manifest,
+) -> Result<(), FormattedError> {
+    let inputs = prepare_file_inputs(input_directory, in_ext)?;
+    let mut tests: Vec<TestStats> = inputs.iterator
+        .map(|test| {
+            let TestInputSource::File(path) = &test.input_source else {
+                unreachable!("Tests read from the input directory are not chained")
+            };
+            let metadata = path.metadata().map_err(|error| FormattedError::from_str(
+                &format!("Failed to read the input file {}:\n{error}", path.display())
+            ))?;
+            Ok(TestStats {
+                has_output: output_names.find_file(output_directory, &test.test_name).is_file(),
+                name: test.test_name,
+                input_size: metadata.len(),
+            })
+        })
+        .collect::<Result<_, FormattedError>>()?;
+    tests.sort_by(|a, b| human_sort::compare(&a.name, &b.name));
+
+    let mut groups: BTreeMap<&str, (usize, u64)> = BTreeMap::new();
+    for test in &tests {
+        let (count, size) = groups.entry(test_group(&test.name)).or_default();
+        *count += 1;
+        *size += test.input_size;
+    }
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    groups.sort_by(|a, b| human_sort::compare(a.0, b.0));
+
+    let missing_outputs = tests.iter().filter(|test| !test.has_output).count();
+    print!("{} {} in {} groups", "Tests:".bold(), tests.len(), groups.len());
+    if missing_outputs > 0 {
+        print!(" {}", format!("({missing_outputs} without an output file)").yellow());
+    }
+    println!();
+
+    let name_width = groups.iter().map(|(group, _)| group.len()).max().unwrap_or(0).max("Group".len());
+    println!("\n{}", format!("{:name_width$}  {:>5}  {:>10}", "Group", "Tests", "Input size").bold());
+    for (group, (count, size)) in &groups {
+        println!("{group:name_width$}  {count:>5}  {:>10}", format_size(*size));
+    }
+
+    let mut sizes: Vec<u64> = tests.iter().map(|test| test.input_size).collect();
+    sizes.sort_unstable();
+    println!(
+        "\n{} smallest {}, median {}, largest {}, total {}",
+        "Input sizes:".bold(),
+        format_size(sizes[0]),
+        format_size(sizes[sizes.len() / 2]),
+        format_size(sizes[sizes.len() - 1]),
+        format_size(sizes.iter().sum()),
+    );
+
+    let mut largest: Vec<&TestStats> = tests.iter().collect();
+    largest.sort_by_key(|test| std::cmp::Reverse(test.input_size));
+    largest.truncate(LARGEST_TEST_COUNT);
+    let name_width = largest.iter().map(|test| test.name.len()).max().unwrap_or(0);
+    println!("\n{}", "Largest tests:".bold());
+    for test in largest {
+        println!("  {:name_width$}  {:>10}", test.name, format_size(test.input_size));
+    }
+
+    println!("\n{}", "Limits declared in the manifest:".bold());
+    if manifest.limits.is_empty() {
+        println!("  {}", "none".bright_black());
+    }
+    for limit in &manifest.limits {
+        let matching = tests.iter().filter(|test| limit.tests.matches(&test.name)).count();
+        let mut description = vec![];
+        if let Some(time_limit) = limit.time_limit {
+            description.push(format!("time limit {:.3}s", time_limit.as_secs_f64()));
+        }
+        if let Some(memory_limit) = limit.memory_limit {
+            description.push(format!("memory limit {memory_limit} KiB"));
+        }
+        println!("  {}: {} ({matching} matching tests)", limit.tests, description.join(", "));
+    }
+    Ok(())
+}
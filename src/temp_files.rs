@@ -1,9 +1,32 @@
 use std::fs::File;
 use std::io;
 use std::process::Stdio;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::Relaxed;
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::NamedTempFile;
+use crate::interrupt;
+use crate::test_errors::{ExecutionError, TestError};
 
-pub(crate) fn make_cloned_stdio(file: &File) -> Stdio {
-    Stdio::from(file.try_clone().unwrap())
+/// How long creating a temporary file waits for other tests to close their files
+/// when the limit of open files is reached, in milliseconds. Set with `--temp-file-timeout`
+static TEMP_FILE_TIMEOUT_MILLIS: AtomicU64 = AtomicU64::new(10_000);
+const TEMP_FILE_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+pub(crate) fn set_timeout(timeout: Duration) {
+    TEMP_FILE_TIMEOUT_MILLIS.store(u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX), Relaxed);
+}
+
+/// Duplicates the file descriptor of the file to pass it to a child process
+pub(crate) fn clone_stdio(file: &File) -> io::Result<Stdio> {
+    file.try_clone().map(Stdio::from)
+}
+
+/// Duplicates the file descriptor like `clone_stdio()` for running a test.
+/// Like `create_test_temp_file()`, testing is stopped if the limit of open files is reached
+pub(crate) fn make_cloned_stdio(file: &File) -> Result<Stdio, TempFileError> {
+    clone_stdio(file).map_err(|error| stop_testing(format!("Failed to duplicate a file descriptor:\n{error}")))
 }
 
 /// Creates a memfile using the `memfile` crate on Linux
@@ -11,8 +34,86 @@ pub(crate) fn make_cloned_stdio(file: &File) -> Stdio {
 ///
 /// These files should be deleted automatically when all file descriptors are closed
 ///
+/// If the limit of open files is reached, waits until other tests close their files,
+/// and returns an error explaining how to raise the limit if that doesn't happen within the timeout set with `set_timeout()`.
+///
 /// Always returns a `File` struct
 pub(crate) fn create_temp_file() -> io::Result<File> {
+    let timeout = Duration::from_millis(TEMP_FILE_TIMEOUT_MILLIS.load(Relaxed));
+    let start = Instant::now();
+    loop {
+        match try_create_temp_file() {
+            Err(error) if is_too_many_open_files(&error) => {
+                if start.elapsed() >= timeout {
+                    return Err(io::Error::other(format!(
+                        "No temporary file could be created for {}s, because too many files are open ({error}). \
+                        Raise the limit of open files (for example with ulimit -n) or run fewer tests at once",
+                        timeout.as_secs_f64(),
+                    )));
+                }
+                thread::sleep(TEMP_FILE_RETRY_INTERVAL);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// A temporary file needed to run a test couldn't be created. Testing was already stopped when it's returned,
+/// so the test which needed the file is cancelled, and a program run outside of testing fails with a sandbox error
+#[derive(Debug)]
+pub(crate) struct TempFileError(pub(crate) String);
+
+impl From<TempFileError> for TestError {
+    fn from(_: TempFileError) -> Self {
+        TestError::Cancelled
+    }
+}
+
+impl From<TempFileError> for ExecutionError {
+    fn from(TempFileError(error): TempFileError) -> Self {
+        ExecutionError::SandboxError(error)
+    }
+}
+
+/// Creates a temporary file like `create_temp_file()` for running a test.
+/// Every other test would fail the same way, so if the file can't be created, testing is stopped
+pub(crate) fn create_test_temp_file() -> Result<File, TempFileError> {
+    create_temp_file().map_err(|error| stop_testing(format!("Failed to create a temporary file:\n{error}")))
+}
+
+/// Creates a temporary file with a path, for passing files to programs as arguments, stopping testing like
+/// `create_test_temp_file()` if it can't be created. The file is deleted when the returned struct is dropped
+pub(crate) fn create_test_named_temp_file() -> Result<NamedTempFile, TempFileError> {
+    NamedTempFile::new().map_err(|error| stop_testing(format!("Failed to create a temporary file:\n{error}")))
+}
+
+/// Stops testing because of an error which would make every other test fail too
+fn stop_testing(error: String) -> TempFileError {
+    interrupt::record_fatal_error(&error);
+    TempFileError(error)
+}
+
+/// Checks if the error was caused by reaching the limit of open files of the process or the system
+fn is_too_many_open_files(error: &io::Error) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        matches!(error.raw_os_error(), Some(libc::EMFILE | libc::ENFILE))
+    }
+
+    #[cfg(windows)]
+    {
+        // ERROR_TOO_MANY_OPEN_FILES
+        error.raw_os_error() == Some(4)
+    }
+
+    #[cfg(not(any(target_os = "linux", windows)))]
+    {
+        // EMFILE and ENFILE have the same values on all common Unix systems
+        matches!(error.raw_os_error(), Some(23 | 24))
+    }
+}
+
+fn try_create_temp_file() -> io::Result<File> {
     #[cfg(target_os = "linux")]
     {
         use memfile::MemFile;
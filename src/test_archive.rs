@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+use rayon::iter::IntoParallelIterator;
+use rayon::vec::IntoIter;
+use zip::ZipArchive;
+use crate::encoding::decode_text;
+use crate::formatted_error::FormattedError;
+use crate::prepare_input::{InputError, Test, TestInputSource, TestingInputs};
+use crate::temp_files::create_test_temp_file;
+
+/// A ZIP archive containing test files, read without extracting it.
+///
+/// Files are found by their name without the directories inside the archive,
+/// so an archive with `in/1.in` and `out/1.out` contains both the input and the output of test `1`
+pub(crate) struct TestArchive {
+    path: PathBuf,
+    /// The full names of the files in the archive, by the file name without the directories
+    entries: HashMap<String, String>,
+}
+
+impl TestArchive {
+    /// Returns true if the path is a ZIP archive that should be read as a test archive
+    pub(crate) fn is_archive(path: &Path) -> bool {
+        path.is_file() && path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+    }
+
+    pub(crate) fn open(path: &Path) -> Result<TestArchive, String> {
+        let file = File::open(path)
+            .map_err(|error| format!("Failed to open the test archive {}:\n{error}", path.display()))?;
+        let archive = ZipArchive::new(file)
+            .map_err(|error| format!("The test archive {} is not a valid ZIP archive:\n{error}", path.display()))?;
+        let mut entries = HashMap::new();
+        for entry in archive.file_names().filter(|entry| !entry.ends_with('/')) {
+            let file_name = entry.rsplit('/').next().unwrap_or(entry);
+            if let Some(previous) = entries.insert(file_name.to_owned(), entry.to_owned()) {
+                return Err(format!(
+                    "The test archive {} contains two files named {file_name}: {previous} and {entry}",
+                    path.display(),
+                ));
+            }
+        }
+        Ok(TestArchive { path: path.to_path_buf(), entries })
+    }
+
+    /// Returns the name of the file in the archive with the given name, if it exists
+    pub(crate) fn find(&self, file_name: &str) -> Option<&str> {
+        self.entries.get(file_name).map(String::as_str)
+    }
+
+    /// Returns the names of the files in the archive, without the directories
+    pub(crate) fn file_names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    /// Returns the tests whose input files in the archive have the given extension
+    pub(crate) fn prepare_inputs(&self, in_ext: &str) -> Result<TestingInputs<IntoIter<Test>>, FormattedError> {
+        let tests: Vec<Test> = self.entries.iter()
+            .filter_map(|(file_name, entry)| Some(Test {
+                test_name: file_name.strip_suffix(in_ext).filter(|name| !name.is_empty())?.to_owned(),
+                input_source: TestInputSource::ArchiveEntry {
+                    archive: self.path.clone(),
+                    entry: entry.clone(),
+                },
+            }))
+            .collect();
+        if tests.is_empty() {
+            return Err(FormattedError::from_str("There are no files in the test archive with the provided file extension"));
+        }
+        Ok(TestingInputs { iterator: tests.into_par_iter() })
+    }
+
+    /// Decompresses a file from the archive to a new rewound temporary file
+    pub(crate) fn extract(archive: &Path, entry: &str) -> Result<File, InputError> {
+        let mut file = create_test_temp_file()?;
+        let result = File::open(archive)
+            .and_then(|archive| Ok(ZipArchive::new(archive)?))
+            .and_then(|mut archive| {
+                io::copy(&mut archive.by_name(entry)?, &mut file)?;
+                file.rewind()
+            });
+        match result {
+            Ok(()) => Ok(file),
+            Err(error) => Err(InputError::InvalidArchiveEntry(format!(
+                "Failed to extract {} from the test archive:\n{error}",
+                Self::display_path(archive, entry).display(),
+            ))),
+        }
+    }
+
+    /// Reads a text file with the given name from the archive, transcoding it to UTF-8 if it's encoded differently.
+    /// Returns `None` if there is no such file in the archive
+    pub(crate) fn read_text_file(&self, file_name: &str) -> Result<Option<String>, InputError> {
+        let Some(entry) = self.find(file_name) else {
+            return Ok(None);
+        };
+        let mut bytes = vec![];
+        let display_path = Self::display_path(&self.path, entry);
+        Self::extract(&self.path, entry)?.read_to_end(&mut bytes).map_err(|error| InputError::InvalidArchiveEntry(
+            format!("Failed to extract {} from the test archive:\n{error}", display_path.display()),
+        ))?;
+        Ok(Some(decode_text(&display_path, bytes)))
+    }
+
+    /// The path shown in messages about a file in the archive, like `tests.zip/in/1.in`
+    pub(crate) fn display_path(archive: &Path, entry: &str) -> PathBuf {
+        archive.join(entry)
+    }
+}
@@ -0,0 +1,86 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::File;
+use crate::formatted_error::FormattedError;
+use crate::manifest::TestDependency;
+use crate::prepare_input::{Test, TestInputSource};
+
+pub(crate) struct ChainedTest {
+    pub(crate) test: Test,
+    /// The index (in the same chain) of the test whose output is prepended to the input of this test
+    pub(crate) dependency: Option<usize>,
+    pub(crate) has_dependents: bool,
+}
+
+/// A group of tests that depend on each other and have to be run sequentially.
+/// Every test is placed after the test it depends on.
+pub(crate) struct TestChain {
+    pub(crate) tests: Vec<ChainedTest>,
+}
+
+impl ChainedTest {
+    /// Returns the test with the output of its dependency prepended to the input
+    pub(crate) fn with_previous_output(self, previous_output: File) -> Test {
+        Test {
+            test_name: self.test.test_name,
+            input_source: TestInputSource::Chained { previous_output, input: Box::new(self.test.input_source) },
+        }
+    }
+}
+
+/// Groups tests into chains according to the dependencies declared in the manifest.
+/// Tests without any dependencies are placed in chains of their own.
+pub(crate) fn build_chains(tests: Vec<Test>, dependencies: &[TestDependency]) -> Result<Vec<TestChain>, FormattedError> {
+    let indices: HashMap<&str, usize> = tests.iter()
+        .enumerate()
+        .map(|(index, test)| (test.test_name.as_str(), index))
+        .collect();
+    let find_test = |name: &str| indices.get(name).copied().ok_or_else(|| FormattedError::from_str(
+        &format!("The test \"{name}\" used in the manifest dependencies does not exist")
+    ));
+
+    let mut parents: Vec<Option<usize>> = vec![None; tests.len()];
+    let mut children: Vec<Vec<usize>> = vec![vec![]; tests.len()];
+    for dependency in dependencies {
+        let test = find_test(&dependency.test)?;
+        let after = find_test(&dependency.after)?;
+        if parents[test].is_some() {
+            return Err(FormattedError::from_str(
+                &format!("The test \"{}\" can't depend on more than one test", dependency.test)
+            ));
+        }
+        parents[test] = Some(after);
+        children[after].push(test);
+    }
+
+    let mut tests: Vec<Option<Test>> = tests.into_iter().map(Some).collect();
+    let mut chains = vec![];
+    for root in (0..tests.len()).filter(|index| parents[*index].is_none()) {
+        let mut chain = vec![];
+        // Pairs of (test index, position of the dependency in the chain)
+        let mut stack = vec![(root, None)];
+        while let Some((index, dependency)) = stack.pop() {
+            let position = chain.len();
+            chain.push(ChainedTest {
+                test: tests[index].take().expect("Every test should be visited once"),
+                dependency,
+                has_dependents: !children[index].is_empty(),
+            });
+            stack.extend(children[index].iter().rev().map(|child| (*child, Some(position))));
+        }
+        chains.push(TestChain { tests: chain });
+    }
+
+    if let Some(test) = tests.into_iter().flatten().next() {
+        return Err(FormattedError::from_str(
+            &format!("The test \"{}\" is part of a dependency cycle", test.test_name)
+        ));
+    }
+    Ok(chains)
+}
+
+/// Orders the chains by the total size of their inputs, largest first,
+/// so the longest tests start early instead of delaying the end of testing
+pub(crate) fn sort_largest_first(chains: &mut [TestChain]) {
+    chains.sort_by_cached_key(|chain| Reverse(chain.tests.iter().map(|test| test.test.input_source.size()).sum::<u64>()));
+}
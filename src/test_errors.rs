@@ -1,7 +1,10 @@
 use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::time::Duration;
+use colored::Color;
+use colored::Color::{Blue, Magenta, Red, Yellow};
 use colored::Colorize;
+use crate::theme;
 
 pub struct ExecutionMetrics {
     pub(crate) memory_kibibytes: Option<u64>,
@@ -23,10 +26,36 @@ pub enum TestError {
         error: ExecutionError
     },
     CheckerError {
+        error: ExecutionError,
+        /// The end of the checker's stderr, if it wrote anything
+        stderr: Option<String>,
+    },
+    InputTransformError {
         error: ExecutionError
     },
+    /// The test input was rejected by the validator
+    InvalidInput {
+        message: String
+    },
     NoOutputFile,
+    /// The program wrote different outputs when run twice on the same input
+    Nondeterministic {
+        message: String
+    },
+    /// The test depends on the output of another test, which wasn't produced
+    DependencyFailed {
+        dependency: String
+    },
+    /// The test wasn't started, because the total timeout of testing was exceeded
+    NotStarted,
     Cancelled,
+    /// A domain-specific verdict not covered by the other variants, for example "protocol violation".
+    /// Tests with custom verdicts are counted separately for each label
+    Custom {
+        label: String,
+        color: Color,
+        message: String,
+    },
 }
 
 #[allow(unused)]
@@ -34,41 +63,76 @@ pub enum TestError {
 pub enum ExecutionError {
     TimedOut,
     MemoryLimitExceeded,
+    OutputLimitExceeded,
     RuntimeError(String),
-    Sio2jailError(String),
+    /// An error found by valgrind's memcheck, with an excerpt of its report
+    MemoryError(String),
+    SandboxError(String),
     PipeError,
     OutputNotUtf8,
     IncorrectCheckerFormat(String),
 }
 
 impl TestError {
-    pub fn to_string(&self, test_name: &str) -> String {
-        let mut result: String = String::new();
+    /// A short, human-readable name of the verdict
+    pub fn verdict(&self) -> &str {
+        match self {
+            TestError::Incorrect { .. } => "wrong answer",
+            TestError::ProgramError { error: ExecutionError::TimedOut } => "timed out",
+            TestError::ProgramError { error: ExecutionError::MemoryLimitExceeded } => "out of memory",
+            TestError::ProgramError { error: ExecutionError::OutputLimitExceeded } => "output limit exceeded",
+            TestError::ProgramError { error: ExecutionError::RuntimeError(_) } => "runtime error",
+            TestError::ProgramError { error: ExecutionError::MemoryError(_) } => "memory error",
+            TestError::ProgramError { error: ExecutionError::SandboxError(_) } => "sandbox error",
+            TestError::ProgramError { error: ExecutionError::PipeError | ExecutionError::OutputNotUtf8 } => "invalid output",
+            TestError::ProgramError { error: ExecutionError::IncorrectCheckerFormat(_) } | TestError::CheckerError { .. } => "checker error",
+            TestError::InputTransformError { .. } => "input transform error",
+            TestError::InvalidInput { .. } => "invalid input",
+            TestError::NoOutputFile => "no output file",
+            TestError::Nondeterministic { .. } => "nondeterministic",
+            TestError::DependencyFailed { .. } | TestError::NotStarted => "skipped",
+            TestError::Cancelled => "cancelled",
+            TestError::Custom { label, .. } => label,
+        }
+    }
 
+    /// The error message without the test name header
+    pub fn message(&self) -> String {
         match self {
-            TestError::Incorrect { error } => {
-                result.push_str(&format!("{}", format!("Test {test_name}:\n").bold()));
-                result.push_str(error);
-            }
-            TestError::ProgramError { error } => {
-                result.push_str(&format!("{}", format!("Test {test_name}:\n").bold()));
-                result.push_str(&format!("{}", error.to_string().red()));
-            }
-            TestError::CheckerError { error } => {
-                result.push_str(&format!("{}", format!("Test {test_name} encountered a checker error:\n").bold()));
-                result.push_str(&format!("{}", error.to_string().blue()));
-            }
-            TestError::NoOutputFile => {
-                result.push_str(&format!("{}", format!("Test {test_name}:\n").bold()));
-                result.push_str(&format!("{}", "Output file does not exist".red()));
+            TestError::Incorrect { error } => error.clone(),
+            TestError::ProgramError { error }
+            | TestError::CheckerError { error, stderr: None }
+            | TestError::InputTransformError { error } => error.to_string(),
+            TestError::CheckerError { error, stderr: Some(stderr) } => format!("{error}\nChecker stderr:\n{stderr}"),
+            TestError::InvalidInput { message } | TestError::Nondeterministic { message } => message.clone(),
+            TestError::NoOutputFile => "Output file does not exist".to_owned(),
+            TestError::DependencyFailed { dependency } => {
+                format!("Skipped, because the program didn't produce an output for test {dependency}")
             }
-            TestError::Cancelled => {
-                result.push_str(&format!("{}", format!("Test {test_name}:\n").bold()));
-                result.push_str(&format!("{}", "Cancelled".yellow()));
+            TestError::NotStarted => "Skipped, because the total timeout was exceeded before the test was started".to_owned(),
+            TestError::Cancelled => "Cancelled".to_owned(),
+            TestError::Custom { label, message, .. } => {
+                if message.is_empty() { label.clone() } else { format!("{label}: {message}") }
             }
         }
+    }
+
+    pub fn to_string(&self, test_name: &str) -> String {
+        let (header, color) = match self {
+            // The diff table is already colored
+            TestError::Incorrect { .. } => (format!("Test {test_name}:\n"), None),
+            TestError::ProgramError { .. } | TestError::NoOutputFile => (format!("Test {test_name}:\n"), Some(Red)),
+            TestError::CheckerError { .. } => (format!("Test {test_name} encountered a checker error:\n"), Some(Blue)),
+            TestError::InputTransformError { .. } => {
+                (format!("Test {test_name} encountered an input transformation error:\n"), Some(Blue))
+            }
+            TestError::InvalidInput { .. } => (format!("Test {test_name} has an invalid input:\n"), Some(Yellow)),
+            TestError::Nondeterministic { .. } => (format!("Test {test_name} gave different outputs in two runs:\n"), Some(Magenta)),
+            TestError::DependencyFailed { .. } | TestError::NotStarted | TestError::Cancelled => (format!("Test {test_name}:\n"), Some(Yellow)),
+            TestError::Custom { color, .. } => (format!("Test {test_name}:\n"), Some(*color)),
+        };
 
-        result
+        format!("{}{}", header.bold(), theme::paint(self.verdict(), &self.message(), color))
     }
 }
 
@@ -77,8 +141,10 @@ impl Display for ExecutionError {
         match self {
             ExecutionError::TimedOut => write!(f, "Timed out"),
             ExecutionError::MemoryLimitExceeded => write!(f, "Memory limit exceeded"),
+            ExecutionError::OutputLimitExceeded => write!(f, "Output limit exceeded"),
             ExecutionError::RuntimeError(error) => write!(f, "Runtime error {error}"),
-            ExecutionError::Sio2jailError(error) => write!(f, "Sio2jail error: {error}"),
+            ExecutionError::MemoryError(report) => write!(f, "Valgrind found memory errors:\n{report}"),
+            ExecutionError::SandboxError(error) => write!(f, "Sandbox error: {error}"),
             ExecutionError::IncorrectCheckerFormat(error) => write!(f, "The checker output didn't follow the Toster checker format - {error}"),
             ExecutionError::PipeError => write!(f, "Failed to read program output"),
             ExecutionError::OutputNotUtf8 => write!(f, "The output contained invalid characters"),
@@ -0,0 +1,66 @@
+use std::collections::HashSet;
+use crate::manifest::{TestDependency, TestTags};
+use crate::prepare_input::Test;
+use crate::test_pattern::TestPattern;
+
+/// The tests excluded from testing with --skip and --skip-tag
+#[derive(Default)]
+pub(crate) struct TestExclusion {
+    pub(crate) patterns: Vec<TestPattern>,
+    /// The tags of tests from the manifest, only with the skipped tags
+    pub(crate) tags: Vec<TestTags>,
+}
+
+impl TestExclusion {
+    /// Keeps only the manifest tags that are skipped.
+    /// Returns an error if a skipped tag isn't given to any tests, which is most likely a typo
+    pub(crate) fn new(patterns: Vec<TestPattern>, skipped_tags: &[String], tags: Vec<TestTags>) -> Result<Self, String> {
+        if let Some(unknown) = skipped_tags.iter().find(|skipped| !tags.iter().any(|tags| tags.tags.contains(skipped))) {
+            return Err(format!("The tag \"{unknown}\" isn't given to any tests in the manifest"));
+        }
+        let tags = tags.into_iter()
+            .filter(|tags| tags.tags.iter().any(|tag| skipped_tags.contains(tag)))
+            .collect();
+        Ok(TestExclusion { patterns, tags })
+    }
+
+    fn is_excluded(&self, test_name: &str) -> bool {
+        self.patterns.iter().any(|pattern| pattern.matches(test_name))
+            || self.tags.iter().any(|tags| tags.tests.matches(test_name))
+    }
+
+    /// Returns the names of the excluded tests, together with the tests depending on them,
+    /// which can't be run without their dependency
+    pub(crate) fn excluded<'a>(&self, test_names: impl Iterator<Item=&'a str>, dependencies: &'a [TestDependency]) -> HashSet<&'a str> {
+        let mut excluded: HashSet<&str> = test_names
+            .filter(|test_name| self.is_excluded(test_name))
+            .collect();
+        loop {
+            let dependents: Vec<&str> = dependencies.iter()
+                .filter(|dependency| excluded.contains(dependency.after.as_str()) && !excluded.contains(dependency.test.as_str()))
+                .map(|dependency| dependency.test.as_str())
+                .collect();
+            if dependents.is_empty() {
+                break;
+            }
+            excluded.extend(dependents);
+        }
+        excluded
+    }
+
+    /// Removes the excluded tests and the tests depending on them.
+    /// Returns the remaining tests and the dependencies between them
+    pub(crate) fn apply(&self, tests: Vec<Test>, dependencies: &[TestDependency]) -> (Vec<Test>, Vec<TestDependency>) {
+        let excluded: HashSet<String> = self.excluded(tests.iter().map(|test| test.test_name.as_str()), dependencies)
+            .into_iter()
+            .map(str::to_owned)
+            .collect();
+
+        let dependencies = dependencies.iter()
+            .filter(|dependency| !excluded.contains(&dependency.test))
+            .cloned()
+            .collect();
+        let tests = tests.into_iter().filter(|test| !excluded.contains(&test.test_name)).collect();
+        (tests, dependencies)
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt::{Display, Formatter};
+use serde::Deserialize;
+
+/// A pattern matching test names, where `*` matches any sequence of characters
+/// and `?` matches any single character
+#[derive(Deserialize, Clone, Debug)]
+#[serde(transparent)]
+pub(crate) struct TestPattern(String);
+
+impl TestPattern {
+    pub(crate) fn new(pattern: String) -> Self {
+        TestPattern(pattern)
+    }
+
+    pub(crate) fn matches(&self, test_name: &str) -> bool {
+        let pattern: Vec<char> = self.0.chars().collect();
+        let name: Vec<char> = test_name.chars().collect();
+
+        // Greedy wildcard matching with backtracking to the last `*`
+        let (mut pattern_index, mut name_index) = (0, 0);
+        let mut last_star: Option<(usize, usize)> = None;
+        while name_index < name.len() {
+            match pattern.get(pattern_index) {
+                Some('*') => {
+                    last_star = Some((pattern_index, name_index));
+                    pattern_index += 1;
+                }
+                Some(&char) if char == '?' || char == name[name_index] => {
+                    pattern_index += 1;
+                    name_index += 1;
+                }
+                _ => {
+                    let Some((star_index, star_name_index)) = last_star else { return false };
+                    pattern_index = star_index + 1;
+                    name_index = star_name_index + 1;
+                    last_star = Some((star_index, star_name_index + 1));
+                }
+            }
+        }
+        pattern[pattern_index..].iter().all(|char| *char == '*')
+    }
+}
+
+impl Display for TestPattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
@@ -0,0 +1,348 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+use crate::args::ActionType;
+use crate::cli::check_ctrlc;
+use crate::builtin_checker::BuiltinChecker;
+use crate::checker::{Checker, CheckerProtocol};
+use crate::diff_renderer::DiffOptions;
+use crate::benchmark::Benchmark;
+use crate::retries::Retries;
+use crate::feedback::FeedbackReport;
+use crate::executor::{AnyTestExecutor, TestExecutor};
+use crate::input_transform::{get_program_input, InputTransformer};
+use crate::validator::Validator;
+use crate::prepare_input::{InputError, Test};
+use crate::temp_files::{create_test_temp_file, TempFileError};
+use crate::test_errors::{ExecutionError, ExecutionMetrics, TestError};
+use crate::test_errors::ExecutionError::OutputLimitExceeded;
+use crate::test_errors::TestError::{CheckerError, NoOutputFile, Nondeterministic, ProgramError};
+use crate::test_pattern::TestPattern;
+use crate::test_archive::TestArchive;
+use crate::testing_utils::{compare_output, compare_with_expected, pluralize_lines};
+
+/// Copies the contents of `file` starting at `start` to a new rewound temporary file.
+/// The position of `file` is restored afterwards.
+fn copy_to_temp(mut file: &File, start: u64) -> Result<File, TempFileError> {
+    let position = file.stream_position().expect("Failed to read file position");
+    let mut copy = create_test_temp_file()?;
+    file.seek(SeekFrom::Start(start)).expect("Failed to seek file");
+    io::copy(&mut file, &mut copy).expect("Failed to copy program output");
+    file.seek(SeekFrom::Start(position)).expect("Failed to seek file");
+    copy.rewind().expect("Failed to rewind memfile");
+    Ok(copy)
+}
+
+/// Everything needed to run a single test, shared between all testing threads
+pub(crate) struct TestRunner {
+    pub(crate) action_type: ActionType,
+    pub(crate) executor: AnyTestExecutor,
+    /// Executors with overridden limits used for tests matching the pattern,
+    /// the first matching pattern is used
+    pub(crate) limit_overrides: Vec<(TestPattern, AnyTestExecutor)>,
+    pub(crate) checker: Option<Checker>,
+    /// Checkers used for tests matching the pattern instead of `checker`,
+    /// `None` means the output is compared with the output file.
+    /// The first matching pattern is used
+    pub(crate) checker_overrides: Vec<(TestPattern, Option<Checker>)>,
+    pub(crate) input_transformer: Option<InputTransformer>,
+    /// Checks the inputs before the program is run on them
+    pub(crate) validator: Option<Validator>,
+    pub(crate) diff_options: DiffOptions,
+    /// Used instead of comparing the output with the output file line by line
+    pub(crate) builtin_checker: Option<BuiltinChecker>,
+    /// The maximum size of the program output in bytes
+    pub(crate) output_limit: u64,
+    /// Used instead of running the program once if every test should be run multiple times
+    pub(crate) benchmark: Option<Benchmark>,
+    /// Reruns tests in which the program timed out or crashed
+    pub(crate) retries: Option<Retries>,
+    /// Runs the program a second time on tests it passed and compares the outputs of both runs
+    pub(crate) check_determinism: bool,
+    pub(crate) time_limit: Duration,
+    /// Time limits used for tests matching the pattern, the first matching pattern is used
+    pub(crate) time_limit_overrides: Vec<(TestPattern, Duration)>,
+    /// The fraction of the time limit above which passed tests are marked as close to the time limit
+    pub(crate) warn_threshold: Option<f64>,
+    /// Collects the feedback written by the checker if it's set
+    pub(crate) feedback: Option<FeedbackReport>,
+    /// The archive the expected outputs are read from instead of the output directory
+    pub(crate) output_archive: Option<TestArchive>,
+}
+
+impl TestRunner {
+    fn executor_for(&self, test_name: &str) -> &AnyTestExecutor {
+        self.limit_overrides.iter()
+            .find(|(pattern, _)| pattern.matches(test_name))
+            .map_or(&self.executor, |(_, executor)| executor)
+    }
+
+    /// Checks the size of the output written to `file` after `start`.
+    /// Sandboxed executors don't stop the program when it exceeds the output limit,
+    /// so the output is checked before it's read into memory
+    fn check_output_size(&self, file: &File, start: u64) -> Result<(), TestError> {
+        let size = file.metadata().expect("Failed to read the output file metadata").len();
+        if size.saturating_sub(start) > self.output_limit {
+            return Err(ProgramError { error: OutputLimitExceeded });
+        }
+        Ok(())
+    }
+
+    fn execute(
+        &self,
+        executor: &AnyTestExecutor,
+        test_name: &str,
+        input_file: &File,
+        output_file: &File,
+    ) -> (ExecutionMetrics, Result<(), ExecutionError>) {
+        let run = || match &self.benchmark {
+            Some(benchmark) => benchmark.run(executor, test_name, input_file, output_file),
+            None => executor.test_to_file(input_file, output_file),
+        };
+        match &self.retries {
+            Some(retries) => retries.run(test_name, input_file, output_file, run),
+            None => run(),
+        }
+    }
+
+    /// Returns the time limit of the test, taking the limits of the manifest into account
+    pub(crate) fn time_limit_for(&self, test_name: &str) -> Duration {
+        self.time_limit_overrides.iter()
+            .find(|(pattern, _)| pattern.matches(test_name))
+            .map_or(self.time_limit, |(_, time_limit)| *time_limit)
+    }
+
+    /// Returns the time limit of the test if the runtime exceeded the warning threshold
+    pub(crate) fn close_to_time_limit(&self, test_name: &str, metrics: &ExecutionMetrics) -> Option<Duration> {
+        let (threshold, time) = (self.warn_threshold?, metrics.time?);
+        let time_limit = self.time_limit_for(test_name);
+        (time > time_limit.mul_f64(threshold)).then_some(time_limit)
+    }
+
+    fn checker_for(&self, test_name: &str) -> Option<&Checker> {
+        self.checker_overrides.iter()
+            .find(|(pattern, _)| pattern.matches(test_name))
+            .map_or(self.checker.as_ref(), |(_, checker)| checker.as_ref())
+    }
+
+    /// Runs a single test.
+    ///
+    /// If `keep_output` is true and the program finished successfully,
+    /// a rewound file containing the program output is returned alongside the result,
+    /// even if the output turned out to be incorrect.
+    pub(crate) fn run_test(&self, input: &Test, keep_output: bool) -> (Result<ExecutionMetrics, TestError>, Option<File>) {
+        let mut kept_output = None;
+        let result = self.run_test_keeping_output(input, (keep_output || self.check_determinism).then_some(&mut kept_output));
+        let result = match (result, &mut kept_output) {
+            (Ok(metrics), Some(output)) if self.check_determinism => self.rerun_and_compare(input, output).map(|()| metrics),
+            (result, _) => result,
+        };
+        (result, kept_output.filter(|_| keep_output))
+    }
+
+    /// Runs the program on the input again and checks that it writes the same output as in the first run.
+    /// `first_output` is rewound afterwards
+    fn rerun_and_compare(&self, input: &Test, first_output: &mut File) -> Result<(), TestError> {
+        check_ctrlc()?;
+        let program_input = get_program_input(self.input_transformer.as_ref(), &input.input_source)?;
+        let mut second_output = create_test_temp_file()?;
+        let (_, result) = self.executor_for(&input.test_name).test_to_file(&program_input, &second_output);
+        check_ctrlc()?;
+
+        if let Err(error) = result {
+            return Err(Nondeterministic { message: format!("The first run finished, but the second one failed:\n{error}") });
+        }
+        self.check_output_size(&second_output, 0)?;
+        second_output.rewind().expect("Failed to rewind memfile");
+        let mut first = vec![];
+        let mut second = vec![];
+        first_output.read_to_end(&mut first).expect("Failed to read the program output");
+        first_output.rewind().expect("Failed to rewind the program output");
+        second_output.read_to_end(&mut second).expect("Failed to read the program output");
+        if first == second {
+            return Ok(());
+        }
+        let first_lines = String::from_utf8_lossy(&first);
+        let second_lines = String::from_utf8_lossy(&second);
+        let message = match first_lines.lines().zip(second_lines.lines()).position(|(first, second)| first != second) {
+            Some(index) => format!(
+                "The outputs of two runs differ at line {}:\nfirst run:  {}\nsecond run: {}",
+                index + 1,
+                first_lines.lines().nth(index).unwrap_or_default(),
+                second_lines.lines().nth(index).unwrap_or_default(),
+            ),
+            None if first_lines.lines().count() == second_lines.lines().count() => {
+                "The outputs of two runs differ only in line endings".to_owned()
+            }
+            None => format!(
+                "The first run wrote {}, the second one {}",
+                pluralize_lines(first_lines.lines().count()),
+                pluralize_lines(second_lines.lines().count()),
+            ),
+        };
+        Err(Nondeterministic { message })
+    }
+
+    fn run_test_keeping_output(
+        &self,
+        input: &Test,
+        kept_output: Option<&mut Option<File>>,
+    ) -> Result<ExecutionMetrics, TestError> {
+        let executor = self.executor_for(&input.test_name);
+        if let Some(validator) = &self.validator {
+            check_ctrlc()?;
+            validator.validate(&input.input_source)?;
+        }
+        match &self.action_type {
+            ActionType::Generate { output_directory, output_names } => {
+                check_ctrlc()?;
+
+                let output_file_path = output_directory.join(output_names.generated_file_name(&input.test_name));
+                // Tests in subdirectories of the input directory are generated into the same subdirectories
+                fs::create_dir_all(output_file_path.parent().expect("The output file should have a parent"))
+                    .expect("Failed to create output directory");
+                let file = File::create(&output_file_path).expect("Failed to create output file");
+                check_ctrlc()?;
+
+                let program_input = get_program_input(self.input_transformer.as_ref(), &input.input_source)?;
+                check_ctrlc()?;
+
+                let (metrics, result) = self.execute(executor, &input.test_name, &program_input, &file);
+                check_ctrlc()?;
+
+                result.map_err(|error| ProgramError { error })?;
+                self.check_output_size(&file, 0)?;
+                if let Some(kept_output) = kept_output {
+                    *kept_output = Some(File::open(&output_file_path).expect("Failed to open output file"));
+                }
+                Ok(metrics)
+            }
+            ActionType::SimpleCompare { output_directory, output_names }
+            | ActionType::Checker { output_directory, output_names, .. } => {
+                let output_file_path = output_names.find_file(output_directory, &input.test_name);
+                if let Some(checker) = self.checker_for(&input.test_name) {
+                    return self.run_with_checker(executor, checker, input, &output_file_path, kept_output);
+                }
+                check_ctrlc()?;
+
+                let program_input = get_program_input(self.input_transformer.as_ref(), &input.input_source)?;
+                check_ctrlc()?;
+
+                let mut output = create_test_temp_file()?;
+                let (metrics, result) = self.execute(executor, &input.test_name, &program_input, &output);
+                check_ctrlc()?;
+
+                result.map_err(|error| ProgramError { error })?;
+                output.rewind().expect("Failed to rewind memfile");
+                self.check_output_size(&output, 0)?;
+                if let Some(kept_output) = kept_output {
+                    *kept_output = Some(copy_to_temp(&output, 0)?);
+                }
+                match &self.output_archive {
+                    None => compare_output(&output_file_path, output, &self.diff_options, self.builtin_checker)?,
+                    Some(archive) => {
+                        let expected_output = output_names.file_names(&input.test_name)
+                            .find_map(|file_name| archive.read_text_file(&file_name).transpose())
+                            .ok_or(NoOutputFile)?
+                            .map_err(|error| match error {
+                                InputError::TempFile(error) => error.into(),
+                                InputError::InvalidArchiveEntry(message) => {
+                                    CheckerError { error: ExecutionError::SandboxError(message), stderr: None }
+                                }
+                            })?;
+                        compare_with_expected(&expected_output, output, &self.diff_options, self.builtin_checker)?;
+                    }
+                }
+                check_ctrlc()?;
+
+                Ok(metrics)
+            }
+        }
+    }
+
+    fn run_with_checker(
+        &self,
+        executor: &AnyTestExecutor,
+        checker: &Checker,
+        input: &Test,
+        answer_path: &Path,
+        kept_output: Option<&mut Option<File>>,
+    ) -> Result<ExecutionMetrics, TestError> {
+        if checker.protocol != CheckerProtocol::Toster {
+            return self.run_with_file_checker(executor, checker, input, answer_path, kept_output);
+        }
+        check_ctrlc()?;
+
+        let mut checker_input = Checker::prepare_checker_input(&input.input_source)?;
+        let output_start = checker_input.stream_position().expect("Failed to read memfile position");
+        check_ctrlc()?;
+
+        let program_input = get_program_input(self.input_transformer.as_ref(), &input.input_source)?;
+        check_ctrlc()?;
+
+        let (metrics, result) = self.execute(executor, &input.test_name, &program_input, &checker_input);
+        check_ctrlc()?;
+
+        result.map_err(|error| ProgramError { error })?;
+        self.check_output_size(&checker_input, output_start)?;
+        if let Some(kept_output) = kept_output {
+            *kept_output = Some(copy_to_temp(&checker_input, output_start)?);
+        }
+        match &self.feedback {
+            None => checker.check(checker_input)?,
+            Some(report) => {
+                let (result, feedback) = checker.check_with_feedback(checker_input);
+                Self::add_feedback(report, &input.test_name, &result, feedback);
+                result?;
+            }
+        }
+        check_ctrlc()?;
+
+        Ok(metrics)
+    }
+
+    fn add_feedback(report: &FeedbackReport, test_name: &str, result: &Result<(), TestError>, feedback: Option<String>) {
+        if let Some(feedback) = feedback {
+            let verdict = result.as_ref().map_or_else(TestError::verdict, |()| "correct");
+            report.add(test_name, verdict, feedback);
+        }
+    }
+
+    /// Runs the program and checks its output with a checker receiving the paths of the input,
+    /// the output and the answer file. Testlib checkers don't write feedback for students,
+    /// so the feedback is only collected from checkers using the files protocol
+    fn run_with_file_checker(
+        &self,
+        executor: &AnyTestExecutor,
+        checker: &Checker,
+        input: &Test,
+        answer_path: &Path,
+        kept_output: Option<&mut Option<File>>,
+    ) -> Result<ExecutionMetrics, TestError> {
+        check_ctrlc()?;
+
+        let program_input = get_program_input(self.input_transformer.as_ref(), &input.input_source)?;
+        check_ctrlc()?;
+
+        let output = create_test_temp_file()?;
+        let (metrics, result) = self.execute(executor, &input.test_name, &program_input, &output);
+        check_ctrlc()?;
+
+        result.map_err(|error| ProgramError { error })?;
+        self.check_output_size(&output, 0)?;
+        if let Some(kept_output) = kept_output {
+            *kept_output = Some(copy_to_temp(&output, 0)?);
+        }
+        let (result, feedback) = checker.check_files(&input.input_source, &output, answer_path, self.feedback.is_some());
+        if let Some(report) = &self.feedback {
+            Self::add_feedback(report, &input.test_name, &result, feedback);
+        }
+        result?;
+        check_ctrlc()?;
+
+        Ok(metrics)
+    }
+}
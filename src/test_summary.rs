@@ -1,31 +1,56 @@
 use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::time::{Duration, Instant};
-use colored::Color::{Blue, Green, Red, Yellow};
-use colored::{Color, Colorize};
-use crate::generic_utils::OptionExt;
+use colored::Color::{Blue, Green, Magenta, Red, Yellow};
+use colored::Color;
+use crate::theme;
 use crate::test_errors::{ExecutionError, ExecutionMetrics, TestError};
-use crate::test_errors::TestError::{Cancelled, CheckerError, Incorrect, NoOutputFile, ProgramError};
+use crate::test_errors::TestError::{Cancelled, CheckerError, Custom, DependencyFailed, Incorrect, InputTransformError, InvalidInput, NoOutputFile, Nondeterministic, NotStarted, ProgramError};
 
 pub(crate) struct TestSummary {
     pub(crate) generate_mode: bool,
     pub(crate) start_time: Instant,
 
     pub(crate) total: usize,
+    /// The number of failed tests after which no new tests are started
+    max_failures: Option<usize>,
+    /// The time after which the remaining tests are skipped instead of being started
+    total_timeout: Option<Duration>,
     pub(crate) processed: usize,
     pub(crate) success: usize,
     pub(crate) incorrect: usize,
     pub(crate) timed_out: usize,
     pub(crate) invalid_output: usize,
     pub(crate) memory_limit_exceeded: usize,
+    pub(crate) output_limit_exceeded: usize,
     pub(crate) runtime_error: usize,
-    pub(crate) sio2jail_error: usize,
+    pub(crate) memory_error: usize,
+    pub(crate) sandbox_error: usize,
     pub(crate) checker_error: usize,
+    pub(crate) input_transform_error: usize,
+    /// The number of tests whose input was rejected by the validator
+    pub(crate) invalid_input: usize,
     pub(crate) no_output_file: usize,
+    /// The number of tests in which the program wrote different outputs in two runs
+    pub(crate) nondeterministic: usize,
+    pub(crate) dependency_failed: usize,
+    /// The number of tests skipped because the total timeout was exceeded
+    pub(crate) not_started: usize,
+    /// The number of tests with each custom verdict and the verdict color, by label
+    pub(crate) custom: BTreeMap<String, (usize, Color)>,
 
     test_errors: Vec<(String, TestError)>,
+    /// The end of the program's stderr in failed tests, by test name
+    stderr: HashMap<String, String>,
+    /// The names, runtimes and peak memory use of the successful tests
+    successful_tests: Vec<(String, Option<Duration>, Option<u64>)>,
 
     pub(crate) slowest_test: Option<(Duration, String)>,
     pub(crate) most_memory_used: Option<(u64, String)>,
+    /// The measured runtimes of all successful tests
+    runtimes: Vec<Duration>,
+    /// Successful tests whose runtime exceeded the warning threshold, with the runtime and the time limit
+    close_to_time_limit: Vec<(String, Duration, Duration)>,
 }
 
 struct CountPart<'a> {
@@ -75,27 +100,60 @@ impl TestSummary {
             start_time: Instant::now(),
 
             total: total_count,
+            max_failures: None,
+            total_timeout: None,
             processed: 0,
             incorrect: 0,
             timed_out: 0,
             invalid_output: 0,
             memory_limit_exceeded: 0,
+            output_limit_exceeded: 0,
             runtime_error: 0,
-            sio2jail_error: 0,
+            memory_error: 0,
+            sandbox_error: 0,
             checker_error: 0,
+            input_transform_error: 0,
+            invalid_input: 0,
             no_output_file: 0,
+            nondeterministic: 0,
+            dependency_failed: 0,
+            not_started: 0,
+            custom: BTreeMap::new(),
             success: 0,
 
             test_errors: vec![],
+            stderr: HashMap::new(),
+            successful_tests: vec![],
 
             slowest_test: None,
             most_memory_used: None,
+            runtimes: vec![],
+            close_to_time_limit: vec![],
         }
     }
 
+    pub(crate) fn with_max_failures(self, max_failures: Option<usize>) -> Self {
+        TestSummary { max_failures, ..self }
+    }
+
+    pub(crate) fn with_total_timeout(self, total_timeout: Option<Duration>) -> Self {
+        TestSummary { total_timeout, ..self }
+    }
+
+    /// Returns true if testing took longer than the total timeout, so the remaining tests should be skipped
+    pub(crate) fn total_timeout_reached(&self) -> bool {
+        self.total_timeout.is_some_and(|total_timeout| self.start_time.elapsed() >= total_timeout)
+    }
+
+    /// Returns true if so many tests failed that no new tests should be started
+    pub(crate) fn failure_limit_reached(&self) -> bool {
+        self.max_failures.is_some_and(|max_failures| self.processed - self.success >= max_failures)
+    }
+
     pub(crate) fn add_success(&mut self, metrics: &ExecutionMetrics, test_name: &str) {
         self.processed += 1;
         self.success += 1;
+        self.successful_tests.push((test_name.to_owned(), metrics.time, metrics.memory_kibibytes));
         self.add_metrics(metrics, test_name);
     }
 
@@ -104,26 +162,55 @@ impl TestSummary {
             Incorrect { .. } => { self.incorrect += 1 }
             ProgramError { error: ExecutionError::TimedOut, .. } => { self.timed_out += 1 }
             ProgramError { error: ExecutionError::MemoryLimitExceeded, .. } => { self.memory_limit_exceeded += 1 }
+            ProgramError { error: ExecutionError::OutputLimitExceeded, .. } => { self.output_limit_exceeded += 1 }
             ProgramError { error: ExecutionError::RuntimeError(_), .. } => { self.runtime_error += 1 }
-            ProgramError { error: ExecutionError::Sio2jailError(_), .. } => { self.sio2jail_error += 1 }
+            ProgramError { error: ExecutionError::MemoryError(_), .. } => { self.memory_error += 1 }
+            ProgramError { error: ExecutionError::SandboxError(_), .. } => { self.sandbox_error += 1 }
             ProgramError { error: ExecutionError::PipeError | ExecutionError::OutputNotUtf8 } => { self.invalid_output += 1 }
             ProgramError { error: ExecutionError::IncorrectCheckerFormat(_), .. } | CheckerError { .. } => { self.checker_error += 1 }
-            NoOutputFile { .. } => { self.no_output_file += 1 }
+            InputTransformError { .. } => { self.input_transform_error += 1 }
+            InvalidInput { .. } => { self.invalid_input += 1 }
+            NoOutputFile => { self.no_output_file += 1 }
+            Nondeterministic { .. } => { self.nondeterministic += 1 }
+            DependencyFailed { .. } => { self.dependency_failed += 1 }
+            NotStarted => { self.not_started += 1 }
+            Custom { label, color, .. } => { self.custom.entry(label.clone()).or_insert((0, *color)).0 += 1 }
             Cancelled => return,
         }
         self.processed += 1;
         self.test_errors.push((test_name, error));
     }
 
+    pub(crate) fn add_stderr(&mut self, test_name: &str, stderr: String) {
+        self.stderr.insert(test_name.to_owned(), stderr);
+    }
+
+    pub(crate) fn add_close_to_time_limit(&mut self, test_name: &str, time: Duration, time_limit: Duration) {
+        self.close_to_time_limit.push((test_name.to_owned(), time, time_limit));
+    }
+
+    /// Formats the list of tests close to the time limit, or returns `None` if there are none
+    pub(crate) fn format_close_to_time_limit(&mut self) -> Option<String> {
+        if self.close_to_time_limit.is_empty() {
+            return None;
+        }
+        self.close_to_time_limit.sort_by(|a, b| human_sort::compare(&a.0, &b.0));
+        Some(self.close_to_time_limit.iter()
+            .map(|(test_name, time, time_limit)| format!("{test_name} ({:.3}s of {:.3}s)", time.as_secs_f64(), time_limit.as_secs_f64()))
+            .collect::<Vec<_>>()
+            .join(", "))
+    }
+
     fn add_metrics(&mut self, metrics: &ExecutionMetrics, test_name: &str) {
         if let Some(new_time) = &metrics.time {
-            if self.slowest_test.is_none_or(|(time, _)| new_time > time) {
+            self.runtimes.push(*new_time);
+            if self.slowest_test.as_ref().is_none_or(|(time, _)| new_time > time) {
                 self.slowest_test = Some((*new_time, test_name.to_owned()));
             }
         }
 
         if let Some(new_memory) = &metrics.memory_kibibytes {
-            if self.most_memory_used.is_none_or(|(memory, _)| new_memory > memory) {
+            if self.most_memory_used.as_ref().is_none_or(|(memory, _)| new_memory > memory) {
                 self.most_memory_used = Some((*new_memory, test_name.to_owned()));
             }
         }
@@ -136,25 +223,64 @@ impl TestSummary {
             CountPart::new(self.timed_out, "timed out"),
             CountPart::new(self.invalid_output, "invalid output").with_plural("invalid outputs"),
             CountPart::new(self.memory_limit_exceeded, "out of memory"),
+            CountPart::new(self.output_limit_exceeded, "output limit exceeded"),
             CountPart::new(self.runtime_error, "runtime error").with_plural("runtime errors"),
+            CountPart::new(self.memory_error, "memory error").with_plural("memory errors"),
             CountPart::new(self.no_output_file, "without output file"),
-            CountPart::new(self.sio2jail_error, "sio2jail error").with_plural("sio2jail errors"),
+            CountPart::new(self.nondeterministic, "nondeterministic").with_color(Magenta),
+            CountPart::new(self.sandbox_error, "sandbox error").with_plural("sandbox errors"),
             CountPart::new(self.checker_error, "checker error").with_plural("checker errors").with_color(Blue),
-            CountPart::new(if show_not_finished { self.total - self.processed } else { 0 }, "not finished").with_color(Yellow),
+            CountPart::new(self.input_transform_error, "input transform error").with_plural("input transform errors").with_color(Blue),
+            CountPart::new(self.invalid_input, "invalid input").with_plural("invalid inputs").with_color(Yellow),
+            CountPart::new(self.dependency_failed + self.not_started, "skipped").with_color(Yellow),
         ]
             .into_iter()
+            .chain(self.custom.iter().map(|(label, (count, color))| CountPart::new(*count, label).with_color(*color)))
+            .chain([
+                CountPart::new(if show_not_finished { self.total - self.processed } else { 0 }, "not finished").with_color(Yellow),
+            ])
             .filter(|part| part.display_empty || part.count > 0)
             .map(|part| {
-                format!("{} {}", part.count, part.get_text()).color(part.color).to_string()
+                theme::paint(part.singular, &format!("{} {}", part.count, part.get_text()), Some(part.color)).to_string()
             })
             .collect::<Vec<String>>()
             .join(", ")
     }
 
+    /// Formats the median, 90th percentile and maximum of the runtimes of successful tests
+    /// and their total, or returns `None` if no runtimes were measured
+    pub(crate) fn format_runtime_statistics(&self) -> Option<String> {
+        if self.runtimes.is_empty() {
+            return None;
+        }
+        let mut runtimes = self.runtimes.clone();
+        runtimes.sort_unstable();
+        let percentile = |percent: usize| runtimes[(runtimes.len() * percent).div_ceil(100).saturating_sub(1)];
+        Some(format!(
+            "p50 {:.3}s, p90 {:.3}s, max {:.3}s, total {:.3}s",
+            percentile(50).as_secs_f64(),
+            percentile(90).as_secs_f64(),
+            runtimes[runtimes.len() - 1].as_secs_f64(),
+            runtimes.iter().sum::<Duration>().as_secs_f64(),
+        ))
+    }
+
+    pub(crate) fn successful_tests(&self) -> &[(String, Option<Duration>, Option<u64>)] {
+        &self.successful_tests
+    }
+
     pub(crate) fn get_errors(&mut self) -> &Vec<(String, TestError)> {
         self.test_errors.sort_by(|a, b| -> Ordering {
             human_sort::compare(&a.0, &b.0)
         });
         &self.test_errors
     }
+
+    /// Returns the sorted errors together with the end of the program's stderr in the test, if it was captured
+    pub(crate) fn get_errors_with_stderr(&mut self) -> impl Iterator<Item = (&String, &TestError, Option<&str>)> {
+        self.get_errors();
+        let test_summary = &*self;
+        test_summary.test_errors.iter()
+            .map(|(test_name, error)| (test_name, error, test_summary.stderr.get(test_name).map(String::as_str)))
+    }
 }
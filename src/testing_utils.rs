@@ -1,76 +1,124 @@
-use std::cmp::max;
-use std::fs;
 use std::io::{Read, read_to_string};
 use std::path::Path;
-use comfy_table::{Attribute, Cell, Color, Table};
-use comfy_table::ContentArrangement::Dynamic;
-use terminal_size::{Height, Width};
+use colored::Colorize;
+use crate::builtin_checker::BuiltinChecker;
+use crate::encoding::read_text_file;
+use crate::diff_renderer::DiffOptions;
+use crate::output;
 use crate::test_errors::TestError;
 use crate::test_errors::TestError::{Incorrect, NoOutputFile};
 
-pub(crate) fn compare_output(expected_output_path: &Path, actual_output: impl Read) -> Result<(), TestError> {
+pub(crate) fn compare_output(
+    expected_output_path: &Path,
+    actual_output: impl Read,
+    diff_options: &DiffOptions,
+    builtin_checker: Option<BuiltinChecker>,
+) -> Result<(), TestError> {
     if !expected_output_path.is_file() {
         return Err(NoOutputFile);
     }
-    let expected_output = fs::read_to_string(expected_output_path).expect("Failed to read output file");
+    output::debug(&format!("Comparing the output with {}", expected_output_path.display()));
+    compare_with_expected(&read_text_file(expected_output_path)?, actual_output, diff_options, builtin_checker)
+}
+
+/// Compares the output with the already read contents of the output file,
+/// using the built-in checker instead of comparing the lines if it's set
+pub(crate) fn compare_with_expected(
+    expected_output: &str,
+    actual_output: impl Read,
+    diff_options: &DiffOptions,
+    builtin_checker: Option<BuiltinChecker>,
+) -> Result<(), TestError> {
     let actual_output = read_to_string(actual_output).expect("Failed to read actual input");
+    if let Some(builtin_checker) = builtin_checker {
+        return builtin_checker.check(expected_output, &actual_output)
+            .map_err(|error| Incorrect { error: format!("Incorrect output: {error}").red().to_string() });
+    }
 
-    let expected_output = split_trim_end(&expected_output);
+    let expected_output = split_trim_end(expected_output);
     let actual_output = split_trim_end(&actual_output);
 
     if actual_output != expected_output {
-        return Err(Incorrect { error: generate_diff(&expected_output, &actual_output) });
+        let hint = explain_mismatch(&expected_output, &actual_output);
+        return Err(Incorrect { error: diff_options.render(&expected_output, &actual_output, hint) });
     }
     Ok(())
 }
 
-fn split_trim_end(to_split: &str) -> Vec<&str> {
-    let mut res = to_split
-        .split('\n')
-        .map(str::trim_end)
-        .collect::<Vec<&str>>();
+pub(crate) fn pluralize_lines(count: usize) -> String {
+    if count == 1 { "1 line".to_owned() } else { format!("{count} lines") }
+}
 
-    while res.last().is_some_and(|last| last.trim().is_empty()) {
-        res.pop();
+/// Classifies the difference between the expected and actual output,
+/// returning a short hint about the likely cause of the wrong answer
+fn explain_mismatch(expected_split: &[&str], actual_split: &[&str]) -> Option<String> {
+    let tokens = |lines: &[&str]| -> Vec<String> {
+        lines.iter().flat_map(|line| line.split_whitespace()).map(str::to_owned).collect()
+    };
+    if tokens(expected_split) == tokens(actual_split) {
+        return Some("the output contains the correct values, but with different whitespace or line breaks".to_owned());
     }
 
-    res
-}
-
-fn generate_diff(expected_split: &[&str], actual_split: &[&str]) -> String {
-    let (Width(w), Height(_)) = terminal_size::terminal_size().unwrap_or((Width(40), Height(0)));
-    let mut table = Table::new();
-    table.set_content_arrangement(Dynamic).set_width(w).set_header(vec![
-        Cell::new("Line").add_attribute(Attribute::Bold),
-        Cell::new("Output file").add_attribute(Attribute::Bold).fg(Color::Green),
-        Cell::new("Your program's output").add_attribute(Attribute::Bold).fg(Color::Red),
-    ]);
+    let (mut expected_sorted, mut actual_sorted) = (expected_split.to_vec(), actual_split.to_vec());
+    expected_sorted.sort_unstable();
+    actual_sorted.sort_unstable();
+    if expected_sorted == actual_sorted {
+        return Some("the output contains the correct lines, but in a different order".to_owned());
+    }
 
-    let mut row_count = 0;
-    for i in 0..max(expected_split.len(), actual_split.len()) {
-        let expected_line = expected_split.get(i).unwrap_or(&"");
-        let actual_line = actual_split.get(i).unwrap_or(&"");
+    if expected_split.len() != actual_split.len() {
+        return Some(if actual_split.len() < expected_split.len() && expected_split.starts_with(actual_split) {
+            format!("the output is incomplete, {} missing at the end", pluralize_lines(expected_split.len() - actual_split.len()))
+        } else if expected_split.len() < actual_split.len() && actual_split.starts_with(expected_split) {
+            format!("the output has {} too many at the end", pluralize_lines(actual_split.len() - expected_split.len()))
+        } else {
+            format!("the output has {} instead of {}", pluralize_lines(actual_split.len()), pluralize_lines(expected_split.len()))
+        });
+    }
 
-        if expected_line != actual_line {
-            table.add_row(vec![
-                Cell::new(i + 1),
-                Cell::new(expected_line).fg(Color::Green),
-                Cell::new(actual_line).fg(Color::Red),
-            ]);
+    explain_token_mismatch(expected_split, actual_split)
+}
 
-            row_count += 1;
+/// Looks for patterns in the differing tokens of outputs with the same number of lines
+fn explain_token_mismatch(expected_split: &[&str], actual_split: &[&str]) -> Option<String> {
+    let mut differing_tokens = vec![];
+    for (expected_line, actual_line) in expected_split.iter().zip(actual_split) {
+        let expected_tokens: Vec<&str> = expected_line.split_whitespace().collect();
+        let actual_tokens: Vec<&str> = actual_line.split_whitespace().collect();
+        if expected_tokens.len() != actual_tokens.len() {
+            return None;
         }
+        differing_tokens.extend(
+            expected_tokens.into_iter().zip(actual_tokens).filter(|(expected, actual)| expected != actual)
+        );
+    }
 
-        if row_count >= 99 {
-            table.add_row(vec![
-                Cell::new("..."),
-                Cell::new("..."),
-                Cell::new("..."),
-            ]);
+    if differing_tokens.is_empty() {
+        return None;
+    }
+    if differing_tokens.iter().all(|(expected, actual)| {
+        matches!((expected.parse::<i128>(), actual.parse::<i128>()), (Ok(expected), Ok(actual)) if expected.abs_diff(actual) == 1)
+    }) {
+        return Some("some numbers differ by exactly one, look for an off-by-one error".to_owned());
+    }
+    if differing_tokens.iter().all(|(expected, actual)| {
+        let (Ok(expected), Ok(actual)) = (expected.parse::<f64>(), actual.parse::<f64>()) else { return false };
+        (expected - actual).abs() <= 1e-6 * expected.abs().max(1.0)
+    }) {
+        return Some("some floating-point numbers differ only slightly, check the precision and formatting".to_owned());
+    }
+    None
+}
 
-            break;
-        }
+fn split_trim_end(to_split: &str) -> Vec<&str> {
+    let mut res = to_split
+        .split('\n')
+        .map(str::trim_end)
+        .collect::<Vec<&str>>();
+
+    while res.last().is_some_and(|last| last.trim().is_empty()) {
+        res.pop();
     }
 
-    table.to_string().replace('\r', "")
+    res
 }
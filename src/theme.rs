@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::io::IsTerminal;
+use std::str::FromStr;
+use std::sync::Mutex;
+use clap::ValueEnum;
+use colored::{Color, ColoredString, Colorize};
+use serde::{Deserialize, Deserializer};
+
+/// When toster uses colors in its output
+#[allow(clippy::doc_markdown)] // Documentation comments are shown in --help
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ColorChoice {
+    /// Colors are used if the output is a terminal and the NO_COLOR environment variable isn't set
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub(crate) fn apply(self) {
+        match self {
+            ColorChoice::Auto => {
+                if !io::stdout().is_terminal() {
+                    colored::control::set_override(false);
+                }
+            }
+            ColorChoice::Always => colored::control::set_override(true),
+            ColorChoice::Never => colored::control::set_override(false),
+        }
+    }
+}
+
+/// The color and attributes of a verdict, written in the manifest as a list of words like `"magenta bold"`
+#[derive(Clone, Copy, Default, Debug)]
+pub(crate) struct ThemeStyle {
+    color: Option<Color>,
+    bold: bool,
+    underline: bool,
+}
+
+impl FromStr for ThemeStyle {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        let mut style = ThemeStyle::default();
+        for word in value.split_whitespace() {
+            match word {
+                "bold" => style.bold = true,
+                "underline" => style.underline = true,
+                _ => style.color = Some(
+                    Color::from_str(word).map_err(|()| format!("\"{word}\" is not a color or an attribute"))?
+                ),
+            }
+        }
+        Ok(style)
+    }
+}
+
+impl<'de> Deserialize<'de> for ThemeStyle {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// The styles of verdicts overridden in the manifest, by the verdict name (like "wrong answer")
+static THEME: Mutex<BTreeMap<String, ThemeStyle>> = Mutex::new(BTreeMap::new());
+
+pub(crate) fn set_theme(theme: BTreeMap<String, ThemeStyle>) {
+    *THEME.lock().expect("Failed to lock the theme") = theme;
+}
+
+fn style_of(verdict: &str) -> Option<ThemeStyle> {
+    THEME.lock().expect("Failed to lock the theme").get(verdict).copied()
+}
+
+/// Returns the color of a verdict, which is `default` unless it's overridden by the theme
+pub(crate) fn verdict_color(verdict: &str, default: Option<Color>) -> Option<Color> {
+    style_of(verdict).and_then(|style| style.color).or(default)
+}
+
+/// Styles text shown for a verdict with the color and attributes of the verdict in the theme,
+/// or with the `default` color if the theme doesn't override it
+pub(crate) fn paint(verdict: &str, text: &str, default: Option<Color>) -> ColoredString {
+    let style = style_of(verdict).unwrap_or_default();
+    let mut painted = match style.color.or(default) {
+        Some(color) => text.color(color),
+        None => text.normal(),
+    };
+    if style.bold {
+        painted = painted.bold();
+    }
+    if style.underline {
+        painted = painted.underline();
+    }
+    painted
+}
@@ -0,0 +1,108 @@
+use clap::ValueEnum;
+use which::which;
+
+/// A C++ compiler toolchain with a default compile command
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Toolchain {
+    /// GCC (g++)
+    Gcc,
+    /// Clang (clang++)
+    Clang,
+    /// MinGW-w64 GCC, which builds Windows executables
+    Mingw,
+    /// The Microsoft Visual C++ compiler (cl), which has to be run from a Developer Command Prompt
+    Msvc,
+}
+
+/// A preset of compile flags, used instead of writing a whole --compile-command
+#[allow(clippy::doc_markdown)] // Documentation comments are shown in --help
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CompileProfile {
+    /// Optimized build, as used by the judges
+    #[default]
+    Release,
+    /// Unoptimized build with debug information and, with GCC, bounds-checked standard library containers
+    Debug,
+    /// AddressSanitizer and UndefinedBehaviorSanitizer, which detect invalid memory accesses and undefined behavior
+    Asan,
+    /// UndefinedBehaviorSanitizer alone, which is much faster than AddressSanitizer
+    Ubsan,
+}
+
+impl CompileProfile {
+    /// Returns true if programs built with the profile write a sanitizer report to stderr when they find an error
+    pub(crate) fn has_sanitizer(self) -> bool {
+        matches!(self, CompileProfile::Asan | CompileProfile::Ubsan)
+    }
+}
+
+/// The toolchains in the order they're tried when no compiler is chosen.
+/// On macOS g++ is usually an alias of Clang, which doesn't support `-static`
+#[cfg(target_os = "windows")]
+const PREFERRED_TOOLCHAINS: [Toolchain; 4] = [Toolchain::Gcc, Toolchain::Mingw, Toolchain::Clang, Toolchain::Msvc];
+#[cfg(target_os = "macos")]
+const PREFERRED_TOOLCHAINS: [Toolchain; 2] = [Toolchain::Clang, Toolchain::Gcc];
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const PREFERRED_TOOLCHAINS: [Toolchain; 2] = [Toolchain::Gcc, Toolchain::Clang];
+
+impl Toolchain {
+    pub(crate) fn compiler_name(self) -> &'static str {
+        match self {
+            Toolchain::Gcc => "g++",
+            Toolchain::Clang => "clang++",
+            Toolchain::Mingw => "x86_64-w64-mingw32-g++",
+            Toolchain::Msvc => "cl",
+        }
+    }
+
+    /// The compile command used if --compile-command isn't provided, or `None` if the toolchain doesn't support the profile.
+    /// <IN> gets replaced with the path to the source code file, <OUT> is the executable output location.
+    ///
+    /// Sanitized executables aren't linked statically, since the sanitizer runtimes don't support it
+    pub(crate) fn compile_command(self, profile: CompileProfile) -> Option<&'static str> {
+        Some(match (self, profile) {
+            (Toolchain::Gcc, CompileProfile::Release) => "g++ -std=c++20 -O3 -static <IN> -o <OUT>",
+            (Toolchain::Gcc, CompileProfile::Debug) => "g++ -std=c++20 -O0 -g -D_GLIBCXX_DEBUG <IN> -o <OUT>",
+            (Toolchain::Gcc, CompileProfile::Asan) => "g++ -std=c++20 -O1 -g -fno-omit-frame-pointer -fsanitize=address,undefined -fno-sanitize-recover=undefined <IN> -o <OUT>",
+            (Toolchain::Gcc, CompileProfile::Ubsan) => "g++ -std=c++20 -O1 -g -fsanitize=undefined -fno-sanitize-recover=undefined <IN> -o <OUT>",
+            (Toolchain::Clang, CompileProfile::Release) => "clang++ -std=c++20 -O3 <IN> -o <OUT>",
+            (Toolchain::Clang, CompileProfile::Debug) => "clang++ -std=c++20 -O0 -g <IN> -o <OUT>",
+            (Toolchain::Clang, CompileProfile::Asan) => "clang++ -std=c++20 -O1 -g -fno-omit-frame-pointer -fsanitize=address,undefined -fno-sanitize-recover=undefined <IN> -o <OUT>",
+            (Toolchain::Clang, CompileProfile::Ubsan) => "clang++ -std=c++20 -O1 -g -fsanitize=undefined -fno-sanitize-recover=undefined <IN> -o <OUT>",
+            (Toolchain::Mingw, CompileProfile::Release) => "x86_64-w64-mingw32-g++ -std=c++20 -O3 -static <IN> -o <OUT>",
+            (Toolchain::Mingw, CompileProfile::Debug) => "x86_64-w64-mingw32-g++ -std=c++20 -O0 -g -D_GLIBCXX_DEBUG -static <IN> -o <OUT>",
+            (Toolchain::Msvc, CompileProfile::Release) => "cl /nologo /std:c++20 /O2 /EHsc <IN> /Fe<OUT> /Fo<OUT>.obj",
+            (Toolchain::Msvc, CompileProfile::Debug) => "cl /nologo /std:c++20 /Od /Zi /EHsc <IN> /Fe<OUT> /Fo<OUT>.obj",
+            (Toolchain::Msvc, CompileProfile::Asan) => "cl /nologo /std:c++20 /Od /Zi /EHsc /fsanitize=address <IN> /Fe<OUT> /Fo<OUT>.obj",
+            (Toolchain::Mingw, CompileProfile::Asan | CompileProfile::Ubsan) | (Toolchain::Msvc, CompileProfile::Ubsan) => return None,
+        })
+    }
+
+    pub(crate) fn is_available(self) -> bool {
+        which(self.compiler_name()).is_ok()
+    }
+
+    /// Returns the available toolchains, in the order of preference on this platform
+    pub(crate) fn detect() -> Vec<Toolchain> {
+        PREFERRED_TOOLCHAINS.into_iter().filter(|toolchain| toolchain.is_available()).collect()
+    }
+
+    /// Returns the compile command of the chosen toolchain, or of the preferred available toolchain if none was chosen.
+    /// If no toolchain is available, the GCC command is used, so testing executables still works
+    pub(crate) fn resolve_compile_command(chosen: Option<Toolchain>, profile: CompileProfile) -> Result<String, String> {
+        let toolchain = match chosen {
+            Some(toolchain) if !toolchain.is_available() => {
+                return Err(format!("The compiler \"{}\" could not be found", toolchain.compiler_name()));
+            }
+            Some(toolchain) => toolchain,
+            None => Self::detect().first().copied().unwrap_or(Toolchain::Gcc),
+        };
+        toolchain.compile_command(profile)
+            .map(str::to_owned)
+            .ok_or_else(|| format!(
+                "The compiler \"{}\" doesn't support the {} profile",
+                toolchain.compiler_name(),
+                profile.to_possible_value().expect("Profiles are never skipped").get_name(),
+            ))
+    }
+}
@@ -0,0 +1,235 @@
+use std::io;
+use std::time::Duration;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use crate::generic_utils::strip_ansi_codes;
+use crate::test_errors::TestError;
+use crate::test_summary::TestSummary;
+use crate::theme;
+
+/// A test shown in the results browser
+struct Entry {
+    test_name: String,
+    verdict: String,
+    color: Color,
+    time: Option<Duration>,
+    details: String,
+}
+
+/// Which tests are shown in the list
+#[derive(PartialEq)]
+enum Filter {
+    All,
+    Failed,
+    Verdict(String),
+}
+
+impl Filter {
+    fn matches(&self, entry: &Entry, success_verdict: &str) -> bool {
+        match self {
+            Filter::All => true,
+            Filter::Failed => entry.verdict != success_verdict,
+            Filter::Verdict(verdict) => entry.verdict == *verdict,
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Filter::All => "all tests",
+            Filter::Failed => "failed tests",
+            Filter::Verdict(verdict) => verdict,
+        }
+    }
+}
+
+fn convert_color(color: colored::Color) -> Color {
+    match color {
+        colored::Color::Black => Color::Black,
+        colored::Color::Red | colored::Color::BrightRed => Color::Red,
+        colored::Color::Green | colored::Color::BrightGreen => Color::Green,
+        colored::Color::Yellow | colored::Color::BrightYellow => Color::Yellow,
+        colored::Color::Blue | colored::Color::BrightBlue => Color::Blue,
+        colored::Color::Magenta | colored::Color::BrightMagenta => Color::Magenta,
+        colored::Color::Cyan | colored::Color::BrightCyan => Color::Cyan,
+        colored::Color::TrueColor { r, g, b } => Color::Rgb(r, g, b),
+        _ => Color::Reset,
+    }
+}
+
+/// Returns the color of a verdict in the theme, or `default` if the theme doesn't override it
+fn themed_color(verdict: &str, default: colored::Color) -> Color {
+    convert_color(theme::verdict_color(verdict, Some(default)).unwrap_or(default))
+}
+
+fn verdict_color(error: &TestError) -> Color {
+    let default = match error {
+        TestError::Incorrect { .. } | TestError::ProgramError { .. } | TestError::NoOutputFile => colored::Color::Red,
+        TestError::CheckerError { .. } | TestError::InputTransformError { .. } => colored::Color::Blue,
+        TestError::InvalidInput { .. } | TestError::DependencyFailed { .. } | TestError::NotStarted | TestError::Cancelled => colored::Color::Yellow,
+        TestError::Nondeterministic { .. } => colored::Color::Magenta,
+        TestError::Custom { color, .. } => *color,
+    };
+    themed_color(error.verdict(), default)
+}
+
+/// A scrollable list of the test results with a pane showing the details of the selected test
+struct Browser {
+    entries: Vec<Entry>,
+    success_verdict: &'static str,
+    filters: Vec<Filter>,
+    filter: usize,
+    /// The indices of the entries matching the current filter
+    visible: Vec<usize>,
+    list_state: ListState,
+    details_scroll: u16,
+}
+
+impl Browser {
+    fn new(test_summary: &mut TestSummary) -> Self {
+        let success_verdict = if test_summary.generate_mode { "successful" } else { "correct" };
+        let mut entries: Vec<Entry> = test_summary.successful_tests().iter()
+            .map(|(test_name, time, _)| Entry {
+                test_name: test_name.clone(),
+                verdict: success_verdict.to_owned(),
+                color: themed_color(success_verdict, colored::Color::Green),
+                time: *time,
+                details: match time {
+                    Some(time) => format!("The test passed in {:.3}s", time.as_secs_f64()),
+                    None => "The test passed".to_owned(),
+                },
+            })
+            .collect();
+        entries.extend(test_summary.get_errors_with_stderr().map(|(test_name, error, stderr)| Entry {
+            test_name: test_name.clone(),
+            verdict: error.verdict().to_owned(),
+            color: verdict_color(error),
+            time: None,
+            details: match stderr {
+                Some(stderr) => format!("{}\n\nProgram stderr:\n{stderr}", strip_ansi_codes(&error.message())),
+                None => strip_ansi_codes(&error.message()),
+            },
+        }));
+        entries.sort_by(|a, b| human_sort::compare(&a.test_name, &b.test_name));
+
+        let mut verdicts: Vec<&str> = entries.iter().map(|entry| entry.verdict.as_str()).collect();
+        verdicts.sort_unstable();
+        verdicts.dedup();
+        let filters = [Filter::All, Filter::Failed].into_iter()
+            .chain(verdicts.into_iter().map(|verdict| Filter::Verdict(verdict.to_owned())))
+            .collect();
+
+        // Failures are what the user is usually looking for, so only they are shown at first if there are any
+        let filter = usize::from(entries.iter().any(|entry| entry.verdict != success_verdict));
+        let mut browser = Browser {
+            entries,
+            success_verdict,
+            filters,
+            filter,
+            visible: vec![],
+            list_state: ListState::default(),
+            details_scroll: 0,
+        };
+        browser.apply_filter();
+        browser
+    }
+
+    fn apply_filter(&mut self) {
+        let filter = &self.filters[self.filter];
+        self.visible = (0..self.entries.len())
+            .filter(|index| filter.matches(&self.entries[*index], self.success_verdict))
+            .collect();
+        self.list_state.select((!self.visible.is_empty()).then_some(0));
+        self.details_scroll = 0;
+    }
+
+    fn move_selection(&mut self, offset: isize) {
+        if self.visible.is_empty() {
+            return;
+        }
+        let selected = self.list_state.selected().unwrap_or(0);
+        let last = self.visible.len() - 1;
+        self.list_state.select(Some(selected.saturating_add_signed(offset).min(last)));
+        self.details_scroll = 0;
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main_area, help_area] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [list_area, details_area] = Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)]).areas(main_area);
+
+        let items: Vec<ListItem> = self.visible.iter()
+            .map(|index| {
+                let entry = &self.entries[*index];
+                let time = entry.time.map_or_else(String::new, |time| format!(" {:.3}s", time.as_secs_f64()));
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{} ", entry.test_name)),
+                    Span::styled(entry.verdict.clone(), Style::new().fg(entry.color)),
+                    Span::raw(time),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::new().borders(Borders::ALL).title(format!(
+                " {} ({}/{}) ",
+                self.filters[self.filter].name(),
+                self.visible.len(),
+                self.entries.len(),
+            )))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+
+        let selected = self.list_state.selected().and_then(|selected| self.visible.get(selected));
+        let (title, details) = match selected {
+            Some(index) => (format!(" Test {} ", self.entries[*index].test_name), self.entries[*index].details.as_str()),
+            None => (" No tests ".to_owned(), ""),
+        };
+        let details = Paragraph::new(details)
+            .block(Block::new().borders(Borders::ALL).title(title))
+            .wrap(Wrap { trim: false })
+            .scroll((self.details_scroll, 0));
+        frame.render_widget(details, details_area);
+
+        frame.render_widget(
+            Paragraph::new("↑/↓ select  PgUp/PgDn scroll details  f change filter  q quit").style(Style::new().add_modifier(Modifier::DIM)),
+            help_area,
+        );
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+                KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+                KeyCode::Home => self.move_selection(isize::MIN),
+                KeyCode::End => self.move_selection(isize::MAX),
+                KeyCode::PageUp => self.details_scroll = self.details_scroll.saturating_sub(10),
+                KeyCode::PageDown => self.details_scroll = self.details_scroll.saturating_add(10),
+                KeyCode::Char('f') => {
+                    self.filter = (self.filter + 1) % self.filters.len();
+                    self.apply_filter();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Shows the results of the tests in an interactive browser until the user quits it
+pub(crate) fn browse_results(test_summary: &mut TestSummary) -> io::Result<()> {
+    let mut browser = Browser::new(test_summary);
+    let mut terminal = ratatui::init();
+    let result = browser.run(&mut terminal);
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,52 @@
+use std::io::{read_to_string, Seek};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use wait_timeout::ChildExt;
+use crate::output::debug;
+use crate::prepare_input::TestInputSource;
+use crate::process_tree::kill_tree;
+use crate::temp_files::{create_test_temp_file, make_cloned_stdio};
+use crate::test_errors::TestError;
+use crate::test_errors::TestError::InvalidInput;
+
+/// Checks every test input before it's given to the tested program.
+/// The validator receives the input on stdin, like testlib validators, and returns
+/// a non-zero exit code with the reason written to stderr if the input is invalid
+pub(crate) struct Validator {
+    executable_path: PathBuf,
+    timeout: Duration,
+}
+
+impl Validator {
+    pub(crate) fn new(executable_path: PathBuf, timeout: Duration) -> Self {
+        Validator { executable_path, timeout }
+    }
+
+    pub(crate) fn validate(&self, input_source: &TestInputSource) -> Result<(), TestError> {
+        debug(&format!("Running: {}", self.executable_path.display()));
+        let mut stderr = create_test_temp_file()?;
+        let mut child = Command::new(&self.executable_path)
+            .stdin(make_cloned_stdio(&input_source.get_file()?)?)
+            .stdout(Stdio::null())
+            .stderr(make_cloned_stdio(&stderr)?)
+            .spawn().expect("Failed to spawn the validator");
+        let Some(status) = child.wait_timeout(self.timeout).unwrap() else {
+            kill_tree(&mut child).unwrap();
+            return Err(InvalidInput { message: "The validator timed out".to_owned() });
+        };
+        if status.success() {
+            return Ok(());
+        }
+        stderr.rewind().unwrap();
+        let message = read_to_string(stderr).expect("Failed to read validator output");
+        let message = message.trim();
+        Err(InvalidInput {
+            message: match status.code() {
+                Some(_) if !message.is_empty() => message.to_owned(),
+                Some(exit_code) => format!("The validator returned exit code {exit_code}"),
+                None => format!("The validator was terminated with the following error:\n{status}"),
+            },
+        })
+    }
+}
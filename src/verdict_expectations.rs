@@ -0,0 +1,73 @@
+use std::sync::Mutex;
+use colored::Colorize;
+use crate::manifest::ExpectedVerdict;
+use crate::test_errors::{ExecutionMetrics, TestError};
+use crate::warnings::{add_warning, WarningKind};
+
+#[derive(Default)]
+struct ExpectationResults {
+    /// The number of tests which got the verdict they were expected to get
+    matched: usize,
+    /// The number of matched tests whose expected verdict is a failure
+    expected_failures: usize,
+    /// The tests which got a different verdict than expected, with the expected and the actual verdict
+    mismatches: Vec<(String, String, String)>,
+}
+
+/// Compares the verdicts of tests with the verdicts expected in the manifest,
+/// for example to make sure a slow reference solution times out on the large tests
+pub(crate) struct VerdictExpectations {
+    expectations: Vec<ExpectedVerdict>,
+    results: Mutex<ExpectationResults>,
+}
+
+impl VerdictExpectations {
+    pub(crate) fn new(expectations: Vec<ExpectedVerdict>) -> Self {
+        VerdictExpectations { expectations, results: Mutex::new(ExpectationResults::default()) }
+    }
+
+    /// Tests are expected to get the verdict of the first entry matching their name
+    pub(crate) fn record(&self, test_name: &str, result: &Result<ExecutionMetrics, TestError>) {
+        let Some(expectation) = self.expectations.iter().find(|expectation| expectation.tests.matches(test_name)) else {
+            return;
+        };
+        let verdict = match result {
+            Ok(_) => "correct",
+            Err(error) => error.verdict(),
+        };
+        let mut results = self.results.lock().expect("Failed to lock the expectation results");
+        if verdict.eq_ignore_ascii_case(&expectation.verdict) {
+            results.matched += 1;
+            if result.is_err() {
+                results.expected_failures += 1;
+            }
+        } else {
+            results.mismatches.push((test_name.to_owned(), expectation.verdict.clone(), verdict.to_owned()));
+        }
+    }
+
+    /// Returns the number of failed tests which were expected to fail and the number of mismatched verdicts
+    pub(crate) fn counts(&self) -> (usize, usize) {
+        let results = self.results.lock().expect("Failed to lock the expectation results");
+        (results.expected_failures, results.mismatches.len())
+    }
+
+    /// Prints the number of tests which got the expected verdict and lists the mismatches
+    pub(crate) fn print(&self) {
+        let mut results = self.results.lock().expect("Failed to lock the expectation results");
+        if results.matched == 0 && results.mismatches.is_empty() {
+            add_warning(WarningKind::Expectations, "No tests match the expected verdicts");
+            return;
+        }
+        let matched_text = format!("Expected verdicts: {} of {} tests matched", results.matched, results.matched + results.mismatches.len());
+        if results.mismatches.is_empty() {
+            println!("{}", matched_text.green());
+            return;
+        }
+        println!("{}", matched_text.red());
+        results.mismatches.sort_by(|a, b| human_sort::compare(&a.0, &b.0));
+        for (test_name, expected, actual) in &results.mismatches {
+            println!("{}", format!("Test {test_name} was expected to get \"{expected}\", but got \"{actual}\"").red());
+        }
+    }
+}
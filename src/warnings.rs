@@ -0,0 +1,84 @@
+use std::sync::Mutex;
+use colored::Colorize;
+use serde::Serialize;
+use crate::output;
+use crate::output::is_quiet;
+
+/// What a warning is about, included in the published results so servers can group warnings
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum WarningKind {
+    /// Output files without a matching input file, which usually means a test is missing
+    UnusedOutputFiles,
+    /// Tests that passed, but whose runtime was close to the time limit
+    CloseToTimeLimit,
+    /// Limits that aren't enforced, because no sandbox is used
+    LimitsNotEnforced,
+    /// Cached executables that couldn't be used or saved
+    CompileCache,
+    /// The precompiled header couldn't be built
+    PrecompiledHeader,
+    /// Test files that weren't UTF-8 encoded
+    Transcoded,
+    /// Other processes that could affect the measured runtimes
+    BusySystem,
+    /// Problems with the scored groups
+    Scoring,
+    /// Problems with the expected verdicts
+    Expectations,
+    /// The results couldn't be saved in the run history
+    History,
+    /// The results couldn't be sent to the results server or the webhook
+    Publish,
+    /// The priority of the tested programs couldn't be raised
+    Priority,
+}
+
+/// A caveat about the results found while testing, shown in a separate section after the results
+#[derive(Serialize, Clone, Debug)]
+pub(crate) struct Warning {
+    pub(crate) kind: WarningKind,
+    pub(crate) message: String,
+}
+
+/// The warnings of the task that is currently tested
+static WARNINGS: Mutex<Vec<Warning>> = Mutex::new(vec![]);
+
+/// Records a warning to be shown after the results.
+/// Warnings with the same kind and message are only recorded once
+pub(crate) fn add_warning(kind: WarningKind, message: impl Into<String>) {
+    let warning = Warning { kind, message: message.into() };
+    let mut warnings = WARNINGS.lock().expect("Failed to lock the warnings");
+    if !warnings.iter().any(|existing| existing.kind == warning.kind && existing.message == warning.message) {
+        warnings.push(warning);
+    }
+}
+
+/// Records a warning found after the warnings were printed, like a failure to publish the results, and prints it right away
+pub(crate) fn add_late_warning(kind: WarningKind, message: impl Into<String>) {
+    let message = message.into();
+    output::info(&format!("Warning: {}", message.replace('\n', "\n  ")).yellow().to_string());
+    add_warning(kind, message);
+}
+
+/// Returns the warnings recorded so far
+pub(crate) fn warnings() -> Vec<Warning> {
+    WARNINGS.lock().expect("Failed to lock the warnings").clone()
+}
+
+/// Removes the recorded warnings, so they aren't shown with the results of the next task
+pub(crate) fn clear_warnings() {
+    WARNINGS.lock().expect("Failed to lock the warnings").clear();
+}
+
+/// Prints the recorded warnings in a separate section, if there are any
+pub(crate) fn print_warnings() {
+    let warnings = warnings();
+    if warnings.is_empty() || is_quiet() {
+        return;
+    }
+    println!("{}", format!("Warnings ({}):", warnings.len()).yellow().bold());
+    for warning in &warnings {
+        println!("{}", format!("- {}", warning.message.replace('\n', "\n  ")).yellow());
+    }
+}
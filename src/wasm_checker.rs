@@ -0,0 +1,150 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{read_to_string, Seek};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use std::fmt::Display;
+use tempfile::TempDir;
+use wasmtime::{Config, Engine, InstancePre, Linker, Module, Store, Trap};
+use wasmtime_wasi::cli::{InputFile, OutputFile};
+use wasmtime_wasi::p1::WasiP1Ctx;
+use wasmtime_wasi::{FsPerms, I32Exit, WasiCtxBuilder};
+use crate::formatted_error::FormattedError;
+use crate::prepare_input::TestInputSource;
+use crate::program_stderr::read_tail;
+use crate::temp_files::create_test_temp_file;
+use crate::test_errors::ExecutionError::{RuntimeError, SandboxError, TimedOut};
+use crate::test_errors::{ExecutionError, TestError};
+use crate::test_errors::TestError::CheckerError;
+
+/// How often the epoch of the engine is incremented, which is the precision of the checker timeout
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// A checker compiled to a WASI module, which is run in-process instead of spawning a checker binary.
+///
+/// The checker is run like with the files protocol, with a preopened directory
+/// containing the input, the program output and the answer as `/input`, `/output` and `/answer`
+pub(crate) struct WasmChecker {
+    instance: InstancePre<WasiP1Ctx>,
+    /// The timeout in epoch ticks
+    deadline: u64,
+    /// Keeps the thread incrementing the epoch running
+    _alive: Arc<()>,
+}
+
+impl WasmChecker {
+    pub(crate) fn new(path: &Path, timeout: Duration) -> Result<Self, FormattedError> {
+        let error_message = |error: wasmtime::Error| {
+            FormattedError::from_str(&format!("The WASM checker {} can't be loaded:\n{error:#}", path.display()))
+        };
+        let mut config = Config::new();
+        config.epoch_interruption(true);
+        let engine = Engine::new(&config).map_err(error_message)?;
+        let module = Module::from_file(&engine, path).map_err(error_message)?;
+        let mut linker = Linker::new(&engine);
+        wasmtime_wasi::p1::add_to_linker_sync(&mut linker, |context| context).map_err(error_message)?;
+        let instance = linker.instantiate_pre(&module).map_err(error_message)?;
+
+        // The deadlines of all checks are measured in the ticks of a single thread,
+        // since incrementing the epoch affects every store of the engine.
+        // The thread stops when the checker is dropped
+        let alive = Arc::new(());
+        let alive_weak = Arc::downgrade(&alive);
+        thread::spawn(move || {
+            while alive_weak.strong_count() > 0 {
+                thread::sleep(EPOCH_TICK);
+                engine.increment_epoch();
+            }
+        });
+        Ok(WasmChecker {
+            instance,
+            _alive: alive,
+            deadline: u64::try_from(timeout.as_millis() / EPOCH_TICK.as_millis()).unwrap_or(u64::MAX).max(1),
+        })
+    }
+
+    /// Runs the checker, returning the stdout and the end of the stderr.
+    /// With `with_feedback`, the checker also receives the path of the feedback file,
+    /// which is returned if the checker wrote any
+    pub(crate) fn check(
+        &self,
+        input_source: &TestInputSource,
+        output: &File,
+        answer_path: &Path,
+        with_feedback: bool,
+    ) -> (Result<String, TestError>, Option<String>, Option<String>) {
+        let prepared = input_source.get_file()
+            .and_then(|input| Ok((input, create_test_temp_file()?, create_test_temp_file()?, create_test_temp_file()?)));
+        let (mut input, mut stdout, stderr, stdin) = match prepared {
+            Ok(files) => files,
+            Err(error) => return (Err(error.into()), None, None),
+        };
+        let directory = match Self::prepare_directory(&mut input, output, answer_path) {
+            Ok(directory) => directory,
+            Err(error) => return (Err(Self::setup_error("prepare the checker directory", &error)), None, None),
+        };
+        let (checker_stdout, checker_stderr) = match stdout.try_clone().and_then(|stdout| Ok((stdout, stderr.try_clone()?))) {
+            Ok(files) => files,
+            Err(error) => return (Err(Self::setup_error("duplicate a file descriptor", &error)), None, None),
+        };
+
+        let mut arguments = vec!["checker", "/input", "/output", "/answer"];
+        if with_feedback {
+            arguments.push("/feedback");
+        }
+        let mut context = WasiCtxBuilder::new();
+        context
+            .stdin(InputFile::new(stdin))
+            .stdout(OutputFile::new(checker_stdout))
+            .stderr(OutputFile::new(checker_stderr))
+            .args(&arguments);
+        if let Err(error) = context.preopened_dir(directory.path(), "/", if with_feedback { FsPerms::ReadWrite } else { FsPerms::ReadOnly }) {
+            return (Err(Self::setup_error("open the checker directory", &error)), None, None);
+        }
+        let context = context.build_p1();
+
+        let result = self.run(context);
+        stdout.rewind().expect("Failed to rewind memfile");
+        let result = result
+            .map(|()| read_to_string(stdout).expect("Failed to read checker output"))
+            .map_err(|error| CheckerError { error, stderr: None });
+        let feedback = fs::read_to_string(directory.path().join("feedback")).ok()
+            .filter(|feedback| !feedback.trim().is_empty());
+        (result, read_tail(&stderr), feedback)
+    }
+
+    /// Creates the directory preopened for the checker, containing the input, the program output and the answer
+    fn prepare_directory(input: &mut File, mut output: &File, answer_path: &Path) -> io::Result<TempDir> {
+        let directory = tempfile::tempdir()?;
+        io::copy(input, &mut File::create(directory.path().join("input"))?)?;
+        output.rewind()?;
+        io::copy(&mut output, &mut File::create(directory.path().join("output"))?)?;
+        fs::copy(answer_path, directory.path().join("answer"))?;
+        Ok(directory)
+    }
+
+    fn setup_error(action: &str, error: &dyn Display) -> TestError {
+        CheckerError { error: SandboxError(format!("Failed to {action}: {error}")), stderr: None }
+    }
+
+    fn run(&self, context: WasiP1Ctx) -> Result<(), ExecutionError> {
+        let mut store = Store::new(self.instance.module().engine(), context);
+        store.set_epoch_deadline(self.deadline);
+        let error = match self.instance.instantiate(&mut store)
+            .and_then(|instance| instance.get_typed_func::<(), ()>(&mut store, "_start"))
+            .and_then(|start| start.call(&mut store, ()))
+        {
+            Ok(()) => return Ok(()),
+            Err(error) => error,
+        };
+        match (error.downcast_ref::<I32Exit>(), error.downcast_ref::<Trap>()) {
+            (Some(I32Exit(0)), _) => Ok(()),
+            (Some(I32Exit(code)), _) => Err(RuntimeError(format!("- the checker returned a non-zero return code: {code}"))),
+            (None, Some(Trap::Interrupt)) => Err(TimedOut),
+            (None, _) => Err(RuntimeError(format!("- the checker was terminated with the following error:\n{error:#}"))),
+        }
+    }
+}